@@ -15,7 +15,9 @@ pub struct Model {
     pub node_content: Option<String>,
     pub style: String,
     pub resolution: String,
+    #[sea_orm(column_type = "Text")]
     pub image_url: String,
+    #[sea_orm(column_type = "Text", nullable)]
     pub temp_url: Option<String>,
     pub temp_url_expires_at: Option<TimeDateTimeWithTimeZone>,
     pub width: i32,
@@ -28,10 +30,27 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     pub error_message: Option<String>,
     pub created_at: TimeDateTimeWithTimeZone,
+    pub storage_key: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub variants: Option<Json>,
+    pub aspect_ratio: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub node_tags: Option<Json>,
+    pub seed: Option<i64>,
+    pub source_image_id: Option<Uuid>,
+    pub derived_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::SourceImageId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    SelfRef,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -3,11 +3,22 @@
 pub mod prelude;
 
 pub mod ai_image_generation;
+pub mod credit_reservations;
+pub mod credit_usage_ledger;
 pub mod credits_events;
+pub mod export_jobs;
+pub mod iap_notifications;
+pub mod media_assets;
+pub mod node_media;
 pub mod quota_usage;
 pub mod refresh_tokens;
 pub mod sea_orm_active_enums;
+pub mod shares;
+pub mod stories;
+pub mod story_node_revisions;
+pub mod story_nodes;
 pub mod user_auth_methods;
 pub mod user_credit_balance;
 pub mod user_iap_receipts;
+pub mod user_sync_state;
 pub mod users;
@@ -1,10 +1,21 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.13
 
 pub use super::ai_image_generation::Entity as AiImageGeneration;
+pub use super::credit_reservations::Entity as CreditReservations;
+pub use super::credit_usage_ledger::Entity as CreditUsageLedger;
 pub use super::credits_events::Entity as CreditsEvents;
+pub use super::export_jobs::Entity as ExportJobs;
+pub use super::iap_notifications::Entity as IapNotifications;
+pub use super::media_assets::Entity as MediaAssets;
+pub use super::node_media::Entity as NodeMedia;
 pub use super::quota_usage::Entity as QuotaUsage;
 pub use super::refresh_tokens::Entity as RefreshTokens;
+pub use super::shares::Entity as Shares;
+pub use super::stories::Entity as Stories;
+pub use super::story_node_revisions::Entity as StoryNodeRevisions;
+pub use super::story_nodes::Entity as StoryNodes;
 pub use super::user_auth_methods::Entity as UserAuthMethods;
 pub use super::user_credit_balance::Entity as UserCreditBalance;
 pub use super::user_iap_receipts::Entity as UserIapReceipts;
+pub use super::user_sync_state::Entity as UserSyncState;
 pub use super::users::Entity as Users;
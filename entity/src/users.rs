@@ -25,16 +25,34 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::ai_image_generation::Entity")]
     AiImageGeneration,
+    #[sea_orm(has_many = "super::credit_reservations::Entity")]
+    CreditReservations,
+    #[sea_orm(has_many = "super::credit_usage_ledger::Entity")]
+    CreditUsageLedger,
     #[sea_orm(has_many = "super::credits_events::Entity")]
     CreditsEvents,
+    #[sea_orm(has_many = "super::export_jobs::Entity")]
+    ExportJobs,
+    #[sea_orm(has_many = "super::media_assets::Entity")]
+    MediaAssets,
+    #[sea_orm(has_many = "super::node_media::Entity")]
+    NodeMedia,
     #[sea_orm(has_many = "super::quota_usage::Entity")]
     QuotaUsage,
     #[sea_orm(has_many = "super::refresh_tokens::Entity")]
     RefreshTokens,
+    #[sea_orm(has_many = "super::shares::Entity")]
+    Shares,
+    #[sea_orm(has_many = "super::stories::Entity")]
+    Stories,
+    #[sea_orm(has_many = "super::story_nodes::Entity")]
+    StoryNodes,
     #[sea_orm(has_many = "super::user_auth_methods::Entity")]
     UserAuthMethods,
     #[sea_orm(has_one = "super::user_credit_balance::Entity")]
     UserCreditBalance,
+    #[sea_orm(has_one = "super::user_sync_state::Entity")]
+    UserSyncState,
 }
 
 impl Related<super::ai_image_generation::Entity> for Entity {
@@ -43,12 +61,42 @@ impl Related<super::ai_image_generation::Entity> for Entity {
     }
 }
 
+impl Related<super::credit_reservations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CreditReservations.def()
+    }
+}
+
+impl Related<super::credit_usage_ledger::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CreditUsageLedger.def()
+    }
+}
+
 impl Related<super::credits_events::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::CreditsEvents.def()
     }
 }
 
+impl Related<super::export_jobs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExportJobs.def()
+    }
+}
+
+impl Related<super::media_assets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MediaAssets.def()
+    }
+}
+
+impl Related<super::node_media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::NodeMedia.def()
+    }
+}
+
 impl Related<super::quota_usage::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::QuotaUsage.def()
@@ -61,6 +109,24 @@ impl Related<super::refresh_tokens::Entity> for Entity {
     }
 }
 
+impl Related<super::shares::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Shares.def()
+    }
+}
+
+impl Related<super::stories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Stories.def()
+    }
+}
+
+impl Related<super::story_nodes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StoryNodes.def()
+    }
+}
+
 impl Related<super::user_auth_methods::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserAuthMethods.def()
@@ -73,4 +139,10 @@ impl Related<super::user_credit_balance::Entity> for Entity {
     }
 }
 
+impl Related<super::user_sync_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserSyncState.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
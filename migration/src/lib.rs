@@ -4,6 +4,21 @@ mod m20220101_000001_create_table;
 mod m20241201_000001_add_extra_credits;
 mod m20241203_000001_create_credit_balance;
 mod m20251210_123319_create_ai_image_generations_table;
+mod m20251215_000001_create_iap_notifications;
+mod m20251216_000001_backfill_credit_consumption;
+mod m20251217_000001_create_credit_usage_ledger;
+mod m20251218_000001_create_credit_reservations;
+mod m20251219_000001_add_credit_holds;
+mod m20251220_000001_add_image_storage_key;
+mod m20251221_000001_add_image_variants;
+mod m20251222_000001_add_image_regeneration;
+mod m20251223_000001_create_shares;
+mod m20251224_000001_create_sync_tables;
+mod m20251225_000001_create_story_node_revisions;
+mod m20251226_000001_create_export_jobs;
+mod m20251227_000001_add_image_derived_count;
+mod m20251228_000001_add_receipt_store_signed_at;
+mod m20251229_000001_add_credit_reservation_delivered_at;
 
 pub struct Migrator;
 
@@ -15,6 +30,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20241201_000001_add_extra_credits::Migration),
             Box::new(m20241203_000001_create_credit_balance::Migration),
             Box::new(m20251210_123319_create_ai_image_generations_table::Migration),
+            Box::new(m20251215_000001_create_iap_notifications::Migration),
+            Box::new(m20251216_000001_backfill_credit_consumption::Migration),
+            Box::new(m20251217_000001_create_credit_usage_ledger::Migration),
+            Box::new(m20251218_000001_create_credit_reservations::Migration),
+            Box::new(m20251219_000001_add_credit_holds::Migration),
+            Box::new(m20251220_000001_add_image_storage_key::Migration),
+            Box::new(m20251221_000001_add_image_variants::Migration),
+            Box::new(m20251222_000001_add_image_regeneration::Migration),
+            Box::new(m20251223_000001_create_shares::Migration),
+            Box::new(m20251224_000001_create_sync_tables::Migration),
+            Box::new(m20251225_000001_create_story_node_revisions::Migration),
+            Box::new(m20251226_000001_create_export_jobs::Migration),
+            Box::new(m20251227_000001_add_image_derived_count::Migration),
+            Box::new(m20251228_000001_add_receipt_store_signed_at::Migration),
+            Box::new(m20251229_000001_add_credit_reservation_delivered_at::Migration),
         ]
     }
 }
@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Store server notifications so each one is processed exactly once
+        manager
+            .create_table(
+                Table::create()
+                    .table(IapNotifications::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IapNotifications::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::Platform)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::NotificationId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::NotificationType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(IapNotifications::Subtype).string().null())
+                    .col(
+                        ColumnDef::new(IapNotifications::OriginalTransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::TransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::Payload)
+                            .json_binary()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::ProcessedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IapNotifications::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Idempotency key: stores retry with the same notification ID
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_iap_notifications_platform_notification_id")
+                    .table(IapNotifications::Table)
+                    .col(IapNotifications::Platform)
+                    .col(IapNotifications::NotificationId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_iap_notifications_original_transaction_id")
+                    .table(IapNotifications::Table)
+                    .col(IapNotifications::OriginalTransactionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IapNotifications::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IapNotifications {
+    Table,
+    Id,
+    Platform,
+    NotificationId,
+    NotificationType,
+    Subtype,
+    OriginalTransactionId,
+    TransactionId,
+    Payload,
+    ProcessedAt,
+    CreatedAt,
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Consumption used to be tracked on user_credit_balance only. Attribute the
+        // difference between the ledger and the balance to grants, oldest first.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                WITH owed AS (
+                    SELECT e.user_id,
+                           SUM(e.amount - e.consumed) - GREATEST(b.extra_credits_remaining, 0) AS owed
+                    FROM credits_events e
+                    JOIN user_credit_balance b ON b.user_id = e.user_id
+                    WHERE e.revoked_at IS NULL AND e.event_type <> 'consumption'
+                    GROUP BY e.user_id, b.extra_credits_remaining
+                    HAVING SUM(e.amount - e.consumed) > GREATEST(b.extra_credits_remaining, 0)
+                ),
+                fifo AS (
+                    SELECT e.id,
+                           e.amount - e.consumed AS remaining,
+                           o.owed,
+                           SUM(e.amount - e.consumed) OVER (
+                               PARTITION BY e.user_id ORDER BY e.occurred_at, e.id
+                           ) - (e.amount - e.consumed) AS consumed_before
+                    FROM credits_events e
+                    JOIN owed o ON o.user_id = e.user_id
+                    WHERE e.revoked_at IS NULL
+                      AND e.event_type <> 'consumption'
+                      AND e.amount > e.consumed
+                )
+                UPDATE credits_events c
+                SET consumed = c.consumed + LEAST(f.remaining, f.owed - f.consumed_before)
+                FROM fifo f
+                WHERE c.id = f.id AND f.owed > f.consumed_before;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Attributed consumption is kept; balances are unaffected either way
+        Ok(())
+    }
+}
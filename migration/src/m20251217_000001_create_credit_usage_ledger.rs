@@ -0,0 +1,153 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per AI operation charge or refund
+        manager
+            .create_table(
+                Table::create()
+                    .table(CreditUsageLedger::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CreditUsageLedger::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::EntryType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::Operation)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CreditUsageLedger::Cost).integer().not_null())
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::SubscriptionCredits)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::ExtraCredits)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CreditUsageLedger::Model).string().null())
+                    .col(ColumnDef::new(CreditUsageLedger::RequestId).string().null())
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::Outcome)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::ErrorMessage)
+                            .text()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(CreditUsageLedger::ChargeId).uuid().null())
+                    .col(
+                        ColumnDef::new(CreditUsageLedger::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_credit_usage_ledger_user_id")
+                            .from(CreditUsageLedger::Table, CreditUsageLedger::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // History is listed newest first per user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_credit_usage_ledger_user_created")
+                    .table(CreditUsageLedger::Table)
+                    .col(CreditUsageLedger::UserId)
+                    .col(CreditUsageLedger::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_credit_usage_ledger_request_id")
+                    .table(CreditUsageLedger::Table)
+                    .col(CreditUsageLedger::RequestId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Ledger rows are immutable; corrections are new rows
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE OR REPLACE FUNCTION credit_usage_ledger_immutable()
+                RETURNS trigger AS $$
+                BEGIN
+                    RAISE EXCEPTION 'credit_usage_ledger rows are immutable';
+                END;
+                $$ LANGUAGE plpgsql;
+
+                CREATE TRIGGER trg_credit_usage_ledger_immutable
+                BEFORE UPDATE ON credit_usage_ledger
+                FOR EACH ROW EXECUTE FUNCTION credit_usage_ledger_immutable();
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CreditUsageLedger::Table).to_owned())
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS credit_usage_ledger_immutable();")
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum CreditUsageLedger {
+    Table,
+    Id,
+    UserId,
+    EntryType,
+    Operation,
+    Cost,
+    SubscriptionCredits,
+    ExtraCredits,
+    Model,
+    RequestId,
+    Outcome,
+    ErrorMessage,
+    ChargeId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
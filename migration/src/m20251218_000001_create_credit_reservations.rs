@@ -0,0 +1,159 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // What each charge took from which source, so a refund can reverse exactly that
+        manager
+            .create_table(
+                Table::create()
+                    .table(CreditReservations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CreditReservations::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CreditReservations::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(CreditReservations::Operation)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::Cost)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::SubscriptionCredits)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::ExtraCredits)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::UsageDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::SubscriptionResetsAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(CreditReservations::RefundedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_credit_reservations_user_id")
+                            .from(CreditReservations::Table, CreditReservations::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_credit_reservations_user_created")
+                    .table(CreditReservations::Table)
+                    .col(CreditReservations::UserId)
+                    .col(CreditReservations::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Link usage ledger entries to the reservation they settle
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditUsageLedger::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(CreditUsageLedger::ReservationId)
+                            .uuid()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_credit_usage_ledger_reservation_id")
+                    .table(CreditUsageLedger::Table)
+                    .col(CreditUsageLedger::ReservationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditUsageLedger::Table)
+                    .drop_column(CreditUsageLedger::ReservationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CreditReservations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CreditReservations {
+    Table,
+    Id,
+    UserId,
+    Operation,
+    Cost,
+    SubscriptionCredits,
+    ExtraCredits,
+    UsageDate,
+    SubscriptionResetsAt,
+    Status,
+    CreatedAt,
+    RefundedAt,
+}
+
+#[derive(DeriveIden)]
+enum CreditUsageLedger {
+    Table,
+    ReservationId,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Reservations become time-limited holds that are committed or released
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(CreditReservations::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(CreditReservations::CommittedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Postgres cannot rename a column in the same statement as adding one
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .rename_column(
+                        CreditReservations::RefundedAt,
+                        CreditReservations::ReleasedAt,
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing reservations were settled under the charge/refund model
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            UPDATE credit_reservations
+            SET status = CASE status WHEN 'refunded' THEN 'released' ELSE 'committed' END,
+                committed_at = CASE status WHEN 'refunded' THEN NULL ELSE created_at END
+            "#,
+        )
+        .await?;
+
+        // The sweeper scans pending holds by expiry
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_credit_reservations_status_expires")
+                    .table(CreditReservations::Table)
+                    .col(CreditReservations::Status)
+                    .col(CreditReservations::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_credit_reservations_status_expires")
+                    .table(CreditReservations::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            UPDATE credit_reservations
+            SET status = CASE status WHEN 'released' THEN 'refunded' ELSE 'charged' END
+            "#,
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .drop_column(CreditReservations::ExpiresAt)
+                    .drop_column(CreditReservations::CommittedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .rename_column(
+                        CreditReservations::ReleasedAt,
+                        CreditReservations::RefundedAt,
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CreditReservations {
+    Table,
+    Status,
+    ExpiresAt,
+    CommittedAt,
+    RefundedAt,
+    ReleasedAt,
+}
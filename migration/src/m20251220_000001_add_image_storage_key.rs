@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Generated images are persisted in object storage under this key
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::StorageKey)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Presigned URLs do not fit in a varchar(255)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .modify_column(ColumnDef::new(AIImageGeneration::TempUrl).text().null())
+                    .modify_column(
+                        ColumnDef::new(AIImageGeneration::ImageUrl)
+                            .text()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .modify_column(ColumnDef::new(AIImageGeneration::TempUrl).string().null())
+                    .modify_column(
+                        ColumnDef::new(AIImageGeneration::ImageUrl)
+                            .string()
+                            .not_null(),
+                    )
+                    .drop_column(AIImageGeneration::StorageKey)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AIImageGeneration {
+    Table,
+    StorageKey,
+    TempUrl,
+    ImageUrl,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Thumbnail and compressed renditions stored next to the original:
+        // [{name, storageKey, mimeType, width, height}]
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::Variants)
+                            .json_binary()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .drop_column(AIImageGeneration::Variants)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AIImageGeneration {
+    Table,
+    Variants,
+}
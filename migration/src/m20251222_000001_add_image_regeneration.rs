@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Prompt inputs not kept so far, so an attempt can be generated again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::AspectRatio)
+                            .string()
+                            .null(),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::NodeTags)
+                            .json_binary()
+                            .null(),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::Seed)
+                            .big_integer()
+                            .null(),
+                    )
+                    // The first generation a regeneration or variation descends from
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::SourceImageId)
+                            .uuid()
+                            .null(),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_ai_image_generation_source_image_id")
+                            .from_tbl(AIImageGeneration::Table)
+                            .from_col(AIImageGeneration::SourceImageId)
+                            .to_tbl(AIImageGeneration::Table)
+                            .to_col(AIImageGeneration::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Regeneration limits count an image's descendants
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_ai_image_generation_source_image_id")
+                    .table(AIImageGeneration::Table)
+                    .col(AIImageGeneration::SourceImageId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_ai_image_generation_source_image_id")
+                    .table(AIImageGeneration::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .drop_foreign_key(Alias::new("fk_ai_image_generation_source_image_id"))
+                    .drop_column(AIImageGeneration::SourceImageId)
+                    .drop_column(AIImageGeneration::Seed)
+                    .drop_column(AIImageGeneration::NodeTags)
+                    .drop_column(AIImageGeneration::AspectRatio)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AIImageGeneration {
+    Table,
+    Id,
+    AspectRatio,
+    NodeTags,
+    Seed,
+    SourceImageId,
+}
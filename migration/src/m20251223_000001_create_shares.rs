@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Read-only web reader links: a snapshot of one reading path, served at /s/{slug}
+        manager
+            .create_table(
+                Table::create()
+                    .table(Shares::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Shares::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Shares::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Shares::Slug).string().not_null())
+                    .col(ColumnDef::new(Shares::Title).string().not_null())
+                    .col(ColumnDef::new(Shares::Snapshot).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Shares::ViewCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Shares::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Shares::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_shares_user_id")
+                            .from(Shares::Table, Shares::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_shares_slug")
+                    .table(Shares::Table)
+                    .col(Shares::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_shares_user_created")
+                    .table(Shares::Table)
+                    .col(Shares::UserId)
+                    .col(Shares::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Shares::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Shares {
+    Table,
+    Id,
+    UserId,
+    Slug,
+    Title,
+    Snapshot,
+    ViewCount,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,375 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Cloud copy of the app's story model (see docs/entity.txt). Every synced row carries its
+        // owner, a version bumped on each accepted change, a soft-delete tombstone and the owner's
+        // change sequence number at which it last changed, which is what pull cursors compare.
+
+        // Per-user change counter; its row is locked while a push is applied
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserSyncState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserSyncState::UserId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UserSyncState::LastChangeSeq)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_sync_state_user_id")
+                            .from(UserSyncState::Table, UserSyncState::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // cover_media_id has no foreign key: the story and its cover refer to each other and
+        // either may reach the server first
+        manager
+            .create_table(
+                sync_columns(
+                    Table::create()
+                        .table(Stories::Table)
+                        .if_not_exists()
+                        .col(ColumnDef::new(Stories::Id).uuid().not_null().primary_key())
+                        .col(ColumnDef::new(Stories::UserId).uuid().not_null())
+                        .col(ColumnDef::new(Stories::Title).text().null())
+                        .col(ColumnDef::new(Stories::Description).text().null())
+                        .col(ColumnDef::new(Stories::CoverMediaId).uuid().null()),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_stories_user_id")
+                        .from(Stories::Table, Stories::UserId)
+                        .to(Users::Table, Users::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                sync_columns(
+                    Table::create()
+                        .table(StoryNodes::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(StoryNodes::Id)
+                                .uuid()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(StoryNodes::UserId).uuid().not_null())
+                        .col(ColumnDef::new(StoryNodes::StoryId).uuid().not_null())
+                        .col(ColumnDef::new(StoryNodes::ParentNodeId).uuid().null())
+                        .col(
+                            ColumnDef::new(StoryNodes::SiblingOrder)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .col(
+                            ColumnDef::new(StoryNodes::Depth)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .col(ColumnDef::new(StoryNodes::Summary).text().null())
+                        .col(
+                            ColumnDef::new(StoryNodes::Content)
+                                .text()
+                                .not_null()
+                                .default(""),
+                        )
+                        .col(ColumnDef::new(StoryNodes::Tags).json_binary().null()),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_story_nodes_user_id")
+                        .from(StoryNodes::Table, StoryNodes::UserId)
+                        .to(Users::Table, Users::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_story_nodes_story_id")
+                        .from(StoryNodes::Table, StoryNodes::StoryId)
+                        .to(Stories::Table, Stories::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_story_nodes_parent_node_id")
+                        .from(StoryNodes::Table, StoryNodes::ParentNodeId)
+                        .to(StoryNodes::Table, StoryNodes::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                sync_columns(
+                    Table::create()
+                        .table(NodeMedia::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(NodeMedia::Id)
+                                .uuid()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(NodeMedia::UserId).uuid().not_null())
+                        .col(ColumnDef::new(NodeMedia::StoryId).uuid().not_null())
+                        .col(ColumnDef::new(NodeMedia::NodeId).uuid().null())
+                        .col(ColumnDef::new(NodeMedia::MediaType).string().not_null())
+                        .col(ColumnDef::new(NodeMedia::Source).string().not_null())
+                        .col(ColumnDef::new(NodeMedia::Caption).text().null())
+                        .col(
+                            ColumnDef::new(NodeMedia::OrderIndex)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .col(
+                            ColumnDef::new(NodeMedia::IsPrimary)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        ),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_node_media_user_id")
+                        .from(NodeMedia::Table, NodeMedia::UserId)
+                        .to(Users::Table, Users::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_node_media_story_id")
+                        .from(NodeMedia::Table, NodeMedia::StoryId)
+                        .to(Stories::Table, Stories::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_node_media_node_id")
+                        .from(NodeMedia::Table, NodeMedia::NodeId)
+                        .to(StoryNodes::Table, StoryNodes::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                sync_columns(
+                    Table::create()
+                        .table(MediaAssets::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(MediaAssets::Id)
+                                .uuid()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(MediaAssets::UserId).uuid().not_null())
+                        .col(ColumnDef::new(MediaAssets::MediaId).uuid().not_null())
+                        .col(ColumnDef::new(MediaAssets::AssetType).string().not_null())
+                        .col(ColumnDef::new(MediaAssets::MimeType).string().not_null())
+                        .col(ColumnDef::new(MediaAssets::LocalPath).text().null())
+                        .col(ColumnDef::new(MediaAssets::RemoteUrl).text().null())
+                        .col(ColumnDef::new(MediaAssets::Width).integer().null())
+                        .col(ColumnDef::new(MediaAssets::Height).integer().null())
+                        .col(ColumnDef::new(MediaAssets::DurationMs).integer().null())
+                        .col(ColumnDef::new(MediaAssets::SizeBytes).big_integer().null())
+                        .col(ColumnDef::new(MediaAssets::ExtraMeta).json_binary().null()),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_media_assets_user_id")
+                        .from(MediaAssets::Table, MediaAssets::UserId)
+                        .to(Users::Table, Users::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_media_assets_media_id")
+                        .from(MediaAssets::Table, MediaAssets::MediaId)
+                        .to(NodeMedia::Table, NodeMedia::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+            )
+            .await?;
+
+        // Pulls scan one user's rows in change order
+        for (name, table) in [
+            ("idx_stories_user_change_seq", Stories::Table.into_iden()),
+            (
+                "idx_story_nodes_user_change_seq",
+                StoryNodes::Table.into_iden(),
+            ),
+            (
+                "idx_node_media_user_change_seq",
+                NodeMedia::Table.into_iden(),
+            ),
+            (
+                "idx_media_assets_user_change_seq",
+                MediaAssets::Table.into_iden(),
+            ),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(table)
+                        .col(Sync::UserId)
+                        .col(Sync::ChangeSeq)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaAssets::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(NodeMedia::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(StoryNodes::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Stories::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserSyncState::Table).to_owned())
+            .await
+    }
+}
+
+/// Bookkeeping columns shared by all synced tables
+fn sync_columns(table: &mut TableCreateStatement) -> &mut TableCreateStatement {
+    table
+        .col(ColumnDef::new(Sync::Version).big_integer().not_null())
+        .col(ColumnDef::new(Sync::ChangeSeq).big_integer().not_null())
+        .col(
+            ColumnDef::new(Sync::DeletedAt)
+                .timestamp_with_time_zone()
+                .null(),
+        )
+        // Client edit times, kept as sent
+        .col(
+            ColumnDef::new(Sync::CreatedAt)
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Sync::UpdatedAt)
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+}
+
+#[derive(DeriveIden)]
+enum Sync {
+    UserId,
+    Version,
+    ChangeSeq,
+    DeletedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserSyncState {
+    Table,
+    UserId,
+    LastChangeSeq,
+}
+
+#[derive(DeriveIden)]
+enum Stories {
+    Table,
+    Id,
+    UserId,
+    Title,
+    Description,
+    CoverMediaId,
+}
+
+#[derive(DeriveIden)]
+enum StoryNodes {
+    Table,
+    Id,
+    UserId,
+    StoryId,
+    ParentNodeId,
+    SiblingOrder,
+    Depth,
+    Summary,
+    Content,
+    Tags,
+}
+
+#[derive(DeriveIden)]
+enum NodeMedia {
+    Table,
+    Id,
+    UserId,
+    StoryId,
+    NodeId,
+    MediaType,
+    Source,
+    Caption,
+    OrderIndex,
+    IsPrimary,
+}
+
+#[derive(DeriveIden)]
+enum MediaAssets {
+    Table,
+    Id,
+    UserId,
+    MediaId,
+    AssetType,
+    MimeType,
+    LocalPath,
+    RemoteUrl,
+    Width,
+    Height,
+    DurationMs,
+    SizeBytes,
+    ExtraMeta,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
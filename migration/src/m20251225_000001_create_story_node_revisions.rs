@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Recent versions of each synced node, used as the common ancestor when two devices
+        // edited the same node version
+        manager
+            .create_table(
+                Table::create()
+                    .table(StoryNodeRevisions::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(StoryNodeRevisions::NodeId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::Version)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::ParentNodeId)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::SiblingOrder)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::Depth)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(StoryNodeRevisions::Summary).text().null())
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::Content)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::Tags)
+                            .json_binary()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::Deleted)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(StoryNodeRevisions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(StoryNodeRevisions::NodeId)
+                            .col(StoryNodeRevisions::Version),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_story_node_revisions_node_id")
+                            .from(StoryNodeRevisions::Table, StoryNodeRevisions::NodeId)
+                            .to(StoryNodes::Table, StoryNodes::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Nodes synced before revisions existed start with their current version
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                INSERT INTO story_node_revisions
+                    (node_id, version, parent_node_id, sibling_order, depth, summary, content, tags, deleted)
+                SELECT id, version, parent_node_id, sibling_order, depth, summary, content, tags,
+                       deleted_at IS NOT NULL
+                FROM story_nodes;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StoryNodeRevisions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StoryNodeRevisions {
+    Table,
+    NodeId,
+    Version,
+    ParentNodeId,
+    SiblingOrder,
+    Depth,
+    Summary,
+    Content,
+    Tags,
+    Deleted,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum StoryNodes {
+    Table,
+    Id,
+}
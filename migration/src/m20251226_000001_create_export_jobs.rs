@@ -0,0 +1,108 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Story exports too large to render within the request; the file goes to object storage
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExportJobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExportJobs::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ExportJobs::UserId).uuid().not_null())
+                    .col(ColumnDef::new(ExportJobs::Format).string().not_null())
+                    .col(ColumnDef::new(ExportJobs::FileName).string().not_null())
+                    // running | completed | failed
+                    .col(ColumnDef::new(ExportJobs::Status).string().not_null())
+                    .col(ColumnDef::new(ExportJobs::StorageKey).string().null())
+                    .col(ColumnDef::new(ExportJobs::SizeBytes).big_integer().null())
+                    .col(ColumnDef::new(ExportJobs::Error).text().null())
+                    .col(
+                        ColumnDef::new(ExportJobs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ExportJobs::CompletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    // Set once the job finishes; the sweeper deletes the row and file after it
+                    .col(
+                        ColumnDef::new(ExportJobs::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_export_jobs_user_id")
+                            .from(ExportJobs::Table, ExportJobs::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_export_jobs_user_status")
+                    .table(ExportJobs::Table)
+                    .col(ExportJobs::UserId)
+                    .col(ExportJobs::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_export_jobs_expires_at")
+                    .table(ExportJobs::Table)
+                    .col(ExportJobs::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExportJobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExportJobs {
+    Table,
+    Id,
+    UserId,
+    Format,
+    FileName,
+    Status,
+    StorageKey,
+    SizeBytes,
+    Error,
+    CreatedAt,
+    CompletedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Regeneration slots claimed on a source image, including generations still running
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(AIImageGeneration::DerivedCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing successful descendants already used their slots
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE ai_image_generation AS source
+                SET derived_count = (
+                    SELECT COUNT(*)
+                    FROM ai_image_generation AS derived
+                    WHERE derived.source_image_id = source.id
+                      AND derived.status = 'success'
+                )
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AIImageGeneration::Table)
+                    .drop_column(AIImageGeneration::DerivedCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AIImageGeneration {
+    Table,
+    DerivedCount,
+}
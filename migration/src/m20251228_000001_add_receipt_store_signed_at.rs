@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Signing time of the newest store notification applied, so late deliveries are skipped
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserIapReceipts::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(UserIapReceipts::StoreSignedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserIapReceipts::Table)
+                    .drop_column(UserIapReceipts::StoreSignedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserIapReceipts {
+    Table,
+    StoreSignedAt,
+}
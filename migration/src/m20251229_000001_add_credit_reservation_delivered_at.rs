@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Set when output was delivered but the commit failed, so the sweeper charges instead of refunding
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(CreditReservations::DeliveredAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CreditReservations::Table)
+                    .drop_column(CreditReservations::DeliveredAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CreditReservations {
+    Table,
+    DeliveredAt,
+}
@@ -1,12 +1,16 @@
 use crate::{
     config::Config,
     services::{
-        AIService, AuthService, CreditsService, IAPService, JWTService, QuotaService,
-        RefreshTokenService, WelcomeBonusService,
+        image_processing::ImageProcessor,
+        object_store::{self, ObjectStore},
+        share_preview::PreviewRenderer,
+        AIService, AuthService, CreditsService, ExportService, IAPNotificationService, IAPService,
+        ImageService, JWTService, QuotaService, RefreshTokenService, ShareService, SyncService,
+        WelcomeBonusService,
     },
 };
 use sea_orm::DatabaseConnection;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -14,10 +18,16 @@ pub struct AppState {
     pub redis: Arc<redis::Client>,
     pub ai_service: Arc<AIService>,
     pub iap_service: Arc<IAPService>,
+    pub iap_notification_service: Arc<IAPNotificationService>,
     pub quota_service: Arc<QuotaService>,
     pub credits_service: Arc<CreditsService>,
     pub jwt_service: Arc<JWTService>,
     pub auth_service: Arc<AuthService>,
+    pub object_store: Arc<dyn ObjectStore>,
+    pub image_service: Arc<ImageService>,
+    pub share_service: Arc<ShareService>,
+    pub sync_service: Arc<SyncService>,
+    pub export_service: Arc<ExportService>,
     pub config: Arc<Config>,
 }
 
@@ -35,8 +45,17 @@ impl AppState {
         // Initialize services
         let ai_service = Arc::new(AIService::new(&config_arc.ai));
         let iap_service = Arc::new(IAPService::new(&config_arc.iap));
-        let quota_service = Arc::new(QuotaService::new(db.clone(), &config_arc.quota));
+        let quota_service = Arc::new(QuotaService::new(
+            db.clone(),
+            &config_arc.quota,
+            &config_arc.iap.products,
+        ));
         let credits_service = Arc::new(CreditsService::new(db.clone()));
+        let iap_notification_service = Arc::new(IAPNotificationService::new(
+            db.clone(),
+            iap_service.clone(),
+            credits_service.clone(),
+        ));
 
         // Initialize authentication services
         let auth_config_arc = Arc::new(config_arc.auth.clone());
@@ -54,15 +73,44 @@ impl AppState {
             auth_config_arc.clone(),
         ));
 
+        let object_store = object_store::from_config(&config_arc.storage);
+        let image_service = Arc::new(ImageService::new(
+            db.clone(),
+            object_store.clone(),
+            Duration::from_secs(config_arc.storage.signed_url_ttl_secs),
+            ImageProcessor::new(&config_arc.images)?,
+        ));
+        let share_service = Arc::new(ShareService::new(
+            db.clone(),
+            object_store.clone(),
+            image_service.clone(),
+            config_arc.shares.clone(),
+            Duration::from_secs(config_arc.storage.signed_url_ttl_secs),
+            PreviewRenderer::new(&config_arc.shares)?,
+        ));
+        let sync_service = Arc::new(SyncService::new(db.clone()));
+        let export_service = Arc::new(ExportService::new(
+            db.clone(),
+            object_store.clone(),
+            config_arc.exports.clone(),
+            Duration::from_secs(config_arc.storage.signed_url_ttl_secs),
+        ));
+
         Ok(Self {
             db,
             redis,
             ai_service,
             iap_service,
+            iap_notification_service,
             quota_service,
             credits_service,
             jwt_service,
             auth_service,
+            object_store,
+            image_service,
+            share_service,
+            sync_service,
+            export_service,
             config: config_arc,
         })
     }
@@ -1,5 +1,9 @@
+use crate::models::{
+    ai::ImageResolution,
+    common::{IAPPlatform, PurchaseTier},
+};
 use serde::Deserialize;
-use std::env;
+use std::{collections::HashMap, env};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -10,12 +14,23 @@ pub struct Config {
     pub iap: IAPConfig,
     pub auth: AuthConfig,
     pub quota: QuotaConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub images: ImageProcessingConfig,
+    #[serde(default)]
+    pub shares: SharesConfig,
+    #[serde(default)]
+    pub exports: ExportConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    // Bearer token for the /internal endpoints; they are disabled when unset
+    #[serde(default)]
+    pub internal_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -31,6 +46,217 @@ pub struct RedisConfig {
 #[derive(Debug, Clone, Deserialize)]
 pub struct AIConfig {
     pub openrouter: OpenRouterConfig,
+    // Additional OpenAI-compatible backends, keyed by the name model entries refer to
+    #[serde(default)]
+    pub providers: HashMap<String, OpenAICompatibleConfig>,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+}
+
+/// Screening of prompts and generated candidates; disabled when it has no rules and no model
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModerationConfig {
+    #[serde(default)]
+    pub rules: Vec<ModerationRule>,
+    #[serde(default)]
+    pub model: Option<ModerationModelConfig>,
+}
+
+/// Local blocklist entry: case-insensitive regex patterns reported under `category`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationRule {
+    pub category: String,
+    #[serde(default)]
+    pub action: ModerationAction,
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    #[default]
+    Block, // Reject prompts, drop candidates
+    Mask, // Replace the matched text in candidates
+    Flag, // Only report the category in safety_flags
+}
+
+/// Classifier model asked which categories a text violates
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationModelConfig {
+    pub model: String,
+    #[serde(default = "default_model_provider")]
+    pub provider: String,
+    #[serde(default = "default_moderation_categories")]
+    pub categories: Vec<String>,
+    // Categories that block; the rest are only flagged
+    #[serde(default)]
+    pub block_categories: Vec<String>,
+}
+
+fn default_moderation_categories() -> Vec<String> {
+    [
+        "sexual",
+        "sexual_minors",
+        "violence",
+        "hate",
+        "harassment",
+        "self_harm",
+        "illegal",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Per-model circuit breaker settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitBreakerConfig {
+    // Consecutive availability failures that open a model's circuit
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub failure_threshold: u32,
+    // How long an open circuit fails fast before admitting a probe
+    #[serde(default = "default_circuit_open_duration_ms")]
+    pub open_duration_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_circuit_failure_threshold(),
+            open_duration_ms: default_circuit_open_duration_ms(),
+        }
+    }
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_open_duration_ms() -> u64 {
+    30000
+}
+
+/// Name of the built-in OpenRouter provider
+pub const OPENROUTER_PROVIDER: &str = "openrouter";
+
+/// Timeout of an image generation request, which is never retried
+pub const IMAGE_REQUEST_TIMEOUT_MS: u64 = 90000;
+
+/// Longest wait between two attempts of a provider request
+pub const RETRY_MAX_DELAY_MS: u64 = 5000;
+
+impl AIConfig {
+    /// Worst case for one generation: every model of the slowest chain timing out on every
+    /// attempt, followed by the moderation model doing the same
+    pub fn max_generation_ms(&self) -> u64 {
+        let model_ms = |provider: &str| {
+            let (timeout_ms, retry_attempts) = match self.providers.get(provider) {
+                Some(config) => (config.request_timeout_ms, config.retry_attempts),
+                None => (
+                    self.openrouter.request_timeout_ms,
+                    self.openrouter.retry_attempts,
+                ),
+            };
+            let retries = u64::from(retry_attempts);
+            timeout_ms * (retries + 1) + RETRY_MAX_DELAY_MS * retries
+        };
+
+        let tiers = &self.openrouter.model_tiers;
+        let text_ms = [&tiers.premium, &tiers.standard, &tiers.light]
+            .into_iter()
+            .map(|entry| entry.chain().map(|(provider, _)| model_ms(provider)).sum())
+            .max()
+            .unwrap_or(0);
+        let moderation_ms = self
+            .moderation
+            .model
+            .as_ref()
+            .map_or(0, |model| model_ms(&model.provider));
+
+        text_ms.max(IMAGE_REQUEST_TIMEOUT_MS) + moderation_ms
+    }
+
+    /// Reject model entries that name a provider which is not configured, and bad moderation rules
+    fn validate(&self) -> Result<(), String> {
+        if self.providers.contains_key(OPENROUTER_PROVIDER) {
+            return Err(format!(
+                "Provider name '{}' is reserved",
+                OPENROUTER_PROVIDER
+            ));
+        }
+
+        let tiers = &self.openrouter.model_tiers;
+        for (tier, entry) in [
+            ("premium", &tiers.premium),
+            ("standard", &tiers.standard),
+            ("light", &tiers.light),
+        ] {
+            for (provider, _) in entry.chain() {
+                if provider != OPENROUTER_PROVIDER && !self.providers.contains_key(provider) {
+                    return Err(format!(
+                        "Model tier {} uses unknown provider '{}'",
+                        tier, provider
+                    ));
+                }
+            }
+        }
+
+        for rule in &self.moderation.rules {
+            for pattern in &rule.patterns {
+                regex::RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| {
+                        format!("Invalid moderation pattern for {}: {}", rule.category, e)
+                    })?;
+            }
+        }
+        if let Some(model) = &self.moderation.model {
+            if model.provider != OPENROUTER_PROVIDER
+                && !self.providers.contains_key(&model.provider)
+            {
+                return Err(format!(
+                    "Moderation model uses unknown provider '{}'",
+                    model.provider
+                ));
+            }
+        }
+
+        // Only OpenRouter generates images
+        let images = &self.openrouter.image_models;
+        for (tier, entry) in [("free", &images.free), ("pro", &images.pro)] {
+            if entry.provider != OPENROUTER_PROVIDER {
+                return Err(format!(
+                    "Image model {} uses provider '{}', which cannot generate images",
+                    tier, entry.provider
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A directly hosted OpenAI-compatible endpoint such as vLLM or Ollama
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAICompatibleConfig {
+    pub api_base: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "default_provider_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    #[serde(default)]
+    pub retry_attempts: u8,
+}
+
+fn default_provider_request_timeout_ms() -> u64 {
+    60000
+}
+
+fn default_model_provider() -> String {
+    OPENROUTER_PROVIDER.to_string()
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -65,6 +291,15 @@ pub struct ImageModels {
 #[derive(Debug, Clone, Deserialize)]
 pub struct ImageModelConfig {
     pub model: String,
+    #[serde(default = "default_model_provider")]
+    pub provider: String,
+    // Model-specific image_config parameters per resolution, e.g. hd: { image_size: 2K };
+    // resolutions without an entry use the model's default size
+    #[serde(default)]
+    pub resolutions: HashMap<ImageResolution, serde_json::Map<String, serde_json::Value>>,
+    // Whether the model accepts an input image for image-to-image generation
+    #[serde(default)]
+    pub supports_reference_image: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -77,6 +312,29 @@ pub struct ModelTiers {
 #[derive(Debug, Clone, Deserialize)]
 pub struct ModelTierConfig {
     pub model: String,
+    #[serde(default = "default_model_provider")]
+    pub provider: String,
+    // Tried in order when the model before them is unavailable or its output is unusable
+    #[serde(default)]
+    pub fallbacks: Vec<ModelFallbackConfig>,
+}
+
+impl ModelTierConfig {
+    /// The primary model followed by its fallbacks, as (provider, model) pairs
+    pub fn chain(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once((self.provider.as_str(), self.model.as_str())).chain(
+            self.fallbacks
+                .iter()
+                .map(|fallback| (fallback.provider.as_str(), fallback.model.as_str())),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelFallbackConfig {
+    pub model: String,
+    #[serde(default = "default_model_provider")]
+    pub provider: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -105,6 +363,175 @@ pub struct TaskRouting {
 pub struct IAPConfig {
     pub apple_shared_secret: String,
     pub apple_environment: String,
+    pub apple_bundle_id: String, // Receipts must belong to this app bundle
+    // App Store Server API credentials (In-App Purchase key from App Store Connect)
+    #[serde(default)]
+    pub apple_issuer_id: Option<String>,
+    #[serde(default)]
+    pub apple_key_id: Option<String>,
+    #[serde(default)]
+    pub apple_private_key: Option<String>, // PKCS#8 PEM contents of the .p8 key
+    #[serde(default)]
+    pub apple_api_base: Option<String>, // Overrides the environment's App Store Server API URL
+    #[serde(default = "default_apple_root_ca_sha256")]
+    pub apple_root_ca_sha256: Vec<String>, // Trusted roots for JWS x5c chains
+    // Google Play Developer API (service account with access to the Play Console app)
+    #[serde(default)]
+    pub google_package_name: Option<String>, // Purchases must belong to this Android app
+    #[serde(default)]
+    pub google_service_account_email: Option<String>,
+    #[serde(default)]
+    pub google_service_account_private_key: Option<String>, // RSA PEM from the key JSON
+    #[serde(default = "default_google_api_base")]
+    pub google_api_base: String,
+    #[serde(default = "default_google_token_uri")]
+    pub google_token_uri: String,
+    // Real-time developer notifications (Pub/Sub push subscription with OIDC auth)
+    #[serde(default)]
+    pub google_pubsub_audience: Option<String>, // Audience set on the push subscription
+    #[serde(default)]
+    pub google_pubsub_service_account: Option<String>, // Service account that signs push tokens
+    #[serde(default = "default_google_oidc_jwks_url")]
+    pub google_oidc_jwks_url: String,
+    // Store products and what they grant (defaults to the launch catalog)
+    #[serde(default)]
+    pub products: ProductCatalog,
+}
+
+/// How a store product is sold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductKind {
+    Consumable,
+    Subscription,
+}
+
+/// A store product and what owning it grants
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductConfig {
+    pub product_id: String,
+    #[serde(default)]
+    pub platform: Option<IAPPlatform>, // None = same product ID on both stores
+    pub kind: ProductKind,
+    #[serde(default)]
+    pub credits: Option<i32>, // Extra credits granted by a consumable
+    #[serde(default = "default_product_tier")]
+    pub tier: PurchaseTier, // Account tier while the purchase is active
+    #[serde(default)]
+    pub monthly_allocation: Option<i32>, // Subscription credits per period (defaults to quota limit)
+}
+
+fn default_product_tier() -> PurchaseTier {
+    PurchaseTier::Free
+}
+
+/// Products the stores may report, resolved by platform and product ID
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct ProductCatalog {
+    products: Vec<ProductConfig>,
+}
+
+impl ProductCatalog {
+    pub fn new(products: Vec<ProductConfig>) -> Self {
+        Self { products }
+    }
+
+    /// Find a product, preferring a platform-specific entry over a shared one
+    pub fn find(&self, platform: IAPPlatform, product_id: &str) -> Option<&ProductConfig> {
+        let mut matches = self.products.iter().filter(|p| p.product_id == product_id);
+        matches
+            .clone()
+            .find(|p| p.platform == Some(platform))
+            .or_else(|| matches.find(|p| p.platform.is_none()))
+    }
+
+    /// Credits granted by a consumable product
+    pub fn credit_amount(&self, platform: IAPPlatform, product_id: &str) -> Option<i32> {
+        self.find(platform, product_id)
+            .filter(|p| p.kind == ProductKind::Consumable)
+            .and_then(|p| p.credits)
+    }
+
+    /// Account tier granted by a product; unknown products grant nothing
+    pub fn tier_for(&self, platform: IAPPlatform, product_id: Option<&str>) -> PurchaseTier {
+        product_id
+            .and_then(|id| self.find(platform, id))
+            .map(|p| p.tier)
+            .unwrap_or(PurchaseTier::Free)
+    }
+
+    /// Subscription credits per period granted by a subscription product
+    pub fn monthly_allocation(&self, platform: IAPPlatform, product_id: &str) -> Option<i32> {
+        self.find(platform, product_id)
+            .filter(|p| p.kind == ProductKind::Subscription)
+            .and_then(|p| p.monthly_allocation)
+    }
+
+    /// Reject catalogs that would grant nothing or resolve ambiguously
+    fn validate(&self) -> Result<(), String> {
+        for (i, product) in self.products.iter().enumerate() {
+            if product.kind == ProductKind::Consumable && product.credits.is_none_or(|c| c <= 0) {
+                return Err(format!(
+                    "Consumable product {} must grant a positive credit amount",
+                    product.product_id
+                ));
+            }
+            let duplicate = self.products[..i]
+                .iter()
+                .any(|p| p.product_id == product.product_id && p.platform == product.platform);
+            if duplicate {
+                return Err(format!("Product {} is listed twice", product.product_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ProductCatalog {
+    fn default() -> Self {
+        let consumable = |product_id: &str, credits: i32| ProductConfig {
+            product_id: product_id.to_string(),
+            platform: None,
+            kind: ProductKind::Consumable,
+            credits: Some(credits),
+            tier: PurchaseTier::Free,
+            monthly_allocation: None,
+        };
+        let pro_subscription = |product_id: &str| ProductConfig {
+            product_id: product_id.to_string(),
+            platform: None,
+            kind: ProductKind::Subscription,
+            credits: None,
+            tier: PurchaseTier::Pro,
+            monthly_allocation: None,
+        };
+
+        Self::new(vec![
+            consumable("com.talevonia.tale.credits.100", 100),
+            consumable("com.talevonia.tale.credits.500", 500),
+            consumable("com.talevonia.tale.credits.2000", 2000),
+            pro_subscription("com.talevonia.pro.monthly"),
+            pro_subscription("com.talevonia.pro.yearly"),
+            pro_subscription("com.talevonia.pro"),
+        ])
+    }
+}
+
+fn default_apple_root_ca_sha256() -> Vec<String> {
+    vec![crate::utils::apple_jws::APPLE_ROOT_CA_G3_SHA256.to_string()]
+}
+
+fn default_google_api_base() -> String {
+    "https://androidpublisher.googleapis.com".to_string()
+}
+
+fn default_google_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+fn default_google_oidc_jwks_url() -> String {
+    "https://www.googleapis.com/oauth2/v3/certs".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -121,6 +548,466 @@ pub struct QuotaConfig {
     // Limits are expressed in weighted quota units (see AIOperation::cost)
     pub free_text_daily_limit: i32,
     pub pro_text_daily_limit: i32,
+    #[serde(default = "default_hold_ttl_secs")]
+    pub hold_ttl_secs: u64, // How long a credit hold lives before the sweeper releases it
+    #[serde(default = "default_hold_sweep_interval_secs")]
+    pub hold_sweep_interval_secs: u64,
+    // Regenerations and variations allowed per generated image
+    #[serde(default = "default_free_image_regenerations")]
+    pub free_image_regenerations: u32,
+    #[serde(default = "default_pro_image_regenerations")]
+    pub pro_image_regenerations: u32,
+}
+
+impl QuotaConfig {
+    /// A hold must outlive the generation it pays for, or the sweeper releases it mid-flight
+    fn validate(&self, ai: &AIConfig) -> Result<(), String> {
+        let max_generation_secs = ai.max_generation_ms().div_ceil(1000);
+        if self.hold_ttl_secs <= max_generation_secs {
+            return Err(format!(
+                "quota.hold_ttl_secs ({}) must exceed the longest possible generation ({}s: \
+                 request timeouts and retries across the longest fallback chain)",
+                self.hold_ttl_secs, max_generation_secs
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_hold_ttl_secs() -> u64 {
+    600
+}
+
+fn default_hold_sweep_interval_secs() -> u64 {
+    60
+}
+
+fn default_free_image_regenerations() -> u32 {
+    2
+}
+
+fn default_pro_image_regenerations() -> u32 {
+    20
+}
+
+/// Object storage for generated images
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    #[serde(default = "default_signed_url_ttl_secs")]
+    pub signed_url_ttl_secs: u64, // Lifetime of URLs handed to clients
+    #[serde(default)]
+    pub local: LocalStorageConfig,
+    // Required when backend is s3
+    #[serde(default)]
+    pub s3: Option<S3StorageConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+/// Filesystem backend; objects are served by this API under /files
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalStorageConfig {
+    #[serde(default = "default_local_storage_root")]
+    pub root: String,
+    // Externally reachable prefix of the /files route
+    #[serde(default = "default_local_public_base_url")]
+    pub public_base_url: String,
+    // HMAC key for URL signatures; required by the local backend, must differ from auth.jwt_secret
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+}
+
+impl Default for LocalStorageConfig {
+    fn default() -> Self {
+        Self {
+            root: default_local_storage_root(),
+            public_base_url: default_local_public_base_url(),
+            signing_secret: None,
+        }
+    }
+}
+
+/// S3-compatible backend (AWS S3, MinIO, R2, ...); URLs are presigned with SigV4
+#[derive(Debug, Clone, Deserialize)]
+pub struct S3StorageConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    // Address the bucket in the path rather than the host name (MinIO)
+    #[serde(default = "default_s3_path_style")]
+    pub path_style: bool,
+}
+
+fn default_signed_url_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_local_storage_root() -> String {
+    "data/objects".to_string()
+}
+
+fn default_local_public_base_url() -> String {
+    "http://localhost:8080/files".to_string()
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_path_style() -> bool {
+    true
+}
+
+impl StorageConfig {
+    fn validate(&self, jwt_secret: &str) -> Result<(), String> {
+        if self.signed_url_ttl_secs == 0 {
+            return Err("storage.signed_url_ttl_secs must be positive".to_string());
+        }
+
+        match self.backend {
+            StorageBackend::Local => {
+                let secret = self.local.signing_secret.as_deref().unwrap_or("");
+                if secret.is_empty() {
+                    return Err(
+                        "storage.local.signing_secret is required when storage.backend is local"
+                            .to_string(),
+                    );
+                }
+                if secret == jwt_secret {
+                    return Err(
+                        "storage.local.signing_secret must differ from auth.jwt_secret".to_string(),
+                    );
+                }
+                reqwest::Url::parse(&self.local.public_base_url)
+                    .map(|_| ())
+                    .map_err(|e| format!("storage.local.public_base_url is invalid: {}", e))
+            }
+            StorageBackend::S3 => {
+                let s3 = self
+                    .s3
+                    .as_ref()
+                    .ok_or("storage.s3 is required when storage.backend is s3")?;
+                let endpoint = reqwest::Url::parse(&s3.endpoint)
+                    .map_err(|e| format!("storage.s3.endpoint is invalid: {}", e))?;
+                if endpoint.host_str().is_none() {
+                    return Err("storage.s3.endpoint must include a host".to_string());
+                }
+                if s3.bucket.is_empty() {
+                    return Err("storage.s3.bucket must not be empty".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Variants derived from each generated image, and the Free tier watermark
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImageProcessingConfig {
+    // Longest edge of the thumbnail variant
+    #[serde(default = "default_thumbnail_max_px")]
+    pub thumbnail_max_px: u32,
+    // Longest edge of the compressed variant; smaller images keep their size
+    #[serde(default = "default_compressed_max_px")]
+    pub compressed_max_px: u32,
+    #[serde(default)]
+    pub compressed_format: CompressedImageFormat,
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8, // 1-100
+    #[serde(default = "default_webp_quality")]
+    pub webp_quality: u8, // 1-100
+    // Applied to Free tier images before anything is stored; none when unset
+    #[serde(default)]
+    pub watermark: Option<WatermarkConfig>,
+}
+
+impl Default for ImageProcessingConfig {
+    fn default() -> Self {
+        Self {
+            thumbnail_max_px: default_thumbnail_max_px(),
+            compressed_max_px: default_compressed_max_px(),
+            compressed_format: CompressedImageFormat::default(),
+            jpeg_quality: default_jpeg_quality(),
+            webp_quality: default_webp_quality(),
+            watermark: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressedImageFormat {
+    #[default]
+    Jpeg,
+    Webp, // Lossy; keeps transparency
+}
+
+/// Overlay image drawn in the bottom-right corner
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatermarkConfig {
+    pub image_path: String,
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32, // 0.0-1.0, multiplied into the overlay's own alpha
+    // Overlay width as a fraction of the image width
+    #[serde(default = "default_watermark_width_ratio")]
+    pub width_ratio: f32,
+    // Distance from the edges as a fraction of the image width
+    #[serde(default = "default_watermark_margin_ratio")]
+    pub margin_ratio: f32,
+}
+
+fn default_thumbnail_max_px() -> u32 {
+    256
+}
+
+fn default_compressed_max_px() -> u32 {
+    1024
+}
+
+fn default_jpeg_quality() -> u8 {
+    80
+}
+
+fn default_webp_quality() -> u8 {
+    75
+}
+
+fn default_watermark_opacity() -> f32 {
+    0.6
+}
+
+fn default_watermark_width_ratio() -> f32 {
+    0.2
+}
+
+fn default_watermark_margin_ratio() -> f32 {
+    0.02
+}
+
+impl ImageProcessingConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.thumbnail_max_px == 0 || self.compressed_max_px == 0 {
+            return Err(
+                "images.thumbnail_max_px and compressed_max_px must be positive".to_string(),
+            );
+        }
+        if !(1..=100).contains(&self.jpeg_quality) {
+            return Err("images.jpeg_quality must be between 1 and 100".to_string());
+        }
+        if !(1..=100).contains(&self.webp_quality) {
+            return Err("images.webp_quality must be between 1 and 100".to_string());
+        }
+        if let Some(watermark) = &self.watermark {
+            if !(0.0..=1.0).contains(&watermark.opacity) {
+                return Err("images.watermark.opacity must be between 0 and 1".to_string());
+            }
+            if !(watermark.width_ratio > 0.0 && watermark.width_ratio <= 1.0) {
+                return Err("images.watermark.width_ratio must be in (0, 1]".to_string());
+            }
+            if !(0.0..0.5).contains(&watermark.margin_ratio) {
+                return Err("images.watermark.margin_ratio must be in [0, 0.5)".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Public web reader links
+#[derive(Debug, Clone, Deserialize)]
+pub struct SharesConfig {
+    // Share URLs are {public_base_url}/s/{slug}
+    #[serde(default = "default_shares_public_base_url")]
+    pub public_base_url: String,
+    // Unexpired shares a user may have at once
+    #[serde(default = "default_free_active_shares")]
+    pub free_active_limit: u32,
+    #[serde(default = "default_pro_active_shares")]
+    pub pro_active_limit: u32,
+    // Free shares always expire, after at most this long; Pro shares only when asked to
+    #[serde(default = "default_free_share_max_ttl_days")]
+    pub free_max_ttl_days: u32,
+    #[serde(default = "default_share_max_nodes")]
+    pub max_nodes: usize,
+    // Largest embedded (base64) image accepted per media item
+    #[serde(default = "default_share_max_media_bytes")]
+    pub max_media_bytes: usize,
+    // Largest embedded image accepted, in pixels; also caps cover decoding for preview cards
+    #[serde(default = "default_share_max_pixels")]
+    pub max_pixels: u64,
+    // Request body limit for POST /shares; embedded media arrives base64-encoded
+    #[serde(default = "default_share_max_request_bytes")]
+    pub max_request_bytes: usize,
+    // TrueType/OpenType font for the text on preview cards; cards show only the cover without it
+    #[serde(default)]
+    pub preview_font_path: Option<String>,
+}
+
+impl Default for SharesConfig {
+    fn default() -> Self {
+        Self {
+            public_base_url: default_shares_public_base_url(),
+            free_active_limit: default_free_active_shares(),
+            pro_active_limit: default_pro_active_shares(),
+            free_max_ttl_days: default_free_share_max_ttl_days(),
+            max_nodes: default_share_max_nodes(),
+            max_media_bytes: default_share_max_media_bytes(),
+            max_pixels: default_share_max_pixels(),
+            max_request_bytes: default_share_max_request_bytes(),
+            preview_font_path: None,
+        }
+    }
+}
+
+fn default_shares_public_base_url() -> String {
+    "http://localhost:8080".to_string()
+}
+
+fn default_free_active_shares() -> u32 {
+    3
+}
+
+fn default_pro_active_shares() -> u32 {
+    100
+}
+
+fn default_free_share_max_ttl_days() -> u32 {
+    30
+}
+
+fn default_share_max_nodes() -> usize {
+    200
+}
+
+fn default_share_max_media_bytes() -> usize {
+    5 * 1024 * 1024
+}
+
+fn default_share_max_pixels() -> u64 {
+    40_000_000
+}
+
+fn default_share_max_request_bytes() -> usize {
+    32 * 1024 * 1024
+}
+
+impl SharesConfig {
+    fn validate(&self) -> Result<(), String> {
+        reqwest::Url::parse(&self.public_base_url)
+            .map_err(|e| format!("shares.public_base_url is invalid: {}", e))?;
+        if self.free_max_ttl_days == 0 {
+            return Err("shares.free_max_ttl_days must be positive".to_string());
+        }
+        if self.max_pixels == 0 {
+            return Err("shares.max_pixels must be positive".to_string());
+        }
+        // Base64 inflates by a third
+        if self.max_request_bytes < self.max_media_bytes / 3 * 4 {
+            return Err(
+                "shares.max_request_bytes must fit one base64-encoded max_media_bytes item"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Story export to EPUB, Markdown and PDF
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportConfig {
+    #[serde(default = "default_export_max_nodes")]
+    pub max_nodes: usize,
+    // Largest illustration accepted, decoded
+    #[serde(default = "default_export_max_illustration_bytes")]
+    pub max_illustration_bytes: usize,
+    // Request body limit for POST /export; illustrations arrive base64-encoded
+    #[serde(default = "default_export_max_request_bytes")]
+    pub max_request_bytes: usize,
+    // Exports with more text and image bytes than this run as a background job
+    #[serde(default = "default_export_sync_max_bytes")]
+    pub sync_max_bytes: usize,
+    // Background exports a user may have running at once
+    #[serde(default = "default_export_max_running_jobs")]
+    pub max_running_jobs: u64,
+    // Finished jobs and their files are deleted after this long
+    #[serde(default = "default_export_retention_secs")]
+    pub retention_secs: u64,
+    // Jobs still running after this long are assumed lost (e.g. to a restart) and marked failed
+    #[serde(default = "default_export_job_timeout_secs")]
+    pub job_timeout_secs: u64,
+    #[serde(default = "default_export_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            max_nodes: default_export_max_nodes(),
+            max_illustration_bytes: default_export_max_illustration_bytes(),
+            max_request_bytes: default_export_max_request_bytes(),
+            sync_max_bytes: default_export_sync_max_bytes(),
+            max_running_jobs: default_export_max_running_jobs(),
+            retention_secs: default_export_retention_secs(),
+            job_timeout_secs: default_export_job_timeout_secs(),
+            sweep_interval_secs: default_export_sweep_interval_secs(),
+        }
+    }
+}
+
+fn default_export_max_nodes() -> usize {
+    500
+}
+
+fn default_export_max_illustration_bytes() -> usize {
+    5 * 1024 * 1024
+}
+
+fn default_export_max_request_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_export_sync_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_export_max_running_jobs() -> u64 {
+    3
+}
+
+fn default_export_retention_secs() -> u64 {
+    24 * 3600
+}
+
+fn default_export_job_timeout_secs() -> u64 {
+    900
+}
+
+fn default_export_sweep_interval_secs() -> u64 {
+    300
+}
+
+impl ExportConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_nodes == 0 {
+            return Err("exports.max_nodes must be positive".to_string());
+        }
+        if self.retention_secs == 0 || self.job_timeout_secs == 0 {
+            return Err(
+                "exports.retention_secs and exports.job_timeout_secs must be positive".to_string(),
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Config {
@@ -137,6 +1024,7 @@ impl Config {
                 "server.port",
                 env::var("PORT").ok().and_then(|v| v.parse::<u16>().ok()),
             )?
+            .set_override_option("server.internal_token", env::var("INTERNAL_API_TOKEN").ok())?
             // Database
             .set_override_option("database.url", env::var("DATABASE_URL").ok())?
             // Redis
@@ -201,6 +1089,37 @@ impl Config {
                 env::var("APPLE_SHARED_SECRET").ok(),
             )?
             .set_override_option("iap.apple_environment", env::var("APPLE_ENVIRONMENT").ok())?
+            .set_override_option("iap.apple_bundle_id", env::var("APPLE_BUNDLE_ID").ok())?
+            .set_override_option("iap.apple_issuer_id", env::var("APPLE_ISSUER_ID").ok())?
+            .set_override_option("iap.apple_key_id", env::var("APPLE_KEY_ID").ok())?
+            .set_override_option("iap.apple_private_key", env::var("APPLE_PRIVATE_KEY").ok())?
+            .set_override_option("iap.apple_api_base", env::var("APPLE_API_BASE").ok())?
+            .set_override_option(
+                "iap.google_package_name",
+                env::var("GOOGLE_PACKAGE_NAME").ok(),
+            )?
+            .set_override_option(
+                "iap.google_service_account_email",
+                env::var("GOOGLE_SERVICE_ACCOUNT_EMAIL").ok(),
+            )?
+            .set_override_option(
+                "iap.google_service_account_private_key",
+                env::var("GOOGLE_SERVICE_ACCOUNT_PRIVATE_KEY").ok(),
+            )?
+            .set_override_option("iap.google_api_base", env::var("GOOGLE_API_BASE").ok())?
+            .set_override_option("iap.google_token_uri", env::var("GOOGLE_TOKEN_URI").ok())?
+            .set_override_option(
+                "iap.google_pubsub_audience",
+                env::var("GOOGLE_PUBSUB_AUDIENCE").ok(),
+            )?
+            .set_override_option(
+                "iap.google_pubsub_service_account",
+                env::var("GOOGLE_PUBSUB_SERVICE_ACCOUNT").ok(),
+            )?
+            .set_override_option(
+                "iap.google_oidc_jwks_url",
+                env::var("GOOGLE_OIDC_JWKS_URL").ok(),
+            )?
             // Auth
             .set_override_option("auth.jwt_secret", env::var("JWT_SECRET").ok())?
             .set_override_option(
@@ -235,8 +1154,57 @@ impl Config {
                     .ok()
                     .and_then(|v| v.parse::<i32>().ok()),
             )?
+            .set_override_option(
+                "quota.hold_ttl_secs",
+                env::var("CREDIT_HOLD_TTL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok()),
+            )?
+            .set_override_option(
+                "quota.hold_sweep_interval_secs",
+                env::var("CREDIT_HOLD_SWEEP_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok()),
+            )?
+            .set_override_option("storage.backend", env::var("STORAGE_BACKEND").ok())?
+            .set_override_option(
+                "storage.local.signing_secret",
+                env::var("STORAGE_SIGNING_SECRET").ok(),
+            )?
+            .set_override_option(
+                "shares.public_base_url",
+                env::var("SHARES_PUBLIC_BASE_URL").ok(),
+            )?
             .build()?;
 
-        config.try_deserialize()
+        let config: Self = config.try_deserialize()?;
+        config
+            .iap
+            .products
+            .validate()
+            .map_err(config::ConfigError::Message)?;
+        config.ai.validate().map_err(config::ConfigError::Message)?;
+        config
+            .quota
+            .validate(&config.ai)
+            .map_err(config::ConfigError::Message)?;
+        config
+            .storage
+            .validate(&config.auth.jwt_secret)
+            .map_err(config::ConfigError::Message)?;
+        config
+            .images
+            .validate()
+            .map_err(config::ConfigError::Message)?;
+        config
+            .shares
+            .validate()
+            .map_err(config::ConfigError::Message)?;
+        config
+            .exports
+            .validate()
+            .map_err(config::ConfigError::Message)?;
+
+        Ok(config)
     }
 }
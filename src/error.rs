@@ -20,6 +20,21 @@ pub enum ApiError {
     #[error("AI provider error: {0}")]
     AIProvider(String),
 
+    // Rate limited, overloaded, timed out or returned an unusable response; another model may succeed
+    #[error("AI provider unavailable: {0}")]
+    AIProviderUnavailable(String),
+
+    // Recent failures tripped the model's circuit breaker, so the call was not attempted
+    #[error("AI provider circuit open: {0}")]
+    AIProviderCircuitOpen(String),
+
+    #[error("Content rejected: {0}")]
+    ContentRejected(String),
+
+    // The feature is limited to Pro subscribers
+    #[error("Pro required: {0}")]
+    ProRequired(String),
+
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
@@ -45,9 +60,10 @@ pub enum ApiError {
     Internal(#[from] anyhow::Error),
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, error_code, message) = match self {
+impl ApiError {
+    /// HTTP status and error body reported to clients for this error
+    pub fn error_response(&self) -> (StatusCode, ErrorResponse) {
+        let (status, error_code, message) = match *self {
             ApiError::Database(ref e) => {
                 tracing::error!("Database error: {:?}", e);
                 (
@@ -64,7 +80,7 @@ impl IntoResponse for ApiError {
                 "INVALID_RECEIPT",
                 msg.clone(),
             ),
-            ApiError::AIProvider(ref msg) => {
+            ApiError::AIProvider(ref msg) | ApiError::AIProviderUnavailable(ref msg) => {
                 tracing::error!("AI provider error: {}", msg);
                 (
                     StatusCode::FAILED_DEPENDENCY,
@@ -72,6 +88,23 @@ impl IntoResponse for ApiError {
                     "AI service temporarily unavailable".to_string(),
                 )
             }
+            ApiError::AIProviderCircuitOpen(ref msg) => {
+                tracing::warn!("AI provider circuit open: {}", msg);
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "AI_PROVIDER_CIRCUIT_OPEN",
+                    "AI service temporarily unavailable, please retry shortly".to_string(),
+                )
+            }
+            ApiError::ContentRejected(ref msg) => {
+                tracing::info!("Content rejected: {}", msg);
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "CONTENT_REJECTED",
+                    "The request or its result violates the content policy".to_string(),
+                )
+            }
+            ApiError::ProRequired(ref msg) => (StatusCode::FORBIDDEN, "PRO_REQUIRED", msg.clone()),
             ApiError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
             ApiError::NotFound(ref msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
             ApiError::Unauthorized(ref msg) => {
@@ -103,7 +136,13 @@ impl IntoResponse for ApiError {
             }
         };
 
-        let body = ErrorResponse::new(error_code, message, None);
+        (status, ErrorResponse::new(error_code, message, None))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.error_response();
 
         (status, Json(body)).into_response()
     }
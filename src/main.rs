@@ -39,6 +39,12 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Initialized application state");
 
+    // Release credit holds abandoned by failed or interrupted requests
+    state.quota_service.clone().spawn_hold_sweeper();
+
+    // Fail lost export jobs and delete expired export files
+    state.export_service.clone().spawn_job_sweeper();
+
     // Create router
     let app = create_router(state);
 
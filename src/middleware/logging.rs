@@ -1,11 +1,21 @@
 use axum::{
-    body::{to_bytes, Body, Bytes},
-    extract::Request,
-    http::StatusCode,
+    body::{to_bytes, Body, HttpBody},
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use std::time::Instant;
+use std::{convert::Infallible, time::Instant};
+
+/// Response header carrying the request ID, so clients can quote it to support
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Largest body buffered for logging; bigger ones pass through without being logged
+const LOG_BODY_LIMIT: usize = 1024 * 1024;
+
+/// ID assigned to each request by logging_middleware
+#[derive(Debug, Clone, Copy)]
+pub struct RequestId(pub uuid::Uuid);
 
 /// Middleware that logs request and response bodies
 pub async fn logging_middleware(request: Request, next: Next) -> Response {
@@ -15,21 +25,28 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
     let start = Instant::now();
 
     // Extract and log request body
-    let (parts, body) = request.into_parts();
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(RequestId(request_id));
 
-    // Read the request body (limit to 1MB to prevent memory issues)
-    let bytes = match to_bytes(body, 1024 * 1024).await {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            tracing::error!(request_id = %request_id, "Failed to read request body: {}", e);
-            return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response();
+    // Large or unsized uploads (shares, exports) go to the handler, whose body limit applies
+    let (body, truncated_request) = match body.size_hint().upper() {
+        Some(len) if len as usize <= LOG_BODY_LIMIT => {
+            let bytes = match to_bytes(body, LOG_BODY_LIMIT).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    tracing::error!(request_id = %request_id, "Failed to read request body: {}", e);
+                    return (StatusCode::BAD_REQUEST, "Failed to read request body")
+                        .into_response();
+                }
+            };
+            // Log request with body (truncate if too long)
+            let truncated = truncate_body(&String::from_utf8_lossy(&bytes), 2000);
+            (Body::from(bytes), truncated)
         }
+        Some(len) => (body, format!("[not logged, {} bytes]", len)),
+        None => (body, "[not logged, streamed]".to_string()),
     };
 
-    // Log request with body (truncate if too long)
-    let request_body = String::from_utf8_lossy(&bytes);
-    let truncated_request = truncate_body(&request_body, 2000);
-
     tracing::info!(
         request_id = %request_id,
         method = %method,
@@ -39,21 +56,50 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
     );
 
     // Reconstruct the request with the body
-    let request = Request::from_parts(parts, Body::from(bytes));
+    let request = Request::from_parts(parts, body);
 
     // Call the next middleware/handler
     let response = next.run(request).await;
 
     // Extract response status before consuming body
     let status = response.status();
-    let (parts, body) = response.into_parts();
+    let (mut parts, body) = response.into_parts();
+    if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+        parts.headers.insert(REQUEST_ID_HEADER, value);
+    }
 
-    // Read the response body (limit to 1MB)
-    let bytes = match to_bytes(body, 1024 * 1024).await {
+    // Event streams, downloads and large bodies are passed through as produced; buffering would
+    // hold streams back and cut off anything over the logged size
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let is_loggable = (content_type.starts_with("application/json")
+        || (content_type.starts_with("text/") && !content_type.starts_with("text/event-stream")))
+        && body
+            .size_hint()
+            .upper()
+            .is_some_and(|len| len as usize <= LOG_BODY_LIMIT);
+    if !is_loggable {
+        tracing::info!(
+            request_id = %request_id,
+            method = %method,
+            uri = %uri,
+            status = %status.as_u16(),
+            latency_ms = %start.elapsed().as_millis(),
+            content_type = %content_type,
+            "← Response (body not logged)"
+        );
+        return Response::from_parts(parts, body);
+    }
+
+    // Read the response body
+    let bytes = match to_bytes(body, LOG_BODY_LIMIT).await {
         Ok(bytes) => bytes,
         Err(e) => {
             tracing::error!(request_id = %request_id, "Failed to read response body: {}", e);
-            Bytes::new()
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
 
@@ -76,6 +122,27 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
     Response::from_parts(parts, Body::from(bytes))
 }
 
+/// Axum extractor for the request ID
+///
+/// Falls back to a fresh ID on routes not wrapped by logging_middleware.
+impl<S> FromRequestParts<S> for RequestId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestId>()
+            .copied()
+            .unwrap_or_else(|| RequestId(uuid::Uuid::new_v4())))
+    }
+}
+
 /// Truncate body for logging, adding ellipsis if truncated
 fn truncate_body(body: &str, max_len: usize) -> String {
     let body = body.trim();
@@ -10,4 +10,4 @@ pub use jwt_auth::{jwt_auth_middleware, UserIdentity};
 pub use rate_limit::create_rate_limiter;
 
 // Export logging middleware
-pub use logging::logging_middleware;
+pub use logging::{logging_middleware, RequestId};
@@ -146,6 +146,7 @@ fn default_avoid_hard_end() -> bool {
 #[serde(rename_all = "camelCase")]
 pub struct AITextContinueResponse {
     pub candidates: Vec<TextCandidate>,
+    pub model: String, // Model that answered, which may be a fallback
 }
 
 #[derive(Debug, Serialize)]
@@ -187,6 +188,22 @@ impl ImageStyle {
             Self::Illustration => "illustration",
         }
     }
+
+    /// Inverse of `as_str`, for styles read back from stored generations
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            Self::Storybook,
+            Self::Anime,
+            Self::DigitalArt,
+            Self::Realistic,
+            Self::Watercolor,
+            Self::InkDrawing,
+            Self::ClassicalIllustration,
+            Self::Illustration,
+        ]
+        .into_iter()
+        .find(|style| style.as_str() == value)
+    }
 }
 
 /// AI Image Generate Request
@@ -240,9 +257,11 @@ pub struct ImageParams {
     #[serde(default = "default_aspect_ratio")]
     #[validate(length(min = 3, max = 10))]
     pub aspect_ratio: String,
-    #[serde(default = "default_resolution")]
-    #[validate(length(min = 3, max = 20))]
-    pub resolution: String,
+    #[serde(default)]
+    pub resolution: ImageResolution,
+    // Fixed seed for reproducible output on models that accept one
+    #[serde(default)]
+    pub seed: Option<u32>,
 }
 
 impl Default for ImageParams {
@@ -250,7 +269,8 @@ impl Default for ImageParams {
         Self {
             style: Some(ImageStyle::default()),
             aspect_ratio: default_aspect_ratio(),
-            resolution: default_resolution(),
+            resolution: ImageResolution::default(),
+            seed: None,
         }
     }
 }
@@ -259,8 +279,52 @@ fn default_aspect_ratio() -> String {
     "3:4".to_string()
 }
 
-fn default_resolution() -> String {
-    "medium".to_string()
+/// Requested image resolution; `hd` is a Pro feature
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageResolution {
+    #[default]
+    Medium,
+    #[serde(alias = "high")]
+    Hd,
+}
+
+impl ImageResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageResolution::Medium => "medium",
+            ImageResolution::Hd => "hd",
+        }
+    }
+
+    /// Inverse of `as_str`, for resolutions read back from stored generations
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "medium" => Some(ImageResolution::Medium),
+            "hd" | "high" => Some(ImageResolution::Hd),
+            _ => None,
+        }
+    }
+}
+
+/// Regenerate or vary a previous generation; unset fields keep the source's values
+#[derive(Debug, Default, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AIImageRegenerateRequest {
+    #[serde(default)]
+    pub style: Option<ImageStyle>,
+    #[serde(default)]
+    #[validate(length(min = 3, max = 10))]
+    pub aspect_ratio: Option<String>,
+    #[serde(default)]
+    pub resolution: Option<ImageResolution>,
+    // A fresh random seed when unset
+    #[serde(default)]
+    pub seed: Option<u32>,
+    // Send the source image to the model as an image-to-image reference;
+    // defaults to false for regenerations and true for variations
+    #[serde(default)]
+    pub use_reference_image: Option<bool>,
 }
 
 /// AI Image Generate Response
@@ -273,10 +337,114 @@ pub struct AIImageGenerateResponse {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GeneratedImage {
-    pub data: String, // base64-encoded image data
+    pub id: uuid::Uuid,
+    pub url: String, // Signed URL; re-issue via GET /ai/images/{id} once expired
+    #[serde(with = "time::serde::rfc3339")]
+    pub url_expires_at: time::OffsetDateTime,
     pub mime_type: String,
     pub width: u32,
     pub height: u32,
+    pub variants: Vec<ImageVariantLink>, // Smaller renditions of the same image
+    pub source_image_id: Option<uuid::Uuid>, // Set for regenerations and variations
+}
+
+/// Signed URL of a stored rendition ("thumbnail" or "compressed")
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVariantLink {
+    pub name: String,
+    pub url: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub url_expires_at: time::OffsetDateTime,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Outcome of an image generation attempt
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AIImageStatus {
+    Success,
+    Failed,
+}
+
+impl AIImageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AIImageStatus::Success => "success",
+            AIImageStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Query parameters for GET /api/v1/ai/images
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AIImageHistoryQuery {
+    #[serde(default = "default_history_page")]
+    #[validate(range(min = 1))]
+    pub page: u64,
+    #[serde(default = "default_history_page_size")]
+    #[validate(range(min = 1, max = 100))]
+    pub page_size: u64,
+    pub story_title: Option<String>, // Exact match
+    pub status: Option<AIImageStatus>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<time::OffsetDateTime>, // Inclusive
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<time::OffsetDateTime>, // Exclusive
+}
+
+fn default_history_page() -> u64 {
+    1
+}
+
+fn default_history_page_size() -> u64 {
+    20
+}
+
+/// A recorded image generation attempt
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AIImageRecord {
+    pub id: uuid::Uuid,
+    pub story_title: String,
+    pub node_summary: Option<String>,
+    pub style: String,
+    pub resolution: String,
+    pub aspect_ratio: Option<String>, // Absent for images generated before it was recorded
+    pub seed: Option<u32>,
+    pub source_image_id: Option<uuid::Uuid>,
+    pub status: String, // "success" or "failed"
+    pub error_message: Option<String>,
+    // Signed URL; absent for failed attempts and images generated before storage existed
+    pub url: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub url_expires_at: Option<time::OffsetDateTime>,
+    pub mime_type: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub variants: Vec<ImageVariantLink>,
+    pub credits_used: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: time::OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AIImageResponse {
+    pub image: AIImageRecord,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AIImageHistoryResponse {
+    pub images: Vec<AIImageRecord>,
+    pub page: u64,
+    pub page_size: u64,
+    pub total_entries: u64,
+    pub total_pages: u64,
 }
 
 /// AI Text Edit Mode
@@ -362,6 +530,7 @@ fn default_edit_candidates() -> u8 {
 pub struct AITextEditResponse {
     pub mode: AITextEditMode,
     pub candidates: Vec<TextEditCandidate>,
+    pub model: String, // Model that answered, which may be a fallback
 }
 
 #[derive(Debug, Serialize)]
@@ -373,6 +542,44 @@ pub struct TextEditCandidate {
     pub safety_flags: Vec<String>,
 }
 
+/// Text delta event sent by the streaming text endpoints
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AITextStreamDelta {
+    pub candidate_index: usize,
+    pub delta: String,
+}
+
+/// State of a model's circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,   // Calls go through
+    Open,     // Calls fail fast
+    HalfOpen, // One probe call decides whether to close
+}
+
+/// Health of one model as seen by its circuit breaker
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCircuitStatus {
+    pub provider: String,
+    pub model: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub opened_at: Option<time::OffsetDateTime>,
+    pub retry_in_ms: Option<u64>, // Until a probe is admitted
+    pub last_error: Option<String>,
+}
+
+/// AI provider health, as reported by the internal status endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AIProviderHealthResponse {
+    pub circuits: Vec<ModelCircuitStatus>,
+}
+
 /// AI Text Summarize Request
 #[derive(Debug, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
@@ -398,6 +605,7 @@ pub struct NodeToSummarize {
 #[serde(rename_all = "camelCase")]
 pub struct AITextSummarizeResponse {
     pub summaries: Vec<NodeSummary>,
+    pub model: String, // Model that answered, which may be a fallback
 }
 
 #[derive(Debug, Serialize)]
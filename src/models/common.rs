@@ -90,4 +90,33 @@ impl AIOperation {
             AIOperation::Summarize => 1,      // Batch summarization (up to 20 nodes)
         }
     }
+
+    /// Stable name recorded in the credits ledger
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AIOperation::ContinueProse => "continue_prose",
+            AIOperation::ContinueIdeas => "continue_ideas",
+            AIOperation::EditExpand => "edit_expand",
+            AIOperation::EditShorten => "edit_shorten",
+            AIOperation::EditRewrite => "edit_rewrite",
+            AIOperation::EditFixGrammar => "edit_fix_grammar",
+            AIOperation::ImageGenerate => "image_generate",
+            AIOperation::Summarize => "summarize",
+        }
+    }
+
+    /// Parse a name produced by as_str
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "continue_prose" => Some(AIOperation::ContinueProse),
+            "continue_ideas" => Some(AIOperation::ContinueIdeas),
+            "edit_expand" => Some(AIOperation::EditExpand),
+            "edit_shorten" => Some(AIOperation::EditShorten),
+            "edit_rewrite" => Some(AIOperation::EditRewrite),
+            "edit_fix_grammar" => Some(AIOperation::EditFixGrammar),
+            "image_generate" => Some(AIOperation::ImageGenerate),
+            "summarize" => Some(AIOperation::Summarize),
+            _ => None,
+        }
+    }
 }
@@ -2,7 +2,7 @@ use entity::sea_orm_active_enums::AccountTier;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use super::common::IAPPlatform;
+use super::common::{AIOperation, IAPPlatform};
 
 /// Request to record a credit purchase
 #[derive(Debug, Deserialize, Validate)]
@@ -22,8 +22,9 @@ pub struct CreditPurchaseRequest {
     #[serde(with = "time::serde::rfc3339")]
     pub purchase_date: time::OffsetDateTime,
 
-    #[validate(length(max = 100000))]
-    pub receipt: Option<String>,
+    /// Store receipt used to verify the purchase server-side
+    #[validate(length(min = 10, max = 100000))]
+    pub receipt: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -70,7 +71,8 @@ pub struct ExtraCreditsInfo {
 pub struct CreditsQuotaInfo {
     pub subscription_credits: SubscriptionCreditsInfo,
     pub extra_credits: ExtraCreditsInfo,
-    pub total_credits: i32,
+    pub total_credits: i32, // Available now; excludes held credits
+    pub held_credits: i32,  // Reserved by operations still in progress
 }
 
 /// Trimmed quota summary without purchase breakdown (used in purchase response)
@@ -80,6 +82,7 @@ pub struct CreditsQuotaSummary {
     pub subscription_credits: SubscriptionCreditsInfo,
     pub extra_credits_total: i32,
     pub total_credits: i32,
+    pub held_credits: i32,
 }
 
 #[derive(Debug, Serialize)]
@@ -89,16 +92,75 @@ pub struct CreditsQuotaResponse {
     pub subscription_credits: SubscriptionCreditsInfo,
     pub extra_credits: i32,
     pub total_credits: i32,
+    pub held_credits: i32,
+}
+
+/// Handle to the credits held for one AI operation
+///
+/// Records how much came from each source and which usage date was charged, so a
+/// release reverses exactly this charge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreditReservation {
+    pub id: uuid::Uuid,
+    pub operation: AIOperation,
+    pub cost: i32,
+    pub subscription_credits: i32,
+    pub extra_credits: i32,
+    pub usage_date: time::Date,
+}
+
+/// Request details recorded with a usage ledger entry
+#[derive(Debug, Clone, Default)]
+pub struct UsageContext {
+    pub request_id: Option<String>,
+    pub model: Option<String>,
+    pub error: Option<String>,
 }
 
-impl CreditPurchaseRequest {
-    /// Extract credit amount from product ID
-    pub fn extract_credit_amount(&self) -> Option<i32> {
-        match self.product_id.as_str() {
-            "com.talevonia.tale.credits.100" => Some(100),
-            "com.talevonia.tale.credits.500" => Some(500),
-            "com.talevonia.tale.credits.2000" => Some(2000),
-            _ => None,
-        }
-    }
+/// Query parameters for GET /api/v1/credits/history
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditHistoryQuery {
+    #[serde(default = "default_history_page")]
+    #[validate(range(min = 1))]
+    pub page: u64,
+    #[serde(default = "default_history_page_size")]
+    #[validate(range(min = 1, max = 100))]
+    pub page_size: u64,
+}
+
+fn default_history_page() -> u64 {
+    1
+}
+
+fn default_history_page_size() -> u64 {
+    20
+}
+
+/// Single usage ledger entry (charge or refund)
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditHistoryEntry {
+    pub id: uuid::Uuid,
+    pub entry_type: String, // "charge" or "refund"
+    pub operation: String,
+    pub cost: i32,
+    pub subscription_credits: i32,
+    pub extra_credits: i32,
+    pub model: Option<String>,
+    pub request_id: Option<String>,
+    pub outcome: String, // "succeeded", "failed" or "refunded"
+    pub charge_id: Option<uuid::Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: time::OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditHistoryResponse {
+    pub entries: Vec<CreditHistoryEntry>,
+    pub page: u64,
+    pub page_size: u64,
+    pub total_entries: u64,
+    pub total_pages: u64,
 }
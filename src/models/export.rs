@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::ai::PathNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Epub,     // EPUB 3 package
+    Markdown, // Zip of story.md and its images
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Epub => "epub",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    /// Inverse of `as_str`, for formats read back from stored jobs
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            ExportFormat::Epub,
+            ExportFormat::Markdown,
+            ExportFormat::Pdf,
+        ]
+        .into_iter()
+        .find(|format| format.as_str() == value)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Epub => "application/epub+zip",
+            ExportFormat::Markdown => "application/zip",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Epub => "epub",
+            ExportFormat::Markdown => "zip",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// POST /api/v1/export: a linear story path to render as a book
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(max = 200))]
+    pub author: Option<String>,
+    #[serde(default = "default_language")]
+    #[validate(length(min = 2, max = 10))]
+    pub language: String,
+    // Nodes in reading order, one chapter each; the per-request maximum is configurable
+    #[validate(length(min = 1), nested)]
+    pub nodes: Vec<PathNode>,
+    // The first one in reading order is also the cover
+    #[serde(default)]
+    #[validate(length(max = 100), nested)]
+    pub illustrations: Vec<ExportIllustration>,
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportIllustration {
+    pub node_index: usize, // Position in `nodes`
+    // Base64-encoded image bytes, or a data URL
+    pub data: String,
+    #[validate(length(max = 300))]
+    pub alt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ExportJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportJobStatus::Running => "running",
+            ExportJobStatus::Completed => "completed",
+            ExportJobStatus::Failed => "failed",
+        }
+    }
+
+    /// Inverse of `as_str`, for statuses read back from stored jobs
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            ExportJobStatus::Running,
+            ExportJobStatus::Completed,
+            ExportJobStatus::Failed,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+}
+
+/// Background export, returned when it is queued and when it is polled
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJobResponse {
+    pub id: uuid::Uuid,
+    pub status: ExportJobStatus,
+    pub format: ExportFormat,
+    pub file_name: String,
+    pub size_bytes: Option<i64>,
+    // Signed, short-lived link to the file once completed; poll again for a fresh one
+    pub download_url: Option<String>,
+    pub error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: time::OffsetDateTime,
+    // The file is deleted after this
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<time::OffsetDateTime>,
+}
@@ -13,6 +13,216 @@ pub struct IAPVerification {
     pub valid_until: Option<time::OffsetDateTime>,
     pub is_family_shared: bool,
     pub subscription_status: Option<String>, // "active", "expired", "grace_period", etc.
+    pub is_acknowledged: bool, // Google purchases must be acknowledged within 3 days; Apple is always true
+    pub linked_purchase_token: Option<String>, // Google: token this purchase replaced (upgrade/resubscribe)
+}
+
+/// Internal structure for a verified consumable (credit pack) transaction
+#[derive(Debug, Clone)]
+pub struct VerifiedConsumable {
+    pub transaction_id: String,
+    pub original_transaction_id: Option<String>,
+    pub product_id: String,
+    pub purchase_date: time::OffsetDateTime,
+    /// Transaction exactly as returned by the store (persisted as receipt_data)
+    pub store_payload: serde_json::Value,
+}
+
+// =============================================================================
+// App Store Server API (StoreKit 2)
+// =============================================================================
+
+/// Decoded claims of an App Store signed transaction (JWSTransactionDecodedPayload)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JWSTransactionDecodedPayload {
+    pub transaction_id: String,
+    pub original_transaction_id: String,
+    pub bundle_id: String,
+    pub product_id: String,
+    pub purchase_date: i64, // Milliseconds since epoch
+    #[serde(default)]
+    pub expires_date: Option<i64>,
+    #[serde(rename = "type")]
+    pub transaction_type: String, // "Consumable", "Auto-Renewable Subscription", ...
+    #[serde(default)]
+    pub in_app_ownership_type: Option<String>, // "PURCHASED" or "FAMILY_SHARED"
+    #[serde(default)]
+    pub revocation_date: Option<i64>,
+    #[serde(default)]
+    pub revocation_reason: Option<i32>,
+    #[serde(default)]
+    pub quantity: Option<i32>,
+    #[serde(default)]
+    pub app_account_token: Option<String>,
+    #[serde(default)]
+    pub environment: Option<String>, // "Sandbox" or "Production"
+    pub signed_date: i64,
+}
+
+/// Decoded claims of an App Store signed renewal info (JWSRenewalInfoDecodedPayload)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JWSRenewalInfoDecodedPayload {
+    pub original_transaction_id: String,
+    #[serde(default)]
+    pub auto_renew_product_id: Option<String>,
+    #[serde(default)]
+    pub auto_renew_status: Option<i32>, // 1 = will renew, 0 = turned off
+    #[serde(default)]
+    pub is_in_billing_retry_period: Option<bool>,
+    #[serde(default)]
+    pub grace_period_expires_date: Option<i64>,
+    #[serde(default)]
+    pub expiration_intent: Option<i32>,
+    pub signed_date: i64,
+}
+
+/// Response of Get Transaction Info
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInfoResponse {
+    pub signed_transaction_info: String,
+}
+
+/// Response of Get All Subscription Statuses
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionStatusesResponse {
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub bundle_id: Option<String>,
+    #[serde(default)]
+    pub data: Vec<SubscriptionGroupStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionGroupStatus {
+    pub subscription_group_identifier: String,
+    #[serde(default)]
+    pub last_transactions: Vec<LastTransactionStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastTransactionStatus {
+    pub status: i32, // 1 active, 2 expired, 3 billing retry, 4 grace period, 5 revoked
+    pub original_transaction_id: String,
+    pub signed_transaction_info: String,
+    #[serde(default)]
+    pub signed_renewal_info: Option<String>,
+}
+
+/// App Store Server Notifications V2 request body
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleNotificationRequest {
+    #[validate(length(min = 10))]
+    pub signed_payload: String,
+}
+
+/// Decoded claims of an App Store server notification (ResponseBodyV2DecodedPayload)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleNotificationPayload {
+    pub notification_type: String, // "DID_RENEW", "EXPIRED", "REFUND", ...
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(rename = "notificationUUID")]
+    pub notification_uuid: String,
+    #[serde(default)]
+    pub data: Option<AppleNotificationData>,
+    #[serde(default)]
+    pub version: Option<String>,
+    pub signed_date: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleNotificationData {
+    #[serde(default)]
+    pub bundle_id: Option<String>,
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub signed_transaction_info: Option<String>,
+    #[serde(default)]
+    pub signed_renewal_info: Option<String>,
+    #[serde(default)]
+    pub status: Option<i32>, // Same codes as LastTransactionStatus::status
+}
+
+// =============================================================================
+// Google Play Real-time Developer Notifications
+// =============================================================================
+
+/// Pub/Sub push request body
+#[derive(Debug, Deserialize, Validate)]
+pub struct PubSubPushRequest {
+    #[validate(nested)]
+    pub message: PubSubMessage,
+    pub subscription: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PubSubMessage {
+    #[validate(length(min = 1))]
+    pub data: String, // Base64-encoded DeveloperNotification
+    #[serde(alias = "message_id")]
+    #[validate(length(min = 1))]
+    pub message_id: String,
+    #[serde(default, alias = "publish_time")]
+    pub publish_time: Option<String>,
+}
+
+/// Decoded Pub/Sub message data (DeveloperNotification)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleDeveloperNotification {
+    #[serde(default)]
+    pub version: Option<String>,
+    pub package_name: String,
+    #[serde(default)]
+    pub event_time_millis: Option<String>,
+    #[serde(default)]
+    pub subscription_notification: Option<GoogleSubscriptionNotification>,
+    #[serde(default)]
+    pub one_time_product_notification: Option<GoogleOneTimeProductNotification>,
+    #[serde(default)]
+    pub voided_purchase_notification: Option<GoogleVoidedPurchaseNotification>,
+    #[serde(default)]
+    pub test_notification: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleSubscriptionNotification {
+    pub notification_type: i32, // 1 RECOVERED, 2 RENEWED, 3 CANCELED, ... 13 EXPIRED
+    pub purchase_token: String,
+    #[serde(default)]
+    pub subscription_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleOneTimeProductNotification {
+    pub notification_type: i32, // 1 PURCHASED, 2 CANCELED
+    pub purchase_token: String,
+    #[serde(default)]
+    pub sku: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleVoidedPurchaseNotification {
+    pub purchase_token: String,
+    pub order_id: String,
+    pub product_type: i32, // 1 subscription, 2 one-time
+    #[serde(default)]
+    pub refund_type: Option<i32>, // 1 full refund, 2 partial (quantity-based) refund
 }
 
 // =============================================================================
@@ -4,4 +4,7 @@ pub mod auth;
 pub mod common;
 pub mod credit_events_ext; // Extension methods for entity::credits_events
 pub mod credits;
+pub mod export;
 pub mod iap;
+pub mod share;
+pub mod sync;
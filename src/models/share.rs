@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Snapshot of a linear reading path to publish as a web reader link
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(max = 2000))]
+    pub summary: Option<String>,
+    // Nodes in reading order; the per-request maximum is configurable
+    #[validate(length(min = 1), nested)]
+    pub nodes: Vec<ShareNodeInput>,
+    // Free shares expire after at most the configured number of days even when unset
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareNodeInput {
+    #[validate(length(max = 200))]
+    pub title: Option<String>,
+    #[validate(length(max = 20000))]
+    pub content: String,
+    #[serde(default)]
+    #[validate(length(max = 4), nested)]
+    pub media: Vec<ShareMediaInput>,
+}
+
+/// An illustration, given as exactly one of `imageId`, `url` or `data`
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_media_source"))]
+pub struct ShareMediaInput {
+    // One of the caller's generated images
+    pub image_id: Option<uuid::Uuid>,
+    // Publicly reachable http(s) image
+    #[validate(url, length(max = 2000))]
+    pub url: Option<String>,
+    // Base64-encoded image bytes, stored with the share
+    pub data: Option<String>,
+    #[validate(length(max = 300))]
+    pub alt: Option<String>,
+}
+
+fn validate_media_source(media: &ShareMediaInput) -> Result<(), ValidationError> {
+    let sources = [
+        media.image_id.is_some(),
+        media.url.is_some(),
+        media.data.is_some(),
+    ];
+    if sources.into_iter().filter(|set| *set).count() != 1 {
+        return Err(ValidationError::new("media_source")
+            .with_message("exactly one of imageId, url or data is required".into()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResponse {
+    pub id: uuid::Uuid,
+    pub slug: String,
+    pub share_url: String,
+    pub title: String,
+    pub node_count: usize,
+    pub view_count: i64,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<time::OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: time::OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareListResponse {
+    pub shares: Vec<ShareResponse>, // Newest first, expired ones included
+    pub active_count: u64,
+    pub active_limit: u32,
+}
+
+/// oEmbed request; `url` must be a share link
+#[derive(Debug, Deserialize)]
+pub struct OEmbedQuery {
+    pub url: String,
+    pub format: Option<String>, // Only json is supported
+    pub maxwidth: Option<u32>,
+    pub maxheight: Option<u32>,
+}
+
+/// oEmbed 1.0 "rich" response; field names follow the spec
+#[derive(Debug, Serialize)]
+pub struct OEmbedResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub version: &'static str,
+    pub title: String,
+    pub provider_name: &'static str,
+    pub provider_url: String,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_height: Option<u32>,
+    pub cache_age: u32,
+}
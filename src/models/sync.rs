@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+// Rows accepted per table in one push
+const MAX_PUSH_ROWS: u64 = 500;
+
+/// GET /api/v1/sync/pull query
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullQuery {
+    // Cursor returned by the previous pull; 0 fetches everything
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub cursor: i64,
+    #[serde(default = "default_pull_limit")]
+    #[validate(range(min = 1, max = 1000))]
+    pub limit: u64,
+}
+
+fn default_pull_limit() -> u64 {
+    500
+}
+
+/// Rows of the synced story model, grouped by table
+///
+/// Pushed by clients with `version` set to the version each edit is based on (0 for new rows);
+/// pulled with the current server version.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncChanges {
+    #[validate(length(max = MAX_PUSH_ROWS), nested)]
+    pub stories: Vec<SyncStory>,
+    #[validate(length(max = MAX_PUSH_ROWS), nested)]
+    pub story_nodes: Vec<SyncStoryNode>,
+    #[validate(length(max = MAX_PUSH_ROWS), nested)]
+    pub node_media: Vec<SyncNodeMedia>,
+    #[validate(length(max = MAX_PUSH_ROWS), nested)]
+    pub media_assets: Vec<SyncMediaAsset>,
+}
+
+impl SyncChanges {
+    pub fn is_empty(&self) -> bool {
+        self.stories.is_empty()
+            && self.story_nodes.is_empty()
+            && self.node_media.is_empty()
+            && self.media_assets.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStory {
+    pub id: Uuid,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub version: i64,
+    #[serde(default)]
+    pub deleted: bool, // Tombstone; the row's last fields are kept
+    #[validate(length(max = 500))]
+    pub title: Option<String>,
+    #[validate(length(max = 5000))]
+    pub description: Option<String>,
+    pub cover_media_id: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStoryNode {
+    pub id: Uuid,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub version: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    pub story_id: Uuid,
+    pub parent_node_id: Option<Uuid>, // None for the root
+    #[serde(default)]
+    pub sibling_order: i32,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub depth: i32,
+    #[validate(length(max = 1000))]
+    pub summary: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 100000))]
+    pub content: String,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub tags: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncNodeMedia {
+    pub id: Uuid,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub version: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    pub story_id: Uuid,
+    pub node_id: Option<Uuid>, // None for story-level media such as the cover
+    #[validate(length(min = 1, max = 50))]
+    pub media_type: String, // image | video | live_photo | ...
+    #[validate(length(min = 1, max = 50))]
+    pub source: String, // user | ai | import | ...
+    #[validate(length(max = 2000))]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub order_index: i32,
+    #[serde(default)]
+    pub is_primary: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncMediaAsset {
+    pub id: Uuid,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub version: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    pub media_id: Uuid,
+    #[validate(length(min = 1, max = 50))]
+    pub asset_type: String, // original | still_image | motion_video | thumbnail | ...
+    #[validate(length(min = 1, max = 100))]
+    pub mime_type: String,
+    #[validate(length(max = 1000))]
+    pub local_path: Option<String>,
+    #[validate(length(max = 2000))]
+    pub remote_url: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_ms: Option<i32>,
+    pub size_bytes: Option<i64>,
+    pub extra_meta: Option<serde_json::Value>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullResponse {
+    pub changes: SyncChanges, // Tombstones included
+    pub cursor: i64,          // Pass to the next pull
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncEntity {
+    Story,
+    StoryNode,
+    NodeMedia,
+    MediaAsset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncConflictReason {
+    // The row changed on the server since the version the edit is based on
+    VersionMismatch,
+    // The story, parent node or media the row points at is not on the server
+    MissingReference,
+    // The id belongs to another account
+    IdInUse,
+}
+
+/// Outcome of a push: every row is applied as sent, merged, or reported as a conflict
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushResponse {
+    pub applied: Vec<SyncApplied>,
+    pub merged: Vec<SyncMerge>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncApplied {
+    pub entity: SyncEntity,
+    pub id: Uuid,
+    pub version: i64, // Base future edits of the row on this version
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub entity: SyncEntity,
+    pub id: Uuid,
+    pub reason: SyncConflictReason,
+    // Server copy of the row on a version mismatch, if it exists
+    pub server: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMergeOutcome {
+    // Both edits combined into a new version of the node
+    Merged,
+    // The edits overlap; the pushed edit became a new sibling node
+    Branched,
+}
+
+/// A story node edit based on an outdated version that was merged rather than rejected
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncMerge {
+    pub id: Uuid,
+    pub outcome: SyncMergeOutcome,
+    // Server copy of the node after the push: the merged edit, or unchanged when branched
+    pub node: SyncStoryNode,
+    // New sibling holding the pushed edit when branched
+    pub branch: Option<SyncStoryNode>,
+}
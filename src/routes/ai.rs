@@ -1,30 +1,43 @@
-use axum::{extract::State, Json};
-use base64::Engine;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
 use tracing::instrument;
 
 use crate::{
     app_state::AppState,
+    config::TaskRouting,
     error::{ApiError, AppJson, Result},
-    middleware::UserIdentity,
+    middleware::{RequestId, UserIdentity},
     models::{
         ai::{
-            AIImageGenerateRequest, AIImageGenerateResponse, AITextContinueRequest,
-            AITextContinueResponse, AITextEditMode, AITextEditRequest, AITextEditResponse,
-            AITextIdeasRequest, AITextSummarizeRequest, AITextSummarizeResponse, GeneratedImage,
+            AIImageGenerateRequest, AIImageGenerateResponse, AIImageHistoryQuery,
+            AIImageHistoryResponse, AIImageRegenerateRequest, AIImageResponse,
+            AITextContinueRequest, AITextContinueResponse, AITextEditMode, AITextEditRequest,
+            AITextEditResponse, AITextIdeasRequest, AITextSummarizeRequest,
+            AITextSummarizeResponse, GeneratedImage, GenerationParams, ImageParams,
+            ImageResolution, ImageStoryContext, ImageStyle, NodeContext, PathNode,
         },
         common::AIOperation,
+        credits::{CreditReservation, UsageContext},
     },
+    services::{ai_provider::ReferenceImage, image_processing::ImageInfo, AIService},
 };
 use entity::ai_image_generation;
 use entity::sea_orm_active_enums::AccountTier;
 use sea_orm::{ActiveModelTrait, Set};
 use uuid::Uuid;
 
+/// Attempts to commit a credit hold before falling back to the sweeper
+const COMMIT_HOLD_ATTEMPTS: u32 = 3;
+const COMMIT_HOLD_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
 /// POST /api/v1/ai/text/continue
 #[instrument(skip(state, identity, request))]
 pub async fn text_continue(
     State(state): State<AppState>,
     identity: UserIdentity,
+    request_id: RequestId,
     AppJson(request): AppJson<AITextContinueRequest>,
 ) -> Result<Json<AITextContinueResponse>> {
     // Validate request
@@ -33,49 +46,27 @@ pub async fn text_continue(
         .validate()
         .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
 
-    // Ensure at least one node has content or summary
-    let has_content = request.path_nodes.iter().any(|node| {
-        !node.content.is_empty() || node.summary.as_ref().is_some_and(|s| !s.is_empty())
-    });
-    if !has_content {
-        return Err(ApiError::BadRequest(
-            "At least one node must have content or summary".to_string(),
-        ));
-    }
+    ensure_path_has_content(&request.path_nodes)?;
 
     let tier = &identity.account_tier;
-
-    // Validate word limits before charging credits
-    let max_words_limit = match tier {
-        AccountTier::Pro => state.config.ai.openrouter.ai_routing.r#continue.max_words_pro,
-        AccountTier::Free => state.config.ai.openrouter.ai_routing.r#continue.max_words_free,
-    };
-    if request.generation_params.min_words > request.generation_params.max_words {
-        return Err(ApiError::BadRequest(
-            "minWords must be <= maxWords".to_string(),
-        ));
-    }
-    if request.generation_params.max_words > max_words_limit {
-        return Err(ApiError::BadRequest(format!(
-            "maxWords exceeds tier limit (max {})",
-            max_words_limit
-        )));
-    }
-
-    // Atomically check and increment quota with weighted cost
-    state
+    validate_word_limits(
+        &state.config.ai.openrouter.ai_routing.r#continue,
+        tier,
+        &request.generation_params,
+    )?;
+
+    // Hold credits for the operation; committed on success, released on failure
+    let reservation = state
         .quota_service
-        .check_and_increment_quota_weighted(identity.user_id, tier, AIOperation::ContinueProse)
+        .reserve(identity.user_id, tier, AIOperation::ContinueProse)
         .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
 
     // Generate prose continuations using JSON-structured output
-    let generation_params = crate::models::ai::GenerationParams {
-        num_candidates: if *tier == AccountTier::Pro { 3 } else { 1 },
-        min_words: request.generation_params.min_words,
-        max_words: request.generation_params.max_words,
-        tone: request.generation_params.tone.clone(),
-        avoid_hard_end: request.generation_params.avoid_hard_end,
-    };
+    let generation_params = effective_generation_params(&request.generation_params, tier);
 
     let generation_result = state
         .ai_service
@@ -88,30 +79,33 @@ pub async fn text_continue(
         )
         .await;
 
-    // Handle errors with credit refund
+    // Handle errors by releasing the credit hold
     match generation_result {
-        Ok(mut candidates) => {
+        Ok((mut candidates, model)) => {
+            usage.model = Some(model.clone());
+            commit_hold(&state, &identity, &reservation, &usage).await;
             if *tier != AccountTier::Pro {
                 candidates.truncate(1);
             }
-            Ok(Json(AITextContinueResponse { candidates }))
+            Ok(Json(AITextContinueResponse { candidates, model }))
         }
         Err(err) => {
-            // Refund credits after failed generation
-            if let Err(refund_err) = state
+            // Release the credit hold after failed generation
+            usage.error = Some(err.to_string());
+            if let Err(release_err) = state
                 .quota_service
-                .refund_quota_weighted(identity.user_id, tier, AIOperation::ContinueProse)
+                .release(identity.user_id, tier, &reservation, &usage)
                 .await
             {
                 tracing::error!(
                     user_id = %identity.user_id,
-                    error = %refund_err,
-                    "Failed to refund credits after text generation failure - user may have lost credits"
+                    error = %release_err,
+                    "Failed to release credit hold after text generation failure; the sweeper releases it on expiry"
                 );
             } else {
                 tracing::info!(
                     user_id = %identity.user_id,
-                    "Successfully refunded credits after text generation failure"
+                    "Released credit hold after text generation failure"
                 );
             }
             Err(err)
@@ -124,6 +118,7 @@ pub async fn text_continue(
 pub async fn image_generate(
     State(state): State<AppState>,
     identity: UserIdentity,
+    request_id: RequestId,
     AppJson(request): AppJson<AIImageGenerateRequest>,
 ) -> Result<Json<AIImageGenerateResponse>> {
     // Validate request
@@ -137,14 +132,174 @@ pub async fn image_generate(
     //     .validate_has_content()
     //     .map_err(|msg| ApiError::BadRequest(msg.to_string()))?;
 
+    // Refuse Pro-only parameters before holding credits or recording an attempt
+    AIService::check_image_params(&request.image_params, &identity.account_tier)?;
+
+    let job = ImageJob {
+        story_context: request.story_context,
+        node: request.node,
+        params: request.image_params,
+        reference: None,
+        source_image_id: None,
+    };
+    generate_image(&state, &identity, &request_id, job).await
+}
+
+/// POST /api/v1/ai/image/{id}/regenerate
+#[instrument(skip(state, identity, request))]
+pub async fn image_regenerate(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    request_id: RequestId,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<AIImageRegenerateRequest>,
+) -> Result<Json<AIImageGenerateResponse>> {
+    derive_image(&state, &identity, &request_id, id, request, false).await
+}
+
+/// POST /api/v1/ai/image/{id}/variations
+#[instrument(skip(state, identity, request))]
+pub async fn image_variations(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    request_id: RequestId,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<AIImageRegenerateRequest>,
+) -> Result<Json<AIImageGenerateResponse>> {
+    derive_image(&state, &identity, &request_id, id, request, true).await
+}
+
+/// Inputs of one image generation
+struct ImageJob {
+    story_context: ImageStoryContext,
+    node: NodeContext,
+    params: ImageParams,
+    reference: Option<ReferenceImage>,
+    source_image_id: Option<Uuid>, // First generation of the lineage, for derived images
+}
+
+/// Generate again from a stored generation's prompt context, within the tier's regeneration limit
+async fn derive_image(
+    state: &AppState,
+    identity: &UserIdentity,
+    request_id: &RequestId,
+    id: Uuid,
+    request: AIImageRegenerateRequest,
+    use_reference_by_default: bool,
+) -> Result<Json<AIImageGenerateResponse>> {
+    use validator::Validate;
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let tier = &identity.account_tier;
+    let source = state.image_service.find(identity.user_id, id).await?;
+
+    // Regenerations of regenerations count against the original
+    let source_image_id = source.source_image_id.unwrap_or(source.id);
+    let limit = match tier {
+        AccountTier::Pro => state.config.quota.pro_image_regenerations,
+        AccountTier::Free => state.config.quota.free_image_regenerations,
+    };
+    let params = ImageParams {
+        style: request.style.or_else(|| ImageStyle::parse(&source.style)),
+        aspect_ratio: request
+            .aspect_ratio
+            .or_else(|| source.aspect_ratio.clone())
+            .unwrap_or_else(|| ImageParams::default().aspect_ratio),
+        resolution: request
+            .resolution
+            .or_else(|| ImageResolution::parse(&source.resolution))
+            .unwrap_or_default(),
+        seed: request.seed,
+    };
+    AIService::check_image_params(&params, tier)?;
+
+    let reference = if request
+        .use_reference_image
+        .unwrap_or(use_reference_by_default)
+    {
+        state.ai_service.check_reference_image(tier)?;
+        Some(state.image_service.reference_image(&source).await?)
+    } else {
+        None
+    };
+
+    // Claimed last, so only a failed generation has to hand the slot back
+    if !state
+        .image_service
+        .claim_derived(source_image_id, limit)
+        .await?
+    {
+        return Err(match tier {
+            AccountTier::Free => ApiError::ProRequired(format!(
+                "Free accounts can regenerate an image {} times; upgrade to Pro for more",
+                limit
+            )),
+            AccountTier::Pro => ApiError::QuotaExceeded(format!(
+                "This image has reached its limit of {} regenerations",
+                limit
+            )),
+        });
+    }
+
+    let job = ImageJob {
+        story_context: ImageStoryContext {
+            title: source.story_title,
+            // Not recorded; the image prompt does not use them
+            language: "en".to_string(),
+            genre: None,
+            tone: None,
+            setting: None,
+        },
+        node: NodeContext {
+            summary: source.node_summary,
+            content: source.node_content,
+            tags: source
+                .node_tags
+                .and_then(|tags| serde_json::from_value(tags).ok())
+                .unwrap_or_default(),
+        },
+        params,
+        reference,
+        source_image_id: Some(source_image_id),
+    };
+    let result = generate_image(state, identity, request_id, job).await;
+
+    // Only successful generations keep their slot
+    if result.is_err() {
+        if let Err(release_err) = state.image_service.release_derived(source_image_id).await {
+            tracing::error!(
+                source_image_id = %source_image_id,
+                error = %release_err,
+                "Failed to release regeneration slot after generation failure"
+            );
+        }
+    }
+    result
+}
+
+/// Generate, store and record an image, holding credits for the duration
+async fn generate_image(
+    state: &AppState,
+    identity: &UserIdentity,
+    request_id: &RequestId,
+    job: ImageJob,
+) -> Result<Json<AIImageGenerateResponse>> {
     let tier = &identity.account_tier;
     let start_time = std::time::Instant::now();
 
-    // Atomically check and increment quota with weighted cost
-    state
+    // Hold credits for the operation; committed on success, released on failure
+    let reservation = state
         .quota_service
-        .check_and_increment_quota_weighted(identity.user_id, tier, AIOperation::ImageGenerate)
+        .reserve(identity.user_id, tier, AIOperation::ImageGenerate)
         .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
+
+    let image_id = Uuid::new_v4();
 
     // Attempt image generation with error tracking
     let generation_result = async {
@@ -152,18 +307,32 @@ pub async fn image_generate(
         let (image_bytes, image_metadata) = state
             .ai_service
             .generate_image(
-                &request.story_context,
-                &request.node,
-                &request.image_params,
+                &job.story_context,
+                &job.node,
+                &job.params,
+                job.reference.clone(),
                 tier,
             )
             .await?;
 
-        // Encode to base64
-        let base64_data = base64::engine::general_purpose::STANDARD.encode(&image_bytes);
-        let file_size = image_bytes.len();
+        // Persist the image and its renditions; clients fetch them through signed URLs
+        let info = ImageInfo {
+            mime_type: image_metadata.mime_type.clone(),
+            width: image_metadata.width,
+            height: image_metadata.height,
+        };
+        let stored = state
+            .image_service
+            .store(
+                identity.user_id,
+                image_id,
+                image_bytes,
+                info,
+                *tier == AccountTier::Free,
+            )
+            .await?;
 
-        Ok::<_, ApiError>((base64_data, file_size, image_metadata))
+        Ok::<_, ApiError>((stored, image_metadata))
     }
     .await;
 
@@ -171,45 +340,43 @@ pub async fn image_generate(
 
     // Handle result and save record
     match generation_result {
-        Ok((base64_data, file_size, image_metadata)) => {
+        Ok((stored, image_metadata)) => {
+            let ai_provider = format!("{}/{}", image_metadata.provider, image_metadata.model);
+            usage.model = Some(image_metadata.model);
+            let signed = state.image_service.signed_url(&stored.storage_key);
+
             // Save successful generation record
             let generation_record = ai_image_generation::ActiveModel {
-                id: Set(Uuid::new_v4()),
-                user_id: Set(identity.user_id),
-                story_title: Set(request.story_context.title.clone()),
-                node_summary: Set(request.node.summary.clone()),
-                node_content: Set(request.node.content.clone()),
-                style: Set(request
-                    .image_params
-                    .style
-                    .map(|s| s.as_str().to_string())
-                    .unwrap_or_else(|| "illustration".to_string())),
-                resolution: Set(request.image_params.resolution.clone()),
-                image_url: Set(String::new()), // No longer storing image URL
-                temp_url: Set(None),
-                temp_url_expires_at: Set(None),
-                width: Set(image_metadata.width as i32),
-                height: Set(image_metadata.height as i32),
-                file_size_bytes: Set(Some(file_size as i32)),
-                credits_used: Set(10),
-                generation_time_ms: Set(Some(generation_time_ms)),
-                ai_provider: Set(Some("openai-dalle3".to_string())),
+                image_url: Set(state.image_service.url(&stored.storage_key)),
+                temp_url: Set(Some(signed.url.clone())),
+                temp_url_expires_at: Set(Some(signed.expires_at)),
+                width: Set(stored.info.width as i32),
+                height: Set(stored.info.height as i32),
+                file_size_bytes: Set(Some(stored.file_size as i32)),
                 status: Set("success".to_string()),
-                error_message: Set(None),
-                created_at: Set(time::OffsetDateTime::now_utc()),
+                storage_key: Set(Some(stored.storage_key.clone())),
+                variants: Set(Some(stored.variants_json())),
+                ..image_record(identity, image_id, &job, ai_provider, generation_time_ms)
             };
 
-            generation_record
-                .insert(&state.db)
-                .await
-                .map_err(ApiError::Database)?;
+            if let Err(db_err) = generation_record.insert(&state.db).await {
+                // Without its record the stored image can never be served
+                state.image_service.discard(&stored).await;
+                return Err(ApiError::Database(db_err));
+            }
+
+            commit_hold(state, identity, &reservation, &usage).await;
 
             Ok(Json(AIImageGenerateResponse {
                 image: GeneratedImage {
-                    data: base64_data,
-                    mime_type: image_metadata.mime_type,
-                    width: image_metadata.width,
-                    height: image_metadata.height,
+                    id: image_id,
+                    url: signed.url,
+                    url_expires_at: signed.expires_at,
+                    variants: state.image_service.variant_links(&stored.variants),
+                    mime_type: stored.info.mime_type,
+                    width: stored.info.width,
+                    height: stored.info.height,
+                    source_image_id: job.source_image_id,
                 },
             }))
         }
@@ -219,29 +386,16 @@ pub async fn image_generate(
             tracing::error!("Image generation failed: {}", error_msg);
 
             let failed_record = ai_image_generation::ActiveModel {
-                id: Set(Uuid::new_v4()),
-                user_id: Set(identity.user_id),
-                story_title: Set(request.story_context.title.clone()),
-                node_summary: Set(request.node.summary.clone()),
-                node_content: Set(request.node.content.clone()),
-                style: Set(request
-                    .image_params
-                    .style
-                    .map(|s| s.as_str().to_string())
-                    .unwrap_or_else(|| "illustration".to_string())),
-                resolution: Set(request.image_params.resolution.clone()),
-                image_url: Set(String::new()),
-                temp_url: Set(None),
-                temp_url_expires_at: Set(None),
-                width: Set(0),
-                height: Set(0),
-                file_size_bytes: Set(None),
-                credits_used: Set(10), // Credits were deducted but will be refunded
-                generation_time_ms: Set(Some(generation_time_ms)),
-                ai_provider: Set(Some("openai-dalle3".to_string())),
                 status: Set("failed".to_string()),
                 error_message: Set(Some(error_msg.clone())),
-                created_at: Set(time::OffsetDateTime::now_utc()),
+                // Image models have no fallbacks, so this is the model that failed
+                ..image_record(
+                    identity,
+                    image_id,
+                    &job,
+                    state.ai_service.image_model_name(tier),
+                    generation_time_ms,
+                )
             };
 
             // Save failed record (don't fail if this fails)
@@ -249,22 +403,24 @@ pub async fn image_generate(
                 tracing::error!("Failed to save error record: {}", db_err);
             }
 
-            // Refund credits after failed generation
-            if let Err(refund_err) = state
+            // Release the credit hold after failed generation
+            usage.error = Some(error_msg.clone());
+            if let Err(release_err) = state
                 .quota_service
-                .refund_quota_weighted(identity.user_id, tier, AIOperation::ImageGenerate)
+                .release(identity.user_id, tier, &reservation, &usage)
                 .await
             {
                 tracing::error!(
                     user_id = %identity.user_id,
-                    error = %refund_err,
-                    "Failed to refund credits after generation failure - user may have lost credits"
+                    error = %release_err,
+                    "Failed to release credit hold after generation failure; the sweeper releases it on expiry"
                 );
                 // Don't fail the request - user already saw generation error
             } else {
                 tracing::info!(
                     user_id = %identity.user_id,
-                    "Successfully refunded 10 credits after generation failure"
+                    "Released {} held credits after generation failure",
+                    reservation.cost
                 );
             }
 
@@ -273,11 +429,97 @@ pub async fn image_generate(
     }
 }
 
+/// Generation record for a job, with nothing stored yet
+///
+/// `ai_provider` names the "provider/model" that served (or failed) the request.
+fn image_record(
+    identity: &UserIdentity,
+    image_id: Uuid,
+    job: &ImageJob,
+    ai_provider: String,
+    generation_time_ms: i32,
+) -> ai_image_generation::ActiveModel {
+    ai_image_generation::ActiveModel {
+        id: Set(image_id),
+        user_id: Set(identity.user_id),
+        story_title: Set(job.story_context.title.clone()),
+        node_summary: Set(job.node.summary.clone()),
+        node_content: Set(job.node.content.clone()),
+        style: Set(job
+            .params
+            .style
+            .map(|s| s.as_str().to_string())
+            .unwrap_or_else(|| "illustration".to_string())),
+        resolution: Set(job.params.resolution.as_str().to_string()),
+        image_url: Set(String::new()),
+        temp_url: Set(None),
+        temp_url_expires_at: Set(None),
+        width: Set(0),
+        height: Set(0),
+        file_size_bytes: Set(None),
+        credits_used: Set(10), // Released again if generation fails
+        generation_time_ms: Set(Some(generation_time_ms)),
+        ai_provider: Set(Some(ai_provider)),
+        status: Set("failed".to_string()),
+        error_message: Set(None),
+        created_at: Set(time::OffsetDateTime::now_utc()),
+        storage_key: Set(None),
+        variants: Set(None),
+        aspect_ratio: Set(Some(job.params.aspect_ratio.clone())),
+        node_tags: Set(Some(serde_json::json!(job.node.tags))),
+        seed: Set(job.params.seed.map(i64::from)),
+        source_image_id: Set(job.source_image_id),
+        derived_count: Set(0),
+    }
+}
+
+/// GET /api/v1/ai/images
+#[instrument(skip(state, identity))]
+pub async fn list_images(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Query(query): Query<AIImageHistoryQuery>,
+) -> Result<Json<AIImageHistoryResponse>> {
+    use validator::Validate;
+    query
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ApiError::BadRequest(
+                "from must not be later than to".to_string(),
+            ));
+        }
+    }
+
+    let history = state
+        .image_service
+        .list_images(identity.user_id, &query)
+        .await?;
+
+    Ok(Json(history))
+}
+
+/// GET /api/v1/ai/images/{id}
+///
+/// Re-issues the signed URL of one of the caller's images once it nears expiry.
+#[instrument(skip(state, identity))]
+pub async fn get_image(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AIImageResponse>> {
+    let image = state.image_service.get_image(identity.user_id, id).await?;
+
+    Ok(Json(AIImageResponse { image }))
+}
+
 /// POST /api/v1/ai/text/edit
 #[instrument(skip(state, identity, request))]
 pub async fn text_edit(
     State(state): State<AppState>,
     identity: UserIdentity,
+    request_id: RequestId,
     AppJson(request): AppJson<AITextEditRequest>,
 ) -> Result<Json<AITextEditResponse>> {
     // Validate request
@@ -296,11 +538,15 @@ pub async fn text_edit(
         AITextEditMode::FixGrammar => AIOperation::EditFixGrammar,
     };
 
-    // Atomically check and increment quota with weighted cost
-    state
+    // Hold credits for the operation; committed on success, released on failure
+    let reservation = state
         .quota_service
-        .check_and_increment_quota_weighted(identity.user_id, tier, operation)
+        .reserve(identity.user_id, tier, operation)
         .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
 
     // Generate edit candidates
     let edit_params = crate::models::ai::EditParams {
@@ -322,33 +568,37 @@ pub async fn text_edit(
         )
         .await;
 
-    // Handle errors with credit refund
+    // Handle errors by releasing the credit hold
     match generation_result {
-        Ok(mut candidates) => {
+        Ok((mut candidates, model)) => {
+            usage.model = Some(model.clone());
+            commit_hold(&state, &identity, &reservation, &usage).await;
             if *tier != AccountTier::Pro {
                 candidates.truncate(1);
             }
             Ok(Json(AITextEditResponse {
                 mode: request.mode,
                 candidates,
+                model,
             }))
         }
         Err(err) => {
-            // Refund credits after failed generation
-            if let Err(refund_err) = state
+            // Release the credit hold after failed generation
+            usage.error = Some(err.to_string());
+            if let Err(release_err) = state
                 .quota_service
-                .refund_quota_weighted(identity.user_id, tier, operation)
+                .release(identity.user_id, tier, &reservation, &usage)
                 .await
             {
                 tracing::error!(
                     user_id = %identity.user_id,
-                    error = %refund_err,
-                    "Failed to refund credits after text edit failure - user may have lost credits"
+                    error = %release_err,
+                    "Failed to release credit hold after text edit failure; the sweeper releases it on expiry"
                 );
             } else {
                 tracing::info!(
                     user_id = %identity.user_id,
-                    "Successfully refunded credits after text edit failure"
+                    "Released credit hold after text edit failure"
                 );
             }
             Err(err)
@@ -361,6 +611,7 @@ pub async fn text_edit(
 pub async fn text_ideas(
     State(state): State<AppState>,
     identity: UserIdentity,
+    request_id: RequestId,
     AppJson(request): AppJson<AITextIdeasRequest>,
 ) -> Result<Json<AITextContinueResponse>> {
     // Validate request
@@ -369,49 +620,27 @@ pub async fn text_ideas(
         .validate()
         .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
 
-    // Ensure at least one node has content or summary
-    let has_content = request.path_nodes.iter().any(|node| {
-        !node.content.is_empty() || node.summary.as_ref().is_some_and(|s| !s.is_empty())
-    });
-    if !has_content {
-        return Err(ApiError::BadRequest(
-            "At least one node must have content or summary".to_string(),
-        ));
-    }
+    ensure_path_has_content(&request.path_nodes)?;
 
     let tier = &identity.account_tier;
-
-    // Validate word limits before charging credits
-    let max_words_limit = match tier {
-        AccountTier::Pro => state.config.ai.openrouter.ai_routing.ideas.max_words_pro,
-        AccountTier::Free => state.config.ai.openrouter.ai_routing.ideas.max_words_free,
-    };
-    if request.generation_params.min_words > request.generation_params.max_words {
-        return Err(ApiError::BadRequest(
-            "minWords must be <= maxWords".to_string(),
-        ));
-    }
-    if request.generation_params.max_words > max_words_limit {
-        return Err(ApiError::BadRequest(format!(
-            "maxWords exceeds tier limit (max {})",
-            max_words_limit
-        )));
-    }
-
-    // Atomically check and increment quota with weighted cost
-    state
+    validate_word_limits(
+        &state.config.ai.openrouter.ai_routing.ideas,
+        tier,
+        &request.generation_params,
+    )?;
+
+    // Hold credits for the operation; committed on success, released on failure
+    let reservation = state
         .quota_service
-        .check_and_increment_quota_weighted(identity.user_id, tier, AIOperation::ContinueIdeas)
+        .reserve(identity.user_id, tier, AIOperation::ContinueIdeas)
         .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
 
     // Generate continuation ideas using JSON-structured output
-    let generation_params = crate::models::ai::GenerationParams {
-        num_candidates: if *tier == AccountTier::Pro { 3 } else { 1 },
-        min_words: request.generation_params.min_words,
-        max_words: request.generation_params.max_words,
-        tone: request.generation_params.tone.clone(),
-        avoid_hard_end: request.generation_params.avoid_hard_end,
-    };
+    let generation_params = effective_generation_params(&request.generation_params, tier);
 
     let generation_result = state
         .ai_service
@@ -424,30 +653,33 @@ pub async fn text_ideas(
         )
         .await;
 
-    // Handle errors with credit refund
+    // Handle errors by releasing the credit hold
     match generation_result {
-        Ok(mut candidates) => {
+        Ok((mut candidates, model)) => {
+            usage.model = Some(model.clone());
+            commit_hold(&state, &identity, &reservation, &usage).await;
             if *tier != AccountTier::Pro {
                 candidates.truncate(1);
             }
-            Ok(Json(AITextContinueResponse { candidates }))
+            Ok(Json(AITextContinueResponse { candidates, model }))
         }
         Err(err) => {
-            // Refund credits after failed generation
-            if let Err(refund_err) = state
+            // Release the credit hold after failed generation
+            usage.error = Some(err.to_string());
+            if let Err(release_err) = state
                 .quota_service
-                .refund_quota_weighted(identity.user_id, tier, AIOperation::ContinueIdeas)
+                .release(identity.user_id, tier, &reservation, &usage)
                 .await
             {
                 tracing::error!(
                     user_id = %identity.user_id,
-                    error = %refund_err,
-                    "Failed to refund credits after ideas generation failure - user may have lost credits"
+                    error = %release_err,
+                    "Failed to release credit hold after ideas generation failure; the sweeper releases it on expiry"
                 );
             } else {
                 tracing::info!(
                     user_id = %identity.user_id,
-                    "Successfully refunded credits after ideas generation failure"
+                    "Released credit hold after ideas generation failure"
                 );
             }
             Err(err)
@@ -460,6 +692,7 @@ pub async fn text_ideas(
 pub async fn text_summarize(
     State(state): State<AppState>,
     identity: UserIdentity,
+    request_id: RequestId,
     AppJson(request): AppJson<AITextSummarizeRequest>,
 ) -> Result<Json<AITextSummarizeResponse>> {
     // Validate request
@@ -483,10 +716,14 @@ pub async fn text_summarize(
     let tier = &identity.account_tier;
 
     // Atomically check and increment quota - 1 credit per batch
-    state
+    let reservation = state
         .quota_service
-        .check_and_increment_quota_weighted(identity.user_id, tier, AIOperation::Summarize)
+        .reserve(identity.user_id, tier, AIOperation::Summarize)
         .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
 
     // Generate summaries
     let generation_result = state
@@ -494,28 +731,141 @@ pub async fn text_summarize(
         .generate_summaries(request.story_context.as_ref(), &request.nodes, tier)
         .await;
 
-    // Handle errors with credit refund
+    // Handle errors by releasing the credit hold
     match generation_result {
-        Ok(summaries) => Ok(Json(AITextSummarizeResponse { summaries })),
+        Ok((summaries, model)) => {
+            usage.model = Some(model.clone());
+            commit_hold(&state, &identity, &reservation, &usage).await;
+            Ok(Json(AITextSummarizeResponse { summaries, model }))
+        }
         Err(err) => {
-            // Refund credits after failed generation
-            if let Err(refund_err) = state
+            // Release the credit hold after failed generation
+            usage.error = Some(err.to_string());
+            if let Err(release_err) = state
                 .quota_service
-                .refund_quota_weighted(identity.user_id, tier, AIOperation::Summarize)
+                .release(identity.user_id, tier, &reservation, &usage)
                 .await
             {
                 tracing::error!(
                     user_id = %identity.user_id,
-                    error = %refund_err,
-                    "Failed to refund credits after summarize failure - user may have lost credits"
+                    error = %release_err,
+                    "Failed to release credit hold after summarize failure; the sweeper releases it on expiry"
                 );
             } else {
                 tracing::info!(
                     user_id = %identity.user_id,
-                    "Successfully refunded credits after summarize failure"
+                    "Released credit hold after summarize failure"
                 );
             }
             Err(err)
         }
     }
 }
+
+/// Ensure at least one node has content or summary
+pub(super) fn ensure_path_has_content(path_nodes: &[PathNode]) -> Result<()> {
+    let has_content = path_nodes.iter().any(|node| {
+        !node.content.is_empty() || node.summary.as_ref().is_some_and(|s| !s.is_empty())
+    });
+    if !has_content {
+        return Err(ApiError::BadRequest(
+            "At least one node must have content or summary".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validate word limits before charging credits
+///
+/// Shared by the streaming and non-streaming endpoints so their checks stay identical.
+pub(super) fn validate_word_limits(
+    routing: &TaskRouting,
+    tier: &AccountTier,
+    params: &GenerationParams,
+) -> Result<()> {
+    let max_words_limit = match tier {
+        AccountTier::Pro => routing.max_words_pro,
+        AccountTier::Free => routing.max_words_free,
+    };
+    if params.min_words > params.max_words {
+        return Err(ApiError::BadRequest(
+            "minWords must be <= maxWords".to_string(),
+        ));
+    }
+    if params.max_words > max_words_limit {
+        return Err(ApiError::BadRequest(format!(
+            "maxWords exceeds tier limit (max {})",
+            max_words_limit
+        )));
+    }
+
+    Ok(())
+}
+
+pub(super) fn effective_generation_params(
+    params: &GenerationParams,
+    tier: &AccountTier,
+) -> GenerationParams {
+    GenerationParams {
+        num_candidates: if *tier == AccountTier::Pro { 3 } else { 1 },
+        min_words: params.min_words,
+        max_words: params.max_words,
+        tone: params.tone.clone(),
+        avoid_hard_end: params.avoid_hard_end,
+    }
+}
+
+/// Commit a credit hold after a successful operation
+///
+/// The operation already succeeded, so a failure is logged rather than returned.
+/// Transient failures are retried; if the commit still fails, the hold is marked
+/// delivered so the sweeper charges it on expiry instead of refunding it.
+pub(super) async fn commit_hold(
+    state: &AppState,
+    identity: &UserIdentity,
+    reservation: &CreditReservation,
+    usage: &UsageContext,
+) {
+    let mut attempt = 1;
+    let err = loop {
+        match state
+            .quota_service
+            .commit(identity.user_id, reservation, usage)
+            .await
+        {
+            Ok(()) => return,
+            // Already settled or gone; retrying won't change that
+            Err(err @ (ApiError::BadRequest(_) | ApiError::NotFound(_))) => break err,
+            Err(err) if attempt >= COMMIT_HOLD_ATTEMPTS => break err,
+            Err(err) => {
+                tracing::warn!(
+                    user_id = %identity.user_id,
+                    attempt,
+                    error = %err,
+                    "Failed to commit credit hold; retrying"
+                );
+                tokio::time::sleep(COMMIT_HOLD_RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+        }
+    };
+
+    tracing::error!(
+        user_id = %identity.user_id,
+        operation = reservation.operation.as_str(),
+        error = %err,
+        "Failed to commit credit hold"
+    );
+    if let Err(mark_err) = state
+        .quota_service
+        .mark_delivered(identity.user_id, reservation.id)
+        .await
+    {
+        tracing::error!(
+            user_id = %identity.user_id,
+            error = %mark_err,
+            "Failed to mark credit hold delivered; the sweeper refunds it on expiry"
+        );
+    }
+}
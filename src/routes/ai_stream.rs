@@ -0,0 +1,282 @@
+use std::{convert::Infallible, future::Future};
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use entity::sea_orm_active_enums::AccountTier;
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
+use tracing::instrument;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, AppJson, Result},
+    middleware::{RequestId, UserIdentity},
+    models::{
+        ai::{
+            AITextContinueRequest, AITextContinueResponse, AITextEditMode, AITextEditRequest,
+            AITextEditResponse, AITextIdeasRequest, AITextStreamDelta, EditParams,
+        },
+        common::AIOperation,
+        credits::UsageContext,
+    },
+    routes::ai::{
+        commit_hold, effective_generation_params, ensure_path_has_content, validate_word_limits,
+    },
+};
+
+/// POST /api/v1/ai/text/continue/stream
+#[instrument(skip(state, identity, request))]
+pub async fn text_continue_stream(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    request_id: RequestId,
+    AppJson(request): AppJson<AITextContinueRequest>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+    ensure_path_has_content(&request.path_nodes)?;
+
+    let tier = identity.account_tier.clone();
+    validate_word_limits(
+        &state.config.ai.openrouter.ai_routing.r#continue,
+        &tier,
+        &request.generation_params,
+    )?;
+    let generation_params = effective_generation_params(&request.generation_params, &tier);
+
+    let ai_service = state.ai_service.clone();
+    start_stream(
+        state,
+        identity,
+        request_id,
+        AIOperation::ContinueProse,
+        move |deltas| async move {
+            let (candidates, model) = ai_service
+                .stream_prose_continuations(
+                    &request.story_context,
+                    &request.path_nodes,
+                    &generation_params,
+                    request.instructions.as_deref(),
+                    &tier,
+                    &deltas,
+                )
+                .await?;
+            Ok((
+                AITextContinueResponse {
+                    candidates,
+                    model: model.clone(),
+                },
+                model,
+            ))
+        },
+    )
+    .await
+}
+
+/// POST /api/v1/ai/text/ideas/stream
+#[instrument(skip(state, identity, request))]
+pub async fn text_ideas_stream(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    request_id: RequestId,
+    AppJson(request): AppJson<AITextIdeasRequest>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+    ensure_path_has_content(&request.path_nodes)?;
+
+    let tier = identity.account_tier.clone();
+    validate_word_limits(
+        &state.config.ai.openrouter.ai_routing.ideas,
+        &tier,
+        &request.generation_params,
+    )?;
+    let generation_params = effective_generation_params(&request.generation_params, &tier);
+
+    let ai_service = state.ai_service.clone();
+    start_stream(
+        state,
+        identity,
+        request_id,
+        AIOperation::ContinueIdeas,
+        move |deltas| async move {
+            let (candidates, model) = ai_service
+                .stream_continuation_ideas(
+                    &request.story_context,
+                    &request.path_nodes,
+                    &generation_params,
+                    request.instructions.as_deref(),
+                    &tier,
+                    &deltas,
+                )
+                .await?;
+            Ok((
+                AITextContinueResponse {
+                    candidates,
+                    model: model.clone(),
+                },
+                model,
+            ))
+        },
+    )
+    .await
+}
+
+/// POST /api/v1/ai/text/edit/stream
+#[instrument(skip(state, identity, request))]
+pub async fn text_edit_stream(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    request_id: RequestId,
+    AppJson(request): AppJson<AITextEditRequest>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let tier = identity.account_tier.clone();
+    let operation = match request.mode {
+        AITextEditMode::Expand => AIOperation::EditExpand,
+        AITextEditMode::Shorten => AIOperation::EditShorten,
+        AITextEditMode::Rewrite => AIOperation::EditRewrite,
+        AITextEditMode::FixGrammar => AIOperation::EditFixGrammar,
+    };
+    let edit_params = EditParams {
+        num_candidates: if tier == AccountTier::Pro { 3 } else { 1 },
+        target_length: request.edit_params.target_length.clone(),
+        tone: request.edit_params.tone.clone(),
+        language: request.edit_params.language.clone(),
+        keep_style: request.edit_params.keep_style,
+    };
+
+    let ai_service = state.ai_service.clone();
+    start_stream(
+        state,
+        identity,
+        request_id,
+        operation,
+        move |deltas| async move {
+            let (candidates, model) = ai_service
+                .stream_text_edit(
+                    request.mode,
+                    request.story_context.as_ref(),
+                    &request.input,
+                    &edit_params,
+                    &tier,
+                    &deltas,
+                )
+                .await?;
+            Ok((
+                AITextEditResponse {
+                    mode: request.mode,
+                    candidates,
+                    model: model.clone(),
+                },
+                model,
+            ))
+        },
+    )
+    .await
+}
+
+/// Hold credits, then run `generate` in the background and relay its output as Server-Sent Events
+///
+/// Sends a `delta` event per text chunk, then either a `done` event carrying the same body as
+/// the non-streaming endpoint or an `error` event. The hold is released if generation fails
+/// before the first delta; once text has reached the client it is committed. Moderated text is
+/// only sent after screening. Clients discard streamed text when an `error` event arrives.
+async fn start_stream<R, F, Fut>(
+    state: AppState,
+    identity: UserIdentity,
+    request_id: RequestId,
+    operation: AIOperation,
+    generate: F,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>>
+where
+    R: Serialize + Send + 'static,
+    F: FnOnce(UnboundedSender<AITextStreamDelta>) -> Fut,
+    Fut: Future<Output = Result<(R, String)>> + Send + 'static,
+{
+    let reservation = state
+        .quota_service
+        .reserve(identity.user_id, &identity.account_tier, operation)
+        .await?;
+    let mut usage = UsageContext {
+        request_id: Some(request_id.0.to_string()),
+        ..Default::default()
+    };
+
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let (deltas_tx, mut deltas_rx) = mpsc::unbounded_channel();
+    let generation = generate(deltas_tx);
+
+    tokio::spawn(async move {
+        tokio::pin!(generation);
+
+        let mut streamed = false;
+        let result = loop {
+            tokio::select! {
+                Some(delta) = deltas_rx.recv() => {
+                    streamed = true;
+                    let _ = events_tx.send(json_event("delta", &delta));
+                }
+                result = &mut generation => break result,
+            }
+        };
+        // Deltas sent just before generation finished
+        while let Ok(delta) = deltas_rx.try_recv() {
+            streamed = true;
+            let _ = events_tx.send(json_event("delta", &delta));
+        }
+
+        match result {
+            Ok((response, model)) => {
+                usage.model = Some(model);
+                commit_hold(&state, &identity, &reservation, &usage).await;
+                let _ = events_tx.send(json_event("done", &response));
+            }
+            Err(err) => {
+                usage.error = Some(err.to_string());
+                if streamed {
+                    // The user already received part of the output
+                    commit_hold(&state, &identity, &reservation, &usage).await;
+                } else if let Err(release_err) = state
+                    .quota_service
+                    .release(
+                        identity.user_id,
+                        &identity.account_tier,
+                        &reservation,
+                        &usage,
+                    )
+                    .await
+                {
+                    tracing::error!(
+                        user_id = %identity.user_id,
+                        error = %release_err,
+                        "Failed to release credit hold after stream failure; the sweeper releases it on expiry"
+                    );
+                }
+
+                let (_, body) = err.error_response();
+                let _ = events_tx.send(json_event("error", &body));
+            }
+        }
+    });
+
+    Ok(Sse::new(UnboundedReceiverStream::new(events_rx).map(Ok)).keep_alive(KeepAlive::default()))
+}
+
+/// Build a named SSE event with a JSON payload
+fn json_event(name: &str, data: &impl Serialize) -> Event {
+    // Payloads are plain structs, so serialization cannot fail
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|_| Event::default().event(name))
+}
@@ -1,4 +1,7 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
 use tracing::instrument;
 use validator::Validate;
 
@@ -6,7 +9,10 @@ use crate::{
     app_state::AppState,
     error::{ApiError, AppJson, Result},
     middleware::UserIdentity,
-    models::credits::{CreditPurchaseRequest, CreditPurchaseResponse, CreditsQuotaResponse},
+    models::credits::{
+        CreditHistoryQuery, CreditHistoryResponse, CreditPurchaseRequest, CreditPurchaseResponse,
+        CreditsQuotaResponse,
+    },
 };
 
 /// POST /api/v1/credits/purchase
@@ -21,23 +27,53 @@ pub async fn record_credit_purchase(
         .validate()
         .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
 
-    // Extract credit amount from product_id
-    let amount = request.extract_credit_amount().ok_or_else(|| {
-        ApiError::BadRequest(format!("Invalid product_id: {}", request.product_id))
-    })?;
+    // Resolve the credit pack from the product catalog
+    let amount = state
+        .config
+        .iap
+        .products
+        .credit_amount(request.platform, &request.product_id)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("Invalid product_id: {}", request.product_id))
+        })?;
+
+    // Verify the transaction with the store - never trust client-supplied claims
+    let verified = state
+        .iap_service
+        .verify_consumable_purchase(request.platform, &request.receipt, &request.transaction_id)
+        .await?;
+
+    if verified.product_id != request.product_id {
+        return Err(ApiError::InvalidReceipt(format!(
+            "Product mismatch: receipt contains {}, request claims {}",
+            verified.product_id, request.product_id
+        )));
+    }
 
-    // Record the purchase
+    // Client clocks drift; the store's purchase date is authoritative
+    if (verified.purchase_date - request.purchase_date).abs() > time::Duration::minutes(5) {
+        tracing::warn!(
+            user_id = %identity.user_id,
+            transaction_id = %verified.transaction_id,
+            claimed = %request.purchase_date,
+            verified = %verified.purchase_date,
+            "Client purchase date differs from store record"
+        );
+    }
+
+    // Record the purchase using the verified store payload
+    let store_payload = verified.store_payload.to_string();
     let (purchase_id, total_extra) = state
         .credits_service
         .record_purchase(
             identity.user_id,
-            request.original_transaction_id.as_deref(),
-            &request.transaction_id,
-            &request.product_id,
+            verified.original_transaction_id.as_deref(),
+            &verified.transaction_id,
+            &verified.product_id,
             request.platform,
             amount,
-            request.purchase_date,
-            request.receipt.as_deref(),
+            verified.purchase_date,
+            Some(&store_payload),
         )
         .await?;
 
@@ -72,5 +108,25 @@ pub async fn get_credits_quota(
         subscription_credits: quota_info.subscription_credits.clone(),
         extra_credits: quota_info.extra_credits_total,
         total_credits: quota_info.total_credits,
+        held_credits: quota_info.held_credits,
     }))
 }
+
+/// GET /api/v1/credits/history
+#[instrument(skip(state, identity))]
+pub async fn get_credit_history(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Query(query): Query<CreditHistoryQuery>,
+) -> Result<Json<CreditHistoryResponse>> {
+    query
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let history = state
+        .credits_service
+        .get_usage_history(identity.user_id, query.page, query.page_size)
+        .await?;
+
+    Ok(Json(history))
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, AppJson, Result},
+    middleware::UserIdentity,
+    models::export::{ExportJobResponse, ExportRequest},
+    services::export_service::ExportOutcome,
+};
+
+/// POST /api/v1/export
+///
+/// Returns the file itself, or 202 with a job to poll when the export is large.
+#[instrument(skip(state, identity, request))]
+pub async fn create_export(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    AppJson(request): AppJson<ExportRequest>,
+) -> Result<Response> {
+    use validator::Validate;
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let response = match state
+        .export_service
+        .export(identity.user_id, request)
+        .await?
+    {
+        ExportOutcome::File(file) => (
+            [
+                (header::CONTENT_TYPE, file.content_type.to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", file.file_name),
+                ),
+                (header::CACHE_CONTROL, "no-store".to_string()),
+            ],
+            file.bytes,
+        )
+            .into_response(),
+        ExportOutcome::Job(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+    };
+
+    Ok(response)
+}
+
+/// GET /api/v1/export/{id}
+#[instrument(skip(state, identity))]
+pub async fn get_export_job(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ExportJobResponse>> {
+    let job = state.export_service.job(identity.user_id, id).await?;
+
+    Ok(Json(job))
+}
@@ -0,0 +1,52 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tracing::instrument;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, Result},
+    services::object_store,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SignedUrlQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// GET /files/{*key}
+///
+/// Serves objects of the local storage backend to holders of a signed URL.
+#[instrument(skip(state, query))]
+pub async fn get_file(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    Query(query): Query<SignedUrlQuery>,
+) -> Result<Response> {
+    if !state
+        .object_store
+        .verify_signature(&key, query.expires, &query.signature)
+    {
+        return Err(ApiError::Unauthorized(
+            "Invalid or expired signature".to_string(),
+        ));
+    }
+
+    let bytes = state.object_store.get(&key).await?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                object_store::content_type_for_key(&key),
+            ),
+            // Objects are immutable; caches may keep them as long as the URL is valid
+            (header::CACHE_CONTROL, "private, immutable"),
+        ],
+        bytes,
+    )
+        .into_response())
+}
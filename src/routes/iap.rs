@@ -1,4 +1,8 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
 use entity::sea_orm_active_enums::AccountTier;
 use tracing::{info, instrument};
 use validator::Validate;
@@ -7,7 +11,7 @@ use crate::{
     app_state::AppState,
     error::{ApiError, AppJson, Result},
     middleware::UserIdentity,
-    models::iap::{IAPLinkRequest, IAPLinkResponse},
+    models::iap::{AppleNotificationRequest, IAPLinkRequest, IAPLinkResponse, PubSubPushRequest},
 };
 
 /// POST /api/v1/iap/verify
@@ -29,7 +33,7 @@ pub async fn verify_iap(
     AppJson(request): AppJson<IAPLinkRequest>,
 ) -> Result<Json<IAPLinkResponse>> {
     use entity::{user_iap_receipts, users};
-    use sea_orm::{ActiveModelTrait, EntityTrait, Set, TransactionTrait};
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
 
     // Validate request
     request
@@ -42,6 +46,15 @@ pub async fn verify_iap(
         .verify_receipt(request.platform, &request.receipt)
         .await?;
 
+    // Unacknowledged Google purchases are refunded automatically after 3 days
+    if !verification.is_acknowledged {
+        tracing::warn!(
+            user_id = %identity.user_id,
+            purchase_identity = %verification.purchase_identity,
+            "IAP purchase has not been acknowledged by the client yet"
+        );
+    }
+
     // Determine account tier based on receipt
     // If has active subscription (Pro tier purchase), set to Pro, otherwise Free
     let account_tier = match verification.purchase_tier {
@@ -81,6 +94,7 @@ pub async fn verify_iap(
         first_linked_at: Set(now),
         created_at: Set(now),
         updated_at: Set(now),
+        store_signed_at: Set(None),
     };
 
     // Insert or update receipt (handle duplicate original_transaction_id + user_id)
@@ -113,6 +127,24 @@ pub async fn verify_iap(
         return Err(ApiError::from(e));
     }
 
+    // An upgrade/downgrade or resubscription replaces the previous purchase token
+    if let Some(linked_token) = &verification.linked_purchase_token {
+        user_iap_receipts::Entity::update_many()
+            .col_expr(
+                user_iap_receipts::Column::SubscriptionStatus,
+                sea_orm::sea_query::Expr::value("replaced"),
+            )
+            .col_expr(
+                user_iap_receipts::Column::UpdatedAt,
+                sea_orm::sea_query::Expr::value(now),
+            )
+            .filter(user_iap_receipts::Column::UserId.eq(identity.user_id))
+            .filter(user_iap_receipts::Column::Platform.eq(platform_str))
+            .filter(user_iap_receipts::Column::OriginalTransactionId.eq(linked_token.as_str()))
+            .exec(&txn)
+            .await?;
+    }
+
     // Update user's account_tier
     let user = users::Entity::find_by_id(identity.user_id)
         .one(&txn)
@@ -142,3 +174,72 @@ pub async fn verify_iap(
         valid_until: verification.valid_until,
     }))
 }
+
+/// POST /api/v1/iap/apple/notifications
+///
+/// App Store Server Notifications V2 endpoint.
+///
+/// **Public** - authenticity comes from the signed payload's Apple certificate chain.
+///
+/// Apple retries any non-2xx response, so a notification is only acknowledged once it
+/// has been applied; redeliveries of an already processed notification return 200.
+#[instrument(skip(state, request))]
+pub async fn apple_notifications(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<AppleNotificationRequest>,
+) -> Result<StatusCode> {
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let outcome = state
+        .iap_notification_service
+        .handle_apple_notification(&request.signed_payload)
+        .await?;
+
+    info!(outcome = ?outcome, "App Store notification acknowledged");
+
+    Ok(StatusCode::OK)
+}
+
+/// POST /api/v1/iap/google/notifications
+///
+/// Google Play real-time developer notifications, delivered by a Pub/Sub push subscription.
+///
+/// **Public** - authenticity comes from the OIDC token Pub/Sub attaches to each push
+/// (the subscription must have authentication enabled with the configured audience).
+///
+/// Pub/Sub redelivers any non-2xx response, so a message is only acknowledged once it
+/// has been applied; redeliveries of an already processed message return 200.
+#[instrument(skip(state, headers, request))]
+pub async fn google_notifications(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<PubSubPushRequest>,
+) -> Result<StatusCode> {
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Missing push authentication token".to_string()))?;
+
+    state.iap_service.verify_pubsub_push_token(token).await?;
+
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let outcome = state
+        .iap_notification_service
+        .handle_google_notification(&request.message.message_id, &request.message.data)
+        .await?;
+
+    info!(
+        outcome = ?outcome,
+        subscription = %request.subscription,
+        publish_time = ?request.message.publish_time,
+        "Google Play notification acknowledged"
+    );
+
+    Ok(StatusCode::OK)
+}
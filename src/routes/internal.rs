@@ -0,0 +1,46 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, Result},
+    models::ai::AIProviderHealthResponse,
+};
+
+/// GET /internal/ai/health
+#[instrument(skip(state, headers))]
+pub async fn ai_provider_health(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AIProviderHealthResponse>> {
+    authorize_internal(&state, &headers)?;
+
+    Ok(Json(AIProviderHealthResponse {
+        circuits: state.ai_service.provider_health(),
+    }))
+}
+
+/// Require the configured internal bearer token; without one the endpoints do not exist
+fn authorize_internal(state: &AppState, headers: &HeaderMap) -> Result<()> {
+    let expected = state
+        .config
+        .server
+        .internal_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ApiError::NotFound("Not found".to_string()))?;
+
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Missing internal token".to_string()))?;
+
+    // Compare digests so the comparison time does not depend on the token contents
+    if Sha256::digest(token.as_bytes()) != Sha256::digest(expected.as_bytes()) {
+        return Err(ApiError::Unauthorized("Invalid internal token".to_string()));
+    }
+
+    Ok(())
+}
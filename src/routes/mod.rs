@@ -1,16 +1,23 @@
 // Route modules
 pub mod ai;
+pub mod ai_stream;
 pub mod auth;
 pub mod credits;
+pub mod export;
+pub mod files;
 pub mod iap;
+pub mod internal;
+pub mod shares;
+pub mod sync;
 
 use crate::{
     app_state::AppState,
     middleware::{create_rate_limiter, jwt_auth_middleware, logging_middleware},
 };
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 
@@ -18,19 +25,39 @@ use axum::{
 pub fn create_router(state: AppState) -> Router {
     Router::new()
         .nest("/api/v1", api_v1_routes(state.clone()))
+        .nest("/internal", internal_routes())
+        // Signed-URL downloads for the local storage backend
+        .route("/files/{*key}", get(files::get_file))
+        // Public web reader for share links
+        .route("/s/{slug}", get(shares::view_share))
+        .route("/s/{slug}/preview.jpg", get(shares::preview_card))
+        .route("/oembed", get(shares::oembed))
         .with_state(state)
 }
 
+/// Operational routes, guarded by the internal token rather than user JWTs
+fn internal_routes() -> Router<AppState> {
+    Router::new().route("/ai/health", get(internal::ai_provider_health))
+}
+
 /// API v1 routes
 fn api_v1_routes(state: AppState) -> Router<AppState> {
     // Protected routes requiring both authentication and rate limiting
     let rate_limiter = create_rate_limiter(state.redis.clone());
     let protected_routes = Router::new()
         .route("/ai/text/continue", post(ai::text_continue))
+        .route(
+            "/ai/text/continue/stream",
+            post(ai_stream::text_continue_stream),
+        )
         .route("/ai/text/ideas", post(ai::text_ideas))
+        .route("/ai/text/ideas/stream", post(ai_stream::text_ideas_stream))
         .route("/ai/text/edit", post(ai::text_edit))
+        .route("/ai/text/edit/stream", post(ai_stream::text_edit_stream))
         .route("/ai/text/summarize", post(ai::text_summarize))
         .route("/ai/image/generate", post(ai::image_generate))
+        .route("/ai/image/{id}/regenerate", post(ai::image_regenerate))
+        .route("/ai/image/{id}/variations", post(ai::image_variations))
         .route_layer(middleware::from_fn(rate_limiter))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -41,6 +68,27 @@ fn api_v1_routes(state: AppState) -> Router<AppState> {
     let auth_only_routes = Router::new()
         .route("/quota", get(credits::get_credits_quota))
         .route("/credits/purchase", post(credits::record_credit_purchase))
+        .route("/credits/history", get(credits::get_credit_history))
+        .route("/ai/images", get(ai::list_images))
+        .route("/ai/images/{id}", get(ai::get_image))
+        // Embedded media arrives base64-encoded, so shares need more than the default body limit
+        .route(
+            "/shares",
+            get(shares::list_shares)
+                .post(shares::create_share)
+                .layer(DefaultBodyLimit::max(state.config.shares.max_request_bytes)),
+        )
+        .route("/shares/{id}", delete(shares::delete_share))
+        .route("/sync/pull", get(sync::pull))
+        .route("/sync/push", post(sync::push))
+        // Illustrations arrive base64-encoded, so exports need more than the default body limit
+        .route(
+            "/export",
+            post(export::create_export).layer(DefaultBodyLimit::max(
+                state.config.exports.max_request_bytes,
+            )),
+        )
+        .route("/export/{id}", get(export::get_export_job))
         .route("/iap/verify", post(iap::verify_iap))
         .route("/auth/me", get(auth::get_me))
         .route("/auth/logout-all", post(auth::logout_all))
@@ -53,7 +101,9 @@ fn api_v1_routes(state: AppState) -> Router<AppState> {
     let public_routes = Router::new()
         .route("/auth/login/apple", post(auth::apple_sign_in))
         .route("/auth/refresh", post(auth::refresh_token))
-        .route("/auth/logout", post(auth::logout));
+        .route("/auth/logout", post(auth::logout))
+        .route("/iap/apple/notifications", post(iap::apple_notifications))
+        .route("/iap/google/notifications", post(iap::google_notifications));
 
     // Combine all routes with request/response body logging
     Router::new()
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, AppJson, Result},
+    middleware::UserIdentity,
+    models::share::{CreateShareRequest, OEmbedQuery, ShareListResponse, ShareResponse},
+    services::{share_page, share_preview},
+};
+
+/// POST /api/v1/shares
+#[instrument(skip(state, identity, request))]
+pub async fn create_share(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    AppJson(request): AppJson<CreateShareRequest>,
+) -> Result<Json<ShareResponse>> {
+    use validator::Validate;
+    request
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let share = state
+        .share_service
+        .create(identity.user_id, &identity.account_tier, request)
+        .await?;
+
+    Ok(Json(share))
+}
+
+/// GET /api/v1/shares
+#[instrument(skip(state, identity))]
+pub async fn list_shares(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+) -> Result<Json<ShareListResponse>> {
+    let shares = state
+        .share_service
+        .list(identity.user_id, &identity.account_tier)
+        .await?;
+
+    Ok(Json(shares))
+}
+
+/// DELETE /api/v1/shares/{id}
+#[instrument(skip(state, identity))]
+pub async fn delete_share(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    state.share_service.delete(identity.user_id, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /s/{slug}
+///
+/// Public, read-only web reader for a share.
+#[instrument(skip(state))]
+pub async fn view_share(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Response> {
+    // Every request counts as a view, so intermediaries must not serve stored copies
+    let headers = [(header::CACHE_CONTROL, "no-store")];
+    let response = match state.share_service.view(&slug).await? {
+        Some(page) => (headers, Html(share_page::render(&page))).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            headers,
+            Html(share_page::render_not_found()),
+        )
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+/// GET /s/{slug}/preview.jpg
+///
+/// Social preview card referenced by the share page's og:image. Does not count as a view.
+#[instrument(skip(state))]
+pub async fn preview_card(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Response> {
+    let bytes = state
+        .share_service
+        .preview_card(&slug)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Share not found".to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, share_preview::CARD_MIME_TYPE),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// GET /oembed
+///
+/// oEmbed provider endpoint for share links.
+#[instrument(skip(state))]
+pub async fn oembed(
+    State(state): State<AppState>,
+    Query(query): Query<OEmbedQuery>,
+) -> Result<Response> {
+    // The spec asks for 501 when a format is not supported
+    if query
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return Ok(StatusCode::NOT_IMPLEMENTED.into_response());
+    }
+
+    let embed = state
+        .share_service
+        .oembed(&query)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No share at this URL".to_string()))?;
+
+    Ok(Json(embed).into_response())
+}
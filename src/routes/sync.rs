@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use tracing::instrument;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, AppJson, Result},
+    middleware::UserIdentity,
+    models::sync::{SyncChanges, SyncPullQuery, SyncPullResponse, SyncPushResponse},
+};
+
+/// GET /api/v1/sync/pull
+///
+/// Rows changed since the cursor, tombstones included.
+#[instrument(skip(state, identity))]
+pub async fn pull(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    Query(query): Query<SyncPullQuery>,
+) -> Result<Json<SyncPullResponse>> {
+    use validator::Validate;
+    query
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let response = state.sync_service.pull(identity.user_id, &query).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/sync/push
+///
+/// Rows whose base version is stale come back as conflicts rather than failing the request.
+#[instrument(skip(state, identity, changes))]
+pub async fn push(
+    State(state): State<AppState>,
+    identity: UserIdentity,
+    AppJson(changes): AppJson<SyncChanges>,
+) -> Result<Json<SyncPushResponse>> {
+    use validator::Validate;
+    changes
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let response = state.sync_service.push(identity.user_id, changes).await?;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,604 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    config::{
+        OpenAICompatibleConfig, OpenRouterConfig, IMAGE_REQUEST_TIMEOUT_MS, RETRY_MAX_DELAY_MS,
+    },
+    error::{ApiError, Result},
+};
+
+/// One message of a chat prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// A text generation request, independent of the backend that serves it
+#[derive(Debug, Clone)]
+pub struct TextRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    /// Number of choices to generate
+    pub n: u8,
+}
+
+/// An image generation request
+#[derive(Debug, Clone)]
+pub struct ImageRequest {
+    pub model: String,
+    pub prompt: String,
+    pub aspect_ratio: String,
+    /// Model-specific size parameters for the requested resolution
+    pub size_params: serde_json::Map<String, serde_json::Value>,
+    pub seed: Option<u32>,
+    /// Input image for image-to-image generation
+    pub reference_image: Option<ReferenceImage>,
+}
+
+/// Image sent to a provider alongside the prompt
+#[derive(Debug, Clone)]
+pub struct ReferenceImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// Image returned by a provider
+#[derive(Debug, Clone)]
+pub struct ProviderImage {
+    pub bytes: Vec<u8>,
+    /// MIME type the provider declared, e.g. from a data URL
+    pub mime_type: Option<String>,
+}
+
+/// Receives the choice index and text of each streamed chunk
+pub type OnDelta<'a> = dyn FnMut(usize, &str) + Send + 'a;
+
+/// Backend that generates text from chat prompts
+#[async_trait]
+pub trait TextProvider: Send + Sync {
+    /// Generate a completion; returns the text of each choice
+    async fn complete(&self, request: &TextRequest) -> Result<Vec<String>>;
+
+    /// Stream a completion, calling `on_delta` with the choice index and text of each chunk
+    /// Returns each choice's full text
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Vec<String>>;
+}
+
+/// Backend that generates images from prompts
+#[async_trait]
+pub trait ImageProvider: Send + Sync {
+    /// Generate an image; returns the decoded image bytes
+    async fn generate_image(&self, request: &ImageRequest) -> Result<ProviderImage>;
+}
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    max_tokens: u32,
+    temperature: f32,
+    n: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+impl<'a> ChatCompletionRequest<'a> {
+    fn new(request: &'a TextRequest, stream: bool) -> Self {
+        Self {
+            model: &request.model,
+            messages: &request.messages,
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            n: request.n,
+            stream: stream.then_some(true),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionMessage {
+    content: String,
+}
+
+// Server-sent chunks of a streamed chat completion
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChatCompletionChunkChoice>,
+    #[serde(default)]
+    error: Option<ChatCompletionError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunkChoice {
+    #[serde(default)]
+    index: usize,
+    delta: ChatCompletionDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionError {
+    message: String,
+}
+
+// OpenRouter image generation via chat completions
+#[derive(Debug, Serialize)]
+struct OpenRouterImageRequest<'a> {
+    model: &'a str,
+    messages: Vec<OpenRouterImagePrompt>,
+    modalities: Vec<String>,
+    image_config: ImageConfig<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenRouterImagePrompt {
+    role: &'static str,
+    content: OpenRouterPromptContent,
+}
+
+// Plain text, or text plus a reference image
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum OpenRouterPromptContent {
+    Text(String),
+    Parts(Vec<OpenRouterContentPart>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenRouterContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenRouterImageUrl },
+}
+
+#[derive(Debug, Serialize)]
+struct OpenRouterImageUrl {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ImageConfig<'a> {
+    aspect_ratio: &'a str,
+    #[serde(flatten)]
+    size_params: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterImageResponse {
+    choices: Vec<OpenRouterImageChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterImageChoice {
+    message: OpenRouterImageMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterImageMessage {
+    #[serde(default)]
+    images: Vec<String>, // Base64-encoded data URLs
+}
+
+// Backoff between retries of a single request
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+const RETRY_MAX_DELAY: Duration = Duration::from_millis(RETRY_MAX_DELAY_MS);
+
+/// Delay before retry number `attempt` (1-based)
+/// Doubles per attempt up to the cap; half of it is random so concurrent requests spread out.
+fn retry_delay(attempt: u8) -> Duration {
+    let ceiling = RETRY_BASE_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(8))
+        .min(RETRY_MAX_DELAY);
+    let half = ceiling / 2;
+    half + half.mul_f64(rand::random::<f64>())
+}
+
+/// Read a `Retry-After` header given in seconds
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// HTTP client for an OpenAI-compatible `/chat/completions` endpoint
+struct ChatCompletionsClient {
+    // Provider name used in error messages
+    name: String,
+    api_base: String,
+    headers: HeaderMap,
+    http_client: reqwest::Client,
+    retry_attempts: u8,
+}
+
+impl ChatCompletionsClient {
+    fn new(
+        name: &str,
+        api_base: &str,
+        api_key: Option<&str>,
+        request_timeout_ms: u64,
+        retry_attempts: u8,
+    ) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(request_timeout_ms))
+            .connect_timeout(Duration::from_secs(10)) // 10s connection timeout
+            .build()
+            .expect("Failed to build HTTP client");
+
+        let mut headers = HeaderMap::new();
+        if let Some(api_key) = api_key {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", api_key)
+                    .parse()
+                    .expect("API key must be a valid header value"),
+            );
+        }
+
+        Self {
+            name: name.to_string(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            headers,
+            http_client,
+            retry_attempts,
+        }
+    }
+
+    /// POST `body` to the completions endpoint, retrying transport errors, 429s and 5xx
+    /// Retries back off exponentially with jitter, or wait as long as `Retry-After` asks.
+    async fn send(
+        &self,
+        body: &impl Serialize,
+        retry_attempts: u8,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::Response> {
+        let mut attempts = 0;
+        let mut last_err = None;
+
+        while attempts <= retry_attempts {
+            let mut retry_after = None;
+            let mut builder = self
+                .http_client
+                .post(format!("{}/chat/completions", self.api_base))
+                .headers(self.headers.clone())
+                .json(body);
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+
+            match builder.send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    retry_after = parse_retry_after(resp.headers());
+                    let text = resp.text().await.unwrap_or_default();
+                    let message = format!("{} error {}: {}", self.name, status.as_u16(), text);
+                    if !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                        // A removed model is worth falling back from, but not retrying
+                        if status == StatusCode::NOT_FOUND {
+                            return Err(ApiError::AIProviderUnavailable(message));
+                        }
+                        return Err(ApiError::AIProvider(message));
+                    }
+                    last_err = Some(message);
+                }
+                Err(e) => {
+                    last_err = Some(format!("{} request failed: {}", self.name, e));
+                }
+            }
+
+            attempts += 1;
+            if attempts <= retry_attempts {
+                let delay = retry_after.unwrap_or_else(|| retry_delay(attempts));
+                // Holding the request longer than that is worse than failing over
+                if delay > RETRY_MAX_DELAY {
+                    break;
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        Err(ApiError::AIProviderUnavailable(
+            last_err.unwrap_or_else(|| format!("{} request failed", self.name)),
+        ))
+    }
+
+    async fn complete(&self, request: &TextRequest) -> Result<Vec<String>> {
+        let resp = self
+            .send(
+                &ChatCompletionRequest::new(request, false),
+                self.retry_attempts,
+                None,
+            )
+            .await?;
+
+        let response: ChatCompletionResponse = resp.json().await.map_err(|e| {
+            ApiError::AIProviderUnavailable(format!("Failed to parse response: {}", e))
+        })?;
+
+        if response.choices.is_empty() {
+            return Err(ApiError::AIProviderUnavailable(
+                "No choices in response".to_string(),
+            ));
+        }
+
+        Ok(response
+            .choices
+            .into_iter()
+            .map(|choice| choice.message.content)
+            .collect())
+    }
+
+    /// Stream a completion as Server-Sent Events
+    /// Only failures before the stream starts are retried.
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Vec<String>> {
+        let mut resp = self
+            .send(
+                &ChatCompletionRequest::new(request, true),
+                self.retry_attempts,
+                None,
+            )
+            .await?;
+
+        let mut contents: Vec<String> = Vec::new();
+        let mut buffer: Vec<u8> = Vec::new();
+
+        while let Some(chunk) = resp.chunk().await.map_err(|e| {
+            ApiError::AIProviderUnavailable(format!("{} stream interrupted: {}", self.name, e))
+        })? {
+            buffer.extend_from_slice(&chunk);
+
+            // Events are newline-delimited; a chunk may end mid-line
+            while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+
+                // Skip comments (keep-alives), blank separators and non-data fields
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    return Ok(contents);
+                }
+
+                let chunk: ChatCompletionChunk = serde_json::from_str(data).map_err(|e| {
+                    ApiError::AIProviderUnavailable(format!("Failed to parse stream chunk: {}", e))
+                })?;
+                if let Some(error) = chunk.error {
+                    return Err(ApiError::AIProviderUnavailable(format!(
+                        "{} stream error: {}",
+                        self.name, error.message
+                    )));
+                }
+
+                for choice in chunk.choices {
+                    let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) else {
+                        continue;
+                    };
+                    if contents.len() <= choice.index {
+                        contents.resize(choice.index + 1, String::new());
+                    }
+                    contents[choice.index].push_str(&content);
+                    on_delta(choice.index, &content);
+                }
+            }
+        }
+
+        Ok(contents)
+    }
+}
+
+/// OpenRouter, which serves both text and image models
+pub struct OpenRouterProvider {
+    client: ChatCompletionsClient,
+}
+
+impl OpenRouterProvider {
+    pub fn new(config: &OpenRouterConfig) -> Self {
+        let mut client = ChatCompletionsClient::new(
+            "OpenRouter",
+            &config.api_base,
+            Some(&config.api_key),
+            config.request_timeout_ms,
+            config.retry_attempts,
+        );
+
+        // Attribution headers for the OpenRouter dashboard
+        if let Some(referer) = config.referer.as_ref().and_then(|r| r.parse().ok()) {
+            client.headers.insert("http-referer", referer);
+        }
+        if let Some(app_title) = config.app_title.as_ref().and_then(|t| t.parse().ok()) {
+            client.headers.insert("x-title", app_title);
+        }
+
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl TextProvider for OpenRouterProvider {
+    async fn complete(&self, request: &TextRequest) -> Result<Vec<String>> {
+        self.client.complete(request).await
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Vec<String>> {
+        self.client.complete_stream(request, on_delta).await
+    }
+}
+
+#[async_trait]
+impl ImageProvider for OpenRouterProvider {
+    async fn generate_image(&self, request: &ImageRequest) -> Result<ProviderImage> {
+        let content = match &request.reference_image {
+            Some(reference) => OpenRouterPromptContent::Parts(vec![
+                OpenRouterContentPart::Text {
+                    text: request.prompt.clone(),
+                },
+                OpenRouterContentPart::ImageUrl {
+                    image_url: OpenRouterImageUrl {
+                        url: format!(
+                            "data:{};base64,{}",
+                            reference.mime_type,
+                            base64::engine::general_purpose::STANDARD.encode(&reference.bytes)
+                        ),
+                    },
+                },
+            ]),
+            None => OpenRouterPromptContent::Text(request.prompt.clone()),
+        };
+        let body = OpenRouterImageRequest {
+            model: &request.model,
+            messages: vec![OpenRouterImagePrompt {
+                role: "user",
+                content,
+            }],
+            modalities: vec!["image".to_string(), "text".to_string()],
+            image_config: ImageConfig {
+                aspect_ratio: &request.aspect_ratio,
+                size_params: &request.size_params,
+            },
+            seed: request.seed,
+        };
+
+        // Image generation is slow and expensive, so it gets a longer timeout and no retries
+        let response = self
+            .client
+            .send(
+                &body,
+                0,
+                Some(Duration::from_millis(IMAGE_REQUEST_TIMEOUT_MS)),
+            )
+            .await?;
+
+        let image_response: OpenRouterImageResponse = response
+            .json()
+            .await
+            .map_err(|e| ApiError::AIProvider(format!("Failed to parse image response: {}", e)))?;
+
+        info!(
+            "OpenRouter image response: {} choices returned",
+            image_response.choices.len()
+        );
+
+        // Extract base64 image data
+        let image_data_url = image_response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.images.first())
+            .ok_or_else(|| ApiError::AIProvider("No image generated".to_string()))?;
+
+        info!("Received image data URL, length: {}", image_data_url.len());
+
+        // Parse data URL: "data:image/png;base64,<base64_data>"
+        let (mime_type, base64_data) = match image_data_url.strip_prefix("data:") {
+            Some(data_url) => {
+                let (header, data) = data_url.split_once(',').ok_or_else(|| {
+                    ApiError::AIProvider("Invalid image data URL format".to_string())
+                })?;
+                let mime_type = header.split(';').next().filter(|mime| !mime.is_empty());
+                (mime_type.map(str::to_string), data)
+            }
+            None => (None, image_data_url.as_str()),
+        };
+
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(base64_data)
+            .map_err(|e| ApiError::AIProvider(format!("Failed to decode base64 image: {}", e)))?;
+
+        Ok(ProviderImage { bytes, mime_type })
+    }
+}
+
+/// A directly hosted OpenAI-compatible endpoint, e.g. vLLM or Ollama
+pub struct OpenAICompatibleProvider {
+    client: ChatCompletionsClient,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(name: &str, config: &OpenAICompatibleConfig) -> Self {
+        Self {
+            client: ChatCompletionsClient::new(
+                name,
+                &config.api_base,
+                config.api_key.as_deref(),
+                config.request_timeout_ms,
+                config.retry_attempts,
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl TextProvider for OpenAICompatibleProvider {
+    async fn complete(&self, request: &TextRequest) -> Result<Vec<String>> {
+        self.client.complete(request).await
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Vec<String>> {
+        self.client.complete_stream(request, on_delta).await
+    }
+}
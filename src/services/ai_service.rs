@@ -1,135 +1,179 @@
 use crate::{
-    config::{AIConfig, ModelTierConfig, TaskRouting},
+    config::{AIConfig, ImageModelConfig, ModelTierConfig, TaskRouting, OPENROUTER_PROVIDER},
     error::{ApiError, Result},
     models::ai::{
-        AITextEditMode, Background, Character, EditInput, EditParams, GenerationParams,
-        ImageParams, ImageStoryContext, ImageStyle, NodeContext, NodeSummary, NodeToSummarize,
-        PathNode, StoryContext, StoryContextSimple, TextCandidate, TextEditCandidate,
+        AITextEditMode, AITextStreamDelta, Background, Character, EditInput, EditParams,
+        GenerationParams, ImageParams, ImageResolution, ImageStoryContext, ImageStyle,
+        ModelCircuitStatus, NodeContext, NodeSummary, NodeToSummarize, PathNode, StoryContext,
+        StoryContextSimple, TextCandidate, TextEditCandidate,
+    },
+    services::{
+        ai_provider::{
+            ChatMessage, ImageProvider, ImageRequest, OnDelta, OpenAICompatibleProvider,
+            OpenRouterProvider, ReferenceImage, TextProvider, TextRequest,
+        },
+        circuit_breaker::CircuitBreakers,
+        image_processing,
+        moderation::{ModerationVerdict, Moderator},
     },
 };
-use base64::Engine;
 use entity::sea_orm_active_enums::AccountTier;
 
 // Simple metadata struct for image generation
+#[derive(Debug, Clone)]
 pub struct ImageMetadata {
     pub mime_type: String,
     pub width: u32,
     pub height: u32,
+    pub provider: String,
+    pub model: String,
 }
-use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
-use tracing::{info, instrument};
+use std::{collections::HashMap, future::Future, sync::Arc};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{info, instrument, warn};
 use uuid::Uuid;
 
 pub struct AIService {
     config: AIConfig,
-    http_client: reqwest::Client,
+    // Backends keyed by the provider name model entries refer to
+    text_providers: HashMap<String, Arc<dyn TextProvider>>,
+    image_providers: HashMap<String, Arc<dyn ImageProvider>>,
+    breakers: CircuitBreakers,
+    moderator: Moderator,
 }
 
-#[derive(Debug, Serialize)]
-struct OpenAIRequest {
-    model: String,
-    messages: Vec<OpenAIMessage>,
-    max_tokens: u32,
-    temperature: f32,
-    n: u8,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    response_format: Option<ResponseFormat>,
-}
+// Note: Removed JSON response structs - now using delimited text format for better reliability
 
-#[derive(Debug, Serialize)]
-struct ResponseFormat {
-    #[serde(rename = "type")]
-    format_type: String,
-}
+impl AIService {
+    pub fn new(config: &AIConfig) -> Self {
+        let openrouter = Arc::new(OpenRouterProvider::new(&config.openrouter));
+        let mut service = Self {
+            config: config.clone(),
+            text_providers: HashMap::new(),
+            image_providers: HashMap::new(),
+            breakers: CircuitBreakers::new(&config.circuit_breaker),
+            moderator: Moderator::new(&config.moderation),
+        }
+        .with_text_provider(OPENROUTER_PROVIDER, openrouter.clone())
+        .with_image_provider(OPENROUTER_PROVIDER, openrouter);
 
-#[derive(Debug, Serialize)]
-struct OpenAIMessage {
-    role: String,
-    content: String,
-}
+        for (name, provider_config) in &config.providers {
+            service = service.with_text_provider(
+                name,
+                Arc::new(OpenAICompatibleProvider::new(name, provider_config)),
+            );
+        }
 
-#[derive(Debug, Deserialize)]
-struct OpenAIResponse {
-    choices: Vec<OpenAIChoice>,
-}
+        service
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenAIChoice {
-    message: OpenAIResponseMessage,
-}
+    /// Register (or replace) the text backend for a provider name
+    pub fn with_text_provider(mut self, name: &str, provider: Arc<dyn TextProvider>) -> Self {
+        self.text_providers.insert(name.to_string(), provider);
+        self
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenAIResponseMessage {
-    content: String,
-}
+    /// Register (or replace) the image backend for a provider name
+    pub fn with_image_provider(mut self, name: &str, provider: Arc<dyn ImageProvider>) -> Self {
+        self.image_providers.insert(name.to_string(), provider);
+        self
+    }
 
-// OpenRouter image generation via chat completions
-#[derive(Debug, Serialize)]
-struct OpenRouterImageRequest {
-    model: String,
-    messages: Vec<OpenAIMessage>,
-    modalities: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    image_config: Option<ImageConfig>,
-}
+    /// Circuit breaker state of every model that has failed since it last answered
+    pub fn provider_health(&self) -> Vec<ModelCircuitStatus> {
+        self.breakers.status()
+    }
 
-#[derive(Debug, Serialize)]
-struct ImageConfig {
-    aspect_ratio: String,
-}
+    /// Run a provider call for a model through the model's circuit breaker
+    async fn guarded<T>(
+        &self,
+        provider: &str,
+        model: &str,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.breakers.acquire(provider, model)?;
+
+        let result = call.await;
+        match &result {
+            Err(err @ ApiError::AIProviderUnavailable(_)) => {
+                self.breakers.record_failure(provider, model, err)
+            }
+            // Any other answer, even a rejection, shows the model is reachable
+            _ => self.breakers.record_success(provider, model),
+        }
+        result
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenRouterImageResponse {
-    choices: Vec<OpenRouterImageChoice>,
-}
+    fn text_provider(&self, name: &str) -> Result<&dyn TextProvider> {
+        self.text_providers
+            .get(name)
+            .map(|provider| provider.as_ref())
+            .ok_or_else(|| ApiError::AIProvider(format!("Unknown text provider: {}", name)))
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenRouterImageChoice {
-    message: OpenRouterImageMessage,
-}
+    fn image_provider(&self, name: &str) -> Result<&dyn ImageProvider> {
+        self.image_providers
+            .get(name)
+            .map(|provider| provider.as_ref())
+            .ok_or_else(|| ApiError::AIProvider(format!("Unknown image provider: {}", name)))
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenRouterImageMessage {
-    #[serde(default)]
-    images: Vec<String>, // Base64-encoded data URLs
-}
+    /// Refuse image parameters the tier is not entitled to
+    pub fn check_image_params(params: &ImageParams, account_tier: &AccountTier) -> Result<()> {
+        if params.resolution == ImageResolution::Hd && *account_tier != AccountTier::Pro {
+            return Err(ApiError::ProRequired(
+                "HD images require a Pro subscription".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-// Note: Removed JSON response structs - now using delimited text format for better reliability
+    /// Refuse a reference image when the tier's image model cannot take one
+    pub fn check_reference_image(&self, account_tier: &AccountTier) -> Result<()> {
+        if !self.image_model(account_tier).supports_reference_image {
+            return Err(ApiError::BadRequest(
+                "The image model does not accept reference images".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-impl AIService {
-    pub fn new(config: &AIConfig) -> Self {
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_millis(
-                config.openrouter.request_timeout_ms,
-            ))
-            .connect_timeout(std::time::Duration::from_secs(10)) // 10s connection timeout
-            .build()
-            .expect("Failed to build HTTP client");
-
-        Self {
-            config: config.clone(),
-            http_client,
+    fn image_model(&self, account_tier: &AccountTier) -> &ImageModelConfig {
+        match account_tier {
+            AccountTier::Pro => &self.config.openrouter.image_models.pro,
+            AccountTier::Free => &self.config.openrouter.image_models.free,
         }
     }
 
-    /// Generate image using OpenRouter
-    #[instrument(skip(self, context, node))]
+    /// "provider/model" of the tier's image model, as recorded in image history
+    pub fn image_model_name(&self, account_tier: &AccountTier) -> String {
+        let image_config = self.image_model(account_tier);
+        format!("{}/{}", image_config.provider, image_config.model)
+    }
+
+    /// Generate image using the provider configured for the tier's image model
+    ///
+    /// `reference` is passed to the model as an image-to-image input.
+    #[instrument(skip(self, context, node, reference))]
     pub async fn generate_image(
         &self,
         context: &ImageStoryContext,
         node: &NodeContext,
         params: &ImageParams,
+        reference: Option<ReferenceImage>,
         account_tier: &AccountTier,
     ) -> Result<(Vec<u8>, ImageMetadata)> {
+        Self::check_image_params(params, account_tier)?;
+        if reference.is_some() {
+            self.check_reference_image(account_tier)?;
+        }
+
         // Build image prompt
         let prompt = self.build_image_prompt(context, node, params);
+        self.screen_input(&[&prompt]).await?;
 
         // Select model based on tier
-        let image_config = match account_tier {
-            AccountTier::Pro => &self.config.openrouter.image_models.pro,
-            AccountTier::Free => &self.config.openrouter.image_models.free,
-        };
+        let image_config = self.image_model(account_tier);
         let model = image_config.model.clone();
 
         // Determine aspect ratio
@@ -141,130 +185,66 @@ impl AIService {
             _ => "1:1",
         };
 
-        // Build request
-        let request = OpenRouterImageRequest {
-            model: model.clone(),
-            messages: vec![OpenAIMessage {
-                role: "user".to_string(),
-                content: prompt.clone(),
-            }],
-            modalities: vec!["image".to_string(), "text".to_string()],
-            image_config: Some(ImageConfig {
-                aspect_ratio: aspect_ratio.to_string(),
-            }),
-        };
-
-        info!(
-            "Generating image with OpenRouter: model={}, aspect_ratio={}, prompt_len={}",
-            model,
-            aspect_ratio,
-            prompt.len()
-        );
-
-        // Call OpenRouter API
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            format!("Bearer {}", self.config.openrouter.api_key)
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
-        );
-        if let Some(referer) = &self.config.openrouter.referer {
-            headers.insert(
-                reqwest::header::HeaderName::from_static("http-referer"),
-                referer.parse().unwrap(),
-            );
-        }
-        if let Some(app_title) = &self.config.openrouter.app_title {
-            headers.insert(
-                reqwest::header::HeaderName::from_static("x-title"),
-                app_title.parse().unwrap(),
+        let size_params = image_config
+            .resolutions
+            .get(&params.resolution)
+            .cloned()
+            .unwrap_or_default();
+        if size_params.is_empty() && params.resolution != ImageResolution::Medium {
+            warn!(
+                "No {} size parameters configured for image model {}, using its default size",
+                params.resolution.as_str(),
+                model
             );
         }
 
-        let response = self
-            .http_client
-            .post(format!(
-                "{}/chat/completions",
-                self.config.openrouter.api_base
-            ))
-            .headers(headers)
-            .json(&request)
-            .timeout(std::time::Duration::from_secs(90))
-            .send()
-            .await
-            .map_err(|e| ApiError::AIProvider(format!("OpenRouter image request failed: {}", e)))?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ApiError::AIProvider(format!(
-                "OpenRouter image API error: {}",
-                error_text
-            )));
-        }
-
-        let image_response: OpenRouterImageResponse = response
-            .json()
-            .await
-            .map_err(|e| ApiError::AIProvider(format!("Failed to parse image response: {}", e)))?;
-
         info!(
-            "OpenRouter image response: {} choices returned",
-            image_response.choices.len()
+            "Generating image: provider={}, model={}, aspect_ratio={}, resolution={}, prompt_len={}, reference={}",
+            image_config.provider,
+            model,
+            aspect_ratio,
+            params.resolution.as_str(),
+            prompt.len(),
+            reference.is_some()
         );
 
-        // Extract base64 image data
-        let image_data_url = image_response
-            .choices
-            .first()
-            .and_then(|choice| choice.message.images.first())
-            .ok_or_else(|| ApiError::AIProvider("No image generated".to_string()))?
-            .clone();
-
-        info!("Received image data URL, length: {}", image_data_url.len());
-
-        // Parse data URL: "data:image/png;base64,<base64_data>"
-        let base64_data = if image_data_url.starts_with("data:") {
-            image_data_url
-                .split(',')
-                .nth(1)
-                .ok_or_else(|| ApiError::AIProvider("Invalid image data URL format".to_string()))?
-        } else {
-            &image_data_url
-        };
-
-        // Decode base64 to bytes
-        let image_bytes = base64::engine::general_purpose::STANDARD
-            .decode(base64_data)
-            .map_err(|e| ApiError::AIProvider(format!("Failed to decode base64 image: {}", e)))?;
-
-        // Determine dimensions based on aspect ratio (approximate)
-        let (width, height) = match aspect_ratio {
-            "3:4" => (768, 1024),
-            "4:3" => (1024, 768),
-            "16:9" => (1024, 576),
-            "9:16" => (576, 1024),
-            _ => (1024, 1024),
+        let image_request = ImageRequest {
+            model: model.clone(),
+            prompt,
+            aspect_ratio: aspect_ratio.to_string(),
+            size_params,
+            seed: params.seed,
+            reference_image: reference,
         };
+        let image = self
+            .guarded(
+                &image_config.provider,
+                &model,
+                self.image_provider(&image_config.provider)?
+                    .generate_image(&image_request),
+            )
+            .await?;
+
+        // Report what the model actually returned rather than what was asked for
+        let info = image_processing::probe(&image.bytes, image.mime_type.as_deref())?;
 
         info!(
-            "Generated image: {}x{}, {} bytes",
-            width,
-            height,
-            image_bytes.len()
+            "Generated image: {} {}x{}, {} bytes",
+            info.mime_type,
+            info.width,
+            info.height,
+            image.bytes.len()
         );
 
         let metadata = ImageMetadata {
-            mime_type: "image/png".to_string(),
-            width,
-            height,
+            mime_type: info.mime_type,
+            width: info.width,
+            height: info.height,
+            provider: image_config.provider.clone(),
+            model,
         };
 
-        Ok((image_bytes, metadata))
+        Ok((image.bytes, metadata))
     }
 
     // ==================== Prompt Section Formatters ====================
@@ -661,7 +641,8 @@ Note: Generate ideas in the same language as the story content. If you cannot de
         )
     }
 
-    /// Generate text edit/transformation via OpenRouter
+    /// Generate text edit/transformation
+    /// Returns the candidates and the model that produced them
     #[instrument(skip(self, input, params, account_tier))]
     pub async fn generate_text_edit(
         &self,
@@ -670,7 +651,107 @@ Note: Generate ideas in the same language as the story content. If you cannot de
         input: &EditInput,
         params: &EditParams,
         account_tier: &AccountTier,
-    ) -> Result<Vec<TextEditCandidate>> {
+    ) -> Result<(Vec<TextEditCandidate>, String)> {
+        let (request, selected) =
+            self.build_edit_request(mode, story_context, input, params, account_tier)?;
+        self.screen_input(&Self::edit_inputs(story_context, input, params))
+            .await?;
+        let effective_num_candidates = request.n;
+
+        let (mut candidates, model) = self
+            .complete_with_fallbacks(&selected, &request, |contents| {
+                info!(
+                    "AI vendor edit response: choices={}, mode={:?}",
+                    contents.len(),
+                    mode
+                );
+
+                // Log first choice content preview
+                if let Some(first_choice) = contents.first() {
+                    let preview: String = first_choice.chars().take(150).collect();
+                    info!(
+                        "AI vendor edit response preview (mode={:?}): {}...",
+                        mode, preview
+                    );
+                }
+
+                Self::parse_edit_candidates(contents)
+            })
+            .await?;
+
+        candidates.truncate(effective_num_candidates as usize);
+        let candidates = self.screen_candidates(candidates).await?;
+
+        info!(
+            "Generated {} edit candidates in mode {:?} using model {} (provider={}, downgraded={})",
+            candidates.len(),
+            mode,
+            model.model,
+            model.provider,
+            selected.downgraded
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Stream a text edit, sending text deltas as they arrive
+    /// Each requested candidate is a separate choice; deltas carry its index.
+    /// With moderation enabled, each candidate is sent whole once it has been screened.
+    #[instrument(skip(self, input, params, account_tier, deltas))]
+    pub async fn stream_text_edit(
+        &self,
+        mode: AITextEditMode,
+        story_context: Option<&StoryContextSimple>,
+        input: &EditInput,
+        params: &EditParams,
+        account_tier: &AccountTier,
+        deltas: &UnboundedSender<AITextStreamDelta>,
+    ) -> Result<(Vec<TextEditCandidate>, String)> {
+        let (request, selected) =
+            self.build_edit_request(mode, story_context, input, params, account_tier)?;
+        self.screen_input(&Self::edit_inputs(story_context, input, params))
+            .await?;
+
+        let live = !self.moderator.is_enabled();
+        let (candidates, model) = self
+            .stream_with_fallbacks(
+                &selected,
+                &request,
+                &mut |candidate_index, delta| {
+                    if live {
+                        let _ = deltas.send(AITextStreamDelta {
+                            candidate_index,
+                            delta: delta.to_string(),
+                        });
+                    }
+                },
+                Self::parse_edit_candidates,
+            )
+            .await?;
+        let candidates = self.screen_candidates(candidates).await?;
+        self.send_screened(&candidates, deltas);
+
+        info!(
+            "Streamed {} edit candidates in mode {:?} using model {} (provider={}, downgraded={})",
+            candidates.len(),
+            mode,
+            model.model,
+            model.provider,
+            selected.downgraded
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Build the text edit request along with the selected model
+    fn build_edit_request(
+        &self,
+        mode: AITextEditMode,
+        story_context: Option<&StoryContextSimple>,
+        input: &EditInput,
+        params: &EditParams,
+        account_tier: &AccountTier,
+    ) -> Result<(TextRequest, SelectedModel)> {
         let effective_num_candidates = if *account_tier == AccountTier::Pro {
             params.num_candidates
         } else {
@@ -684,137 +765,40 @@ Note: Generate ideas in the same language as the story content. If you cannot de
         let model = self.select_model(TaskKind::from(mode), account_tier, input_chars)?;
 
         // Prepare request with configurable number of candidates
-        let request = OpenAIRequest {
-            model: model.model.clone(),
+        let request = TextRequest {
+            model: model.primary().model.clone(),
             messages: vec![
-                OpenAIMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                OpenAIMessage {
-                    role: "user".to_string(),
-                    content: user_prompt,
-                },
+                ChatMessage::system(system_prompt),
+                ChatMessage::user(user_prompt),
             ],
             max_tokens: 2000,
             temperature: 0.7,
             n: effective_num_candidates,
-            response_format: None,
         };
 
-        // Call OpenRouter API
-        let mut attempts = 0;
-        let mut last_err = None;
-        while attempts <= self.config.openrouter.retry_attempts {
-            let mut builder = self
-                .http_client
-                .post(format!(
-                    "{}/chat/completions",
-                    self.config.openrouter.api_base
-                ))
-                .header(
-                    "Authorization",
-                    format!("Bearer {}", self.config.openrouter.api_key),
-                );
-
-            if let Some(ref referer) = self.config.openrouter.referer {
-                builder = builder.header("HTTP-Referer", referer);
-            }
-            if let Some(ref title) = self.config.openrouter.app_title {
-                builder = builder.header("X-Title", title);
-            }
-
-            let response = builder.json(&request).send().await;
-
-            match response {
-                Ok(resp) => {
-                    if !resp.status().is_success() {
-                        let status = resp.status();
-                        let text = resp.text().await.unwrap_or_default();
-                        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
-                            attempts += 1;
-                            last_err = Some(format!(
-                                "OpenRouter edit error {}: {}",
-                                status.as_u16(),
-                                text
-                            ));
-                            tokio::time::sleep(std::time::Duration::from_millis(
-                                200 * attempts as u64,
-                            ))
-                            .await;
-                            continue;
-                        }
-                        return Err(ApiError::AIProvider(format!(
-                            "OpenRouter edit error {}: {}",
-                            status.as_u16(),
-                            text
-                        )));
-                    }
-
-                    let openai_response: OpenAIResponse = resp.json().await.map_err(|e| {
-                        ApiError::AIProvider(format!("Failed to parse edit response: {}", e))
-                    })?;
-
-                    info!(
-                        "AI vendor edit response: model={}, choices={}, mode={:?}",
-                        model.model,
-                        openai_response.choices.len(),
-                        mode
-                    );
-
-                    // Log first choice content preview
-                    if let Some(first_choice) = openai_response.choices.first() {
-                        let preview: String =
-                            first_choice.message.content.chars().take(150).collect();
-                        info!(
-                            "AI vendor edit response preview (mode={:?}): {}...",
-                            mode, preview
-                        );
-                    }
-
-                    let mut candidates: Vec<TextEditCandidate> = openai_response
-                        .choices
-                        .into_iter()
-                        .enumerate()
-                        .map(|(idx, choice)| {
-                            info!(
-                                "Edit candidate {}: content_len={}",
-                                idx,
-                                choice.message.content.len()
-                            );
-                            TextEditCandidate {
-                                id: Uuid::new_v4().to_string(),
-                                content: choice.message.content,
-                                safety_flags: vec![],
-                            }
-                        })
-                        .collect();
-
-                    candidates.truncate(effective_num_candidates as usize);
-
-                    info!(
-                        "Generated {} edit candidates in mode {:?} using model {} (downgraded={}, attempts={})",
-                        candidates.len(),
-                        mode,
-                        model.model,
-                        model.downgraded,
-                        attempts
-                    );
+        Ok((request, model))
+    }
 
-                    return Ok(candidates);
-                }
-                Err(e) => {
-                    attempts += 1;
-                    last_err = Some(format!("OpenRouter edit request failed: {}", e));
-                    tokio::time::sleep(std::time::Duration::from_millis(200 * attempts as u64))
-                        .await;
-                }
-            }
+    /// Turn the choices of an edit response into candidates
+    fn parse_edit_candidates(contents: Vec<String>) -> Result<Vec<TextEditCandidate>> {
+        if contents.iter().all(|content| content.trim().is_empty()) {
+            return Err(ApiError::AIProvider(
+                "No content in edit response".to_string(),
+            ));
         }
 
-        Err(ApiError::AIProvider(last_err.unwrap_or_else(|| {
-            "OpenRouter edit request failed".to_string()
-        })))
+        Ok(contents
+            .into_iter()
+            .enumerate()
+            .map(|(idx, content)| {
+                info!("Edit candidate {}: content_len={}", idx, content.len());
+                TextEditCandidate {
+                    id: Uuid::new_v4().to_string(),
+                    content,
+                    safety_flags: vec![],
+                }
+            })
+            .collect())
     }
 
     fn build_edit_system_prompt(&self, mode: AITextEditMode) -> String {
@@ -905,13 +889,14 @@ Note: Generate ideas in the same language as the story content. If you cannot de
     }
 
     /// Generate summaries for multiple nodes
+    /// Returns the summaries and the model that produced them
     #[instrument(skip(self, story_context, nodes, account_tier))]
     pub async fn generate_summaries(
         &self,
         story_context: Option<&StoryContextSimple>,
         nodes: &[NodeToSummarize],
         account_tier: &AccountTier,
-    ) -> Result<Vec<NodeSummary>> {
+    ) -> Result<(Vec<NodeSummary>, String)> {
         // Build system prompt
         let system_prompt =
             "You are a writing assistant that generates concise one-line summaries. \
@@ -958,130 +943,56 @@ Note: Generate ideas in the same language as the story content. If you cannot de
         }
 
         let input_chars = system_prompt.len() + user_prompt.len();
-        let model = self.select_model(TaskKind::Summarize, account_tier, input_chars)?;
+        let selected = self.select_model(TaskKind::Summarize, account_tier, input_chars)?;
 
         // Prepare request
-        let request = OpenAIRequest {
-            model: model.model.clone(),
+        let request = TextRequest {
+            model: selected.primary().model.clone(),
             messages: vec![
-                OpenAIMessage {
-                    role: "system".to_string(),
-                    content: system_prompt.to_string(),
-                },
-                OpenAIMessage {
-                    role: "user".to_string(),
-                    content: user_prompt,
-                },
+                ChatMessage::system(system_prompt),
+                ChatMessage::user(user_prompt),
             ],
             max_tokens: 500,
             temperature: 0.5,
             n: 1,
-            response_format: None,
         };
 
-        // Call OpenRouter API
-        let mut attempts = 0;
-        let mut last_err = None;
-        while attempts <= self.config.openrouter.retry_attempts {
-            let mut builder = self
-                .http_client
-                .post(format!(
-                    "{}/chat/completions",
-                    self.config.openrouter.api_base
-                ))
-                .header(
-                    "Authorization",
-                    format!("Bearer {}", self.config.openrouter.api_key),
+        let (summaries, model) = self
+            .complete_with_fallbacks(&selected, &request, |contents| {
+                info!(
+                    "AI vendor summarize response: choices={}, nodes_count={}",
+                    contents.len(),
+                    nodes.len()
                 );
 
-            if let Some(ref referer) = self.config.openrouter.referer {
-                builder = builder.header("HTTP-Referer", referer);
-            }
-            if let Some(ref title) = self.config.openrouter.app_title {
-                builder = builder.header("X-Title", title);
-            }
-
-            let response = builder.json(&request).send().await;
-
-            match response {
-                Ok(resp) => {
-                    if !resp.status().is_success() {
-                        let status = resp.status();
-                        let text = resp.text().await.unwrap_or_default();
-                        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
-                            attempts += 1;
-                            last_err = Some(format!(
-                                "OpenRouter summarize error {}: {}",
-                                status.as_u16(),
-                                text
-                            ));
-                            tokio::time::sleep(std::time::Duration::from_millis(
-                                200 * attempts as u64,
-                            ))
-                            .await;
-                            continue;
-                        }
-                        return Err(ApiError::AIProvider(format!(
-                            "OpenRouter summarize error {}: {}",
-                            status.as_u16(),
-                            text
-                        )));
-                    }
+                let content = contents.first().map(String::as_str).unwrap_or("");
 
-                    let openai_response: OpenAIResponse = resp.json().await.map_err(|e| {
-                        ApiError::AIProvider(format!("Failed to parse summarize response: {}", e))
-                    })?;
-
-                    info!(
-                        "AI vendor summarize response: model={}, choices={}, nodes_count={}",
-                        model.model,
-                        openai_response.choices.len(),
-                        nodes.len()
-                    );
-
-                    let content = openai_response
-                        .choices
-                        .first()
-                        .map(|c| c.message.content.as_str())
-                        .unwrap_or("");
-
-                    // Log raw response
-                    info!(
-                        "AI vendor summarize raw response: {}",
-                        content.chars().take(300).collect::<String>()
-                    );
-
-                    // Parse numbered list
-                    let summaries = self.parse_numbered_summaries(content, nodes);
+                // Log raw response
+                info!(
+                    "AI vendor summarize raw response: {}",
+                    content.chars().take(300).collect::<String>()
+                );
 
-                    info!(
-                        "Parsed {} summaries from response (expected {})",
-                        summaries.len(),
-                        nodes.len()
-                    );
+                // Parse numbered list
+                Ok(self.parse_numbered_summaries(content, nodes))
+            })
+            .await?;
 
-                    info!(
-                        "Generated {} summaries using model {} (downgraded={}, attempts={})",
-                        nodes.len(),
-                        model.model,
-                        model.downgraded,
-                        attempts
-                    );
+        info!(
+            "Parsed {} summaries from response (expected {})",
+            summaries.len(),
+            nodes.len()
+        );
 
-                    return Ok(summaries);
-                }
-                Err(e) => {
-                    attempts += 1;
-                    last_err = Some(format!("OpenRouter summarize request failed: {}", e));
-                    tokio::time::sleep(std::time::Duration::from_millis(200 * attempts as u64))
-                        .await;
-                }
-            }
-        }
+        info!(
+            "Generated {} summaries using model {} (provider={}, downgraded={})",
+            nodes.len(),
+            model.model,
+            model.provider,
+            selected.downgraded
+        );
 
-        Err(ApiError::AIProvider(last_err.unwrap_or_else(|| {
-            "OpenRouter summarize request failed".to_string()
-        })))
+        Ok((summaries, model.model))
     }
 
     /// Parse numbered list of summaries
@@ -1128,10 +1039,23 @@ Note: Generate ideas in the same language as the story content. If you cannot de
 
 #[derive(Debug, Clone)]
 struct SelectedModel {
-    model: String,
+    // Models to try in order, starting with the tier's primary model
+    chain: Vec<ModelChoice>,
     downgraded: bool,
 }
 
+impl SelectedModel {
+    fn primary(&self) -> &ModelChoice {
+        &self.chain[0]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ModelChoice {
+    model: String,
+    provider: String,
+}
+
 #[derive(Debug, Clone, Copy)]
 enum TaskKind {
     FixGrammar,
@@ -1205,7 +1129,13 @@ impl AIService {
         };
 
         Ok(SelectedModel {
-            model: tier_config.model.clone(),
+            chain: tier_config
+                .chain()
+                .map(|(provider, model)| ModelChoice {
+                    model: model.to_string(),
+                    provider: provider.to_string(),
+                })
+                .collect(),
             downgraded,
         })
     }
@@ -1239,6 +1169,7 @@ impl AIService {
     }
 
     /// Generate prose story continuations using delimited text format
+    /// Returns the candidates and the model that produced them
     #[instrument(skip(self, context, nodes, account_tier))]
     pub async fn generate_prose_continuations(
         &self,
@@ -1247,7 +1178,77 @@ impl AIService {
         params: &GenerationParams,
         instructions: Option<&str>,
         account_tier: &AccountTier,
-    ) -> Result<Vec<TextCandidate>> {
+    ) -> Result<(Vec<TextCandidate>, String)> {
+        let (request, selected, num_candidates) =
+            self.build_prose_request(context, nodes, params, instructions, account_tier)?;
+        self.screen_input(&Self::user_inputs(context, nodes, params, instructions))
+            .await?;
+
+        let (mut candidates, model) = self
+            .complete_with_fallbacks(&selected, &request, |contents| {
+                // Parse delimited text response
+                self.parse_delimited_continuations(
+                    contents.first().map(String::as_str).unwrap_or(""),
+                )
+            })
+            .await?;
+        candidates.truncate(num_candidates as usize);
+        let candidates = self.screen_candidates(candidates).await?;
+
+        info!(
+            "Generated {} prose continuations using model {} (provider={}, delimited format)",
+            candidates.len(),
+            model.model,
+            model.provider
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Stream prose story continuations, sending text deltas as they arrive
+    /// Returns the parsed candidates and the model once the stream completes.
+    /// With moderation enabled, each candidate is sent whole once it has been screened.
+    #[instrument(skip(self, context, nodes, account_tier, deltas))]
+    pub async fn stream_prose_continuations(
+        &self,
+        context: &StoryContext,
+        nodes: &[PathNode],
+        params: &GenerationParams,
+        instructions: Option<&str>,
+        account_tier: &AccountTier,
+        deltas: &UnboundedSender<AITextStreamDelta>,
+    ) -> Result<(Vec<TextCandidate>, String)> {
+        let (request, selected, num_candidates) =
+            self.build_prose_request(context, nodes, params, instructions, account_tier)?;
+        self.screen_input(&Self::user_inputs(context, nodes, params, instructions))
+            .await?;
+
+        let (mut candidates, model) = self
+            .stream_delimited_candidates(&selected, &request, deltas)
+            .await?;
+        candidates.truncate(num_candidates as usize);
+        let candidates = self.screen_candidates(candidates).await?;
+        self.send_screened(&candidates, deltas);
+
+        info!(
+            "Streamed {} prose continuations using model {} (provider={})",
+            candidates.len(),
+            model.model,
+            model.provider
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Build the prose continuation request along with the selected model and effective candidate count
+    fn build_prose_request(
+        &self,
+        context: &StoryContext,
+        nodes: &[PathNode],
+        params: &GenerationParams,
+        instructions: Option<&str>,
+        account_tier: &AccountTier,
+    ) -> Result<(TextRequest, SelectedModel, u8)> {
         self.validate_generation_params(TaskKind::Continue, params, account_tier)?;
 
         let effective_params = GenerationParams {
@@ -1293,7 +1294,7 @@ Each continuation should explore a different narrative direction."#,
 
         info!(
             "Prose continuation request: model={}, max_words={}, num_candidates={}, calculated_max_tokens={}, tokens_per_item={}",
-            model.model,
+            model.primary().model,
             effective_params.max_words,
             effective_params.num_candidates,
             max_tokens,
@@ -1301,38 +1302,18 @@ Each continuation should explore a different narrative direction."#,
         );
 
         // Prepare request with plain text format (no JSON)
-        let request = OpenAIRequest {
-            model: model.model.clone(),
+        let request = TextRequest {
+            model: model.primary().model.clone(),
             messages: vec![
-                OpenAIMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                OpenAIMessage {
-                    role: "user".to_string(),
-                    content: user_prompt,
-                },
+                ChatMessage::system(system_prompt),
+                ChatMessage::user(user_prompt),
             ],
             max_tokens,
             temperature: 0.7,
             n: 1,
-            response_format: None, // Plain text format
         };
 
-        // Call API with retry logic
-        let response_text = self.call_openrouter_api(request).await?;
-
-        // Parse delimited text response
-        let mut candidates = self.parse_delimited_continuations(&response_text)?;
-        candidates.truncate(effective_params.num_candidates as usize);
-
-        info!(
-            "Generated {} prose continuations using model {} (delimited format)",
-            candidates.len(),
-            model.model
-        );
-
-        Ok(candidates)
+        Ok((request, model, effective_params.num_candidates))
     }
 
     /// Parse delimited text format into TextCandidates
@@ -1349,11 +1330,9 @@ Each continuation should explore a different narrative direction."#,
             // Extract title
             let title = if let Some(title_start) = section.find("TITLE:") {
                 let title_text = &section[title_start + 6..];
-                if let Some(title_end) = title_text.find("CONTENT:") {
-                    Some(title_text[..title_end].trim().to_string())
-                } else {
-                    None
-                }
+                title_text
+                    .find("CONTENT:")
+                    .map(|title_end| title_text[..title_end].trim().to_string())
             } else {
                 None
             };
@@ -1386,6 +1365,7 @@ Each continuation should explore a different narrative direction."#,
     }
 
     /// Generate high-level continuation ideas using delimited text format
+    /// Returns the candidates and the model that produced them
     #[instrument(skip(self, context, nodes, account_tier))]
     pub async fn generate_continuation_ideas(
         &self,
@@ -1394,7 +1374,77 @@ Each continuation should explore a different narrative direction."#,
         params: &GenerationParams,
         instructions: Option<&str>,
         account_tier: &AccountTier,
-    ) -> Result<Vec<TextCandidate>> {
+    ) -> Result<(Vec<TextCandidate>, String)> {
+        let (request, selected, num_candidates) =
+            self.build_ideas_request(context, nodes, params, instructions, account_tier)?;
+        self.screen_input(&Self::user_inputs(context, nodes, params, instructions))
+            .await?;
+
+        let (mut candidates, model) = self
+            .complete_with_fallbacks(&selected, &request, |contents| {
+                // Parse delimited text response
+                self.parse_delimited_continuations(
+                    contents.first().map(String::as_str).unwrap_or(""),
+                )
+            })
+            .await?;
+        candidates.truncate(num_candidates as usize);
+        let candidates = self.screen_candidates(candidates).await?;
+
+        info!(
+            "Generated {} continuation ideas using model {} (provider={}, delimited format)",
+            candidates.len(),
+            model.model,
+            model.provider
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Stream high-level continuation ideas, sending text deltas as they arrive
+    /// Returns the parsed candidates and the model once the stream completes.
+    /// With moderation enabled, each candidate is sent whole once it has been screened.
+    #[instrument(skip(self, context, nodes, account_tier, deltas))]
+    pub async fn stream_continuation_ideas(
+        &self,
+        context: &StoryContext,
+        nodes: &[PathNode],
+        params: &GenerationParams,
+        instructions: Option<&str>,
+        account_tier: &AccountTier,
+        deltas: &UnboundedSender<AITextStreamDelta>,
+    ) -> Result<(Vec<TextCandidate>, String)> {
+        let (request, selected, num_candidates) =
+            self.build_ideas_request(context, nodes, params, instructions, account_tier)?;
+        self.screen_input(&Self::user_inputs(context, nodes, params, instructions))
+            .await?;
+
+        let (mut candidates, model) = self
+            .stream_delimited_candidates(&selected, &request, deltas)
+            .await?;
+        candidates.truncate(num_candidates as usize);
+        let candidates = self.screen_candidates(candidates).await?;
+        self.send_screened(&candidates, deltas);
+
+        info!(
+            "Streamed {} continuation ideas using model {} (provider={})",
+            candidates.len(),
+            model.model,
+            model.provider
+        );
+
+        Ok((candidates, model.model))
+    }
+
+    /// Build the continuation ideas request along with the selected model and effective candidate count
+    fn build_ideas_request(
+        &self,
+        context: &StoryContext,
+        nodes: &[PathNode],
+        params: &GenerationParams,
+        instructions: Option<&str>,
+        account_tier: &AccountTier,
+    ) -> Result<(TextRequest, SelectedModel, u8)> {
         self.validate_generation_params(TaskKind::Ideas, params, account_tier)?;
 
         let effective_params = GenerationParams {
@@ -1439,7 +1489,7 @@ Each idea should suggest a distinct narrative direction: character decision, plo
 
         info!(
             "Ideas request: model={}, max_words={}, num_candidates={}, calculated_max_tokens={}, tokens_per_item={}",
-            model.model,
+            model.primary().model,
             effective_params.max_words,
             effective_params.num_candidates,
             max_tokens,
@@ -1447,110 +1497,372 @@ Each idea should suggest a distinct narrative direction: character decision, plo
         );
 
         // Prepare request with plain text format (no JSON)
-        let request = OpenAIRequest {
-            model: model.model.clone(),
+        let request = TextRequest {
+            model: model.primary().model.clone(),
             messages: vec![
-                OpenAIMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                OpenAIMessage {
-                    role: "user".to_string(),
-                    content: user_prompt,
-                },
+                ChatMessage::system(system_prompt),
+                ChatMessage::user(user_prompt),
             ],
             max_tokens,
             temperature: 0.8, // Higher creativity for ideas
             n: 1,
-            response_format: None, // Plain text format
         };
 
-        // Call API with retry logic
-        let response_text = self.call_openrouter_api(request).await?;
+        Ok((request, model, effective_params.num_candidates))
+    }
 
-        // Parse delimited text response
-        let mut candidates = self.parse_delimited_continuations(&response_text)?;
-        candidates.truncate(effective_params.num_candidates as usize);
+    /// Complete `request` with each model of the chain in turn until one gives usable output
+    /// Returns the parsed output and the model that produced it
+    async fn complete_with_fallbacks<T>(
+        &self,
+        selected: &SelectedModel,
+        request: &TextRequest,
+        parse: impl Fn(Vec<String>) -> Result<T>,
+    ) -> Result<(T, ModelChoice)> {
+        let mut last_err = None;
 
-        info!(
-            "Generated {} continuation ideas using model {} (delimited format)",
-            candidates.len(),
-            model.model
-        );
+        for (attempt, choice) in selected.chain.iter().enumerate() {
+            let request = TextRequest {
+                model: choice.model.clone(),
+                ..request.clone()
+            };
 
-        Ok(candidates)
+            let provider = self.text_provider(&choice.provider)?;
+            let result = self
+                .guarded(&choice.provider, &choice.model, provider.complete(&request))
+                .await;
+
+            let err = match result {
+                Ok(contents) => match parse(contents) {
+                    Ok(output) => {
+                        if attempt > 0 {
+                            info!(
+                                "Fallback model {} (provider={}) answered after {} failed attempts",
+                                choice.model, choice.provider, attempt
+                            );
+                        }
+                        return Ok((output, choice.clone()));
+                    }
+                    // Output we cannot use is treated like no answer at all
+                    Err(err) => err,
+                },
+                Err(
+                    err @ (ApiError::AIProviderUnavailable(_) | ApiError::AIProviderCircuitOpen(_)),
+                ) => err,
+                Err(err) => return Err(err),
+            };
+
+            warn!(
+                "Model {} (provider={}) failed: {}",
+                choice.model, choice.provider, err
+            );
+            last_err = Some(err);
+        }
+
+        Err(last_err.unwrap_or_else(|| ApiError::AIProvider("No models configured".to_string())))
     }
 
-    /// Helper function to call OpenRouter API with retry logic
-    async fn call_openrouter_api(&self, request: OpenAIRequest) -> Result<String> {
-        let mut attempts = 0;
+    /// Stream `request` with each model of the chain in turn until one gives usable output
+    /// Falls back only while nothing has been streamed; once the client has seen text, errors are final.
+    async fn stream_with_fallbacks<T>(
+        &self,
+        selected: &SelectedModel,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+        parse: impl Fn(Vec<String>) -> Result<T>,
+    ) -> Result<(T, ModelChoice)> {
         let mut last_err = None;
 
-        while attempts <= self.config.openrouter.retry_attempts {
-            let mut builder = self
-                .http_client
-                .post(format!(
-                    "{}/chat/completions",
-                    self.config.openrouter.api_base
-                ))
-                .header(
-                    "Authorization",
-                    format!("Bearer {}", self.config.openrouter.api_key),
-                );
-
-            if let Some(ref referer) = self.config.openrouter.referer {
-                builder = builder.header("HTTP-Referer", referer);
-            }
-            if let Some(ref title) = self.config.openrouter.app_title {
-                builder = builder.header("X-Title", title);
-            }
+        for (attempt, choice) in selected.chain.iter().enumerate() {
+            let request = TextRequest {
+                model: choice.model.clone(),
+                ..request.clone()
+            };
 
-            let response = builder.json(&request).send().await;
-
-            match response {
-                Ok(resp) => {
-                    if !resp.status().is_success() {
-                        let status = resp.status();
-                        let text = resp.text().await.unwrap_or_default();
-                        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
-                            attempts += 1;
-                            last_err =
-                                Some(format!("OpenRouter error {}: {}", status.as_u16(), text));
-                            tokio::time::sleep(std::time::Duration::from_millis(
-                                200 * attempts as u64,
-                            ))
-                            .await;
-                            continue;
+            let provider = self.text_provider(&choice.provider)?;
+            let mut streamed = false;
+            let result = self
+                .guarded(
+                    &choice.provider,
+                    &choice.model,
+                    provider.complete_stream(&request, &mut |candidate_index, delta| {
+                        streamed = true;
+                        on_delta(candidate_index, delta);
+                    }),
+                )
+                .await;
+
+            let err = match result {
+                Ok(contents) => match parse(contents) {
+                    Ok(output) => {
+                        if attempt > 0 {
+                            info!(
+                                "Fallback model {} (provider={}) answered after {} failed attempts",
+                                choice.model, choice.provider, attempt
+                            );
                         }
-                        return Err(ApiError::AIProvider(format!(
-                            "OpenRouter error {}: {}",
-                            status.as_u16(),
-                            text
-                        )));
+                        return Ok((output, choice.clone()));
                     }
+                    Err(err) if !streamed => err,
+                    Err(err) => return Err(err),
+                },
+                Err(
+                    err @ (ApiError::AIProviderUnavailable(_) | ApiError::AIProviderCircuitOpen(_)),
+                ) if !streamed => err,
+                Err(err) => return Err(err),
+            };
 
-                    let openai_response: OpenAIResponse = resp.json().await.map_err(|e| {
-                        ApiError::AIProvider(format!("Failed to parse response: {}", e))
-                    })?;
+            warn!(
+                "Model {} (provider={}) failed before streaming: {}",
+                choice.model, choice.provider, err
+            );
+            last_err = Some(err);
+        }
 
-                    // Extract content from first choice
-                    if let Some(first_choice) = openai_response.choices.first() {
-                        return Ok(first_choice.message.content.clone());
-                    } else {
-                        return Err(ApiError::AIProvider("No choices in response".to_string()));
-                    }
-                }
-                Err(e) => {
-                    attempts += 1;
-                    last_err = Some(format!("OpenRouter request failed: {}", e));
-                    tokio::time::sleep(std::time::Duration::from_millis(200 * attempts as u64))
-                        .await;
+        Err(last_err.unwrap_or_else(|| ApiError::AIProvider("No models configured".to_string())))
+    }
+
+    /// Stream a delimited-format completion, tagging deltas with the candidate being written
+    /// Returns the parsed candidates and the model that produced them. Deltas are held back
+    /// while moderation is enabled; the caller sends the screened candidates instead.
+    async fn stream_delimited_candidates(
+        &self,
+        selected: &SelectedModel,
+        request: &TextRequest,
+        deltas: &UnboundedSender<AITextStreamDelta>,
+    ) -> Result<(Vec<TextCandidate>, ModelChoice)> {
+        let live = !self.moderator.is_enabled();
+        let mut text = String::new();
+        self.stream_with_fallbacks(
+            selected,
+            request,
+            &mut |_, delta| {
+                if !live {
+                    return;
                 }
+                text.push_str(delta);
+                let candidate_index = text.matches("=== CANDIDATE").count().saturating_sub(1);
+                let _ = deltas.send(AITextStreamDelta {
+                    candidate_index,
+                    delta: delta.to_string(),
+                });
+            },
+            |contents| {
+                self.parse_delimited_continuations(
+                    contents.first().map(String::as_str).unwrap_or(""),
+                )
+            },
+        )
+        .await
+    }
+
+    // ==================== Moderation ====================
+
+    /// User-written text of a path-based request
+    fn user_inputs<'a>(
+        context: &'a StoryContext,
+        nodes: &'a [PathNode],
+        params: &'a GenerationParams,
+        instructions: Option<&'a str>,
+    ) -> Vec<&'a str> {
+        let background = context.background.as_ref();
+        let characters = context.active_characters.iter().flatten().flat_map(|c| {
+            [
+                Some(c.name.as_str()),
+                c.role.as_deref(),
+                c.description.as_deref(),
+            ]
+        });
+
+        [context.title.as_deref()]
+            .into_iter()
+            .chain(context.tags.iter().map(|tag| Some(tag.as_str())))
+            .chain([
+                background.and_then(|b| b.genre.as_deref()),
+                background.and_then(|b| b.tone.as_deref()),
+                background.and_then(|b| b.setting.as_deref()),
+            ])
+            .chain(characters)
+            .chain(
+                nodes
+                    .iter()
+                    .flat_map(|node| [node.summary.as_deref(), Some(node.content.as_str())]),
+            )
+            .chain([params.tone.as_deref(), instructions])
+            .flatten()
+            .collect()
+    }
+
+    /// User-written text of an edit request
+    fn edit_inputs<'a>(
+        story_context: Option<&'a StoryContextSimple>,
+        input: &'a EditInput,
+        params: &'a EditParams,
+    ) -> Vec<&'a str> {
+        story_context
+            .into_iter()
+            .flat_map(|context| {
+                [context.title.as_deref()]
+                    .into_iter()
+                    .chain(context.tags.iter().map(|tag| Some(tag.as_str())))
+            })
+            .chain([
+                Some(input.text.as_str()),
+                input.selection.as_deref(),
+                params.tone.as_deref(),
+            ])
+            .flatten()
+            .collect()
+    }
+
+    /// Screen text with the blocklist and, when configured, the classifier model
+    async fn moderate(&self, text: &str) -> ModerationVerdict {
+        let mut verdict = self.moderator.check_rules(text);
+        let Some(model) = self.moderator.model().filter(|_| !verdict.blocked) else {
+            return verdict;
+        };
+
+        let request = Moderator::classification_request(model, text);
+        let result = match self.text_provider(&model.provider) {
+            Ok(provider) => {
+                self.guarded(&model.provider, &model.model, provider.complete(&request))
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(choices) => verdict.merge(Moderator::parse_classification(
+                model,
+                choices.first().map(String::as_str).unwrap_or(""),
+            )),
+            // Fail open: the blocklist still applies while the classifier is down
+            Err(err) => warn!("Moderation model {} failed: {}", model.model, err),
+        }
+        verdict
+    }
+
+    /// Reject the request when the user's input is disallowed
+    async fn screen_input(&self, parts: &[&str]) -> Result<()> {
+        if !self.moderator.is_enabled() {
+            return Ok(());
+        }
+
+        let verdict = self.moderate(&parts.join("\n\n")).await;
+        if verdict.blocked {
+            return Err(ApiError::ContentRejected(format!(
+                "Input flagged: {}",
+                verdict.flags.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send each screened candidate as a single delta when moderation held back the live stream
+    fn send_screened<C: Screened>(
+        &self,
+        candidates: &[C],
+        deltas: &UnboundedSender<AITextStreamDelta>,
+    ) {
+        if !self.moderator.is_enabled() {
+            return;
+        }
+
+        for (candidate_index, candidate) in candidates.iter().enumerate() {
+            let _ = deltas.send(AITextStreamDelta {
+                candidate_index,
+                delta: candidate.streamed_text(candidate_index),
+            });
+        }
+    }
+
+    /// Fill in safety flags, mask matched text and drop disallowed candidates
+    /// Fails when no candidate remains, so the caller releases the credit hold.
+    async fn screen_candidates<C: Screened>(&self, candidates: Vec<C>) -> Result<Vec<C>> {
+        if !self.moderator.is_enabled() {
+            return Ok(candidates);
+        }
+
+        let total = candidates.len();
+        let mut kept = Vec::with_capacity(total);
+        let mut flags = Vec::new();
+        for mut candidate in candidates {
+            let verdict = self.moderate(&candidate.screened_text()).await;
+            if verdict.blocked {
+                info!("Dropped candidate flagged {:?}", verdict.flags);
+                flags.extend(verdict.flags);
+                continue;
             }
+            candidate.mask(&self.moderator);
+            candidate.safety_flags_mut().extend(verdict.flags);
+            kept.push(candidate);
         }
 
-        Err(ApiError::AIProvider(
-            last_err.unwrap_or_else(|| "OpenRouter request failed".to_string()),
-        ))
+        if kept.is_empty() {
+            return Err(ApiError::ContentRejected(format!(
+                "All {} candidates flagged: {}",
+                total,
+                flags.join(", ")
+            )));
+        }
+        Ok(kept)
+    }
+}
+
+/// Generated output that passes through moderation
+trait Screened {
+    fn screened_text(&self) -> String;
+    /// Text sent to a streaming client, in the format the live stream would have used
+    fn streamed_text(&self, candidate_index: usize) -> String;
+    fn mask(&mut self, moderator: &Moderator);
+    fn safety_flags_mut(&mut self) -> &mut Vec<String>;
+}
+
+impl Screened for TextCandidate {
+    fn screened_text(&self) -> String {
+        match &self.title {
+            Some(title) => format!("{}\n{}", title, self.content),
+            None => self.content.clone(),
+        }
+    }
+
+    fn streamed_text(&self, candidate_index: usize) -> String {
+        let title = self
+            .title
+            .as_ref()
+            .map(|title| format!("TITLE: {}\n", title))
+            .unwrap_or_default();
+        format!(
+            "=== CANDIDATE {} ===\n{}CONTENT: {}",
+            candidate_index + 1,
+            title,
+            self.content
+        )
+    }
+
+    fn mask(&mut self, moderator: &Moderator) {
+        self.title = self.title.as_deref().map(|title| moderator.mask(title));
+        self.content = moderator.mask(&self.content);
+    }
+
+    fn safety_flags_mut(&mut self) -> &mut Vec<String> {
+        &mut self.safety_flags
+    }
+}
+
+impl Screened for TextEditCandidate {
+    fn screened_text(&self) -> String {
+        self.content.clone()
+    }
+
+    fn streamed_text(&self, _candidate_index: usize) -> String {
+        self.content.clone()
+    }
+
+    fn mask(&mut self, moderator: &Moderator) {
+        self.content = moderator.mask(&self.content);
+    }
+
+    fn safety_flags_mut(&mut self) -> &mut Vec<String> {
+        &mut self.safety_flags
     }
 }
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::{
+    config::CircuitBreakerConfig,
+    error::{ApiError, Result},
+    models::ai::{CircuitState, ModelCircuitStatus},
+};
+
+/// Per-model circuit breakers shared by every request
+///
+/// A circuit opens after `failure_threshold` consecutive availability failures and rejects calls
+/// until `open_duration_ms` has passed. It then admits a single probe (half-open): success
+/// closes the circuit, failure re-opens it.
+pub struct CircuitBreakers {
+    failure_threshold: u32,
+    open_duration: Duration,
+    // Keyed by (provider, model)
+    circuits: Mutex<HashMap<(String, String), Circuit>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    // Set while open or half-open
+    opened: Option<Opened>,
+    // When the half-open probe was admitted; a probe that never reports back expires
+    probe_started: Option<Instant>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Opened {
+    at: Instant,
+    at_utc: OffsetDateTime,
+}
+
+impl Circuit {
+    fn state(&self, open_duration: Duration) -> CircuitState {
+        match self.opened {
+            None => CircuitState::Closed,
+            Some(opened) if opened.at.elapsed() < open_duration => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl CircuitBreakers {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            open_duration: Duration::from_millis(config.open_duration_ms),
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admit a call to the model, or fail fast while its circuit is open
+    pub fn acquire(&self, provider: &str, model: &str) -> Result<()> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(&(provider.to_string(), model.to_string())) else {
+            return Ok(());
+        };
+
+        match circuit.state(self.open_duration) {
+            CircuitState::Closed => Ok(()),
+            CircuitState::HalfOpen
+                if circuit
+                    .probe_started
+                    .is_none_or(|started| started.elapsed() >= self.open_duration) =>
+            {
+                info!(
+                    "Circuit half-open for model {} (provider={}), admitting probe",
+                    model, provider
+                );
+                circuit.probe_started = Some(Instant::now());
+                Ok(())
+            }
+            CircuitState::Open | CircuitState::HalfOpen => Err(ApiError::AIProviderCircuitOpen(
+                format!("Circuit open for model {} (provider={})", model, provider),
+            )),
+        }
+    }
+
+    /// Record that the model answered, closing its circuit
+    pub fn record_success(&self, provider: &str, model: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if let Some(circuit) = circuits.remove(&(provider.to_string(), model.to_string())) {
+            if circuit.opened.is_some() {
+                info!("Circuit closed for model {} (provider={})", model, provider);
+            }
+        }
+    }
+
+    /// Record an availability failure, opening the circuit once the threshold is reached
+    pub fn record_failure(&self, provider: &str, model: &str, error: &ApiError) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry((provider.to_string(), model.to_string()))
+            .or_default();
+
+        circuit.consecutive_failures += 1;
+        circuit.last_error = Some(error.to_string());
+
+        let probe_failed = circuit.probe_started.take().is_some();
+        if probe_failed || circuit.consecutive_failures >= self.failure_threshold {
+            if circuit.state(self.open_duration) != CircuitState::Open {
+                warn!(
+                    "Circuit opened for model {} (provider={}) after {} consecutive failures",
+                    model, provider, circuit.consecutive_failures
+                );
+            }
+            circuit.opened = Some(Opened {
+                at: Instant::now(),
+                at_utc: OffsetDateTime::now_utc(),
+            });
+        }
+    }
+
+    /// Current state of every model that has failed since it last answered
+    pub fn status(&self) -> Vec<ModelCircuitStatus> {
+        let circuits = self.circuits.lock().unwrap();
+        let mut status: Vec<ModelCircuitStatus> = circuits
+            .iter()
+            .map(|((provider, model), circuit)| ModelCircuitStatus {
+                provider: provider.clone(),
+                model: model.clone(),
+                state: circuit.state(self.open_duration),
+                consecutive_failures: circuit.consecutive_failures,
+                opened_at: circuit.opened.map(|opened| opened.at_utc),
+                retry_in_ms: circuit.opened.map(|opened| {
+                    self.open_duration
+                        .saturating_sub(opened.at.elapsed())
+                        .as_millis() as u64
+                }),
+                last_error: circuit.last_error.clone(),
+            })
+            .collect();
+        status.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        status
+    }
+}
@@ -4,15 +4,18 @@ use crate::{
         common::IAPPlatform,
         credit_events_ext::CreditEventExt,
         credits::{
-            CreditPurchaseRecord, CreditsQuotaInfo, CreditsQuotaSummary, ExtraCreditsInfo,
-            SubscriptionCreditsInfo,
+            CreditHistoryEntry, CreditHistoryResponse, CreditPurchaseRecord, CreditReservation,
+            CreditsQuotaInfo, CreditsQuotaSummary, ExtraCreditsInfo, SubscriptionCreditsInfo,
+            UsageContext,
         },
     },
 };
 use anyhow::anyhow;
 use sea_orm::{
-    entity::*, query::*, sea_query::OnConflict, DatabaseConnection, DatabaseTransaction,
-    TransactionTrait,
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+    DatabaseConnection, DatabaseTransaction, TransactionTrait,
 };
 use tracing::{info, instrument};
 use uuid::Uuid;
@@ -95,6 +98,15 @@ impl CreditsService {
                 ))
             })?;
 
+        // A transaction can only ever be redeemed by the account that first recorded it
+        if persisted_purchase.user_id != user_id {
+            txn.rollback().await?;
+            return Err(ApiError::InvalidReceipt(format!(
+                "Transaction {} was already redeemed by another account",
+                transaction_id
+            )));
+        }
+
         // Recalculate totals (idempotent: same result if already existed)
         let total_extra = self.recalculate_extra_credits_txn(user_id, &txn).await?;
         txn.commit().await?;
@@ -183,6 +195,299 @@ impl CreditsService {
         Ok((event_id, total_extra))
     }
 
+    /// Revoke a purchased credit pack (refund or revocation by the store) within an existing transaction
+    ///
+    /// Returns the owning user and their recalculated extra credits, or None if the
+    /// transaction was never redeemed here. Revoking twice keeps the original revocation.
+    #[instrument(skip(self, txn))]
+    pub async fn revoke_purchase_in_txn(
+        &self,
+        transaction_id: &str,
+        reason: &str,
+        revoked_at: time::OffsetDateTime,
+        txn: &DatabaseTransaction,
+    ) -> Result<Option<(Uuid, i32)>> {
+        let Some(purchase) = entity::credits_events::Entity::find()
+            .filter(entity::credits_events::Column::TransactionId.eq(transaction_id))
+            .filter(entity::credits_events::Column::EventType.eq("purchase"))
+            .lock_exclusive()
+            .one(txn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let user_id = purchase.user_id;
+        // Only what the pack still holds is clawed back; consumed credits stay spent
+        let clawed_back = if purchase.revoked_at.is_none() {
+            purchase.remaining()
+        } else {
+            0
+        };
+        if purchase.revoked_at.is_none() {
+            let mut active: entity::credits_events::ActiveModel = purchase.into();
+            active.revoked_at = Set(Some(revoked_at));
+            active.revoked_reason = Set(Some(reason.to_string()));
+            active.update(txn).await?;
+        }
+
+        let total_extra = self.recalculate_extra_credits_txn(user_id, txn).await?;
+
+        info!(
+            "Revoked credit purchase: user={}, transaction={}, reason={}, clawed_back={}, total_extra={}",
+            user_id, transaction_id, reason, clawed_back, total_extra
+        );
+
+        Ok(Some((user_id, total_extra)))
+    }
+
+    /// Consume extra credits oldest grant first within an existing transaction
+    ///
+    /// Draws `amount` down from the user's unrevoked grants ordered by occurred_at and
+    /// records a consumption event for the reservation whose metadata lists the funding
+    /// events. Returns the recalculated extra credits.
+    #[instrument(skip(self, metadata, txn))]
+    pub async fn consume_extra_credits_in_txn(
+        &self,
+        user_id: Uuid,
+        reservation_id: Uuid,
+        amount: i32,
+        metadata: serde_json::Value,
+        txn: &DatabaseTransaction,
+    ) -> Result<i32> {
+        let grants = entity::credits_events::Entity::find()
+            .filter(entity::credits_events::Column::UserId.eq(user_id))
+            .filter(entity::credits_events::Column::RevokedAt.is_null())
+            .filter(entity::credits_events::Column::EventType.ne("consumption"))
+            .filter(
+                Expr::col(entity::credits_events::Column::Amount)
+                    .gt(Expr::col(entity::credits_events::Column::Consumed)),
+            )
+            .order_by_asc(entity::credits_events::Column::OccurredAt)
+            .order_by_asc(entity::credits_events::Column::Id)
+            .lock_exclusive()
+            .all(txn)
+            .await?;
+
+        let available: i32 = grants.iter().map(|g| g.remaining()).sum();
+        if available < amount {
+            return Err(ApiError::QuotaExceeded(format!(
+                "Insufficient extra credits: need {}, have {}",
+                amount, available
+            )));
+        }
+
+        // Draw down FIFO by purchase date
+        let mut outstanding = amount;
+        let mut allocations = Vec::new();
+        for grant in grants {
+            if outstanding == 0 {
+                break;
+            }
+            let take = outstanding.min(grant.remaining());
+            outstanding -= take;
+            allocations.push(serde_json::json!({ "eventId": grant.id, "amount": take }));
+
+            let consumed = grant.consumed;
+            let mut active: entity::credits_events::ActiveModel = grant.into();
+            active.consumed = Set(consumed + take);
+            active.update(txn).await?;
+        }
+
+        let mut metadata = metadata;
+        metadata["allocations"] = serde_json::Value::Array(allocations);
+
+        let now = time::OffsetDateTime::now_utc();
+        let consumption = entity::credits_events::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            event_type: Set("consumption".to_string()),
+            original_transaction_id: Set(None),
+            transaction_id: Set(consumption_transaction_id(reservation_id)),
+            product_id: Set(None),
+            platform: Set(None),
+            amount: Set(amount),
+            consumed: Set(amount),
+            occurred_at: Set(now),
+            verified_at: Set(now),
+            receipt_data: Set(None),
+            revoked_at: Set(None),
+            revoked_reason: Set(None),
+            device_id: Set(None),
+            provider: Set(None),
+            provider_user_id: Set(None),
+            metadata: Set(Some(metadata)),
+        };
+        entity::credits_events::Entity::insert(consumption)
+            .exec(txn)
+            .await?;
+
+        self.recalculate_extra_credits_txn(user_id, txn).await
+    }
+
+    /// Give back the extra credits a reservation consumed within an existing transaction
+    ///
+    /// Each funding grant gets back exactly what it gave, so FIFO order is preserved.
+    /// Grants revoked in the meantime stay revoked. Returns the credits restored and
+    /// the recalculated extra credits.
+    #[instrument(skip(self, txn))]
+    pub async fn restore_extra_credits_in_txn(
+        &self,
+        user_id: Uuid,
+        reservation_id: Uuid,
+        txn: &DatabaseTransaction,
+    ) -> Result<(i32, i32)> {
+        let Some(consumption) = entity::credits_events::Entity::find()
+            .filter(
+                entity::credits_events::Column::TransactionId
+                    .eq(consumption_transaction_id(reservation_id)),
+            )
+            .filter(entity::credits_events::Column::UserId.eq(user_id))
+            .filter(entity::credits_events::Column::EventType.eq("consumption"))
+            .lock_exclusive()
+            .one(txn)
+            .await?
+        else {
+            let total_extra = self.recalculate_extra_credits_txn(user_id, txn).await?;
+            return Ok((0, total_extra));
+        };
+
+        if consumption.revoked_at.is_some() {
+            return Err(ApiError::BadRequest(format!(
+                "Credits of reservation {} were already restored",
+                reservation_id
+            )));
+        }
+
+        let allocations: Vec<(Uuid, i32)> = consumption
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("allocations"))
+            .and_then(|a| a.as_array())
+            .map(|allocations| {
+                allocations
+                    .iter()
+                    .filter_map(|a| {
+                        let event_id = a.get("eventId")?.as_str()?.parse().ok()?;
+                        let amount = a.get("amount")?.as_i64()? as i32;
+                        Some((event_id, amount))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut restored = 0;
+        for (event_id, amount) in allocations {
+            let Some(grant) = entity::credits_events::Entity::find_by_id(event_id)
+                .lock_exclusive()
+                .one(txn)
+                .await?
+            else {
+                continue;
+            };
+            if grant.revoked_at.is_some() {
+                continue;
+            }
+
+            let consumed = grant.consumed;
+            let mut active: entity::credits_events::ActiveModel = grant.into();
+            active.consumed = Set((consumed - amount).max(0));
+            active.update(txn).await?;
+            restored += amount.min(consumed);
+        }
+
+        let mut active: entity::credits_events::ActiveModel = consumption.into();
+        active.revoked_at = Set(Some(time::OffsetDateTime::now_utc()));
+        active.revoked_reason = Set(Some("refunded".to_string()));
+        active.update(txn).await?;
+
+        let total_extra = self.recalculate_extra_credits_txn(user_id, txn).await?;
+
+        Ok((restored, total_extra))
+    }
+
+    /// Append an entry to the usage ledger within an existing transaction
+    ///
+    /// Entries are never updated; a refund is a new entry pointing at its charge.
+    #[instrument(skip(self, reservation, usage, txn))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_usage_entry_in_txn(
+        &self,
+        user_id: Uuid,
+        entry_type: &str,
+        reservation: &CreditReservation,
+        usage: &UsageContext,
+        outcome: &str,
+        charge_id: Option<Uuid>,
+        txn: &DatabaseTransaction,
+    ) -> Result<Uuid> {
+        let entry_id = Uuid::new_v4();
+        let entry = entity::credit_usage_ledger::ActiveModel {
+            id: Set(entry_id),
+            user_id: Set(user_id),
+            entry_type: Set(entry_type.to_string()),
+            operation: Set(reservation.operation.as_str().to_string()),
+            cost: Set(reservation.cost),
+            subscription_credits: Set(reservation.subscription_credits),
+            extra_credits: Set(reservation.extra_credits),
+            model: Set(usage.model.clone()),
+            request_id: Set(usage.request_id.clone()),
+            outcome: Set(outcome.to_string()),
+            error_message: Set(usage.error.clone()),
+            charge_id: Set(charge_id),
+            created_at: Set(time::OffsetDateTime::now_utc()),
+            reservation_id: Set(Some(reservation.id)),
+        };
+        entity::credit_usage_ledger::Entity::insert(entry)
+            .exec(txn)
+            .await?;
+
+        Ok(entry_id)
+    }
+
+    /// Get a page of the user's usage ledger, newest first
+    #[instrument(skip(self))]
+    pub async fn get_usage_history(
+        &self,
+        user_id: Uuid,
+        page: u64,
+        page_size: u64,
+    ) -> Result<CreditHistoryResponse> {
+        let paginator = entity::credit_usage_ledger::Entity::find()
+            .filter(entity::credit_usage_ledger::Column::UserId.eq(user_id))
+            .order_by_desc(entity::credit_usage_ledger::Column::CreatedAt)
+            .order_by_desc(entity::credit_usage_ledger::Column::Id)
+            .paginate(&self.db, page_size);
+
+        let totals = paginator.num_items_and_pages().await?;
+        let entries = paginator
+            .fetch_page(page.saturating_sub(1))
+            .await?
+            .into_iter()
+            .map(|entry| CreditHistoryEntry {
+                id: entry.id,
+                entry_type: entry.entry_type,
+                operation: entry.operation,
+                cost: entry.cost,
+                subscription_credits: entry.subscription_credits,
+                extra_credits: entry.extra_credits,
+                model: entry.model,
+                request_id: entry.request_id,
+                outcome: entry.outcome,
+                charge_id: entry.charge_id,
+                created_at: entry.created_at,
+            })
+            .collect();
+
+        Ok(CreditHistoryResponse {
+            entries,
+            page,
+            page_size,
+            total_entries: totals.number_of_items,
+            total_pages: totals.number_of_pages,
+        })
+    }
+
     /// Get all purchases for a user (ordered by purchase_date for FIFO)
     #[instrument(skip(self))]
     pub async fn get_user_purchases(
@@ -287,6 +592,17 @@ impl CreditsService {
         // Use balance.extra_credits_remaining from user_credit_balance (accurate!)
         let extra_credits_total = balance.extra_credits_remaining;
 
+        // Held credits are already deducted from the balance until committed or released
+        let held_credits: Option<i64> = entity::credit_reservations::Entity::find()
+            .select_only()
+            .column_as(entity::credit_reservations::Column::Cost.sum(), "held")
+            .filter(entity::credit_reservations::Column::UserId.eq(user_id))
+            .filter(entity::credit_reservations::Column::Status.eq("held"))
+            .into_tuple()
+            .one(&self.db)
+            .await?
+            .flatten();
+
         Ok(CreditsQuotaInfo {
             subscription_credits: SubscriptionCreditsInfo {
                 current: balance.subscription_credits,
@@ -298,6 +614,7 @@ impl CreditsService {
                 purchases: purchase_records,
             },
             total_credits: balance.subscription_credits + extra_credits_total,
+            held_credits: held_credits.unwrap_or(0) as i32,
         })
     }
 
@@ -309,6 +626,12 @@ impl CreditsService {
             subscription_credits: full.subscription_credits,
             extra_credits_total: full.extra_credits.total,
             total_credits: full.total_credits,
+            held_credits: full.held_credits,
         })
     }
 }
+
+/// Consumption events are keyed by the reservation that drew them
+fn consumption_transaction_id(reservation_id: Uuid) -> String {
+    format!("consumption-{}", reservation_id)
+}
@@ -0,0 +1,374 @@
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    error::{ApiError, Result},
+    models::export::ExportFormat,
+    services::{export_pdf, object_store, share_page::escape},
+};
+
+/// A linear story path ready to be rendered in any export format
+#[derive(Debug, Clone)]
+pub struct ExportDocument {
+    pub title: String,
+    pub author: Option<String>,
+    pub language: String,
+    pub chapters: Vec<ExportChapter>,
+}
+
+/// One node of the path
+#[derive(Debug, Clone)]
+pub struct ExportChapter {
+    pub title: String,
+    pub content: String,
+    pub illustrations: Vec<ExportImage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    pub alt: Option<String>,
+}
+
+impl ExportDocument {
+    /// The first illustration in reading order
+    pub fn cover(&self) -> Option<&ExportImage> {
+        self.chapters
+            .iter()
+            .flat_map(|chapter| &chapter.illustrations)
+            .next()
+    }
+
+    /// Text and image bytes, which is roughly what rendering has to process
+    pub fn input_bytes(&self) -> usize {
+        self.title.len()
+            + self
+                .chapters
+                .iter()
+                .map(|chapter| {
+                    chapter.title.len()
+                        + chapter.content.len()
+                        + chapter
+                            .illustrations
+                            .iter()
+                            .map(|image| image.bytes.len())
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl ExportChapter {
+    /// Blank lines separate paragraphs; single line breaks are kept inside them
+    pub fn paragraphs(&self) -> impl Iterator<Item = &str> {
+        self.content
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+    }
+}
+
+/// Encode the document; CPU bound, call from a blocking task
+pub fn render(document: &ExportDocument, format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Epub => render_epub(document),
+        ExportFormat::Markdown => render_markdown(document),
+        ExportFormat::Pdf => export_pdf::render(document),
+    }
+}
+
+/// Download file name for the document, e.g. "the-echo-keeper.epub"
+pub fn file_name(document: &ExportDocument, format: ExportFormat) -> String {
+    let mut stem = String::new();
+    for c in document.title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    let stem = if stem.is_empty() { "story" } else { stem };
+    let stem: String = stem.chars().take(60).collect();
+
+    format!("{}.{}", stem.trim_end_matches('-'), format.extension())
+}
+
+/// Archive path of the n-th illustration (counted across chapters, from 1)
+fn image_path(index: usize, image: &ExportImage) -> String {
+    format!(
+        "images/illustration-{}.{}",
+        index,
+        object_store::extension_for_content_type(&image.mime_type)
+    )
+}
+
+/// A story.md with its illustrations in images/, zipped
+fn render_markdown(document: &ExportDocument) -> Result<Vec<u8>> {
+    let mut markdown = format!("# {}\n\n", single_line(&document.title));
+    if let Some(author) = &document.author {
+        let _ = writeln!(markdown, "*by {}*\n", single_line(author));
+    }
+
+    let mut archive = Archive::new();
+    let mut index = 0;
+    for chapter in &document.chapters {
+        let _ = writeln!(markdown, "## {}\n", single_line(&chapter.title));
+        for image in &chapter.illustrations {
+            index += 1;
+            let path = image_path(index, image);
+            let alt = image.alt.as_deref().map(single_line).unwrap_or_default();
+            let _ = writeln!(markdown, "![{}]({})\n", alt.replace(['[', ']'], ""), path);
+            archive.add(&path, &image.bytes, CompressionMethod::Stored)?;
+        }
+        for paragraph in chapter.paragraphs() {
+            // Trailing double spaces keep single line breaks as hard breaks
+            let _ = writeln!(markdown, "{}\n", paragraph.replace('\n', "  \n"));
+        }
+    }
+
+    archive.add("story.md", markdown.as_bytes(), CompressionMethod::Deflated)?;
+    archive.finish()
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const EPUB_STYLE: &str = "body{font-family:serif;line-height:1.5;margin:0 5%}\
+h1,h2{text-align:center;margin:2em 0 1em}\
+p{margin:0 0 .8em;text-indent:0}\
+figure{margin:1em 0;text-align:center}\
+img{max-width:100%;height:auto}\
+figcaption{font-size:.85em;font-style:italic}\
+.author{text-align:center;font-style:italic}\
+.cover{text-align:center;margin:0}.cover img{max-height:100vh}";
+
+/// EPUB 3 package: a title page and one XHTML chapter per node, with the first illustration as
+/// the cover image
+fn render_epub(document: &ExportDocument) -> Result<Vec<u8>> {
+    let mut archive = Archive::new();
+    // The mimetype entry must come first and be stored uncompressed
+    archive.add(
+        "mimetype",
+        b"application/epub+zip",
+        CompressionMethod::Stored,
+    )?;
+    archive.add(
+        "META-INF/container.xml",
+        CONTAINER_XML.as_bytes(),
+        CompressionMethod::Deflated,
+    )?;
+    archive.add(
+        "OEBPS/style.css",
+        EPUB_STYLE.as_bytes(),
+        CompressionMethod::Deflated,
+    )?;
+
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\
+         <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>",
+    );
+    let mut spine = String::new();
+    let mut toc = String::new();
+
+    // Cover page
+    if let Some(cover) = document.cover() {
+        let path = image_path(1, cover);
+        let _ = write!(
+            manifest,
+            "<item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>"
+        );
+        spine.push_str("<itemref idref=\"cover\" linear=\"no\"/>");
+        let body = format!(
+            "<figure class=\"cover\"><img src=\"{}\" alt=\"{}\"/></figure>",
+            path,
+            escape(&document.title)
+        );
+        archive.add(
+            "OEBPS/cover.xhtml",
+            xhtml(&document.language, &document.title, &body).as_bytes(),
+            CompressionMethod::Deflated,
+        )?;
+    }
+
+    // Title page
+    let mut title_body = format!("<h1>{}</h1>", escape(&document.title));
+    if let Some(author) = &document.author {
+        let _ = write!(title_body, "<p class=\"author\">{}</p>", escape(author));
+    }
+    archive.add(
+        "OEBPS/title.xhtml",
+        xhtml(&document.language, &document.title, &title_body).as_bytes(),
+        CompressionMethod::Deflated,
+    )?;
+    manifest
+        .push_str("<item id=\"title\" href=\"title.xhtml\" media-type=\"application/xhtml+xml\"/>");
+    spine.push_str("<itemref idref=\"title\"/>");
+
+    let mut index = 0;
+
+    for (number, chapter) in document.chapters.iter().enumerate() {
+        let number = number + 1;
+        let href = format!("chapter-{}.xhtml", number);
+
+        let mut body = format!("<h2>{}</h2>", escape(&chapter.title));
+        for image in &chapter.illustrations {
+            index += 1;
+            let path = image_path(index, image);
+            // The cover is the first illustration
+            let properties = if index == 1 {
+                " properties=\"cover-image\""
+            } else {
+                ""
+            };
+            let _ = write!(
+                manifest,
+                "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"{}/>",
+                index,
+                path,
+                escape(&image.mime_type),
+                properties
+            );
+            archive.add(
+                &format!("OEBPS/{}", path),
+                &image.bytes,
+                CompressionMethod::Stored,
+            )?;
+
+            let alt = image.alt.as_deref().unwrap_or("");
+            let _ = write!(
+                body,
+                "<figure><img src=\"{}\" alt=\"{}\"/>",
+                path,
+                escape(alt)
+            );
+            if !alt.is_empty() {
+                let _ = write!(body, "<figcaption>{}</figcaption>", escape(alt));
+            }
+            body.push_str("</figure>");
+        }
+        for paragraph in chapter.paragraphs() {
+            let _ = write!(body, "<p>{}</p>", escape(paragraph).replace('\n', "<br/>"));
+        }
+
+        archive.add(
+            &format!("OEBPS/{}", href),
+            xhtml(&document.language, &chapter.title, &body).as_bytes(),
+            CompressionMethod::Deflated,
+        )?;
+        let _ = write!(
+            manifest,
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+            number, href
+        );
+        let _ = write!(spine, "<itemref idref=\"chapter-{}\"/>", number);
+        let _ = write!(
+            toc,
+            "<li><a href=\"{}\">{}</a></li>",
+            href,
+            escape(&chapter.title)
+        );
+    }
+
+    let nav_body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\"><h2>Contents</h2><ol>{}</ol></nav>",
+        toc
+    );
+    archive.add(
+        "OEBPS/nav.xhtml",
+        xhtml(&document.language, "Contents", &nav_body).as_bytes(),
+        CompressionMethod::Deflated,
+    )?;
+
+    let mut metadata = format!(
+        "<dc:identifier id=\"book-id\">urn:uuid:{}</dc:identifier>\
+         <dc:title>{}</dc:title><dc:language>{}</dc:language>",
+        Uuid::new_v4(),
+        escape(&document.title),
+        escape(&document.language)
+    );
+    if let Some(author) = &document.author {
+        let _ = write!(metadata, "<dc:creator>{}</dc:creator>", escape(author));
+    }
+    let modified = OffsetDateTime::now_utc()
+        .replace_nanosecond(0)
+        .ok()
+        .and_then(|now| now.format(&Rfc3339).ok())
+        .unwrap_or_default();
+    let _ = write!(
+        metadata,
+        "<meta property=\"dcterms:modified\">{}</meta>",
+        modified
+    );
+    let opf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">{}</metadata>\
+         <manifest>{}</manifest><spine>{}</spine></package>\n",
+        metadata, manifest, spine
+    );
+    archive.add(
+        "OEBPS/content.opf",
+        opf.as_bytes(),
+        CompressionMethod::Deflated,
+    )?;
+
+    archive.finish()
+}
+
+fn xhtml(language: &str, title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+         xml:lang=\"{0}\" lang=\"{0}\"><head><meta charset=\"utf-8\"/><title>{1}</title>\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/></head>\
+         <body>{2}</body></html>\n",
+        escape(language),
+        escape(title),
+        body
+    )
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Zip archive built in memory
+struct Archive {
+    writer: ZipWriter<Cursor<Vec<u8>>>,
+}
+
+impl Archive {
+    fn new() -> Self {
+        Self {
+            writer: ZipWriter::new(Cursor::new(Vec::new())),
+        }
+    }
+
+    fn add(&mut self, path: &str, bytes: &[u8], method: CompressionMethod) -> Result<()> {
+        self.writer
+            .start_file(
+                path,
+                SimpleFileOptions::default().compression_method(method),
+            )
+            .and_then(|()| self.writer.write_all(bytes).map_err(Into::into))
+            .map_err(|e| ApiError::Internal(anyhow::anyhow!("Failed to write {}: {}", path, e)))
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        self.writer
+            .finish()
+            .map(Cursor::into_inner)
+            .map_err(|e| ApiError::Internal(anyhow::anyhow!("Failed to finish archive: {}", e)))
+    }
+}
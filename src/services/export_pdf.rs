@@ -0,0 +1,484 @@
+use std::fmt::Write as _;
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, Rgb, RgbImage};
+
+use crate::{
+    error::{ApiError, Result},
+    services::export_document::{ExportDocument, ExportImage},
+};
+
+// A5 portrait, in points
+const PAGE_WIDTH: f32 = 420.0;
+const PAGE_HEIGHT: f32 = 595.0;
+const MARGIN: f32 = 50.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const TITLE_SIZE: f32 = 26.0;
+const HEADING_SIZE: f32 = 17.0;
+const BODY_SIZE: f32 = 11.0;
+const BODY_LEADING: f32 = 15.5;
+const PARAGRAPH_GAP: f32 = 6.0;
+
+// Illustrations are downscaled to at most this many pixels on their longer side
+const MAX_IMAGE_PIXELS: u32 = 1600;
+
+/// The PDF standard fonts used, which viewers provide, so nothing is embedded
+#[derive(Debug, Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+}
+
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Italic];
+
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Times-Roman",
+            Font::Bold => "Times-Bold",
+            Font::Italic => "Times-Italic",
+        }
+    }
+}
+
+/// Paginate the document: cover, title page, then each chapter from a new page
+///
+/// Text is set in the standard Times fonts with WinAnsi encoding; characters outside it print
+/// as "?". CPU bound; call from a blocking task.
+pub fn render(document: &ExportDocument) -> Result<Vec<u8>> {
+    let mut layout = Layout::default();
+
+    if let Some(cover) = document.cover() {
+        let image = layout.add_image(cover)?;
+        layout.start_page(false);
+        layout.centered_image(image, PAGE_HEIGHT - 2.0 * MARGIN);
+    }
+
+    layout.start_page(false);
+    layout.y = PAGE_HEIGHT * 0.62;
+    layout.text_block(
+        &document.title,
+        Font::Bold,
+        TITLE_SIZE,
+        TITLE_SIZE * 1.25,
+        true,
+    );
+    if let Some(author) = &document.author {
+        layout.y -= 18.0;
+        layout.text_block(author, Font::Italic, 14.0, 18.0, true);
+    }
+
+    for chapter in &document.chapters {
+        layout.start_page(true);
+        layout.y -= 30.0;
+        layout.text_block(
+            &chapter.title,
+            Font::Bold,
+            HEADING_SIZE,
+            HEADING_SIZE * 1.3,
+            true,
+        );
+        layout.y -= 14.0;
+
+        for illustration in &chapter.illustrations {
+            let image = layout.add_image(illustration)?;
+            layout.centered_image(image, (PAGE_HEIGHT - 2.0 * MARGIN) * 0.6);
+            layout.y -= PARAGRAPH_GAP * 2.0;
+        }
+        for paragraph in chapter.paragraphs() {
+            layout.text_block(paragraph, Font::Regular, BODY_SIZE, BODY_LEADING, false);
+            layout.y -= PARAGRAPH_GAP;
+        }
+    }
+    layout.finish_page();
+
+    Ok(write_pdf(&document.title, &layout))
+}
+
+struct PdfImage {
+    jpeg: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+struct Page {
+    content: String,
+    numbered: bool,
+}
+
+#[derive(Default)]
+struct Layout {
+    pages: Vec<Page>,
+    current: Option<Page>,
+    // Top of the next line, measured from the bottom of the page
+    y: f32,
+    images: Vec<PdfImage>,
+}
+
+impl Layout {
+    fn start_page(&mut self, numbered: bool) {
+        self.finish_page();
+        self.current = Some(Page {
+            content: String::new(),
+            numbered,
+        });
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn finish_page(&mut self) {
+        if let Some(mut page) = self.current.take() {
+            if page.numbered {
+                let number = (self.pages.len() + 1).to_string();
+                let x = (PAGE_WIDTH - text_width(&number, Font::Regular, 9.0)) / 2.0;
+                show_text(
+                    &mut page.content,
+                    Font::Regular,
+                    9.0,
+                    x,
+                    MARGIN / 2.0,
+                    &number,
+                );
+            }
+            self.pages.push(page);
+        }
+    }
+
+    /// Continue on a new page unless `height` more fits on this one
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let numbered = self.current.as_ref().is_none_or(|page| page.numbered);
+            self.start_page(numbered);
+        }
+    }
+
+    fn content(&mut self) -> &mut String {
+        &mut self
+            .current
+            .get_or_insert_with(|| Page {
+                content: String::new(),
+                numbered: true,
+            })
+            .content
+    }
+
+    /// Wrapped text; single line breaks are kept
+    fn text_block(&mut self, text: &str, font: Font, size: f32, leading: f32, centered: bool) {
+        for line in text.lines().flat_map(|line| wrap(line, font, size)) {
+            self.reserve(leading);
+            self.y -= leading;
+            let x = if centered {
+                MARGIN + (CONTENT_WIDTH - text_width(&line, font, size)) / 2.0
+            } else {
+                MARGIN
+            };
+            // Baseline sits a little above the bottom of the line box
+            let baseline = self.y + (leading - size) / 2.0 + size * 0.2;
+            show_text(self.content(), font, size, x, baseline, &line);
+        }
+    }
+
+    fn add_image(&mut self, image: &ExportImage) -> Result<usize> {
+        self.images.push(encode_image(image)?);
+        Ok(self.images.len() - 1)
+    }
+
+    /// Scale an image to the content width and `max_height`, centered horizontally
+    fn centered_image(&mut self, index: usize, max_height: f32) {
+        let image = &self.images[index];
+        let scale = (CONTENT_WIDTH / image.width as f32).min(max_height / image.height as f32);
+        let width = image.width as f32 * scale;
+        let height = image.height as f32 * scale;
+
+        self.reserve(height);
+        self.y -= height;
+        let x = MARGIN + (CONTENT_WIDTH - width) / 2.0;
+        let y = self.y;
+        let _ = writeln!(
+            self.content(),
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q",
+            width,
+            height,
+            x,
+            y,
+            index
+        );
+    }
+}
+
+fn show_text(content: &mut String, font: Font, size: f32, x: f32, baseline: f32, text: &str) {
+    let _ = writeln!(
+        content,
+        "BT /{} {} Tf {:.2} {:.2} Td {} Tj ET",
+        font.resource(),
+        size,
+        x,
+        baseline,
+        pdf_string(text)
+    );
+}
+
+/// Greedy word wrap to the content width; words wider than a line are broken
+fn wrap(text: &str, font: Font, size: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if text_width(&candidate, font, size) <= CONTENT_WIDTH {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if text_width(&current, font, size) > CONTENT_WIDTH && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    let units: u32 = text.chars().map(|c| glyph_width(win_ansi(c)) as u32).sum();
+    // Bold glyphs run slightly wider than the Roman metrics
+    let factor = match font {
+        Font::Bold => 1.06,
+        Font::Regular | Font::Italic => 1.0,
+    };
+    units as f32 * size / 1000.0 * factor
+}
+
+// Times-Roman advance widths for WinAnsi 32..=126, in 1/1000 em
+const TIMES_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250,
+    278, // space../
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, // 0..?
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, // @..O
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500, // P.._
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500, // `..o
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541, // p..~
+];
+
+fn glyph_width(byte: u8) -> u16 {
+    match byte {
+        32..=126 => TIMES_WIDTHS[(byte - 32) as usize],
+        0x85 | 0x89 | 0x97 => 1000, // … ‰ —
+        0x91 | 0x92 => 333,         // ‘ ’
+        0x93 | 0x94 => 444,         // “ ”
+        0x95 => 350,                // •
+        0x96 => 500,                // –
+        0xC0..=0xDE => 722,         // Accented capitals
+        _ => 500,
+    }
+}
+
+/// WinAnsiEncoding code for a character; "?" when it has none
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '\t' => b' ',
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => b'?',
+    }
+}
+
+/// Literal string in WinAnsi encoding
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for byte in text.chars().map(win_ansi) {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            32..=126 => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{:03o}", byte);
+            }
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Re-encode as baseline RGB JPEG, which PDF embeds as is; transparency is flattened onto white
+fn encode_image(image: &ExportImage) -> Result<PdfImage> {
+    let decoded = image::load_from_memory(&image.bytes)
+        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Failed to decode illustration: {}", e)))?;
+    let decoded = if decoded.width().max(decoded.height()) > MAX_IMAGE_PIXELS {
+        decoded.resize(MAX_IMAGE_PIXELS, MAX_IMAGE_PIXELS, FilterType::Lanczos3)
+    } else {
+        decoded
+    };
+
+    let rgba = decoded.to_rgba8();
+    let rgb = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let alpha = a as u16;
+        let blend = |channel: u8| ((channel as u16 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    });
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 85)
+        .encode_image(&rgb)
+        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Failed to encode illustration: {}", e)))?;
+
+    Ok(PdfImage {
+        jpeg,
+        width: rgb.width(),
+        height: rgb.height(),
+    })
+}
+
+/// Serialize pages and images as a PDF 1.4 file
+fn write_pdf(title: &str, layout: &Layout) -> Vec<u8> {
+    // Object numbers: 1 catalog, 2 page tree, 3 info, then fonts, images and pages
+    let font_base = 4;
+    let image_base = font_base + Font::ALL.len();
+    let page_base = image_base + layout.images.len();
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids: Vec<String> = (0..layout.pages.len())
+        .map(|i| format!("{} 0 R", page_base + i * 2))
+        .collect();
+    objects.push(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            layout.pages.len()
+        )
+        .into_bytes(),
+    );
+    objects.push(format!("<< /Title {} /Producer (Talevonia) >>", pdf_string(title)).into_bytes());
+
+    for font in Font::ALL {
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font.base_font()
+            )
+            .into_bytes(),
+        );
+    }
+
+    for image in &layout.images {
+        let mut object = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+            image.width,
+            image.height,
+            image.jpeg.len()
+        )
+        .into_bytes();
+        object.extend_from_slice(&image.jpeg);
+        object.extend_from_slice(b"\nendstream");
+        objects.push(object);
+    }
+
+    let fonts: String = Font::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, font)| format!("/{} {} 0 R", font.resource(), font_base + i))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let images: String = (0..layout.images.len())
+        .map(|i| format!("/Im{} {} 0 R", i, image_base + i))
+        .collect::<Vec<_>>()
+        .join(" ");
+    for (i, page) in layout.pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << {} >> /XObject << {} >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                fonts,
+                images,
+                page_base + i * 2 + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            )
+            .into_bytes(),
+        );
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
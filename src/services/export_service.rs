@@ -0,0 +1,378 @@
+use std::{sync::Arc, time::Duration};
+
+use entity::export_jobs;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use time::OffsetDateTime;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::ExportConfig,
+    error::{ApiError, Result},
+    models::export::{
+        ExportFormat, ExportIllustration, ExportJobResponse, ExportJobStatus, ExportRequest,
+    },
+    services::{
+        export_document::{self, ExportChapter, ExportDocument, ExportImage},
+        image_processing,
+        object_store::ObjectStore,
+    },
+};
+
+// Finished jobs removed per sweep
+const EXPIRED_JOBS_BATCH_SIZE: u64 = 100;
+
+/// Story export to EPUB, Markdown and PDF
+///
+/// Small exports are rendered within the request. Larger ones run as a background job whose file
+/// is stored under exports/ and offered through a signed download URL until the job expires.
+#[derive(Clone)]
+pub struct ExportService {
+    db: DatabaseConnection,
+    object_store: Arc<dyn ObjectStore>,
+    config: ExportConfig,
+    signed_url_ttl: Duration,
+}
+
+/// An export rendered within the request
+pub struct ExportFile {
+    pub bytes: Vec<u8>,
+    pub content_type: &'static str,
+    pub file_name: String,
+}
+
+pub enum ExportOutcome {
+    File(ExportFile),
+    Job(ExportJobResponse),
+}
+
+impl ExportService {
+    pub fn new(
+        db: DatabaseConnection,
+        object_store: Arc<dyn ObjectStore>,
+        config: ExportConfig,
+        signed_url_ttl: Duration,
+    ) -> Self {
+        Self {
+            db,
+            object_store,
+            config,
+            signed_url_ttl,
+        }
+    }
+
+    /// Render the export, or start a background job when it is too large for one request
+    #[instrument(skip(self, request), fields(format = request.format.as_str()))]
+    pub async fn export(&self, user_id: Uuid, request: ExportRequest) -> Result<ExportOutcome> {
+        let format = request.format;
+        let document = self.document(request)?;
+        let file_name = export_document::file_name(&document, format);
+
+        if document.input_bytes() <= self.config.sync_max_bytes {
+            let bytes = render(document, format).await?;
+            return Ok(ExportOutcome::File(ExportFile {
+                bytes,
+                content_type: format.content_type(),
+                file_name,
+            }));
+        }
+
+        let running = export_jobs::Entity::find()
+            .filter(export_jobs::Column::UserId.eq(user_id))
+            .filter(export_jobs::Column::Status.eq(ExportJobStatus::Running.as_str()))
+            .count(&self.db)
+            .await?;
+        if running >= self.config.max_running_jobs {
+            return Err(ApiError::QuotaExceeded(format!(
+                "At most {} exports can run at once; wait for one to finish",
+                self.config.max_running_jobs
+            )));
+        }
+
+        let job = export_jobs::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            format: Set(format.as_str().to_string()),
+            file_name: Set(file_name),
+            status: Set(ExportJobStatus::Running.as_str().to_string()),
+            storage_key: Set(None),
+            size_bytes: Set(None),
+            error: Set(None),
+            created_at: Set(OffsetDateTime::now_utc()),
+            completed_at: Set(None),
+            expires_at: Set(None),
+        }
+        .insert(&self.db)
+        .await?;
+
+        let service = self.clone();
+        let job_id = job.id;
+        tokio::spawn(async move { service.run_job(job_id, user_id, document, format).await });
+
+        Ok(ExportOutcome::Job(self.job_response(job)))
+    }
+
+    /// One of the user's export jobs
+    #[instrument(skip(self))]
+    pub async fn job(&self, user_id: Uuid, job_id: Uuid) -> Result<ExportJobResponse> {
+        let job = export_jobs::Entity::find_by_id(job_id)
+            .filter(export_jobs::Column::UserId.eq(user_id))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Export not found".to_string()))?;
+
+        Ok(self.job_response(job))
+    }
+
+    /// Fail jobs that outlived the timeout, then delete expired jobs and their files
+    ///
+    /// Returns the number of jobs deleted.
+    pub async fn sweep_jobs(&self) -> Result<u64> {
+        let now = OffsetDateTime::now_utc();
+
+        let lost = export_jobs::Entity::update_many()
+            .col_expr(
+                export_jobs::Column::Status,
+                ExportJobStatus::Failed.as_str().into(),
+            )
+            .col_expr(
+                export_jobs::Column::Error,
+                Some("The export timed out".to_string()).into(),
+            )
+            .col_expr(export_jobs::Column::CompletedAt, Some(now).into())
+            .col_expr(
+                export_jobs::Column::ExpiresAt,
+                Some(now + self.retention()).into(),
+            )
+            .filter(export_jobs::Column::Status.eq(ExportJobStatus::Running.as_str()))
+            .filter(
+                export_jobs::Column::CreatedAt
+                    .lt(now - Duration::from_secs(self.config.job_timeout_secs)),
+            )
+            .exec(&self.db)
+            .await?;
+        if lost.rows_affected > 0 {
+            warn!("Marked {} timed out export jobs failed", lost.rows_affected);
+        }
+
+        let expired = export_jobs::Entity::find()
+            .filter(export_jobs::Column::ExpiresAt.lt(now))
+            .order_by_asc(export_jobs::Column::ExpiresAt)
+            .limit(EXPIRED_JOBS_BATCH_SIZE)
+            .all(&self.db)
+            .await?;
+
+        let mut deleted = 0;
+        for job in expired {
+            if let Some(key) = &job.storage_key {
+                if let Err(e) = self.object_store.delete(key).await {
+                    warn!("Failed to delete export file {}: {}", key, e);
+                    continue;
+                }
+            }
+            export_jobs::Entity::delete_by_id(job.id)
+                .exec(&self.db)
+                .await?;
+            deleted += 1;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Run sweep_jobs periodically in the background
+    pub fn spawn_job_sweeper(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let period = Duration::from_secs(self.config.sweep_interval_secs.max(1));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                match self.sweep_jobs().await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} expired export jobs", deleted),
+                    Err(e) => warn!("Failed to sweep export jobs: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn run_job(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+        document: ExportDocument,
+        format: ExportFormat,
+    ) {
+        let Err(e) = self.complete_job(job_id, user_id, document, format).await else {
+            return;
+        };
+
+        warn!("Export job {} failed: {}", job_id, e);
+        let now = OffsetDateTime::now_utc();
+        // The sweeper may already have failed a job that ran past the timeout
+        let failed = export_jobs::Entity::update_many()
+            .col_expr(
+                export_jobs::Column::Status,
+                ExportJobStatus::Failed.as_str().into(),
+            )
+            .col_expr(
+                export_jobs::Column::Error,
+                Some("The export could not be rendered".to_string()).into(),
+            )
+            .col_expr(export_jobs::Column::CompletedAt, Some(now).into())
+            .col_expr(
+                export_jobs::Column::ExpiresAt,
+                Some(now + self.retention()).into(),
+            )
+            .filter(export_jobs::Column::Id.eq(job_id))
+            .filter(export_jobs::Column::Status.eq(ExportJobStatus::Running.as_str()))
+            .exec(&self.db)
+            .await;
+        if let Err(e) = failed {
+            warn!("Failed to record export job {} failure: {}", job_id, e);
+        }
+    }
+
+    async fn complete_job(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+        document: ExportDocument,
+        format: ExportFormat,
+    ) -> Result<()> {
+        let bytes = render(document, format).await?;
+        let size_bytes = bytes.len() as i64;
+        let storage_key = format!("exports/{}/{}.{}", user_id, job_id, format.extension());
+        self.object_store
+            .put(&storage_key, bytes, format.content_type())
+            .await?;
+
+        let now = OffsetDateTime::now_utc();
+        let completed = export_jobs::Entity::update_many()
+            .col_expr(
+                export_jobs::Column::Status,
+                ExportJobStatus::Completed.as_str().into(),
+            )
+            .col_expr(
+                export_jobs::Column::StorageKey,
+                Some(storage_key.clone()).into(),
+            )
+            .col_expr(export_jobs::Column::SizeBytes, Some(size_bytes).into())
+            .col_expr(export_jobs::Column::CompletedAt, Some(now).into())
+            .col_expr(
+                export_jobs::Column::ExpiresAt,
+                Some(now + self.retention()).into(),
+            )
+            .filter(export_jobs::Column::Id.eq(job_id))
+            .filter(export_jobs::Column::Status.eq(ExportJobStatus::Running.as_str()))
+            .exec(&self.db)
+            .await?;
+
+        // Already failed by the sweeper; nothing will ever point at the file
+        if completed.rows_affected == 0 {
+            warn!(
+                "Export job {} finished after it was settled; discarding its file",
+                job_id
+            );
+            if let Err(e) = self.object_store.delete(&storage_key).await {
+                warn!("Failed to delete export file {}: {}", storage_key, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate the request and decode its illustrations
+    fn document(&self, request: ExportRequest) -> Result<ExportDocument> {
+        if request.nodes.len() > self.config.max_nodes {
+            return Err(ApiError::BadRequest(format!(
+                "An export can have at most {} nodes",
+                self.config.max_nodes
+            )));
+        }
+
+        let mut chapters: Vec<ExportChapter> = request
+            .nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| ExportChapter {
+                title: node
+                    .summary
+                    .map(|summary| summary.trim().to_string())
+                    .filter(|summary| !summary.is_empty())
+                    .unwrap_or_else(|| format!("Chapter {}", index + 1)),
+                content: node.content,
+                illustrations: Vec::new(),
+            })
+            .collect();
+
+        for illustration in request.illustrations {
+            let chapter = chapters.get_mut(illustration.node_index).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Illustration nodeIndex {} is out of range",
+                    illustration.node_index
+                ))
+            })?;
+            chapter
+                .illustrations
+                .push(self.decode_illustration(illustration)?);
+        }
+
+        Ok(ExportDocument {
+            title: request.title,
+            author: request.author,
+            language: request.language,
+            chapters,
+        })
+    }
+
+    fn decode_illustration(&self, illustration: ExportIllustration) -> Result<ExportImage> {
+        let (bytes, info) = image_processing::decode_embedded(
+            &illustration.data,
+            self.config.max_illustration_bytes,
+        )?;
+
+        Ok(ExportImage {
+            bytes,
+            mime_type: info.mime_type,
+            alt: illustration.alt,
+        })
+    }
+
+    fn job_response(&self, job: export_jobs::Model) -> ExportJobResponse {
+        let status = ExportJobStatus::parse(&job.status).unwrap_or(ExportJobStatus::Failed);
+        let download_url = match (status, &job.storage_key) {
+            (ExportJobStatus::Completed, Some(key)) => {
+                Some(self.object_store.signed_url(key, self.signed_url_ttl).url)
+            }
+            _ => None,
+        };
+
+        ExportJobResponse {
+            id: job.id,
+            status,
+            format: ExportFormat::parse(&job.format).unwrap_or(ExportFormat::Epub),
+            file_name: job.file_name,
+            size_bytes: job.size_bytes,
+            download_url,
+            error: job.error,
+            created_at: job.created_at,
+            expires_at: job.expires_at,
+        }
+    }
+
+    fn retention(&self) -> Duration {
+        Duration::from_secs(self.config.retention_secs)
+    }
+}
+
+async fn render(document: ExportDocument, format: ExportFormat) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || export_document::render(&document, format))
+        .await
+        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Export render task failed: {}", e)))?
+}
@@ -0,0 +1,666 @@
+use crate::{
+    error::{ApiError, Result},
+    models::{
+        common::{IAPPlatform, PurchaseTier},
+        iap::{
+            AppleNotificationPayload, GoogleDeveloperNotification,
+            GoogleVoidedPurchaseNotification, IAPVerification, JWSTransactionDecodedPayload,
+        },
+    },
+    services::{CreditsService, IAPService},
+};
+use entity::{iap_notifications, sea_orm_active_enums::AccountTier, user_iap_receipts, users};
+use sea_orm::{
+    entity::*, query::*, sea_query::OnConflict, DatabaseConnection, DatabaseTransaction,
+    TransactionTrait,
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+/// Result of handling a store notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationOutcome {
+    Processed,
+    Duplicate,
+}
+
+/// Store-reported state applied to every receipt linked to a purchase
+struct ReceiptUpdate {
+    product_id: Option<String>,
+    purchase_tier: AccountTier,
+    subscription_status: Option<String>,
+    expires_at: Option<time::OffsetDateTime>,
+    // When the store signed this state; receipts already holding newer state are left alone
+    signed_at: Option<time::OffsetDateTime>,
+}
+
+/// Applies store server-to-server notifications to receipts, account tiers and credits
+pub struct IAPNotificationService {
+    db: DatabaseConnection,
+    iap_service: Arc<IAPService>,
+    credits_service: Arc<CreditsService>,
+}
+
+impl IAPNotificationService {
+    pub fn new(
+        db: DatabaseConnection,
+        iap_service: Arc<IAPService>,
+        credits_service: Arc<CreditsService>,
+    ) -> Self {
+        Self {
+            db,
+            iap_service,
+            credits_service,
+        }
+    }
+
+    /// Handle an App Store Server Notification V2
+    ///
+    /// This method:
+    /// 1. Verifies the signed payload and the embedded signed transaction
+    /// 2. Records the notification UUID (duplicates are acknowledged without reprocessing)
+    /// 3. Revokes refunded consumables, or updates linked subscription receipts
+    /// 4. Recomputes the account tier of every affected user
+    #[instrument(skip(self, signed_payload))]
+    pub async fn handle_apple_notification(
+        &self,
+        signed_payload: &str,
+    ) -> Result<NotificationOutcome> {
+        let notification = self.iap_service.decode_notification(signed_payload)?;
+        let transaction = notification
+            .data
+            .as_ref()
+            .and_then(|d| d.signed_transaction_info.as_deref())
+            .map(|info| self.iap_service.decode_signed_transaction(info))
+            .transpose()?;
+
+        // Everything happens in one transaction so a failed delivery can be retried
+        let txn = self.db.begin().await?;
+
+        let inserted = iap_notifications::Entity::insert(iap_notifications::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            platform: Set("apple".to_string()),
+            notification_id: Set(notification.notification_uuid.clone()),
+            notification_type: Set(notification.notification_type.clone()),
+            subtype: Set(notification.subtype.clone()),
+            original_transaction_id: Set(transaction
+                .as_ref()
+                .map(|t| t.original_transaction_id.clone())),
+            transaction_id: Set(transaction.as_ref().map(|t| t.transaction_id.clone())),
+            payload: Set(serde_json::to_value(&notification).ok()),
+            processed_at: Set(time::OffsetDateTime::now_utc()),
+            created_at: Set(time::OffsetDateTime::now_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                iap_notifications::Column::Platform,
+                iap_notifications::Column::NotificationId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+
+        if inserted == 0 {
+            txn.rollback().await?;
+            info!(
+                notification_uuid = %notification.notification_uuid,
+                notification_type = %notification.notification_type,
+                "Duplicate App Store notification ignored"
+            );
+            return Ok(NotificationOutcome::Duplicate);
+        }
+
+        match transaction {
+            Some(transaction)
+                if transaction.transaction_type == "Consumable"
+                    && matches!(notification.notification_type.as_str(), "REFUND" | "REVOKE") =>
+            {
+                let reason = notification.notification_type.to_lowercase();
+                let revoked_at = transaction
+                    .revocation_date
+                    .and_then(|ms| {
+                        time::OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()
+                    })
+                    .unwrap_or_else(time::OffsetDateTime::now_utc);
+
+                let revoked = self
+                    .credits_service
+                    .revoke_purchase_in_txn(&transaction.transaction_id, &reason, revoked_at, &txn)
+                    .await?;
+
+                if revoked.is_none() {
+                    warn!(
+                        transaction_id = %transaction.transaction_id,
+                        "Refunded consumable was never redeemed"
+                    );
+                }
+            }
+            Some(transaction) => {
+                self.apply_apple_subscription_change_txn(&notification, &transaction, &txn)
+                    .await?;
+            }
+            None => {
+                debug!(
+                    notification_type = %notification.notification_type,
+                    "App Store notification carries no transaction"
+                );
+            }
+        }
+
+        txn.commit().await?;
+
+        info!(
+            notification_uuid = %notification.notification_uuid,
+            notification_type = %notification.notification_type,
+            subtype = ?notification.subtype,
+            "App Store notification processed"
+        );
+
+        Ok(NotificationOutcome::Processed)
+    }
+
+    /// Handle a Google Play real-time developer notification delivered by Pub/Sub
+    ///
+    /// This method:
+    /// 1. Decodes the message data and re-fetches subscription state from the Play Developer API
+    /// 2. Records the Pub/Sub message ID (duplicates are acknowledged without reprocessing)
+    /// 3. Revokes voided consumables, or updates linked subscription receipts
+    /// 4. Recomputes the account tier of every affected user
+    #[instrument(skip(self, data))]
+    pub async fn handle_google_notification(
+        &self,
+        message_id: &str,
+        data: &str,
+    ) -> Result<NotificationOutcome> {
+        let notification = self.iap_service.decode_developer_notification(data)?;
+
+        let (notification_type, purchase_token, order_id) =
+            if let Some(sub) = &notification.subscription_notification {
+                (
+                    google_subscription_notification_type(sub.notification_type),
+                    Some(sub.purchase_token.clone()),
+                    None,
+                )
+            } else if let Some(voided) = &notification.voided_purchase_notification {
+                (
+                    "VOIDED_PURCHASE",
+                    Some(voided.purchase_token.clone()),
+                    Some(voided.order_id.clone()),
+                )
+            } else if let Some(product) = &notification.one_time_product_notification {
+                let notification_type = match product.notification_type {
+                    1 => "ONE_TIME_PRODUCT_PURCHASED",
+                    2 => "ONE_TIME_PRODUCT_CANCELED",
+                    _ => "ONE_TIME_PRODUCT_UNKNOWN",
+                };
+                (
+                    notification_type,
+                    Some(product.purchase_token.clone()),
+                    None,
+                )
+            } else {
+                ("TEST_NOTIFICATION", None, None)
+            };
+
+        // Notifications only say that something changed; the current state comes from Google.
+        // This happens before the transaction so no connection is held during the API call.
+        let subscription = match &notification.subscription_notification {
+            Some(sub) => Some(self.fetch_google_subscription(&sub.purchase_token).await?),
+            None => None,
+        };
+
+        // Everything happens in one transaction so a failed delivery can be retried
+        let txn = self.db.begin().await?;
+
+        let inserted = iap_notifications::Entity::insert(iap_notifications::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            platform: Set("google".to_string()),
+            notification_id: Set(message_id.to_string()),
+            notification_type: Set(notification_type.to_string()),
+            subtype: Set(None),
+            original_transaction_id: Set(purchase_token.clone()),
+            transaction_id: Set(order_id),
+            payload: Set(serde_json::to_value(&notification).ok()),
+            processed_at: Set(time::OffsetDateTime::now_utc()),
+            created_at: Set(time::OffsetDateTime::now_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                iap_notifications::Column::Platform,
+                iap_notifications::Column::NotificationId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+
+        if inserted == 0 {
+            txn.rollback().await?;
+            info!(
+                message_id = %message_id,
+                notification_type = %notification_type,
+                "Duplicate Google Play notification ignored"
+            );
+            return Ok(NotificationOutcome::Duplicate);
+        }
+
+        if let (Some(sub), Some(verification)) =
+            (&notification.subscription_notification, subscription)
+        {
+            // A revoked subscription ends immediately, like an App Store refund
+            let status_override = (sub.notification_type == 12).then(|| "cancelled".to_string());
+            self.apply_google_subscription_change_txn(
+                &sub.purchase_token,
+                verification,
+                status_override,
+                &txn,
+            )
+            .await?;
+        } else if let Some(voided) = &notification.voided_purchase_notification {
+            self.apply_google_voided_purchase_txn(&notification, voided, &txn)
+                .await?;
+        } else if let Some(product) = &notification.one_time_product_notification {
+            // Credits are granted when the client redeems the purchase, so neither a completed
+            // nor a cancelled pending purchase needs any change here
+            debug!(
+                notification_type = %notification_type,
+                sku = ?product.sku,
+                "Google Play one-time product notification recorded"
+            );
+        } else if notification.test_notification.is_some() {
+            info!("Google Play test notification received");
+        }
+
+        txn.commit().await?;
+
+        info!(
+            message_id = %message_id,
+            notification_type = %notification_type,
+            version = ?notification.version,
+            event_time_millis = ?notification.event_time_millis,
+            "Google Play notification processed"
+        );
+
+        Ok(NotificationOutcome::Processed)
+    }
+
+    /// Fetch a subscription's current state, treating tokens Google no longer knows as expired
+    async fn fetch_google_subscription(&self, purchase_token: &str) -> Result<IAPVerification> {
+        match self
+            .iap_service
+            .get_google_subscription(purchase_token)
+            .await
+        {
+            Err(ApiError::InvalidReceipt(reason)) => {
+                // Google forgets tokens some time after expiry; retrying would never succeed
+                warn!(reason = %reason, "Google Play subscription no longer available");
+                Ok(IAPVerification {
+                    purchase_identity: purchase_token.to_string(),
+                    purchase_tier: PurchaseTier::Free,
+                    product_id: None,
+                    valid_until: None,
+                    is_family_shared: false,
+                    subscription_status: Some("expired".to_string()),
+                    is_acknowledged: true,
+                    linked_purchase_token: None,
+                })
+            }
+            other => other,
+        }
+    }
+
+    /// Update every receipt linked to a Play subscription, carrying ownership over from the
+    /// purchase token it replaced (upgrade, downgrade or resubscription)
+    async fn apply_google_subscription_change_txn(
+        &self,
+        purchase_token: &str,
+        verification: IAPVerification,
+        status_override: Option<String>,
+        txn: &DatabaseTransaction,
+    ) -> Result<()> {
+        let (purchase_tier, subscription_status) = match status_override {
+            Some(status) => (AccountTier::Free, Some(status)),
+            None => {
+                let tier = match verification.purchase_tier {
+                    PurchaseTier::Pro => AccountTier::Pro,
+                    PurchaseTier::Free => AccountTier::Free,
+                };
+                (tier, verification.subscription_status)
+            }
+        };
+        let update = ReceiptUpdate {
+            product_id: verification.product_id,
+            purchase_tier,
+            subscription_status,
+            expires_at: verification.valid_until,
+            signed_at: None,
+        };
+
+        let mut user_ids = Vec::new();
+
+        if let Some(linked_token) = &verification.linked_purchase_token {
+            let now = time::OffsetDateTime::now_utc();
+            let previous = user_iap_receipts::Entity::find()
+                .filter(user_iap_receipts::Column::Platform.eq("google"))
+                .filter(user_iap_receipts::Column::OriginalTransactionId.eq(linked_token.as_str()))
+                .all(txn)
+                .await?;
+
+            for receipt in previous {
+                user_ids.push(receipt.user_id);
+
+                // The new token belongs to whoever owned the one it replaced
+                user_iap_receipts::Entity::insert(user_iap_receipts::ActiveModel {
+                    id: Set(Uuid::now_v7()),
+                    user_id: Set(receipt.user_id),
+                    original_transaction_id: Set(purchase_token.to_string()),
+                    platform: Set("google".to_string()),
+                    is_family_shared: Set(receipt.is_family_shared),
+                    family_primary_user_id: Set(receipt.family_primary_user_id),
+                    product_id: Set(update
+                        .product_id
+                        .clone()
+                        .unwrap_or_else(|| receipt.product_id.clone())),
+                    purchase_tier: Set(update.purchase_tier.clone()),
+                    subscription_status: Set(update.subscription_status.clone()),
+                    expires_at: Set(update.expires_at),
+                    receipt_hash: Set(self.iap_service.hash_receipt(purchase_token)),
+                    last_verified_at: Set(now),
+                    first_linked_at: Set(now),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    store_signed_at: Set(update.signed_at),
+                })
+                .on_conflict(
+                    OnConflict::columns([
+                        user_iap_receipts::Column::UserId,
+                        user_iap_receipts::Column::OriginalTransactionId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(txn)
+                .await?;
+
+                let mut active: user_iap_receipts::ActiveModel = receipt.into();
+                active.subscription_status = Set(Some("replaced".to_string()));
+                active.updated_at = Set(now);
+                active.update(txn).await?;
+            }
+        }
+
+        user_ids.extend(
+            self.update_receipts_txn("google", purchase_token, &update, txn)
+                .await?,
+        );
+
+        if user_ids.is_empty() {
+            warn!("No linked receipt for Google Play subscription notification");
+            return Ok(());
+        }
+
+        user_ids.sort_unstable();
+        user_ids.dedup();
+        for user_id in user_ids {
+            self.refresh_account_tier_txn(user_id, txn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Revoke the credits of a voided one-time purchase, or end a voided subscription
+    async fn apply_google_voided_purchase_txn(
+        &self,
+        notification: &GoogleDeveloperNotification,
+        voided: &GoogleVoidedPurchaseNotification,
+        txn: &DatabaseTransaction,
+    ) -> Result<()> {
+        match voided.product_type {
+            // One-time product: credit packs are recorded under their Play order ID
+            2 => {
+                let revoked_at = notification
+                    .event_time_millis
+                    .as_deref()
+                    .and_then(|ms| ms.parse::<i64>().ok())
+                    .and_then(|ms| {
+                        time::OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()
+                    })
+                    .unwrap_or_else(time::OffsetDateTime::now_utc);
+
+                let revoked = self
+                    .credits_service
+                    .revoke_purchase_in_txn(&voided.order_id, "voided", revoked_at, txn)
+                    .await?;
+
+                if revoked.is_none() {
+                    warn!(
+                        order_id = %voided.order_id,
+                        refund_type = ?voided.refund_type,
+                        "Voided Google Play purchase was never redeemed"
+                    );
+                }
+            }
+            // Subscription: access ends immediately
+            1 => {
+                let update = ReceiptUpdate {
+                    product_id: None,
+                    purchase_tier: AccountTier::Free,
+                    subscription_status: Some("cancelled".to_string()),
+                    expires_at: None,
+                    signed_at: None,
+                };
+                let user_ids = self
+                    .update_receipts_txn("google", &voided.purchase_token, &update, txn)
+                    .await?;
+
+                if user_ids.is_empty() {
+                    warn!(
+                        order_id = %voided.order_id,
+                        "No linked receipt for voided Google Play subscription"
+                    );
+                }
+                for user_id in user_ids {
+                    self.refresh_account_tier_txn(user_id, txn).await?;
+                }
+            }
+            other => {
+                debug!("Ignoring voided purchase of product type {}", other);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update every receipt linked to the subscription and the owners' account tiers
+    async fn apply_apple_subscription_change_txn(
+        &self,
+        notification: &AppleNotificationPayload,
+        transaction: &JWSTransactionDecodedPayload,
+        txn: &DatabaseTransaction,
+    ) -> Result<()> {
+        let subscription_status = match notification.notification_type.as_str() {
+            "SUBSCRIBED" | "DID_RENEW" | "DID_RECOVER" => Some("active".to_string()),
+            "DID_FAIL_TO_RENEW" if notification.subtype.as_deref() == Some("GRACE_PERIOD") => {
+                Some("grace_period".to_string())
+            }
+            "DID_FAIL_TO_RENEW" => Some("billing_retry".to_string()),
+            "EXPIRED" | "GRACE_PERIOD_EXPIRED" => Some("expired".to_string()),
+            "REFUND" | "REVOKE" => Some("cancelled".to_string()),
+            // Turning auto-renew on/off doesn't change access until the period ends
+            "DID_CHANGE_RENEWAL_STATUS" => notification
+                .data
+                .as_ref()
+                .and_then(|d| d.status)
+                .and_then(IAPService::subscription_status_from_code),
+            other => {
+                debug!("Ignoring App Store notification type {}", other);
+                return Ok(());
+            }
+        };
+
+        let purchase_tier = match self
+            .iap_service
+            .tier_for_product(IAPPlatform::Apple, Some(&transaction.product_id))
+        {
+            PurchaseTier::Pro => AccountTier::Pro,
+            PurchaseTier::Free => AccountTier::Free,
+        };
+        let update = ReceiptUpdate {
+            product_id: Some(transaction.product_id.clone()),
+            purchase_tier,
+            subscription_status,
+            expires_at: transaction
+                .expires_date
+                .and_then(|ms| time::OffsetDateTime::from_unix_timestamp(ms / 1000).ok()),
+            // Apple may deliver (and retry) notifications out of order
+            signed_at: time::OffsetDateTime::from_unix_timestamp_nanos(
+                notification.signed_date as i128 * 1_000_000,
+            )
+            .ok(),
+        };
+
+        let user_ids = self
+            .update_receipts_txn("apple", &transaction.original_transaction_id, &update, txn)
+            .await?;
+
+        if user_ids.is_empty() {
+            warn!(
+                original_transaction_id = %transaction.original_transaction_id,
+                notification_type = %notification.notification_type,
+                "No linked receipt for App Store notification"
+            );
+            return Ok(());
+        }
+
+        for user_id in user_ids {
+            self.refresh_account_tier_txn(user_id, txn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply store-reported state to every receipt linked to a purchase, returning the owners
+    async fn update_receipts_txn(
+        &self,
+        platform: &str,
+        original_transaction_id: &str,
+        update: &ReceiptUpdate,
+        txn: &DatabaseTransaction,
+    ) -> Result<Vec<Uuid>> {
+        let receipts = user_iap_receipts::Entity::find()
+            .filter(user_iap_receipts::Column::Platform.eq(platform))
+            .filter(user_iap_receipts::Column::OriginalTransactionId.eq(original_transaction_id))
+            .all(txn)
+            .await?;
+
+        let now = time::OffsetDateTime::now_utc();
+        let mut user_ids = Vec::with_capacity(receipts.len());
+        for receipt in receipts {
+            user_ids.push(receipt.user_id);
+
+            if let (Some(applied), Some(signed_at)) = (receipt.store_signed_at, update.signed_at) {
+                if applied > signed_at {
+                    info!(
+                        receipt_id = %receipt.id,
+                        "Skipping store state older than the receipt's current state"
+                    );
+                    continue;
+                }
+            }
+
+            let mut active: user_iap_receipts::ActiveModel = receipt.into();
+            if let Some(product_id) = &update.product_id {
+                active.product_id = Set(product_id.clone());
+            }
+            active.purchase_tier = Set(update.purchase_tier.clone());
+            if let Some(status) = &update.subscription_status {
+                active.subscription_status = Set(Some(status.clone()));
+            }
+            if update.expires_at.is_some() {
+                active.expires_at = Set(update.expires_at);
+            }
+            if update.signed_at.is_some() {
+                active.store_signed_at = Set(update.signed_at);
+            }
+            active.last_verified_at = Set(now);
+            active.updated_at = Set(now);
+            active.update(txn).await?;
+        }
+
+        Ok(user_ids)
+    }
+
+    /// Recompute users.account_tier from all of the user's linked receipts
+    async fn refresh_account_tier_txn(
+        &self,
+        user_id: Uuid,
+        txn: &DatabaseTransaction,
+    ) -> Result<()> {
+        let now = time::OffsetDateTime::now_utc();
+        let receipts = user_iap_receipts::Entity::find()
+            .filter(user_iap_receipts::Column::UserId.eq(user_id))
+            .all(txn)
+            .await?;
+
+        let has_pro_access = receipts.iter().any(|receipt| {
+            receipt.purchase_tier == AccountTier::Pro
+                && match receipt.subscription_status.as_deref() {
+                    Some("active") | Some("grace_period") => true,
+                    Some(_) => false,
+                    // Non-subscription purchases have no status; honour the expiry if any
+                    None => receipt.expires_at.is_none_or(|expires| expires > now),
+                }
+        });
+        let account_tier = if has_pro_access {
+            AccountTier::Pro
+        } else {
+            AccountTier::Free
+        };
+
+        let Some(user) = users::Entity::find_by_id(user_id).one(txn).await? else {
+            return Ok(());
+        };
+
+        if user.account_tier != account_tier {
+            info!(
+                user_id = %user_id,
+                from = ?user.account_tier,
+                to = ?account_tier,
+                "Account tier changed by store notification"
+            );
+
+            let mut user_active: users::ActiveModel = user.into();
+            user_active.account_tier = Set(account_tier);
+            user_active.updated_at = Set(now);
+            user_active.update(txn).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Name of a Play subscription notification type (SubscriptionNotification.notificationType)
+fn google_subscription_notification_type(code: i32) -> &'static str {
+    match code {
+        1 => "SUBSCRIPTION_RECOVERED",
+        2 => "SUBSCRIPTION_RENEWED",
+        3 => "SUBSCRIPTION_CANCELED",
+        4 => "SUBSCRIPTION_PURCHASED",
+        5 => "SUBSCRIPTION_ON_HOLD",
+        6 => "SUBSCRIPTION_IN_GRACE_PERIOD",
+        7 => "SUBSCRIPTION_RESTARTED",
+        8 => "SUBSCRIPTION_PRICE_CHANGE_CONFIRMED",
+        9 => "SUBSCRIPTION_DEFERRED",
+        10 => "SUBSCRIPTION_PAUSED",
+        11 => "SUBSCRIPTION_PAUSE_SCHEDULE_CHANGED",
+        12 => "SUBSCRIPTION_REVOKED",
+        13 => "SUBSCRIPTION_EXPIRED",
+        19 => "SUBSCRIPTION_PRICE_CHANGE_UPDATED",
+        20 => "SUBSCRIPTION_PENDING_PURCHASE_CANCELED",
+        _ => "SUBSCRIPTION_UNKNOWN",
+    }
+}
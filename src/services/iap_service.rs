@@ -3,16 +3,61 @@ use crate::{
     error::{ApiError, Result},
     models::{
         common::{IAPPlatform, PurchaseTier},
-        iap::IAPVerification,
+        iap::{
+            AppleNotificationPayload, GoogleDeveloperNotification, IAPVerification,
+            JWSRenewalInfoDecodedPayload, JWSTransactionDecodedPayload,
+            SubscriptionStatusesResponse, TransactionInfoResponse, VerifiedConsumable,
+        },
     },
+    utils::apple_jws,
 };
-use serde::Deserialize;
+use base64::Engine;
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tracing::{info, instrument, warn};
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument, warn};
+
+/// App Store Server API base URLs
+const APP_STORE_API_PRODUCTION: &str = "https://api.storekit.itunes.apple.com";
+const APP_STORE_API_SANDBOX: &str = "https://api.storekit-sandbox.itunes.apple.com";
+/// Audience required in App Store Server API tokens
+const APP_STORE_API_AUDIENCE: &str = "appstoreconnect-v1";
+/// Lifetime of App Store Server API tokens (Apple rejects anything over 60 minutes)
+const APP_STORE_API_TOKEN_TTL_SECS: i64 = 20 * 60;
+/// Transaction type reported for auto-renewable subscriptions
+const AUTO_RENEWABLE_SUBSCRIPTION: &str = "Auto-Renewable Subscription";
+/// OAuth scope for the Google Play Developer API
+const GOOGLE_ANDROIDPUBLISHER_SCOPE: &str = "https://www.googleapis.com/auth/androidpublisher";
+/// Refresh Google access tokens this long before they expire
+const GOOGLE_TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+/// Issuers of the OIDC tokens Pub/Sub attaches to push requests
+const GOOGLE_OIDC_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+/// Cache duration for Google's OIDC signing keys (1 hour)
+const GOOGLE_JWKS_CACHE_DURATION_SECS: i64 = 3600;
 
 pub struct IAPService {
     config: IAPConfig,
     http_client: reqwest::Client,
+    /// Cached Google OAuth access token for the Play Developer API
+    google_token_cache: RwLock<Option<CachedAccessToken>>,
+    /// Cached Google OIDC JWKS for Pub/Sub push authentication
+    google_jwks_cache: RwLock<Option<CachedJwks>>,
+}
+
+/// Cached OAuth access token with expiry
+struct CachedAccessToken {
+    token: String,
+    expires_at: time::OffsetDateTime,
+}
+
+/// Cached JWKS with timestamp
+struct CachedJwks {
+    jwks: JwkSet,
+    fetched_at: time::OffsetDateTime,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +71,115 @@ struct AppleReceiptResponse {
 struct AppleReceipt {
     original_transaction_id: String,
     product_id: Option<String>,
+    #[serde(default)]
+    bundle_id: Option<String>,
+    #[serde(default)]
+    in_app: Vec<serde_json::Value>, // Kept raw so the verified payload can be persisted as-is
+}
+
+#[derive(Debug, Deserialize)]
+struct AppleInAppPurchase {
+    transaction_id: String,
+    #[serde(default)]
+    original_transaction_id: Option<String>,
+    product_id: String,
+    purchase_date_ms: String,
+    #[serde(default)]
+    cancellation_date_ms: Option<String>,
+}
+
+/// Claims of an App Store Server API token
+#[derive(Debug, Serialize)]
+struct AppStoreApiClaims<'a> {
+    iss: &'a str,
+    iat: i64,
+    exp: i64,
+    aud: &'a str,
+    bid: &'a str,
+}
+
+/// Purchase data sent by Android clients (Play Billing `Purchase.getOriginalJson()`)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GooglePurchaseReceipt {
+    package_name: String,
+    #[serde(default)]
+    product_id: Option<String>, // Billing Library < 5
+    #[serde(default)]
+    product_ids: Vec<String>, // Billing Library 5+
+    purchase_token: String,
+    #[serde(default)]
+    auto_renewing: Option<bool>, // Only present for subscriptions
+}
+
+impl GooglePurchaseReceipt {
+    fn product_id(&self) -> Option<&str> {
+        self.product_id
+            .as_deref()
+            .or_else(|| self.product_ids.first().map(String::as_str))
+    }
+}
+
+/// purchases.subscriptionsv2 resource (SubscriptionPurchaseV2)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleSubscriptionPurchase {
+    subscription_state: String, // "SUBSCRIPTION_STATE_ACTIVE", ...
+    #[serde(default)]
+    line_items: Vec<GoogleSubscriptionLineItem>,
+    #[serde(default)]
+    linked_purchase_token: Option<String>,
+    #[serde(default)]
+    acknowledgement_state: Option<String>, // "ACKNOWLEDGEMENT_STATE_ACKNOWLEDGED" or "..._PENDING"
+    #[serde(default)]
+    latest_order_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleSubscriptionLineItem {
+    product_id: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    expiry_time: Option<time::OffsetDateTime>,
+}
+
+/// purchases.products resource (ProductPurchase)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleProductPurchase {
+    purchase_time_millis: String,
+    purchase_state: i32, // 0 purchased, 1 canceled, 2 pending
+    #[serde(default)]
+    acknowledgement_state: Option<i32>, // 0 pending, 1 acknowledged
+    #[serde(default)]
+    order_id: Option<String>,
+    #[serde(default)]
+    quantity: Option<i32>,
+}
+
+/// Claims of the service-account assertion exchanged for an access token
+#[derive(Debug, Serialize)]
+struct GoogleServiceAccountClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleTokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// Claims of the OIDC token on a Pub/Sub push request (iss/aud/exp checked by Validation)
+#[derive(Debug, Deserialize)]
+struct PubSubPushClaims {
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -46,6 +200,8 @@ impl IAPService {
         Self {
             config: config.clone(),
             http_client: reqwest::Client::new(),
+            google_token_cache: RwLock::new(None),
+            google_jwks_cache: RwLock::new(None),
         }
     }
 
@@ -62,8 +218,25 @@ impl IAPService {
         }
     }
 
-    /// Verify Apple IAP receipt
-    async fn verify_apple_receipt(&self, receipt: &str) -> Result<IAPVerification> {
+    /// Verify a consumable purchase against the store and return the verified transaction
+    ///
+    /// The transaction must exist in the receipt, belong to our app bundle and not be cancelled.
+    /// Callers must compare the returned product ID with any client-supplied claims.
+    #[instrument(skip(self, receipt))]
+    pub async fn verify_consumable_purchase(
+        &self,
+        platform: IAPPlatform,
+        receipt: &str,
+        transaction_id: &str,
+    ) -> Result<VerifiedConsumable> {
+        match platform {
+            IAPPlatform::Apple => self.verify_apple_consumable(receipt, transaction_id).await,
+            IAPPlatform::Google => self.verify_google_consumable(receipt, transaction_id).await,
+        }
+    }
+
+    /// Call Apple's verifyReceipt endpoint and check status and bundle ID
+    async fn fetch_apple_receipt(&self, receipt: &str) -> Result<AppleReceiptResponse> {
         // Determine endpoint based on environment
         let endpoint = match self.config.apple_environment.as_str() {
             "production" => "https://buy.itunes.apple.com/verifyReceipt",
@@ -97,6 +270,480 @@ impl IAPService {
             )));
         }
 
+        // Reject receipts issued for a different app
+        if let Some(bundle_id) = apple_response
+            .receipt
+            .as_ref()
+            .and_then(|r| r.bundle_id.as_deref())
+        {
+            if bundle_id != self.config.apple_bundle_id {
+                return Err(ApiError::InvalidReceipt(format!(
+                    "Receipt belongs to a different app: {}",
+                    bundle_id
+                )));
+            }
+        }
+
+        Ok(apple_response)
+    }
+
+    /// Verify an Apple consumable transaction contained in the app receipt
+    async fn verify_apple_consumable(
+        &self,
+        receipt: &str,
+        transaction_id: &str,
+    ) -> Result<VerifiedConsumable> {
+        if apple_jws::looks_like_jws(receipt) {
+            return self
+                .verify_apple_signed_consumable(receipt, transaction_id)
+                .await;
+        }
+
+        let apple_response = self.fetch_apple_receipt(receipt).await?;
+
+        let receipt_info = apple_response
+            .receipt
+            .ok_or_else(|| ApiError::InvalidReceipt("No receipt found".to_string()))?;
+
+        if receipt_info.bundle_id.is_none() {
+            return Err(ApiError::InvalidReceipt(
+                "Receipt is missing bundle ID".to_string(),
+            ));
+        }
+
+        // Find the claimed transaction among the receipt's in-app purchases
+        let store_payload = receipt_info
+            .in_app
+            .into_iter()
+            .find(|txn| txn.get("transaction_id").and_then(|v| v.as_str()) == Some(transaction_id))
+            .ok_or_else(|| {
+                ApiError::InvalidReceipt(format!(
+                    "Transaction {} not found in receipt",
+                    transaction_id
+                ))
+            })?;
+
+        let purchase: AppleInAppPurchase = serde_json::from_value(store_payload.clone())
+            .map_err(|e| ApiError::InvalidReceipt(format!("Invalid transaction format: {}", e)))?;
+
+        if purchase.cancellation_date_ms.is_some() {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Transaction {} was cancelled",
+                transaction_id
+            )));
+        }
+
+        let purchase_date = purchase
+            .purchase_date_ms
+            .parse::<i64>()
+            .ok()
+            .and_then(|ts_ms| {
+                time::OffsetDateTime::from_unix_timestamp_nanos(ts_ms as i128 * 1_000_000).ok()
+            })
+            .ok_or_else(|| {
+                ApiError::InvalidReceipt(format!(
+                    "Invalid purchase date: {}",
+                    purchase.purchase_date_ms
+                ))
+            })?;
+
+        info!(
+            "Successfully verified Apple consumable: transaction_id={}, product_id={}",
+            purchase.transaction_id, purchase.product_id
+        );
+
+        Ok(VerifiedConsumable {
+            transaction_id: purchase.transaction_id,
+            original_transaction_id: purchase.original_transaction_id,
+            product_id: purchase.product_id,
+            purchase_date,
+            store_payload,
+        })
+    }
+
+    /// Verify a StoreKit 2 signed consumable transaction
+    async fn verify_apple_signed_consumable(
+        &self,
+        signed_transaction: &str,
+        transaction_id: &str,
+    ) -> Result<VerifiedConsumable> {
+        let mut transaction = self.decode_signed_transaction(signed_transaction)?;
+
+        if transaction.transaction_id != transaction_id {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Signed transaction {} does not match claimed transaction {}",
+                transaction.transaction_id, transaction_id
+            )));
+        }
+
+        // Prefer Apple's current copy so refunds issued after the purchase are visible
+        if self.app_store_api_configured() {
+            transaction = self.get_transaction_info(transaction_id).await?;
+        }
+
+        if transaction.transaction_type != "Consumable" {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Transaction {} is not a consumable: {}",
+                transaction_id, transaction.transaction_type
+            )));
+        }
+
+        if transaction.revocation_date.is_some() {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Transaction {} was revoked",
+                transaction_id
+            )));
+        }
+
+        let purchase_date = time::OffsetDateTime::from_unix_timestamp_nanos(
+            transaction.purchase_date as i128 * 1_000_000,
+        )
+        .map_err(|_| {
+            ApiError::InvalidReceipt(format!(
+                "Invalid purchase date: {}",
+                transaction.purchase_date
+            ))
+        })?;
+
+        let store_payload = serde_json::to_value(&transaction).map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Failed to encode transaction: {}", e))
+        })?;
+
+        info!(
+            "Successfully verified Apple signed consumable: transaction_id={}, product_id={}, environment={:?}",
+            transaction.transaction_id, transaction.product_id, transaction.environment
+        );
+
+        Ok(VerifiedConsumable {
+            transaction_id: transaction.transaction_id,
+            original_transaction_id: Some(transaction.original_transaction_id),
+            product_id: transaction.product_id,
+            purchase_date,
+            store_payload,
+        })
+    }
+
+    /// Verify a StoreKit 2 signed subscription (or non-consumable) transaction
+    async fn verify_apple_signed_transaction(
+        &self,
+        signed_transaction: &str,
+    ) -> Result<IAPVerification> {
+        let mut transaction = self.decode_signed_transaction(signed_transaction)?;
+        let mut status_code = None;
+        let mut renewal_info = None;
+
+        // Refresh from Apple so renewals, expirations and refunds since signing are reflected
+        if self.app_store_api_configured()
+            && transaction.transaction_type == AUTO_RENEWABLE_SUBSCRIPTION
+        {
+            let statuses = self
+                .get_all_subscription_statuses(&transaction.original_transaction_id)
+                .await?;
+
+            if let Some(bundle_id) = statuses.bundle_id.as_deref() {
+                if bundle_id != self.config.apple_bundle_id {
+                    return Err(ApiError::InvalidReceipt(format!(
+                        "Subscription belongs to a different app: {}",
+                        bundle_id
+                    )));
+                }
+            }
+
+            let latest = statuses.data.iter().find_map(|group| {
+                group
+                    .last_transactions
+                    .iter()
+                    .find(|t| t.original_transaction_id == transaction.original_transaction_id)
+                    .map(|t| (group.subscription_group_identifier.as_str(), t))
+            });
+
+            if let Some((group_id, latest)) = latest {
+                debug!(
+                    "App Store subscription status: group={}, status={}, environment={:?}",
+                    group_id, latest.status, statuses.environment
+                );
+                transaction = self.decode_signed_transaction(&latest.signed_transaction_info)?;
+                status_code = Some(latest.status);
+                renewal_info = latest
+                    .signed_renewal_info
+                    .as_deref()
+                    .map(|info| self.decode_signed_renewal_info(info))
+                    .transpose()?;
+            }
+        }
+
+        let is_family_shared =
+            transaction.in_app_ownership_type.as_deref() == Some("FAMILY_SHARED");
+
+        let subscription_status = match status_code {
+            Some(code) => Self::subscription_status_from_code(code),
+            None => {
+                let expires_ms = transaction.expires_date.map(|ms| ms.to_string());
+                let revoked_ms = transaction.revocation_date.map(|ms| ms.to_string());
+                Self::determine_subscription_status(
+                    expires_ms.as_deref(),
+                    revoked_ms.as_deref(),
+                    None,
+                )
+            }
+        };
+
+        let purchase_tier = if transaction.revocation_date.is_some() {
+            PurchaseTier::Free
+        } else {
+            self.tier_for_product(IAPPlatform::Apple, Some(&transaction.product_id))
+        };
+
+        // Access continues through Apple's billing grace period
+        let access_expires_ms = match &renewal_info {
+            Some(JWSRenewalInfoDecodedPayload {
+                grace_period_expires_date: Some(grace_ms),
+                ..
+            }) => transaction.expires_date.map(|ms| ms.max(*grace_ms)),
+            _ => transaction.expires_date,
+        };
+
+        let valid_until = access_expires_ms
+            .and_then(|ts_ms| time::OffsetDateTime::from_unix_timestamp(ts_ms / 1000).ok());
+
+        info!(
+            "Successfully verified Apple signed transaction: tier={:?}, product_id={}, family_shared={}, status={:?}",
+            purchase_tier, transaction.product_id, is_family_shared, subscription_status
+        );
+
+        Ok(IAPVerification {
+            purchase_identity: transaction.original_transaction_id,
+            purchase_tier,
+            product_id: Some(transaction.product_id),
+            valid_until,
+            is_family_shared,
+            subscription_status,
+            is_acknowledged: true,
+            linked_purchase_token: None,
+        })
+    }
+
+    /// Verify an App Store signed transaction and check it belongs to our app
+    pub fn decode_signed_transaction(
+        &self,
+        signed_transaction: &str,
+    ) -> Result<JWSTransactionDecodedPayload> {
+        let transaction: JWSTransactionDecodedPayload = apple_jws::verify_signed_payload(
+            signed_transaction,
+            &self.config.apple_root_ca_sha256,
+        )?;
+
+        if transaction.bundle_id != self.config.apple_bundle_id {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Transaction belongs to a different app: {}",
+                transaction.bundle_id
+            )));
+        }
+        self.check_apple_environment(transaction.environment.as_deref(), "Transaction")?;
+
+        Ok(transaction)
+    }
+
+    /// Reject payloads signed in another App Store environment, e.g. Sandbox on production
+    fn check_apple_environment(&self, environment: Option<&str>, what: &str) -> Result<()> {
+        let expected = match self.config.apple_environment.as_str() {
+            "production" => "Production",
+            _ => "Sandbox",
+        };
+        if environment != Some(expected) {
+            return Err(ApiError::InvalidReceipt(format!(
+                "{} is from the {} environment, expected {}",
+                what,
+                environment.unwrap_or("unknown"),
+                expected
+            )));
+        }
+        Ok(())
+    }
+
+    /// Verify an App Store Server Notification V2 payload and check it belongs to our app
+    pub fn decode_notification(&self, signed_payload: &str) -> Result<AppleNotificationPayload> {
+        let notification: AppleNotificationPayload =
+            apple_jws::verify_signed_payload(signed_payload, &self.config.apple_root_ca_sha256)?;
+
+        if let Some(data) = &notification.data {
+            if let Some(bundle_id) = data.bundle_id.as_deref() {
+                if bundle_id != self.config.apple_bundle_id {
+                    return Err(ApiError::InvalidReceipt(format!(
+                        "Notification belongs to a different app: {}",
+                        bundle_id
+                    )));
+                }
+            }
+            self.check_apple_environment(data.environment.as_deref(), "Notification")?;
+        }
+
+        Ok(notification)
+    }
+
+    /// Verify an App Store signed renewal info payload
+    pub fn decode_signed_renewal_info(
+        &self,
+        signed_renewal_info: &str,
+    ) -> Result<JWSRenewalInfoDecodedPayload> {
+        apple_jws::verify_signed_payload(signed_renewal_info, &self.config.apple_root_ca_sha256)
+    }
+
+    /// Fetch a transaction from the App Store Server API (Get Transaction Info)
+    #[instrument(skip(self))]
+    pub async fn get_transaction_info(
+        &self,
+        transaction_id: &str,
+    ) -> Result<JWSTransactionDecodedPayload> {
+        let response: TransactionInfoResponse = self
+            .app_store_api_get(&format!("/inApps/v1/transactions/{}", transaction_id))
+            .await?;
+
+        self.decode_signed_transaction(&response.signed_transaction_info)
+    }
+
+    /// Fetch subscription statuses from the App Store Server API (Get All Subscription Statuses)
+    #[instrument(skip(self))]
+    pub async fn get_all_subscription_statuses(
+        &self,
+        original_transaction_id: &str,
+    ) -> Result<SubscriptionStatusesResponse> {
+        self.app_store_api_get(&format!(
+            "/inApps/v1/subscriptions/{}",
+            original_transaction_id
+        ))
+        .await
+    }
+
+    /// Whether App Store Server API credentials are configured
+    fn app_store_api_configured(&self) -> bool {
+        self.config.apple_issuer_id.is_some()
+            && self.config.apple_key_id.is_some()
+            && self.config.apple_private_key.is_some()
+    }
+
+    fn app_store_api_base(&self) -> &str {
+        if let Some(base) = self.config.apple_api_base.as_deref() {
+            return base.trim_end_matches('/');
+        }
+
+        match self.config.apple_environment.as_str() {
+            "production" => APP_STORE_API_PRODUCTION,
+            _ => APP_STORE_API_SANDBOX,
+        }
+    }
+
+    /// Sign a short-lived ES256 token for the App Store Server API
+    fn app_store_api_token(&self) -> Result<String> {
+        let (Some(issuer_id), Some(key_id), Some(private_key)) = (
+            self.config.apple_issuer_id.as_deref(),
+            self.config.apple_key_id.as_deref(),
+            self.config.apple_private_key.as_deref(),
+        ) else {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "App Store Server API credentials are not configured"
+            )));
+        };
+
+        // Keys passed through env vars usually have escaped newlines
+        let private_key = private_key.replace("\\n", "\n");
+        let encoding_key = EncodingKey::from_ec_pem(private_key.as_bytes()).map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Invalid App Store private key: {}", e))
+        })?;
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(key_id.to_string());
+
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let claims = AppStoreApiClaims {
+            iss: issuer_id,
+            iat: now,
+            exp: now + APP_STORE_API_TOKEN_TTL_SECS,
+            aud: APP_STORE_API_AUDIENCE,
+            bid: &self.config.apple_bundle_id,
+        };
+
+        encode(&header, &claims, &encoding_key).map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Failed to sign App Store API token: {}", e))
+        })
+    }
+
+    /// Authenticated GET against the App Store Server API
+    async fn app_store_api_get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let token = self.app_store_api_token()?;
+        let url = format!("{}{}", self.app_store_api_base(), path);
+
+        debug!("Calling App Store Server API: {}", url);
+        let response = self
+            .http_client
+            .get(&url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!(
+                    "App Store Server API request failed: {}",
+                    e
+                ))
+            })?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(ApiError::InvalidReceipt(
+                "Transaction not found in the App Store".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "App Store Server API returned status: {}",
+                status
+            )));
+        }
+
+        response.json().await.map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!(
+                "Invalid App Store Server API response: {}",
+                e
+            ))
+        })
+    }
+
+    /// Map an App Store Server API subscription status code to our status strings
+    pub fn subscription_status_from_code(code: i32) -> Option<String> {
+        let status = match code {
+            1 => "active",
+            2 => "expired",
+            3 => "billing_retry",
+            4 => "grace_period",
+            5 => "cancelled", // Revoked by Apple (refund or family sharing removal)
+            _ => return None,
+        };
+        Some(status.to_string())
+    }
+
+    /// Determine tier based on product_id, as defined by the product catalog
+    pub fn tier_for_product(
+        &self,
+        platform: IAPPlatform,
+        product_id: Option<&str>,
+    ) -> PurchaseTier {
+        if let Some(id) = product_id {
+            if self.config.products.find(platform, id).is_none() {
+                warn!("Product {} is not in the catalog; granting no tier", id);
+            }
+        }
+        self.config.products.tier_for(platform, product_id)
+    }
+
+    /// Verify Apple IAP receipt
+    async fn verify_apple_receipt(&self, receipt: &str) -> Result<IAPVerification> {
+        // StoreKit 2 clients send a signed transaction instead of the app receipt
+        if apple_jws::looks_like_jws(receipt) {
+            return self.verify_apple_signed_transaction(receipt).await;
+        }
+
+        let apple_response = self.fetch_apple_receipt(receipt).await?;
+
         // Extract transaction info from latest_receipt_info or receipt
         let transaction_opt = apple_response
             .latest_receipt_info
@@ -146,14 +793,7 @@ impl IAPService {
             ));
         };
 
-        // Determine tier based on product_id
-        let purchase_tier = match product_id.as_deref() {
-            Some("com.talevonia.pro.monthly") => PurchaseTier::Pro,
-            Some("com.talevonia.pro.yearly") => PurchaseTier::Pro,
-            Some("com.talevonia.pro") => PurchaseTier::Pro,
-            Some(id) if id.contains("pro") => PurchaseTier::Pro, // Fallback for pro variants
-            _ => PurchaseTier::Free,
-        };
+        let purchase_tier = self.tier_for_product(IAPPlatform::Apple, product_id.as_deref());
 
         // Parse expiration for subscriptions
         let valid_until = expires_date_ms
@@ -172,6 +812,8 @@ impl IAPService {
             valid_until,
             is_family_shared,
             subscription_status,
+            is_acknowledged: true,
+            linked_purchase_token: None,
         })
     }
 
@@ -217,16 +859,557 @@ impl IAPService {
     }
 
     /// Verify Google IAP receipt
-    async fn verify_google_receipt(&self, _receipt: &str) -> Result<IAPVerification> {
-        // TODO: Implement Google Play verification
-        // This would use Google Play Developer API with service account credentials
-        // For MVP, return a placeholder or error
+    ///
+    /// Subscriptions are checked with purchases.subscriptionsv2, one-time products with
+    /// purchases.products. Play Billing only includes `autoRenewing` for subscriptions.
+    async fn verify_google_receipt(&self, receipt: &str) -> Result<IAPVerification> {
+        let purchase = self.parse_google_receipt(receipt)?;
 
-        warn!("Google IAP verification not yet implemented");
+        if purchase.auto_renewing.is_some() {
+            self.verify_google_subscription(&purchase).await
+        } else {
+            self.verify_google_product(&purchase).await
+        }
+    }
 
-        Err(ApiError::InvalidReceipt(
-            "Google IAP verification not yet implemented".to_string(),
-        ))
+    /// Verify a Google Play subscription purchase token
+    async fn verify_google_subscription(
+        &self,
+        purchase: &GooglePurchaseReceipt,
+    ) -> Result<IAPVerification> {
+        let payload = self
+            .google_api_get(&[
+                "androidpublisher",
+                "v3",
+                "applications",
+                &purchase.package_name,
+                "purchases",
+                "subscriptionsv2",
+                "tokens",
+                &purchase.purchase_token,
+            ])
+            .await?;
+
+        let subscription: GoogleSubscriptionPurchase = serde_json::from_value(payload)
+            .map_err(|e| ApiError::InvalidReceipt(format!("Invalid subscription format: {}", e)))?;
+
+        // Access lasts until the latest line item expires
+        let valid_until = subscription
+            .line_items
+            .iter()
+            .filter_map(|item| item.expiry_time)
+            .max();
+        let product_id = subscription
+            .line_items
+            .first()
+            .map(|item| item.product_id.clone())
+            .or_else(|| purchase.product_id().map(str::to_string));
+
+        let subscription_status =
+            Self::google_subscription_status(&subscription.subscription_state, valid_until);
+
+        let purchase_tier = match subscription_status.as_deref() {
+            Some("active") | Some("grace_period") => {
+                self.tier_for_product(IAPPlatform::Google, product_id.as_deref())
+            }
+            _ => PurchaseTier::Free,
+        };
+
+        let is_acknowledged = subscription.acknowledgement_state.as_deref()
+            == Some("ACKNOWLEDGEMENT_STATE_ACKNOWLEDGED");
+
+        info!(
+            "Successfully verified Google subscription: tier={:?}, product_id={:?}, state={}, order_id={:?}, acknowledged={}",
+            purchase_tier,
+            product_id,
+            subscription.subscription_state,
+            subscription.latest_order_id,
+            is_acknowledged
+        );
+
+        Ok(IAPVerification {
+            purchase_identity: purchase.purchase_token.clone(),
+            purchase_tier,
+            product_id,
+            valid_until,
+            is_family_shared: false, // Play family library sharing doesn't cover subscriptions
+            subscription_status,
+            is_acknowledged,
+            linked_purchase_token: subscription.linked_purchase_token,
+        })
+    }
+
+    /// Verify a Google Play one-time (non-subscription) purchase
+    async fn verify_google_product(
+        &self,
+        purchase: &GooglePurchaseReceipt,
+    ) -> Result<IAPVerification> {
+        let product_id = purchase.product_id().ok_or_else(|| {
+            ApiError::InvalidReceipt("Purchase is missing product ID".to_string())
+        })?;
+
+        let (product, _) = self
+            .fetch_google_product(&purchase.package_name, product_id, &purchase.purchase_token)
+            .await?;
+
+        let purchase_tier = match product.purchase_state {
+            0 => self.tier_for_product(IAPPlatform::Google, Some(product_id)),
+            _ => PurchaseTier::Free,
+        };
+
+        let is_acknowledged = product.acknowledgement_state == Some(1);
+
+        info!(
+            "Successfully verified Google product purchase: tier={:?}, product_id={}, order_id={:?}, acknowledged={}",
+            purchase_tier, product_id, product.order_id, is_acknowledged
+        );
+
+        Ok(IAPVerification {
+            purchase_identity: purchase.purchase_token.clone(),
+            purchase_tier,
+            product_id: Some(product_id.to_string()),
+            valid_until: None,
+            is_family_shared: false,
+            subscription_status: None,
+            is_acknowledged,
+            linked_purchase_token: None,
+        })
+    }
+
+    /// Verify a Google Play consumable (credit pack) purchase
+    ///
+    /// The transaction ID is the Play order ID (GPA.xxxx-xxxx-xxxx-xxxxx).
+    async fn verify_google_consumable(
+        &self,
+        receipt: &str,
+        transaction_id: &str,
+    ) -> Result<VerifiedConsumable> {
+        let purchase = self.parse_google_receipt(receipt)?;
+        let product_id = purchase.product_id().ok_or_else(|| {
+            ApiError::InvalidReceipt("Purchase is missing product ID".to_string())
+        })?;
+
+        let (product, store_payload) = self
+            .fetch_google_product(&purchase.package_name, product_id, &purchase.purchase_token)
+            .await?;
+
+        if product.purchase_state != 0 {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Purchase is not completed (purchase_state={})",
+                product.purchase_state
+            )));
+        }
+
+        // Test purchases have no order ID; fall back to the purchase token
+        let verified_transaction_id = product
+            .order_id
+            .clone()
+            .unwrap_or_else(|| purchase.purchase_token.clone());
+
+        if verified_transaction_id != transaction_id {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Purchase order {} does not match claimed transaction {}",
+                verified_transaction_id, transaction_id
+            )));
+        }
+
+        if product.quantity.unwrap_or(1) > 1 {
+            warn!(
+                "Google purchase {} has quantity {}; crediting a single pack",
+                transaction_id,
+                product.quantity.unwrap_or(1)
+            );
+        }
+
+        let purchase_date = product
+            .purchase_time_millis
+            .parse::<i64>()
+            .ok()
+            .and_then(|ts_ms| {
+                time::OffsetDateTime::from_unix_timestamp_nanos(ts_ms as i128 * 1_000_000).ok()
+            })
+            .ok_or_else(|| {
+                ApiError::InvalidReceipt(format!(
+                    "Invalid purchase time: {}",
+                    product.purchase_time_millis
+                ))
+            })?;
+
+        info!(
+            "Successfully verified Google consumable: order_id={}, product_id={}, acknowledged={}",
+            verified_transaction_id,
+            product_id,
+            product.acknowledgement_state == Some(1)
+        );
+
+        Ok(VerifiedConsumable {
+            transaction_id: verified_transaction_id,
+            original_transaction_id: Some(purchase.purchase_token.clone()),
+            product_id: product_id.to_string(),
+            purchase_date,
+            store_payload,
+        })
+    }
+
+    /// Parse the client's purchase JSON and check it belongs to our Android app
+    fn parse_google_receipt(&self, receipt: &str) -> Result<GooglePurchaseReceipt> {
+        let purchase: GooglePurchaseReceipt = serde_json::from_str(receipt).map_err(|e| {
+            ApiError::InvalidReceipt(format!("Invalid Google purchase data: {}", e))
+        })?;
+
+        if self.config.google_package_name.as_deref() != Some(purchase.package_name.as_str()) {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Purchase belongs to a different app: {}",
+                purchase.package_name
+            )));
+        }
+
+        Ok(purchase)
+    }
+
+    /// Fetch a one-time purchase, returning the typed resource and the raw payload
+    async fn fetch_google_product(
+        &self,
+        package_name: &str,
+        product_id: &str,
+        purchase_token: &str,
+    ) -> Result<(GoogleProductPurchase, serde_json::Value)> {
+        let payload = self
+            .google_api_get(&[
+                "androidpublisher",
+                "v3",
+                "applications",
+                package_name,
+                "purchases",
+                "products",
+                product_id,
+                "tokens",
+                purchase_token,
+            ])
+            .await?;
+
+        let product = serde_json::from_value(payload.clone())
+            .map_err(|e| ApiError::InvalidReceipt(format!("Invalid purchase format: {}", e)))?;
+
+        Ok((product, payload))
+    }
+
+    /// Map a subscriptionsv2 state onto our subscription status strings
+    fn google_subscription_status(
+        state: &str,
+        valid_until: Option<time::OffsetDateTime>,
+    ) -> Option<String> {
+        let status = match state {
+            "SUBSCRIPTION_STATE_ACTIVE" => "active",
+            // Auto-renew turned off: access continues until the paid period ends
+            "SUBSCRIPTION_STATE_CANCELED" => match valid_until {
+                Some(expires) if expires > time::OffsetDateTime::now_utc() => "active",
+                _ => "expired",
+            },
+            "SUBSCRIPTION_STATE_IN_GRACE_PERIOD" => "grace_period",
+            "SUBSCRIPTION_STATE_ON_HOLD" => "billing_retry",
+            "SUBSCRIPTION_STATE_PAUSED" => "paused",
+            "SUBSCRIPTION_STATE_EXPIRED" => "expired",
+            "SUBSCRIPTION_STATE_PENDING" | "SUBSCRIPTION_STATE_PENDING_PURCHASE_CANCELED" => {
+                "pending"
+            }
+            _ => return None,
+        };
+        Some(status.to_string())
+    }
+
+    /// Get an OAuth access token for the Play Developer API (cached until near expiry)
+    async fn google_access_token(&self) -> Result<String> {
+        let now = time::OffsetDateTime::now_utc();
+
+        {
+            let cache = self.google_token_cache.read().await;
+            if let Some(ref cached) = *cache {
+                if cached.expires_at - time::Duration::seconds(GOOGLE_TOKEN_REFRESH_MARGIN_SECS)
+                    > now
+                {
+                    return Ok(cached.token.clone());
+                }
+            }
+        }
+
+        let (Some(client_email), Some(private_key)) = (
+            self.config.google_service_account_email.as_deref(),
+            self.config.google_service_account_private_key.as_deref(),
+        ) else {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "Google service account credentials are not configured"
+            )));
+        };
+
+        // Keys passed through env vars usually have escaped newlines
+        let private_key = private_key.replace("\\n", "\n");
+        let encoding_key = EncodingKey::from_rsa_pem(private_key.as_bytes()).map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Invalid Google service account key: {}", e))
+        })?;
+
+        let claims = GoogleServiceAccountClaims {
+            iss: client_email,
+            scope: GOOGLE_ANDROIDPUBLISHER_SCOPE,
+            aud: &self.config.google_token_uri,
+            iat: now.unix_timestamp(),
+            exp: now.unix_timestamp() + 3600,
+        };
+        let assertion =
+            encode(&Header::new(Algorithm::RS256), &claims, &encoding_key).map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!("Failed to sign Google assertion: {}", e))
+            })?;
+
+        debug!("Exchanging Google service account assertion for access token");
+        let response = self
+            .http_client
+            .post(&self.config.google_token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!("Google token request failed: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "Google token request failed with status: {}",
+                response.status()
+            )));
+        }
+
+        let token: GoogleTokenResponse = response.json().await.map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Invalid Google token response: {}", e))
+        })?;
+
+        {
+            let mut cache = self.google_token_cache.write().await;
+            *cache = Some(CachedAccessToken {
+                token: token.access_token.clone(),
+                expires_at: now + time::Duration::seconds(token.expires_in),
+            });
+        }
+
+        Ok(token.access_token)
+    }
+
+    /// Play Developer API URL with each path segment percent-encoded
+    ///
+    /// Package names, product ids and purchase tokens come from client receipts, so none of
+    /// them may add segments, a query or a fragment to the path.
+    fn google_api_url(&self, segments: &[&str]) -> Result<reqwest::Url> {
+        // Dot segments would be dropped rather than encoded, changing the endpoint
+        if segments
+            .iter()
+            .any(|segment| matches!(*segment, "" | "." | ".."))
+        {
+            return Err(ApiError::InvalidReceipt(
+                "Malformed Google Play purchase".to_string(),
+            ));
+        }
+
+        let mut url = reqwest::Url::parse(&self.config.google_api_base).map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Invalid Google Play API base URL: {}", e))
+        })?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                ApiError::Internal(anyhow::anyhow!(
+                    "Google Play API base URL cannot have a path"
+                ))
+            })?
+            .pop_if_empty()
+            // '%' is left as is by the path setter, so escape it to keep the segment opaque
+            .extend(segments.iter().map(|segment| segment.replace('%', "%25")));
+
+        Ok(url)
+    }
+
+    /// Authenticated GET against the Play Developer API
+    async fn google_api_get(&self, segments: &[&str]) -> Result<serde_json::Value> {
+        let url = self.google_api_url(segments)?;
+        let token = self.google_access_token().await?;
+
+        debug!("Calling Google Play Developer API: {}", url);
+        let response = self
+            .http_client
+            .get(url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!("Google Play API request failed: {}", e))
+            })?;
+
+        let status = response.status();
+        // Unknown, malformed or expired purchase tokens
+        if matches!(
+            status,
+            reqwest::StatusCode::BAD_REQUEST
+                | reqwest::StatusCode::NOT_FOUND
+                | reqwest::StatusCode::GONE
+        ) {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Purchase not found in Google Play (status {})",
+                status
+            )));
+        }
+        if !status.is_success() {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "Google Play API returned status: {}",
+                status
+            )));
+        }
+
+        response.json().await.map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Invalid Google Play API response: {}", e))
+        })
+    }
+
+    /// Authenticate a Pub/Sub push request by its OIDC bearer token
+    ///
+    /// This method:
+    /// 1. Finds the signing key in Google's JWKS (cached)
+    /// 2. Verifies the signature, issuer, expiry and the push subscription's audience
+    /// 3. Checks the token was issued to the configured push service account
+    pub async fn verify_pubsub_push_token(&self, token: &str) -> Result<()> {
+        let Some(audience) = self.config.google_pubsub_audience.as_deref() else {
+            return Err(ApiError::Unauthorized(
+                "Google Pub/Sub push authentication is not configured".to_string(),
+            ));
+        };
+
+        // 1. Find the signing key
+        let header = decode_header(token)
+            .map_err(|e| ApiError::InvalidToken(format!("Invalid JWT header: {}", e)))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| ApiError::InvalidToken("Missing key ID in token header".to_string()))?;
+
+        let jwks = self.get_google_jwks().await?;
+        let jwk = jwks.find(&kid).ok_or_else(|| {
+            ApiError::InvalidToken(format!("Key ID '{}' not found in Google's JWKS", kid))
+        })?;
+        let decoding_key = DecodingKey::from_jwk(jwk)
+            .map_err(|e| ApiError::InvalidToken(format!("Failed to create decoding key: {}", e)))?;
+
+        // 2. Verify the token
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&GOOGLE_OIDC_ISSUERS);
+        validation.set_audience(&[audience]);
+
+        let claims = decode::<PubSubPushClaims>(token, &decoding_key, &validation)
+            .map_err(|e| match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiError::ExpiredToken,
+                jsonwebtoken::errors::ErrorKind::InvalidAudience => {
+                    ApiError::InvalidToken("Invalid token audience".to_string())
+                }
+                _ => ApiError::InvalidToken(format!("Token validation failed: {}", e)),
+            })?
+            .claims;
+
+        // 3. Check the push service account
+        if let Some(expected) = self.config.google_pubsub_service_account.as_deref() {
+            if !claims.email_verified || claims.email.as_deref() != Some(expected) {
+                return Err(ApiError::InvalidToken(format!(
+                    "Push token issued to unexpected account: {:?}",
+                    claims.email
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode a real-time developer notification and check it belongs to our Android app
+    pub fn decode_developer_notification(&self, data: &str) -> Result<GoogleDeveloperNotification> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| {
+                ApiError::InvalidReceipt(format!("Invalid notification encoding: {}", e))
+            })?;
+
+        let notification: GoogleDeveloperNotification = serde_json::from_slice(&bytes)
+            .map_err(|e| ApiError::InvalidReceipt(format!("Invalid notification format: {}", e)))?;
+
+        if self.config.google_package_name.as_deref() != Some(notification.package_name.as_str()) {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Notification belongs to a different app: {}",
+                notification.package_name
+            )));
+        }
+
+        Ok(notification)
+    }
+
+    /// Fetch the current state of a Google Play subscription by purchase token
+    #[instrument(skip(self, purchase_token))]
+    pub async fn get_google_subscription(&self, purchase_token: &str) -> Result<IAPVerification> {
+        let package_name = self.config.google_package_name.clone().ok_or_else(|| {
+            ApiError::Internal(anyhow::anyhow!("Google package name is not configured"))
+        })?;
+
+        self.verify_google_subscription(&GooglePurchaseReceipt {
+            package_name,
+            product_id: None,
+            product_ids: Vec::new(),
+            purchase_token: purchase_token.to_string(),
+            auto_renewing: Some(true),
+        })
+        .await
+    }
+
+    /// Fetch Google's OIDC JWKS with caching
+    async fn get_google_jwks(&self) -> Result<JwkSet> {
+        let now = time::OffsetDateTime::now_utc();
+
+        {
+            let cache = self.google_jwks_cache.read().await;
+            if let Some(ref cached) = *cache {
+                let age = (now - cached.fetched_at).whole_seconds();
+                if age < GOOGLE_JWKS_CACHE_DURATION_SECS {
+                    debug!("Using cached Google JWKS (age: {}s)", age);
+                    return Ok(cached.jwks.clone());
+                }
+            }
+        }
+
+        debug!(
+            "Fetching Google JWKS from {}",
+            self.config.google_oidc_jwks_url
+        );
+        let response = self
+            .http_client
+            .get(&self.config.google_oidc_jwks_url)
+            .send()
+            .await
+            .map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!("Failed to fetch Google JWKS: {}", e))
+            })?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::Internal(anyhow::anyhow!(
+                "Google JWKS request failed with status: {}",
+                response.status()
+            )));
+        }
+
+        let jwks: JwkSet = response.json().await.map_err(|e| {
+            ApiError::Internal(anyhow::anyhow!("Failed to parse Google JWKS: {}", e))
+        })?;
+
+        {
+            let mut cache = self.google_jwks_cache.write().await;
+            *cache = Some(CachedJwks {
+                jwks: jwks.clone(),
+                fetched_at: now,
+            });
+        }
+
+        info!("Google JWKS fetched and cached ({} keys)", jwks.keys.len());
+        Ok(jwks)
     }
 
     /// Generate hash for receipt caching
@@ -0,0 +1,251 @@
+use std::io::Cursor;
+
+use base64::Engine;
+use image::{
+    codecs::jpeg::JpegEncoder,
+    imageops::{self, FilterType},
+    DynamicImage, ImageFormat, ImageReader, RgbaImage,
+};
+use tracing::warn;
+
+use crate::{
+    config::{CompressedImageFormat, ImageProcessingConfig, WatermarkConfig},
+    error::{ApiError, Result},
+};
+
+/// Format and dimensions read from an image's bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Detect the format and dimensions of encoded image bytes
+///
+/// The bytes' signature wins over `declared_mime`, which is only used when the format cannot be
+/// sniffed. Only the header is decoded.
+pub fn probe(bytes: &[u8], declared_mime: Option<&str>) -> Result<ImageInfo> {
+    let sniffed = image::guess_format(bytes).ok();
+    let declared = declared_mime.and_then(ImageFormat::from_mime_type);
+
+    if let (Some(sniffed), Some(declared)) = (sniffed, declared) {
+        if sniffed != declared {
+            warn!(
+                "Image declared as {} but its bytes are {}",
+                declared.to_mime_type(),
+                sniffed.to_mime_type()
+            );
+        }
+    }
+
+    let format = sniffed
+        .or(declared)
+        .ok_or_else(|| ApiError::AIProvider("Unrecognised image format".to_string()))?;
+    let (width, height) = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|e| ApiError::AIProvider(format!("Failed to read image dimensions: {}", e)))?;
+
+    Ok(ImageInfo {
+        mime_type: format.to_mime_type().to_string(),
+        width,
+        height,
+    })
+}
+
+/// Decode a client-supplied image, given as a data URL or bare base64
+///
+/// Payloads whose decoded size would exceed `max_bytes` are refused before decoding.
+pub fn decode_embedded(data: &str, max_bytes: usize) -> Result<(Vec<u8>, ImageInfo)> {
+    let encoded = data.split_once(',').map_or(data, |(_, data)| data).trim();
+    // Base64 inflates by a third
+    if encoded.len() / 4 * 3 > max_bytes {
+        return Err(ApiError::BadRequest(format!(
+            "Embedded images must not exceed {} bytes",
+            max_bytes
+        )));
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| ApiError::BadRequest("Embedded image is not valid base64".to_string()))?;
+    let info = probe(&bytes, None).map_err(|_| {
+        ApiError::BadRequest("Embedded image is not a supported image format".to_string())
+    })?;
+
+    Ok((bytes, info))
+}
+
+/// An encoded image ready to be stored
+#[derive(Debug, Clone)]
+pub struct EncodedImage {
+    pub bytes: Vec<u8>,
+    pub info: ImageInfo,
+}
+
+/// Named, smaller rendition stored alongside an original
+#[derive(Debug, Clone)]
+pub struct ImageVariant {
+    pub name: &'static str,
+    pub image: EncodedImage,
+}
+
+pub const THUMBNAIL_VARIANT: &str = "thumbnail";
+pub const COMPRESSED_VARIANT: &str = "compressed";
+
+/// Result of post-processing a generated image
+#[derive(Debug, Clone)]
+pub struct ProcessedImage {
+    // Watermarked and re-encoded in its own format when a watermark was applied
+    pub original: EncodedImage,
+    pub variants: Vec<ImageVariant>,
+}
+
+/// Derives thumbnails and compressed renditions, watermarking first when asked to
+pub struct ImageProcessor {
+    config: ImageProcessingConfig,
+    watermark: Option<RgbaImage>,
+}
+
+impl ImageProcessor {
+    /// Build a processor, loading the watermark overlay if one is configured
+    pub fn new(config: &ImageProcessingConfig) -> anyhow::Result<Self> {
+        let watermark = match &config.watermark {
+            Some(watermark) => {
+                let overlay = image::open(&watermark.image_path).map_err(|e| {
+                    anyhow::anyhow!("Failed to load watermark {}: {}", watermark.image_path, e)
+                })?;
+                Some(overlay.into_rgba8())
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            watermark,
+        })
+    }
+
+    /// Encoder quality for lossy `format`s
+    fn quality(&self, format: ImageFormat) -> u8 {
+        match format {
+            ImageFormat::WebP => self.config.webp_quality,
+            _ => self.config.jpeg_quality,
+        }
+    }
+
+    /// Decode `bytes`, optionally watermark them, and encode the thumbnail and compressed variants
+    ///
+    /// CPU bound; call from a blocking task.
+    pub fn process(
+        &self,
+        bytes: Vec<u8>,
+        info: ImageInfo,
+        watermark: bool,
+    ) -> Result<ProcessedImage> {
+        let format = ImageFormat::from_mime_type(&info.mime_type).ok_or_else(|| {
+            ApiError::Internal(anyhow::anyhow!("Unsupported image type {}", info.mime_type))
+        })?;
+        let mut decoded = image::load_from_memory_with_format(&bytes, format)
+            .map_err(|e| ApiError::AIProvider(format!("Failed to decode image: {}", e)))?;
+
+        let original = match (&self.watermark, &self.config.watermark) {
+            (Some(overlay), Some(settings)) if watermark => {
+                let mut canvas = decoded.into_rgba8();
+                apply_watermark(&mut canvas, overlay, settings);
+                decoded = DynamicImage::ImageRgba8(canvas);
+                encode(&decoded, format, self.quality(format))?
+            }
+            _ => EncodedImage { bytes, info },
+        };
+
+        let compressed_format = match self.config.compressed_format {
+            CompressedImageFormat::Jpeg => ImageFormat::Jpeg,
+            CompressedImageFormat::Webp => ImageFormat::WebP,
+        };
+        let thumbnail = encode(
+            &fit_within(&decoded, self.config.thumbnail_max_px),
+            compressed_format,
+            self.quality(compressed_format),
+        )?;
+        let compressed = encode(
+            &fit_within(&decoded, self.config.compressed_max_px),
+            compressed_format,
+            self.quality(compressed_format),
+        )?;
+
+        Ok(ProcessedImage {
+            original,
+            variants: vec![
+                ImageVariant {
+                    name: THUMBNAIL_VARIANT,
+                    image: thumbnail,
+                },
+                ImageVariant {
+                    name: COMPRESSED_VARIANT,
+                    image: compressed,
+                },
+            ],
+        })
+    }
+}
+
+/// Downscale to fit a `max_px` square, never enlarging
+fn fit_within(image: &DynamicImage, max_px: u32) -> DynamicImage {
+    if image.width() <= max_px && image.height() <= max_px {
+        image.clone()
+    } else {
+        image.resize(max_px, max_px, FilterType::Lanczos3)
+    }
+}
+
+/// Scale the overlay to the configured share of the width and blend it into the bottom-right corner
+fn apply_watermark(canvas: &mut RgbaImage, overlay: &RgbaImage, settings: &WatermarkConfig) {
+    let target_width = ((canvas.width() as f32 * settings.width_ratio).round() as u32).max(1);
+    let target_height = ((overlay.height() as f64 * target_width as f64 / overlay.width() as f64)
+        .round() as u32)
+        .clamp(1, canvas.height());
+    let mut scaled = imageops::resize(overlay, target_width, target_height, FilterType::Triangle);
+    for pixel in scaled.pixels_mut() {
+        pixel.0[3] = (pixel.0[3] as f32 * settings.opacity).round() as u8;
+    }
+
+    let margin = (canvas.width() as f32 * settings.margin_ratio).round() as i64;
+    let x = canvas.width() as i64 - target_width as i64 - margin;
+    let y = canvas.height() as i64 - target_height as i64 - margin;
+    imageops::overlay(canvas, &scaled, x.max(0), y.max(0));
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<EncodedImage> {
+    let mut bytes = Vec::new();
+    let encoded = match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode_image(&image.to_rgb8())
+            .map_err(|e| e.to_string()),
+        // The image crate only writes lossless WebP
+        ImageFormat::WebP => {
+            let (pixels, layout) = if image.color().has_alpha() {
+                (image.to_rgba8().into_raw(), webp::PixelLayout::Rgba)
+            } else {
+                (image.to_rgb8().into_raw(), webp::PixelLayout::Rgb)
+            };
+            webp::Encoder::new(&pixels, layout, image.width(), image.height())
+                .encode_simple(false, f32::from(quality))
+                .map(|encoded| bytes.extend_from_slice(&encoded))
+                .map_err(|e| format!("{:?}", e))
+        }
+        _ => image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .map_err(|e| e.to_string()),
+    };
+    encoded.map_err(|e| ApiError::Internal(anyhow::anyhow!("Failed to encode image: {}", e)))?;
+
+    Ok(EncodedImage {
+        bytes,
+        info: ImageInfo {
+            mime_type: format.to_mime_type().to_string(),
+            width: image.width(),
+            height: image.height(),
+        },
+    })
+}
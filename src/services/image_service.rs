@@ -0,0 +1,364 @@
+use std::{sync::Arc, time::Duration};
+
+use entity::ai_image_generation;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{error, instrument};
+use uuid::Uuid;
+
+use crate::{
+    error::{ApiError, Result},
+    models::ai::{AIImageHistoryQuery, AIImageHistoryResponse, AIImageRecord, ImageVariantLink},
+    services::{
+        ai_provider::ReferenceImage,
+        image_processing::{ImageInfo, ImageProcessor, COMPRESSED_VARIANT},
+        object_store::{self, ObjectStore, SignedUrl},
+    },
+};
+
+/// Stored generated images and the signed URLs clients read them through
+pub struct ImageService {
+    db: DatabaseConnection,
+    object_store: Arc<dyn ObjectStore>,
+    signed_url_ttl: Duration,
+    processor: Arc<ImageProcessor>,
+}
+
+/// An original and its renditions, as written to object storage
+#[derive(Debug, Clone)]
+pub struct StoredImage {
+    pub storage_key: String,
+    pub info: ImageInfo,
+    pub file_size: usize,
+    pub variants: Vec<StoredVariant>,
+}
+
+/// Rendition entry kept in the record's `variants` column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredVariant {
+    pub name: String,
+    pub storage_key: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl StoredImage {
+    /// Value for the record's `variants` column
+    pub fn variants_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.variants).expect("variants serialize to JSON")
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.storage_key.as_str())
+            .chain(self.variants.iter().map(|v| v.storage_key.as_str()))
+    }
+}
+
+impl ImageService {
+    pub fn new(
+        db: DatabaseConnection,
+        object_store: Arc<dyn ObjectStore>,
+        signed_url_ttl: Duration,
+        processor: ImageProcessor,
+    ) -> Self {
+        Self {
+            db,
+            object_store,
+            signed_url_ttl,
+            processor: Arc::new(processor),
+        }
+    }
+
+    /// Object key of a generated image: images/{user_id}/{image_id}.{ext}
+    pub fn storage_key(user_id: Uuid, image_id: Uuid, mime_type: &str) -> String {
+        format!(
+            "images/{}/{}.{}",
+            user_id,
+            image_id,
+            object_store::extension_for_content_type(mime_type)
+        )
+    }
+
+    /// Object key of a rendition: images/{user_id}/{image_id}_{name}.{ext}
+    pub fn variant_key(user_id: Uuid, image_id: Uuid, name: &str, mime_type: &str) -> String {
+        format!(
+            "images/{}/{}_{}.{}",
+            user_id,
+            image_id,
+            name,
+            object_store::extension_for_content_type(mime_type)
+        )
+    }
+
+    /// Post-process image bytes and store the original with its renditions
+    ///
+    /// `watermark` applies the configured watermark (if any) to the original and every rendition.
+    pub async fn store(
+        &self,
+        user_id: Uuid,
+        image_id: Uuid,
+        bytes: Vec<u8>,
+        info: ImageInfo,
+        watermark: bool,
+    ) -> Result<StoredImage> {
+        let processor = self.processor.clone();
+        let processed =
+            tokio::task::spawn_blocking(move || processor.process(bytes, info, watermark))
+                .await
+                .map_err(|e| {
+                    ApiError::Internal(anyhow::anyhow!("Image processing panicked: {}", e))
+                })??;
+
+        let original = processed.original;
+        let mut uploads = vec![(
+            Self::storage_key(user_id, image_id, &original.info.mime_type),
+            original.bytes,
+            original.info.mime_type.clone(),
+        )];
+        let mut stored = StoredImage {
+            storage_key: uploads[0].0.clone(),
+            file_size: uploads[0].1.len(),
+            info: original.info,
+            variants: Vec::new(),
+        };
+        for variant in processed.variants {
+            let info = variant.image.info;
+            let key = Self::variant_key(user_id, image_id, variant.name, &info.mime_type);
+            uploads.push((key.clone(), variant.image.bytes, info.mime_type.clone()));
+            stored.variants.push(StoredVariant {
+                name: variant.name.to_string(),
+                storage_key: key,
+                mime_type: info.mime_type,
+                width: info.width,
+                height: info.height,
+            });
+        }
+
+        for (key, bytes, mime_type) in uploads {
+            if let Err(err) = self.object_store.put(&key, bytes, &mime_type).await {
+                // Leave nothing behind for a record that will not be written
+                self.discard(&stored).await;
+                return Err(err);
+            }
+        }
+
+        Ok(stored)
+    }
+
+    /// Remove a stored image and its renditions, e.g. when its record could not be saved
+    pub async fn discard(&self, stored: &StoredImage) {
+        for key in stored.keys() {
+            if let Err(err) = self.object_store.delete(key).await {
+                error!("Failed to delete orphaned image {}: {}", key, err);
+            }
+        }
+    }
+
+    /// Signed URLs of an image's renditions
+    pub fn variant_links(&self, variants: &[StoredVariant]) -> Vec<ImageVariantLink> {
+        variants
+            .iter()
+            .map(|variant| {
+                let signed = self.signed_url(&variant.storage_key);
+                ImageVariantLink {
+                    name: variant.name.clone(),
+                    url: signed.url,
+                    url_expires_at: signed.expires_at,
+                    mime_type: variant.mime_type.clone(),
+                    width: variant.width,
+                    height: variant.height,
+                }
+            })
+            .collect()
+    }
+
+    /// Permanent, unsigned location of a stored image
+    pub fn url(&self, storage_key: &str) -> String {
+        self.object_store.url(storage_key)
+    }
+
+    pub fn signed_url(&self, storage_key: &str) -> SignedUrl {
+        self.object_store
+            .signed_url(storage_key, self.signed_url_ttl)
+    }
+
+    /// One of the user's generation attempts, with a usable URL if it has a stored image
+    #[instrument(skip(self))]
+    pub async fn get_image(&self, user_id: Uuid, image_id: Uuid) -> Result<AIImageRecord> {
+        let record = self.find(user_id, image_id).await?;
+        Ok(self.to_record(record))
+    }
+
+    /// The user's generation row
+    pub async fn find(&self, user_id: Uuid, image_id: Uuid) -> Result<ai_image_generation::Model> {
+        ai_image_generation::Entity::find_by_id(image_id)
+            .filter(ai_image_generation::Column::UserId.eq(user_id))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Image not found".to_string()))
+    }
+
+    /// Claim one of the `limit` regenerations and variations of `source_image_id`
+    ///
+    /// A single conditional update, so concurrent requests cannot overshoot the limit. Returns
+    /// false when every slot is taken; failed generations hand theirs back with `release_derived`.
+    pub async fn claim_derived(&self, source_image_id: Uuid, limit: u32) -> Result<bool> {
+        let result = ai_image_generation::Entity::update_many()
+            .col_expr(
+                ai_image_generation::Column::DerivedCount,
+                Expr::col(ai_image_generation::Column::DerivedCount).add(1),
+            )
+            .filter(ai_image_generation::Column::Id.eq(source_image_id))
+            .filter(ai_image_generation::Column::DerivedCount.lt(limit as i32))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    /// Give back a slot taken by `claim_derived`
+    pub async fn release_derived(&self, source_image_id: Uuid) -> Result<()> {
+        ai_image_generation::Entity::update_many()
+            .col_expr(
+                ai_image_generation::Column::DerivedCount,
+                Expr::col(ai_image_generation::Column::DerivedCount).sub(1),
+            )
+            .filter(ai_image_generation::Column::Id.eq(source_image_id))
+            .filter(ai_image_generation::Column::DerivedCount.gt(0))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// A generated image as model input, preferring its smaller compressed rendition
+    pub async fn reference_image(
+        &self,
+        record: &ai_image_generation::Model,
+    ) -> Result<ReferenceImage> {
+        let storage_key = Self::display_key(record).ok_or_else(|| {
+            ApiError::BadRequest("Image has no stored output to use as a reference".to_string())
+        })?;
+
+        Ok(ReferenceImage {
+            bytes: self.object_store.get(&storage_key).await?,
+            mime_type: object_store::content_type_for_key(&storage_key).to_string(),
+        })
+    }
+
+    /// Key of the rendition to show a generated image with: the compressed one, else the original
+    pub fn display_key(record: &ai_image_generation::Model) -> Option<String> {
+        Self::variants(record)
+            .into_iter()
+            .find(|variant| variant.name == COMPRESSED_VARIANT)
+            .map(|variant| variant.storage_key)
+            .or_else(|| record.storage_key.clone())
+    }
+
+    fn variants(record: &ai_image_generation::Model) -> Vec<StoredVariant> {
+        // Images stored before post-processing have no renditions
+        record
+            .variants
+            .clone()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Page of the user's generation attempts, newest first
+    #[instrument(skip(self))]
+    pub async fn list_images(
+        &self,
+        user_id: Uuid,
+        query: &AIImageHistoryQuery,
+    ) -> Result<AIImageHistoryResponse> {
+        let mut select = ai_image_generation::Entity::find()
+            .filter(ai_image_generation::Column::UserId.eq(user_id));
+        if let Some(story_title) = &query.story_title {
+            select = select.filter(ai_image_generation::Column::StoryTitle.eq(story_title));
+        }
+        if let Some(status) = query.status {
+            select = select.filter(ai_image_generation::Column::Status.eq(status.as_str()));
+        }
+        if let Some(from) = query.from {
+            select = select.filter(ai_image_generation::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = query.to {
+            select = select.filter(ai_image_generation::Column::CreatedAt.lt(to));
+        }
+
+        let paginator = select
+            .order_by_desc(ai_image_generation::Column::CreatedAt)
+            .order_by_desc(ai_image_generation::Column::Id)
+            .paginate(&self.db, query.page_size);
+
+        let totals = paginator.num_items_and_pages().await?;
+        let images = paginator
+            .fetch_page(query.page.saturating_sub(1))
+            .await?
+            .into_iter()
+            .map(|record| self.to_record(record))
+            .collect();
+
+        Ok(AIImageHistoryResponse {
+            images,
+            page: query.page,
+            page_size: query.page_size,
+            total_entries: totals.number_of_items,
+            total_pages: totals.number_of_pages,
+        })
+    }
+
+    fn to_record(&self, record: ai_image_generation::Model) -> AIImageRecord {
+        // Failed attempts and images from before persistent storage have nothing to serve
+        let signed = record
+            .storage_key
+            .as_deref()
+            .map(|storage_key| self.current_url(&record, storage_key));
+        let variants = Self::variants(&record);
+
+        AIImageRecord {
+            id: record.id,
+            mime_type: record
+                .storage_key
+                .as_deref()
+                .map(|key| object_store::content_type_for_key(key).to_string()),
+            url: signed.as_ref().map(|signed| signed.url.clone()),
+            url_expires_at: signed.map(|signed| signed.expires_at),
+            story_title: record.story_title,
+            node_summary: record.node_summary,
+            style: record.style,
+            resolution: record.resolution,
+            aspect_ratio: record.aspect_ratio,
+            seed: record.seed.map(|seed| seed as u32),
+            source_image_id: record.source_image_id,
+            status: record.status,
+            error_message: record.error_message,
+            width: record.width as u32,
+            height: record.height as u32,
+            variants: self.variant_links(&variants),
+            credits_used: record.credits_used,
+            created_at: record.created_at,
+        }
+    }
+
+    /// The record's URL while it has at least half its lifetime left, otherwise a fresh one
+    ///
+    /// Signing is local and cheap, so fresh URLs are not written back; reads stay read-only.
+    fn current_url(&self, record: &ai_image_generation::Model, storage_key: &str) -> SignedUrl {
+        let now = OffsetDateTime::now_utc();
+        if let (Some(url), Some(expires_at)) = (&record.temp_url, record.temp_url_expires_at) {
+            if expires_at - now > self.signed_url_ttl / 2 {
+                return SignedUrl {
+                    url: url.clone(),
+                    expires_at,
+                };
+            }
+        }
+
+        self.signed_url(storage_key)
+    }
+}
@@ -1,18 +1,38 @@
 // Service modules
+pub mod ai_provider;
 pub mod ai_service;
 pub mod auth_service;
+pub mod circuit_breaker;
 pub mod credits_service;
+pub mod export_document;
+pub mod export_pdf;
+pub mod export_service;
+pub mod iap_notification_service;
 pub mod iap_service;
+pub mod image_processing;
+pub mod image_service;
 pub mod jwt_service;
+pub mod moderation;
+pub mod node_merge;
+pub mod object_store;
 pub mod quota_service;
 pub mod refresh_token_service;
+pub mod share_page;
+pub mod share_preview;
+pub mod share_service;
+pub mod sync_service;
 pub mod welcome_bonus_service;
 
 pub use ai_service::AIService;
 pub use auth_service::AuthService;
 pub use credits_service::CreditsService;
+pub use export_service::ExportService;
+pub use iap_notification_service::IAPNotificationService;
 pub use iap_service::IAPService;
+pub use image_service::ImageService;
 pub use jwt_service::JWTService;
 pub use quota_service::QuotaService;
 pub use refresh_token_service::RefreshTokenService;
+pub use share_service::ShareService;
+pub use sync_service::SyncService;
 pub use welcome_bonus_service::WelcomeBonusService;
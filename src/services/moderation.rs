@@ -0,0 +1,157 @@
+use regex::{Regex, RegexBuilder};
+use tracing::error;
+
+use crate::{
+    config::{ModerationAction, ModerationConfig, ModerationModelConfig},
+    services::ai_provider::{ChatMessage, TextRequest},
+};
+
+/// Outcome of screening a text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModerationVerdict {
+    /// Categories the text matched, in first-seen order
+    pub flags: Vec<String>,
+    /// Whether any matched category disallows the text
+    pub blocked: bool,
+}
+
+impl ModerationVerdict {
+    fn flag(&mut self, category: &str, blocks: bool) {
+        if !self.flags.iter().any(|flag| flag == category) {
+            self.flags.push(category.to_string());
+        }
+        self.blocked |= blocks;
+    }
+
+    pub fn merge(&mut self, other: ModerationVerdict) {
+        for flag in other.flags {
+            self.flag(&flag, false);
+        }
+        self.blocked |= other.blocked;
+    }
+}
+
+struct CompiledRule {
+    category: String,
+    action: ModerationAction,
+    patterns: Vec<Regex>,
+}
+
+/// Local blocklist and classifier prompt for the moderation pipeline
+///
+/// The classifier call itself goes through `AIService`, which owns the providers.
+pub struct Moderator {
+    rules: Vec<CompiledRule>,
+    model: Option<ModerationModelConfig>,
+}
+
+impl Moderator {
+    pub fn new(config: &ModerationConfig) -> Self {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| CompiledRule {
+                category: rule.category.clone(),
+                action: rule.action,
+                // Patterns are validated when the config loads
+                patterns: rule
+                    .patterns
+                    .iter()
+                    .filter_map(|pattern| {
+                        RegexBuilder::new(pattern)
+                            .case_insensitive(true)
+                            .build()
+                            .inspect_err(|e| {
+                                error!(
+                                    "Skipping invalid moderation pattern for {}: {}",
+                                    rule.category, e
+                                )
+                            })
+                            .ok()
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            rules,
+            model: config.model.clone(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty() || self.model.is_some()
+    }
+
+    pub fn model(&self) -> Option<&ModerationModelConfig> {
+        self.model.as_ref()
+    }
+
+    /// Match `text` against the local blocklist
+    pub fn check_rules(&self, text: &str) -> ModerationVerdict {
+        let mut verdict = ModerationVerdict::default();
+        for rule in &self.rules {
+            if rule.patterns.iter().any(|pattern| pattern.is_match(text)) {
+                verdict.flag(&rule.category, rule.action == ModerationAction::Block);
+            }
+        }
+        verdict
+    }
+
+    /// Replace text matched by mask rules with asterisks
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = text.to_string();
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.action == ModerationAction::Mask)
+        {
+            for pattern in &rule.patterns {
+                masked = pattern
+                    .replace_all(&masked, |caps: &regex::Captures| {
+                        "*".repeat(caps[0].chars().count())
+                    })
+                    .into_owned();
+            }
+        }
+        masked
+    }
+
+    /// Request asking the classifier which categories `text` violates
+    pub fn classification_request(model: &ModerationModelConfig, text: &str) -> TextRequest {
+        let system_prompt = format!(
+            "You are a content safety classifier for a storytelling app. \
+            Fiction may depict conflict and mature themes; only flag content that clearly violates a category.\n\
+            Categories: {}\n\
+            Reply with NONE if the text violates no category, otherwise with a comma-separated list of \
+            the violated categories and nothing else.",
+            model.categories.join(", ")
+        );
+
+        TextRequest {
+            model: model.model.clone(),
+            messages: vec![ChatMessage::system(system_prompt), ChatMessage::user(text)],
+            max_tokens: 50,
+            temperature: 0.0,
+            n: 1,
+        }
+    }
+
+    /// Verdict from the classifier's reply; unknown categories are ignored
+    pub fn parse_classification(model: &ModerationModelConfig, reply: &str) -> ModerationVerdict {
+        let mut verdict = ModerationVerdict::default();
+        for label in reply.split([',', '\n']) {
+            let label = label
+                .trim()
+                .trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+            if let Some(category) = model
+                .categories
+                .iter()
+                .find(|category| category.eq_ignore_ascii_case(label))
+            {
+                verdict.flag(category, model.block_categories.contains(category));
+            }
+        }
+        verdict
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+/// The fields of a story node that two devices can edit independently
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeFields {
+    pub parent_node_id: Option<Uuid>,
+    pub sibling_order: i32,
+    pub depth: i32,
+    pub summary: Option<String>,
+    pub content: String,
+    pub tags: Vec<String>,
+}
+
+/// Three-way merge of a pushed edit and the server's edit of the same base version
+///
+/// Content and summary merge line by line; tags merge as a set; the node's position must have
+/// been changed on at most one side. Returns None when the edits overlap.
+pub fn merge(base: &NodeFields, local: &NodeFields, server: &NodeFields) -> Option<NodeFields> {
+    Some(NodeFields {
+        parent_node_id: merge_value(
+            &base.parent_node_id,
+            &local.parent_node_id,
+            &server.parent_node_id,
+        )?,
+        sibling_order: merge_value(
+            &base.sibling_order,
+            &local.sibling_order,
+            &server.sibling_order,
+        )?,
+        depth: merge_value(&base.depth, &local.depth, &server.depth)?,
+        summary: match (&base.summary, &local.summary, &server.summary) {
+            (Some(base), Some(local), Some(server)) => Some(merge_text(base, local, server)?),
+            (base, local, server) => merge_value(base, local, server)?,
+        },
+        content: merge_text(&base.content, &local.content, &server.content)?,
+        tags: merge_tags(&base.tags, &local.tags, &server.tags),
+    })
+}
+
+/// Take whichever side changed; None when both changed it differently
+fn merge_value<T: PartialEq + Clone>(base: &T, local: &T, server: &T) -> Option<T> {
+    if local == base || local == server {
+        Some(server.clone())
+    } else if server == base {
+        Some(local.clone())
+    } else {
+        None
+    }
+}
+
+fn merge_text(base: &str, local: &str, server: &str) -> Option<String> {
+    merge_value(&base, &local, &server)
+        .map(str::to_string)
+        .or_else(|| diffy::merge(base, local, server).ok())
+}
+
+/// Server's tags plus the ones added locally, minus the ones removed locally
+fn merge_tags(base: &[String], local: &[String], server: &[String]) -> Vec<String> {
+    let base: HashSet<&String> = base.iter().collect();
+    let local_set: HashSet<&String> = local.iter().collect();
+
+    let mut merged: Vec<String> = server
+        .iter()
+        .filter(|tag| local_set.contains(tag) || !base.contains(tag))
+        .cloned()
+        .collect();
+    for tag in local {
+        if !base.contains(tag) && !merged.contains(tag) {
+            merged.push(tag.clone());
+        }
+    }
+    merged
+}
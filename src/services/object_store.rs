@@ -0,0 +1,450 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::{
+    config::{LocalStorageConfig, S3StorageConfig, StorageBackend, StorageConfig},
+    error::{ApiError, Result},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Time-limited URL for reading a stored object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedUrl {
+    pub url: String,
+    pub expires_at: OffsetDateTime,
+}
+
+/// Blob storage for generated assets
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()>;
+
+    /// Object contents; `NotFound` when the key does not exist
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Permanent, unsigned location of the object, kept for records
+    fn url(&self, key: &str) -> String;
+
+    /// URL granting read access until it expires
+    fn signed_url(&self, key: &str, expires_in: Duration) -> SignedUrl;
+
+    /// Check a signature from `signed_url`; only backends served by this API implement it
+    fn verify_signature(&self, _key: &str, _expires: i64, _signature: &str) -> bool {
+        false
+    }
+}
+
+/// Build the configured backend
+pub fn from_config(config: &StorageConfig) -> Arc<dyn ObjectStore> {
+    match (config.backend, &config.s3) {
+        (StorageBackend::S3, Some(s3)) => Arc::new(S3ObjectStore::new(s3)),
+        // Rejected by config validation
+        (StorageBackend::S3, None) => panic!("storage.s3 is required when storage.backend is s3"),
+        (StorageBackend::Local, _) => match &config.local.signing_secret {
+            Some(secret) => Arc::new(LocalObjectStore::new(&config.local, secret)),
+            // Rejected by config validation
+            None => {
+                panic!("storage.local.signing_secret is required when storage.backend is local")
+            }
+        },
+    }
+}
+
+/// File extension for a stored object of the given MIME type
+pub fn extension_for_content_type(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "application/epub+zip" => "epub",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        _ => "bin",
+    }
+}
+
+/// MIME type of a stored object, from its key's extension
+pub fn content_type_for_key(key: &str) -> &'static str {
+    match key.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("epub") => "application/epub+zip",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Percent-encode per RFC 3986, leaving unreserved characters (and `/` unless `encode_slash`)
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn storage_error(context: &str, err: impl std::fmt::Display) -> ApiError {
+    ApiError::Internal(anyhow::anyhow!("{}: {}", context, err))
+}
+
+/// Filesystem backend; signed URLs point at this API's `/files` route
+pub struct LocalObjectStore {
+    root: PathBuf,
+    public_base_url: String,
+    signing_secret: Vec<u8>,
+}
+
+impl LocalObjectStore {
+    pub fn new(config: &LocalStorageConfig, signing_secret: &str) -> Self {
+        Self {
+            root: PathBuf::from(&config.root),
+            public_base_url: config.public_base_url.trim_end_matches('/').to_string(),
+            signing_secret: signing_secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Path of the object, refusing keys that would escape the root
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let is_plain = !key.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !is_plain {
+            return Err(ApiError::BadRequest(format!("Invalid object key: {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn signature(&self, key: &str, expires: i64) -> String {
+        hex::encode(hmac_sha256(
+            &self.signing_secret,
+            &format!("{}\n{}", key, expires),
+        ))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| storage_error("Failed to create storage directory", e))?;
+        }
+
+        // Write then rename so readers never see a partial object
+        let tmp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        tokio::fs::write(&tmp, bytes)
+            .await
+            .map_err(|e| storage_error("Failed to write object", e))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| storage_error("Failed to write object", e))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(ApiError::NotFound(format!("Object {} not found", key)))
+            }
+            Err(e) => Err(storage_error("Failed to read object", e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(storage_error("Failed to delete object", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, uri_encode(key, false))
+    }
+
+    fn signed_url(&self, key: &str, expires_in: Duration) -> SignedUrl {
+        let expires_at = (OffsetDateTime::now_utc() + expires_in)
+            .replace_nanosecond(0)
+            .unwrap();
+        let expires = expires_at.unix_timestamp();
+        SignedUrl {
+            url: format!(
+                "{}?expires={}&signature={}",
+                self.url(key),
+                expires,
+                self.signature(key, expires)
+            ),
+            expires_at,
+        }
+    }
+
+    fn verify_signature(&self, key: &str, expires: i64, signature: &str) -> bool {
+        if expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+
+        let mut mac = HmacSha256::new_from_slice(&self.signing_secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{}\n{}", key, expires).as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// S3-compatible backend using SigV4 request signing and presigned GET URLs
+pub struct S3ObjectStore {
+    http_client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    path_style: bool,
+}
+
+const S3_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+// SigV4 caps presigned URLs at 7 days
+const S3_MAX_PRESIGN_SECS: u64 = 7 * 24 * 3600;
+
+impl S3ObjectStore {
+    pub fn new(config: &S3StorageConfig) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            http_client,
+            // Validated when the config loads
+            endpoint: Url::parse(&config.endpoint).expect("Invalid storage.s3.endpoint"),
+            bucket: config.bucket.clone(),
+            region: config.region.clone(),
+            access_key_id: config.access_key_id.clone(),
+            secret_access_key: config.secret_access_key.clone(),
+            path_style: config.path_style,
+        }
+    }
+
+    /// Host header and canonical path addressing the object
+    fn location(&self, key: &str) -> (String, String) {
+        let mut host = self.endpoint.host_str().unwrap_or_default().to_string();
+        if let Some(port) = self.endpoint.port() {
+            host = format!("{}:{}", host, port);
+        }
+        let key = uri_encode(key, false);
+
+        if self.path_style {
+            (host, format!("/{}/{}", self.bucket, key))
+        } else {
+            (format!("{}.{}", self.bucket, host), format!("/{}", key))
+        }
+    }
+
+    fn credential_scope(&self, date: &str) -> String {
+        format!("{}/{}/s3/aws4_request", date, self.region)
+    }
+
+    /// Sign a canonical request, returning the hex signature
+    fn sign(&self, now: OffsetDateTime, canonical_request: &str) -> String {
+        let (date, amz_date) = amz_dates(now);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            S3_ALGORITHM,
+            amz_date,
+            self.credential_scope(&date),
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), &date);
+        let key = hmac_sha256(&key, &self.region);
+        let key = hmac_sha256(&key, "s3");
+        let key = hmac_sha256(&key, "aws4_request");
+        hex::encode(hmac_sha256(&key, &string_to_sign))
+    }
+
+    /// Presigned URL for `method` on the object, valid for `expires_in` from `now`
+    pub fn presigned_url(
+        &self,
+        method: &str,
+        key: &str,
+        expires_in: Duration,
+        now: OffsetDateTime,
+    ) -> String {
+        let (host, path) = self.location(key);
+        let (date, amz_date) = amz_dates(now);
+        let expires = expires_in.as_secs().clamp(1, S3_MAX_PRESIGN_SECS);
+
+        // Parameters are already in canonical (sorted) order
+        let query = format!(
+            "X-Amz-Algorithm={}&X-Amz-Credential={}&X-Amz-Date={}&X-Amz-Expires={}&X-Amz-SignedHeaders=host",
+            S3_ALGORITHM,
+            uri_encode(
+                &format!("{}/{}", self.access_key_id, self.credential_scope(&date)),
+                true
+            ),
+            amz_date,
+            expires
+        );
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\n\nhost\n{}",
+            method, path, query, host, UNSIGNED_PAYLOAD
+        );
+        let signature = self.sign(now, &canonical_request);
+
+        format!(
+            "{}://{}{}?{}&X-Amz-Signature={}",
+            self.endpoint.scheme(),
+            host,
+            path,
+            query,
+            signature
+        )
+    }
+
+    /// Send a header-signed request for the object
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Option<(Vec<u8>, &str)>,
+    ) -> Result<reqwest::Response> {
+        let (host, path) = self.location(key);
+        let now = OffsetDateTime::now_utc();
+        let (date, amz_date) = amz_dates(now);
+        let payload_hash = hex::encode(Sha256::digest(
+            body.as_ref()
+                .map(|(bytes, _)| bytes.as_slice())
+                .unwrap_or(&[]),
+        ));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            S3_ALGORITHM,
+            self.access_key_id,
+            self.credential_scope(&date),
+            signed_headers,
+            self.sign(now, &canonical_request)
+        );
+
+        let url = format!("{}://{}{}", self.endpoint.scheme(), host, path);
+        let mut request = self
+            .http_client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header("authorization", authorization);
+        if let Some((bytes, content_type)) = body {
+            request = request.header("content-type", content_type).body(bytes);
+        }
+
+        request
+            .send()
+            .await
+            .map_err(|e| storage_error("Object storage request failed", e))
+    }
+}
+
+/// SigV4 date stamp (YYYYMMDD) and timestamp (YYYYMMDD'T'HHMMSS'Z')
+fn amz_dates(now: OffsetDateTime) -> (String, String) {
+    let amz_date = now
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .expect("Timestamp is formattable");
+    (amz_date[..8].to_string(), amz_date)
+}
+
+async fn check_status(response: reqwest::Response, context: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(storage_error(context, format!("{} {}", status, body)))
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        let response = self
+            .send(Method::PUT, key, Some((bytes, content_type)))
+            .await?;
+        check_status(response, "Failed to upload object").await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self.send(Method::GET, key, None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ApiError::NotFound(format!("Object {} not found", key)));
+        }
+        let response = check_status(response, "Failed to download object").await?;
+        response
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| storage_error("Failed to download object", e))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self.send(Method::DELETE, key, None).await?;
+        check_status(response, "Failed to delete object").await?;
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        let (host, path) = self.location(key);
+        format!("{}://{}{}", self.endpoint.scheme(), host, path)
+    }
+
+    fn signed_url(&self, key: &str, expires_in: Duration) -> SignedUrl {
+        let now = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap();
+        let expires_in = Duration::from_secs(expires_in.as_secs().clamp(1, S3_MAX_PRESIGN_SECS));
+        SignedUrl {
+            url: self.presigned_url("GET", key, expires_in, now),
+            expires_at: now + expires_in,
+        }
+    }
+}
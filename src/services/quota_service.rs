@@ -1,184 +1,585 @@
 use crate::{
-    config::QuotaConfig,
+    config::{ProductCatalog, QuotaConfig},
     error::{ApiError, Result},
-    models::common::AIOperation,
+    models::{
+        common::{AIOperation, IAPPlatform},
+        credits::{CreditReservation, UsageContext},
+    },
+    services::CreditsService,
 };
 use entity::sea_orm_active_enums::AccountTier;
 use sea_orm::{
-    entity::*, query::*, sea_query::OnConflict, DatabaseConnection, DatabaseTransaction,
-    TransactionTrait,
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+    DatabaseConnection, DatabaseTransaction, TransactionTrait,
 };
-use tracing::{info, instrument};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
 use uuid::Uuid;
 
+/// Expired holds released per sweep
+const EXPIRED_HOLDS_BATCH_SIZE: u64 = 100;
+
 pub struct QuotaService {
     db: DatabaseConnection,
     config: QuotaConfig,
+    products: ProductCatalog,
+    credits_service: CreditsService,
 }
 
 impl QuotaService {
-    pub fn new(db: DatabaseConnection, config: &QuotaConfig) -> Self {
+    pub fn new(db: DatabaseConnection, config: &QuotaConfig, products: &ProductCatalog) -> Self {
         Self {
+            credits_service: CreditsService::new(db.clone()),
             db,
             config: config.clone(),
+            products: products.clone(),
         }
     }
 
-    /// Get monthly allocation for a tier
-    fn get_monthly_allocation(&self, tier: &AccountTier) -> i32 {
-        match tier {
+    /// Get monthly allocation for a user's tier
+    /// Pro users get their subscription product's allocation when the catalog defines one
+    async fn get_monthly_allocation(
+        &self,
+        user_id: Uuid,
+        tier: &AccountTier,
+        txn: &DatabaseTransaction,
+    ) -> Result<i32> {
+        let tier_allocation = match tier {
             AccountTier::Free => self.config.free_text_daily_limit, // Treat as monthly for now
             AccountTier::Pro => self.config.pro_text_daily_limit,   // Treat as monthly for now
+        };
+
+        if *tier != AccountTier::Pro {
+            return Ok(tier_allocation);
         }
+
+        let subscription = entity::user_iap_receipts::Entity::find()
+            .filter(entity::user_iap_receipts::Column::UserId.eq(user_id))
+            .filter(entity::user_iap_receipts::Column::PurchaseTier.eq(AccountTier::Pro))
+            .filter(
+                entity::user_iap_receipts::Column::SubscriptionStatus
+                    .is_in(["active", "grace_period"]),
+            )
+            .order_by_desc(entity::user_iap_receipts::Column::ExpiresAt)
+            .one(txn)
+            .await?;
+
+        let product_allocation = subscription.and_then(|receipt| {
+            let platform = match receipt.platform.as_str() {
+                "apple" => IAPPlatform::Apple,
+                "google" => IAPPlatform::Google,
+                _ => return None,
+            };
+            self.products
+                .monthly_allocation(platform, &receipt.product_id)
+        });
+
+        Ok(product_allocation.unwrap_or(tier_allocation))
     }
 
-    /// Check and increment quota atomically with weighted cost
-    /// Deducts from subscription first, then extra credits (FIFO-like behavior)
+    /// Place a time-limited credit hold for an operation, atomically with weighted cost
+    /// Deducts from subscription first, then extra credits (FIFO by purchase date)
+    /// The hold must be settled with commit or release; unsettled holds are released by the sweeper
     #[instrument(skip(self))]
-    pub async fn check_and_increment_quota_weighted(
+    pub async fn reserve(
         &self,
         user_id: Uuid,
         tier: &AccountTier,
         operation: AIOperation,
+    ) -> Result<CreditReservation> {
+        let txn = self.db.begin().await?;
+        let (reservation, total_remaining) = self
+            .charge_in_txn(user_id, tier, operation, false, &txn)
+            .await?;
+        txn.commit().await?;
+
+        info!(
+            "Held {} credits for {} operation by user: {} (remaining: {})",
+            reservation.cost,
+            if matches!(operation, AIOperation::ImageGenerate) {
+                "image"
+            } else {
+                "text"
+            },
+            user_id,
+            total_remaining
+        );
+
+        Ok(reservation)
+    }
+
+    /// Finalise a hold after a successful operation and record it in the usage ledger
+    /// An operation that produced output before failing (`usage.error` set) is recorded as interrupted
+    /// If the sweeper released the hold while the operation ran, the output is charged again.
+    #[instrument(skip(self))]
+    pub async fn commit(
+        &self,
+        user_id: Uuid,
+        reservation: &CreditReservation,
+        usage: &UsageContext,
     ) -> Result<()> {
-        let cost = operation.cost() as i32;
-        let today = time::OffsetDateTime::now_utc().date();
+        let now = time::OffsetDateTime::now_utc();
 
         let txn = self.db.begin().await?;
 
-        // 1. Lock credit balance (persistent across days)
-        let balance = self
-            .find_and_lock_credit_balance(user_id, tier, &txn)
+        let hold = self.lock_hold(user_id, reservation.id, &txn).await?;
+        let charged = match hold.status.as_str() {
+            "held" => {
+                let charged = Self::reservation_from_hold(&hold)?;
+                let mut hold_active: entity::credit_reservations::ActiveModel = hold.into();
+                hold_active.status = Set("committed".to_string());
+                hold_active.committed_at = Set(Some(now));
+                hold_active.update(&txn).await?;
+                charged
+            }
+            // Expired and refunded, but the output was delivered. committed_at on a released
+            // hold marks it as charged again, so that happens at most once.
+            "released" if hold.committed_at.is_none() => {
+                let operation = Self::reservation_from_hold(&hold)?.operation;
+                let tier = entity::users::Entity::find_by_id(user_id)
+                    .one(&txn)
+                    .await?
+                    .map(|user| user.account_tier)
+                    .unwrap_or(AccountTier::Free);
+                let (charged, _) = self
+                    .charge_in_txn(user_id, &tier, operation, true, &txn)
+                    .await?;
+                warn!(
+                    "Credit hold {} was released before commit; charged {} of {} credits again as hold {}",
+                    hold.id, charged.cost, hold.cost, charged.id
+                );
+
+                let mut hold_active: entity::credit_reservations::ActiveModel = hold.into();
+                hold_active.committed_at = Set(Some(now));
+                hold_active.update(&txn).await?;
+                charged
+            }
+            status => {
+                return Err(ApiError::BadRequest(format!(
+                    "Credit hold {} is already {}",
+                    reservation.id, status
+                )));
+            }
+        };
+        let outcome = if usage.error.is_some() {
+            "interrupted"
+        } else {
+            "succeeded"
+        };
+
+        self.credits_service
+            .record_usage_entry_in_txn(user_id, "charge", &charged, usage, outcome, None, &txn)
             .await?;
 
-        // 2. Check subscription reset
-        let balance = self
-            .reset_subscription_if_needed_tx(balance, tier, &txn)
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Drop a hold after a failed operation
+    /// Reverses exactly the held credits: each source gets back what it gave and
+    /// the usage date that was charged is decremented. A hold is settled at most once.
+    #[instrument(skip(self))]
+    pub async fn release(
+        &self,
+        user_id: Uuid,
+        tier: &AccountTier,
+        reservation: &CreditReservation,
+        usage: &UsageContext,
+    ) -> Result<()> {
+        self.release_hold(user_id, tier, reservation.id, usage, "failed")
+            .await
+    }
+
+    /// Note that a hold's output was delivered when committing it failed
+    /// The sweeper then commits the hold on expiry instead of refunding it
+    #[instrument(skip(self))]
+    pub async fn mark_delivered(&self, user_id: Uuid, reservation_id: Uuid) -> Result<()> {
+        entity::credit_reservations::Entity::update_many()
+            .col_expr(
+                entity::credit_reservations::Column::DeliveredAt,
+                Expr::value(time::OffsetDateTime::now_utc()),
+            )
+            .filter(entity::credit_reservations::Column::Id.eq(reservation_id))
+            .filter(entity::credit_reservations::Column::UserId.eq(user_id))
+            .filter(entity::credit_reservations::Column::Status.eq("held"))
+            .exec(&self.db)
             .await?;
 
-        // 3. Calculate total available
-        let total_available = balance.subscription_credits + balance.extra_credits_remaining;
+        Ok(())
+    }
 
-        if total_available < cost {
-            txn.rollback().await?;
-            return Err(ApiError::QuotaExceeded(format!(
-                "Insufficient credits: need {}, have {} (subscription: {}, extra: {})",
-                cost,
-                total_available,
-                balance.subscription_credits,
-                balance.extra_credits_remaining
-            )));
+    /// Settle holds that were never settled, e.g. because the process died mid-generation
+    /// Holds marked delivered are committed; the rest are released
+    /// Returns the number of holds settled
+    #[instrument(skip(self))]
+    pub async fn release_expired_holds(&self) -> Result<usize> {
+        let now = time::OffsetDateTime::now_utc();
+
+        let expired = entity::credit_reservations::Entity::find()
+            .filter(entity::credit_reservations::Column::Status.eq("held"))
+            .filter(entity::credit_reservations::Column::ExpiresAt.lt(now))
+            .order_by_asc(entity::credit_reservations::Column::ExpiresAt)
+            .limit(EXPIRED_HOLDS_BATCH_SIZE)
+            .all(&self.db)
+            .await?;
+
+        let usage = UsageContext {
+            error: Some("Credit hold expired".to_string()),
+            ..Default::default()
+        };
+
+        let mut settled = 0;
+        for hold in expired {
+            if hold.delivered_at.is_some() {
+                let result = match Self::reservation_from_hold(&hold) {
+                    Ok(reservation) => {
+                        self.commit(hold.user_id, &reservation, &UsageContext::default())
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(()) => settled += 1,
+                    Err(ApiError::BadRequest(_)) => {}
+                    Err(e) => warn!("Failed to commit delivered credit hold {}: {}", hold.id, e),
+                }
+                continue;
+            }
+
+            let tier = entity::users::Entity::find_by_id(hold.user_id)
+                .one(&self.db)
+                .await?
+                .map(|user| user.account_tier)
+                .unwrap_or(AccountTier::Free);
+
+            match self
+                .release_hold(hold.user_id, &tier, hold.id, &usage, "expired")
+                .await
+            {
+                Ok(()) => settled += 1,
+                // Settled between the scan and the lock
+                Err(ApiError::BadRequest(_)) => {}
+                Err(e) => warn!("Failed to release expired credit hold {}: {}", hold.id, e),
+            }
         }
 
-        // 4. Deduct from subscription first, then extra (subscription credits are "use it or lose it")
-        let mut balance_active: entity::user_credit_balance::ActiveModel = balance.into();
+        Ok(settled)
+    }
 
-        if *balance_active.subscription_credits.as_ref() >= cost {
-            // Deduct entirely from subscription
-            let current = *balance_active.subscription_credits.as_ref();
-            balance_active.subscription_credits = Set(current - cost);
-        } else {
-            // Deduct partially from both
-            let from_subscription = *balance_active.subscription_credits.as_ref();
-            let from_extra = cost - from_subscription;
+    /// Run release_expired_holds periodically in the background
+    pub fn spawn_hold_sweeper(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let period = std::time::Duration::from_secs(self.config.hold_sweep_interval_secs.max(1));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                match self.release_expired_holds().await {
+                    Ok(0) => {}
+                    Ok(settled) => info!("Settled {} expired credit holds", settled),
+                    Err(e) => warn!("Failed to sweep expired credit holds: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Return held credits to their sources and mark the hold released
+    /// `outcome` is recorded on the ledger's charge entry ("failed" or "expired")
+    async fn release_hold(
+        &self,
+        user_id: Uuid,
+        tier: &AccountTier,
+        reservation_id: Uuid,
+        usage: &UsageContext,
+        outcome: &str,
+    ) -> Result<()> {
+        let now = time::OffsetDateTime::now_utc();
+
+        let txn = self.db.begin().await?;
+
+        // 1. Lock the hold; the stored row is authoritative
+        let hold = self
+            .find_and_lock_hold(user_id, reservation_id, &txn)
+            .await?;
+        let charged = Self::reservation_from_hold(&hold)?;
 
-            balance_active.subscription_credits = Set(0);
+        // 2. Lock credit balance
+        let balance = self
+            .find_and_lock_credit_balance(user_id, tier, &txn)
+            .await?;
 
-            let current_extra = *balance_active.extra_credits_remaining.as_ref();
-            balance_active.extra_credits_remaining = Set(current_extra - from_extra);
+        // 3. Subscription credits only come back within the period they were taken from
+        let subscription_refund = if balance.subscription_resets_at == hold.subscription_resets_at {
+            charged.subscription_credits
+        } else {
+            info!(
+                "Subscription period ended since hold {}; {} subscription credits expired",
+                hold.id, charged.subscription_credits
+            );
+            0
+        };
+
+        let extra_before = balance.extra_credits_remaining;
+        if subscription_refund > 0 {
+            let current = balance.subscription_credits;
+            let mut balance_active: entity::user_credit_balance::ActiveModel = balance.into();
+            balance_active.subscription_credits = Set(current + subscription_refund);
+            balance_active.last_updated = Set(now);
+            balance_active.update(&txn).await?;
         }
 
-        balance_active.last_updated = Set(time::OffsetDateTime::now_utc());
-        let updated_balance = balance_active.update(&txn).await?;
+        // 4. Extra credits go back to the grants that funded them
+        let (extra_refund, extra_remaining) = if charged.extra_credits > 0 {
+            self.credits_service
+                .restore_extra_credits_in_txn(user_id, hold.id, &txn)
+                .await?
+        } else {
+            (0, extra_before)
+        };
 
-        // 5. Update daily consumption log (for analytics)
-        let usage = self.find_and_lock_usage(user_id, today, &txn).await?;
-        let mut usage_active: entity::quota_usage::ActiveModel = usage.into();
+        // 5. Decrement the usage log of the day that was charged
+        let usage_row = self
+            .find_and_lock_usage(user_id, charged.usage_date, &txn)
+            .await?;
+        let mut usage_active: entity::quota_usage::ActiveModel = usage_row.into();
 
-        let is_image_op = matches!(operation, AIOperation::ImageGenerate);
+        let is_image_op = matches!(charged.operation, AIOperation::ImageGenerate);
         if is_image_op {
             let current = *usage_active.image_count.as_ref();
-            usage_active.image_count = Set(current + cost);
+            // Prevent negative counts
+            usage_active.image_count = Set(std::cmp::max(0, current - charged.cost));
         } else {
             let current = *usage_active.text_count.as_ref();
-            usage_active.text_count = Set(current + cost);
+            usage_active.text_count = Set(std::cmp::max(0, current - charged.cost));
         }
-        usage_active.updated_at = Set(time::OffsetDateTime::now_utc());
+        usage_active.updated_at = Set(now);
         usage_active.update(&txn).await?;
 
-        txn.commit().await?;
+        // 6. Mark the hold released and record the unsuccessful charge and its refund
+        let mut hold_active: entity::credit_reservations::ActiveModel = hold.into();
+        hold_active.status = Set("released".to_string());
+        hold_active.released_at = Set(Some(now));
+        hold_active.update(&txn).await?;
 
-        // 6. Return updated status
-        let total_remaining =
-            updated_balance.subscription_credits + updated_balance.extra_credits_remaining;
+        let charge_id = self
+            .credits_service
+            .record_usage_entry_in_txn(user_id, "charge", &charged, usage, outcome, None, &txn)
+            .await?;
+        let refund = CreditReservation {
+            cost: subscription_refund + extra_refund,
+            subscription_credits: subscription_refund,
+            extra_credits: extra_refund,
+            ..charged
+        };
+        self.credits_service
+            .record_usage_entry_in_txn(
+                user_id,
+                "refund",
+                &refund,
+                usage,
+                "refunded",
+                Some(charge_id),
+                &txn,
+            )
+            .await?;
+
+        txn.commit().await?;
 
         info!(
-            "Deducted {} credits for {} operation by user: {} (remaining: {})",
-            cost,
+            "Released {} credits for {} operation to user: {} (subscription: {}, extra: {}, new extra credits: {})",
+            subscription_refund + extra_refund,
             if is_image_op { "image" } else { "text" },
             user_id,
-            total_remaining
+            subscription_refund,
+            extra_refund,
+            extra_remaining
         );
 
         Ok(())
     }
 
-    /// Refund credits after a failed operation
-    /// Reverses the deduction made by check_and_increment_quota_weighted
-    #[instrument(skip(self))]
-    pub async fn refund_quota_weighted(
+    /// Deduct an operation's cost and record the hold (within transaction)
+    /// Returns the hold and the credits remaining afterwards. A `settled` charge pays for output
+    /// that was already delivered: it takes what is left when the full cost is not available,
+    /// and its hold is stored as committed rather than held.
+    async fn charge_in_txn(
         &self,
         user_id: Uuid,
         tier: &AccountTier,
         operation: AIOperation,
-    ) -> Result<()> {
-        let cost = operation.cost() as i32;
-        let today = time::OffsetDateTime::now_utc().date();
+        settled: bool,
+        txn: &DatabaseTransaction,
+    ) -> Result<(CreditReservation, i32)> {
+        let now = time::OffsetDateTime::now_utc();
+        let today = now.date();
+        let reservation_id = Uuid::new_v4();
 
-        let txn = self.db.begin().await?;
+        // 1. Lock credit balance (persistent across days)
+        let balance = self
+            .find_and_lock_credit_balance(user_id, tier, txn)
+            .await?;
 
-        // 1. Lock credit balance
+        // 2. Check subscription reset
         let balance = self
-            .find_and_lock_credit_balance(user_id, tier, &txn)
+            .reset_subscription_if_needed_tx(balance, tier, txn)
             .await?;
 
-        // 2. Refund credits (add them back)
-        // Logic: Refund to extra credits first (they were deducted last in FIFO)
-        // This is a simplification - we add to extra credits for safety
-        let mut balance_active: entity::user_credit_balance::ActiveModel = balance.into();
+        // 3. Calculate total available
+        let total_available = balance.subscription_credits + balance.extra_credits_remaining;
 
-        let current_extra = *balance_active.extra_credits_remaining.as_ref();
-        balance_active.extra_credits_remaining = Set(current_extra + cost);
-        balance_active.last_updated = Set(time::OffsetDateTime::now_utc());
-        balance_active.update(&txn).await?;
+        let mut cost = operation.cost() as i32;
+        if total_available < cost {
+            if !settled {
+                return Err(ApiError::QuotaExceeded(format!(
+                    "Insufficient credits: need {}, have {} (subscription: {}, extra: {})",
+                    cost,
+                    total_available,
+                    balance.subscription_credits,
+                    balance.extra_credits_remaining
+                )));
+            }
+            cost = total_available.max(0);
+        }
+
+        // 4. Deduct from subscription first, then extra (subscription credits are "use it or lose it")
+        let from_subscription = balance.subscription_credits.clamp(0, cost);
+        let from_extra = cost - from_subscription;
+        let subscription_remaining = balance.subscription_credits - from_subscription;
+        let subscription_resets_at = balance.subscription_resets_at;
 
-        // 3. Decrement daily usage log (for analytics accuracy)
-        let usage = self.find_and_lock_usage(user_id, today, &txn).await?;
+        let mut balance_active: entity::user_credit_balance::ActiveModel = balance.into();
+        balance_active.subscription_credits = Set(subscription_remaining);
+        balance_active.last_updated = Set(now);
+        let updated_balance = balance_active.update(txn).await?;
+
+        // Extra credits are drawn from ledger grants oldest-first; the balance row follows the ledger
+        let extra_remaining = if from_extra > 0 {
+            let metadata = serde_json::json!({
+                "operation": operation.as_str(),
+                "cost": cost,
+                "subscription": from_subscription,
+                "extra": from_extra,
+            });
+            self.credits_service
+                .consume_extra_credits_in_txn(user_id, reservation_id, from_extra, metadata, txn)
+                .await?
+        } else {
+            updated_balance.extra_credits_remaining
+        };
+
+        // 5. Update daily consumption log (for analytics)
+        let usage = self.find_and_lock_usage(user_id, today, txn).await?;
         let mut usage_active: entity::quota_usage::ActiveModel = usage.into();
 
-        let is_image_op = matches!(operation, AIOperation::ImageGenerate);
-        if is_image_op {
+        if matches!(operation, AIOperation::ImageGenerate) {
             let current = *usage_active.image_count.as_ref();
-            // Prevent negative counts
-            usage_active.image_count = Set(std::cmp::max(0, current - cost));
+            usage_active.image_count = Set(current + cost);
         } else {
             let current = *usage_active.text_count.as_ref();
-            usage_active.text_count = Set(std::cmp::max(0, current - cost));
+            usage_active.text_count = Set(current + cost);
         }
-        usage_active.updated_at = Set(time::OffsetDateTime::now_utc());
-        usage_active.update(&txn).await?;
+        usage_active.updated_at = Set(now);
+        usage_active.update(txn).await?;
 
-        txn.commit().await?;
+        // 6. Hold what was taken from where, so a release can reverse exactly this charge
+        let (status, expires_at, committed_at) = if settled {
+            ("committed", None, Some(now))
+        } else {
+            let ttl = time::Duration::seconds(self.config.hold_ttl_secs as i64);
+            ("held", Some(now + ttl), None)
+        };
+        let reservation = entity::credit_reservations::ActiveModel {
+            id: Set(reservation_id),
+            user_id: Set(user_id),
+            operation: Set(operation.as_str().to_string()),
+            cost: Set(cost),
+            subscription_credits: Set(from_subscription),
+            extra_credits: Set(from_extra),
+            usage_date: Set(today),
+            subscription_resets_at: Set(subscription_resets_at),
+            status: Set(status.to_string()),
+            created_at: Set(now),
+            released_at: Set(None),
+            expires_at: Set(expires_at),
+            committed_at: Set(committed_at),
+            delivered_at: Set(None),
+        };
+        entity::credit_reservations::Entity::insert(reservation)
+            .exec(txn)
+            .await?;
 
-        info!(
-            "Refunded {} credits for {} operation to user: {} (new extra credits: {})",
-            cost,
-            if is_image_op { "image" } else { "text" },
-            user_id,
-            current_extra + cost
-        );
+        Ok((
+            CreditReservation {
+                id: reservation_id,
+                operation,
+                cost,
+                subscription_credits: from_subscription,
+                extra_credits: from_extra,
+                usage_date: today,
+            },
+            updated_balance.subscription_credits + extra_remaining,
+        ))
+    }
 
-        Ok(())
+    /// Helper: Find and lock a credit hold in any status (within transaction)
+    async fn lock_hold(
+        &self,
+        user_id: Uuid,
+        reservation_id: Uuid,
+        txn: &DatabaseTransaction,
+    ) -> Result<entity::credit_reservations::Model> {
+        entity::credit_reservations::Entity::find_by_id(reservation_id)
+            .filter(entity::credit_reservations::Column::UserId.eq(user_id))
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Credit hold {} not found", reservation_id)))
+    }
+
+    /// Helper: Find and lock a pending credit hold (within transaction)
+    async fn find_and_lock_hold(
+        &self,
+        user_id: Uuid,
+        reservation_id: Uuid,
+        txn: &DatabaseTransaction,
+    ) -> Result<entity::credit_reservations::Model> {
+        let hold = self.lock_hold(user_id, reservation_id, txn).await?;
+
+        if hold.status != "held" {
+            return Err(ApiError::BadRequest(format!(
+                "Credit hold {} is already {}",
+                reservation_id, hold.status
+            )));
+        }
+
+        Ok(hold)
+    }
+
+    /// Helper: Rebuild the reservation handle from its stored hold
+    fn reservation_from_hold(
+        hold: &entity::credit_reservations::Model,
+    ) -> Result<CreditReservation> {
+        let operation = AIOperation::from_name(&hold.operation).ok_or_else(|| {
+            ApiError::Internal(anyhow::anyhow!(
+                "Unknown operation {} on credit hold {}",
+                hold.operation,
+                hold.id
+            ))
+        })?;
+
+        Ok(CreditReservation {
+            id: hold.id,
+            operation,
+            cost: hold.cost,
+            subscription_credits: hold.subscription_credits,
+            extra_credits: hold.extra_credits,
+            usage_date: hold.usage_date,
+        })
     }
 
     /// Helper: Find and lock credit balance for update (within transaction)
@@ -214,7 +615,7 @@ impl QuotaService {
         // If not found, insert (no-op if another transaction races) then re-lock
         let now = time::OffsetDateTime::now_utc();
         let next_month = now + time::Duration::days(30);
-        let monthly_allocation = self.get_monthly_allocation(tier);
+        let monthly_allocation = self.get_monthly_allocation(user_id, tier, txn).await?;
 
         let new_balance = entity::user_credit_balance::ActiveModel {
             id: Set(Uuid::new_v4()),
@@ -261,11 +662,14 @@ impl QuotaService {
         if let Some(resets_at) = balance.subscription_resets_at {
             if now >= resets_at {
                 // Reset subscription credits
-                let monthly_allocation = self.get_monthly_allocation(tier);
+                let monthly_allocation = self
+                    .get_monthly_allocation(balance.user_id, tier, txn)
+                    .await?;
                 let next_reset = now + time::Duration::days(30);
 
                 let mut balance_active: entity::user_credit_balance::ActiveModel = balance.into();
                 balance_active.subscription_credits = Set(monthly_allocation);
+                balance_active.subscription_monthly_allocation = Set(monthly_allocation);
                 balance_active.subscription_resets_at = Set(Some(next_reset));
                 balance_active.last_updated = Set(now);
 
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+use crate::services::{
+    share_preview::{CARD_HEIGHT, CARD_WIDTH},
+    share_service::{SharePage, SharePageNode},
+};
+
+const STYLE: &str = "\
+body{margin:0;background:#faf8f4;color:#222;font:18px/1.6 Georgia,'Times New Roman',serif}\
+main{max-width:680px;margin:0 auto;padding:32px 20px 64px}\
+h1{font-size:2em;line-height:1.2;margin:0 0 8px}\
+h2{font-size:1.25em;margin:40px 0 12px}\
+.summary{color:#555;font-style:italic}\
+figure{margin:24px 0}\
+img{display:block;max-width:100%;height:auto;border-radius:8px}\
+figcaption{color:#666;font-size:.85em;margin-top:6px}\
+footer{margin-top:48px;color:#888;font:14px/1.4 -apple-system,sans-serif;text-align:center}";
+
+/// Render a share as a standalone, read-only HTML page
+pub fn render(page: &SharePage) -> String {
+    let mut body = String::new();
+    let _ = write!(body, "<header><h1>{}</h1>", escape(&page.title));
+    if let Some(summary) = &page.summary {
+        let _ = write!(body, "<p class=\"summary\">{}</p>", escape(summary));
+    }
+    body.push_str("</header><article>");
+    for node in &page.nodes {
+        render_node(&mut body, node);
+    }
+    let _ = write!(
+        body,
+        "</article><footer>Shared from Talevonia · {} {}</footer>",
+        page.view_count,
+        if page.view_count == 1 {
+            "view"
+        } else {
+            "views"
+        }
+    );
+
+    document(&page.title, &meta_tags(page), &body)
+}
+
+/// Page for unknown, deleted and expired shares
+pub fn render_not_found() -> String {
+    document(
+        "Story not found",
+        "",
+        "<header><h1>Story not found</h1></header>\
+         <p>This story is no longer shared. The link may have expired or been removed by its author.</p>",
+    )
+}
+
+/// Open Graph and Twitter Card tags, plus oEmbed discovery, for link unfurling
+fn meta_tags(page: &SharePage) -> String {
+    let title = escape(&page.title);
+    let description = escape(&page.excerpt);
+    let url = escape(&page.url);
+    let image = escape(&page.preview_image_url);
+
+    let mut head = String::new();
+    let _ = write!(
+        head,
+        "<link rel=\"canonical\" href=\"{url}\">\
+         <meta name=\"description\" content=\"{description}\">\
+         <meta property=\"og:type\" content=\"article\">\
+         <meta property=\"og:site_name\" content=\"Talevonia\">\
+         <meta property=\"og:title\" content=\"{title}\">\
+         <meta property=\"og:description\" content=\"{description}\">\
+         <meta property=\"og:url\" content=\"{url}\">\
+         <meta property=\"og:image\" content=\"{image}\">\
+         <meta property=\"og:image:type\" content=\"image/jpeg\">\
+         <meta property=\"og:image:width\" content=\"{CARD_WIDTH}\">\
+         <meta property=\"og:image:height\" content=\"{CARD_HEIGHT}\">\
+         <meta property=\"og:image:alt\" content=\"{title}\">\
+         <meta name=\"twitter:card\" content=\"summary_large_image\">\
+         <meta name=\"twitter:title\" content=\"{title}\">\
+         <meta name=\"twitter:description\" content=\"{description}\">\
+         <meta name=\"twitter:image\" content=\"{image}\">\
+         <link rel=\"alternate\" type=\"application/json+oembed\" href=\"{}\" title=\"{title}\">",
+        escape(&page.oembed_url)
+    );
+    head
+}
+
+fn render_node(out: &mut String, node: &SharePageNode) {
+    out.push_str("<section>");
+    if let Some(title) = &node.title {
+        let _ = write!(out, "<h2>{}</h2>", escape(title));
+    }
+    for image in &node.images {
+        let alt = image.alt.as_deref().unwrap_or("");
+        let _ = write!(
+            out,
+            "<figure><img src=\"{}\" alt=\"{}\" loading=\"lazy\">",
+            escape(&image.url),
+            escape(alt)
+        );
+        if !alt.is_empty() {
+            let _ = write!(out, "<figcaption>{}</figcaption>", escape(alt));
+        }
+        out.push_str("</figure>");
+    }
+    // Blank lines separate paragraphs; single line breaks are kept
+    for paragraph in node
+        .content
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let _ = write!(out, "<p>{}</p>", escape(paragraph).replace('\n', "<br>"));
+    }
+    out.push_str("</section>");
+}
+
+fn document(title: &str, head: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <meta name=\"robots\" content=\"noindex\">\
+         <title>{} · Talevonia</title>{}<style>{}</style></head>\
+         <body><main>{}</main></body></html>\n",
+        escape(title),
+        head,
+        STYLE,
+        body
+    )
+}
+
+/// Escape text for use in HTML content and double-quoted attributes
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
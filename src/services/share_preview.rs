@@ -0,0 +1,268 @@
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont};
+use image::{
+    codecs::jpeg::JpegEncoder,
+    imageops::{self, FilterType},
+    Rgba, RgbaImage,
+};
+use tracing::warn;
+
+use crate::{
+    config::SharesConfig,
+    error::{ApiError, Result},
+};
+
+/// Open Graph's recommended 1.91:1 size
+pub const CARD_WIDTH: u32 = 1200;
+pub const CARD_HEIGHT: u32 = 630;
+pub const CARD_MIME_TYPE: &str = "image/jpeg";
+
+const MARGIN: f32 = 64.0;
+const BACKGROUND: Rgba<u8> = Rgba([250, 248, 244, 255]);
+const TITLE_COLOR: Rgba<u8> = Rgba([34, 34, 34, 255]);
+const EXCERPT_COLOR: Rgba<u8> = Rgba([85, 85, 85, 255]);
+const BRAND_COLOR: Rgba<u8> = Rgba([136, 136, 136, 255]);
+
+/// Draws the social preview card of a share: its title and excerpt beside the cover illustration
+#[derive(Clone)]
+pub struct PreviewRenderer {
+    font: Option<FontArc>,
+    max_pixels: u64,
+}
+
+impl PreviewRenderer {
+    pub fn new(config: &SharesConfig) -> anyhow::Result<Self> {
+        let font = match &config.preview_font_path {
+            Some(path) => {
+                let bytes = std::fs::read(path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read preview font {}: {}", path, e))?;
+                let font = FontArc::try_from_vec(bytes)
+                    .map_err(|e| anyhow::anyhow!("Failed to load preview font {}: {}", path, e))?;
+                Some(font)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            font,
+            max_pixels: config.max_pixels,
+        })
+    }
+
+    /// Encode a card as JPEG; a cover that cannot be decoded is left out
+    ///
+    /// CPU bound; call from a blocking task.
+    pub fn render(&self, title: &str, excerpt: &str, cover: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut canvas = RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, BACKGROUND);
+
+        let cover = cover.and_then(|bytes| match self.decode_cover(bytes) {
+            Ok(cover) => Some(cover),
+            Err(err) => {
+                warn!("Skipping undecodable cover on preview card: {}", err);
+                None
+            }
+        });
+        let text_right = match (&cover, &self.font) {
+            (Some(cover), Some(_)) => {
+                // Square cover on the right, text on the left
+                let x = CARD_WIDTH - CARD_HEIGHT;
+                let fitted = cover.resize_to_fill(CARD_HEIGHT, CARD_HEIGHT, FilterType::Lanczos3);
+                imageops::overlay(&mut canvas, &fitted.to_rgba8(), x as i64, 0);
+                x as f32 - MARGIN
+            }
+            (Some(cover), None) => {
+                let fitted = cover.resize_to_fill(CARD_WIDTH, CARD_HEIGHT, FilterType::Lanczos3);
+                imageops::overlay(&mut canvas, &fitted.to_rgba8(), 0, 0);
+                CARD_WIDTH as f32 - MARGIN
+            }
+            (None, _) => CARD_WIDTH as f32 - MARGIN,
+        };
+
+        if let Some(font) = &self.font {
+            let max_width = text_right - MARGIN;
+            let title_scale = PxScale::from(if cover.is_some() { 56.0 } else { 68.0 });
+            let excerpt_scale = PxScale::from(30.0);
+
+            let mut baseline = MARGIN + font.as_scaled(title_scale).ascent();
+            for line in wrap(font, title_scale, title, max_width, 3) {
+                draw_text(
+                    &mut canvas,
+                    font,
+                    title_scale,
+                    MARGIN,
+                    baseline,
+                    &line,
+                    TITLE_COLOR,
+                );
+                baseline += font.as_scaled(title_scale).height() * 1.1;
+            }
+
+            baseline += 16.0;
+            for line in wrap(font, excerpt_scale, excerpt, max_width, 5) {
+                let scaled = font.as_scaled(excerpt_scale);
+                if baseline + scaled.height() > CARD_HEIGHT as f32 - MARGIN - 48.0 {
+                    break;
+                }
+                draw_text(
+                    &mut canvas,
+                    font,
+                    excerpt_scale,
+                    MARGIN,
+                    baseline,
+                    &line,
+                    EXCERPT_COLOR,
+                );
+                baseline += scaled.height() * 1.35;
+            }
+
+            let brand_scale = PxScale::from(28.0);
+            draw_text(
+                &mut canvas,
+                font,
+                brand_scale,
+                MARGIN,
+                CARD_HEIGHT as f32 - MARGIN,
+                "Talevonia",
+                BRAND_COLOR,
+            );
+        }
+
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, 85)
+            .encode_image(&image::DynamicImage::ImageRgba8(canvas).to_rgb8())
+            .map_err(|e| {
+                ApiError::Internal(anyhow::anyhow!("Failed to encode preview card: {}", e))
+            })?;
+        Ok(bytes)
+    }
+
+    /// Decode a cover, refusing bitmaps larger than max_pixels before allocating them
+    fn decode_cover(&self, bytes: &[u8]) -> image::ImageResult<image::DynamicImage> {
+        let mut limits = image::Limits::default();
+        // Room for up to 16-bit RGBA per pixel
+        limits.max_alloc = Some(self.max_pixels.saturating_mul(8));
+
+        let mut reader =
+            image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()?;
+        reader.limits(limits);
+        reader.decode()
+    }
+}
+
+/// Greedy word wrap; the last line ends in an ellipsis when text is left over
+fn wrap(
+    font: &FontArc,
+    scale: PxScale,
+    text: &str,
+    max_width: f32,
+    max_lines: usize,
+) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if text_width(font, scale, &candidate) <= max_width {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        if lines.len() == max_lines {
+            return ellipsize(font, scale, lines, max_width);
+        }
+        // A single word wider than the line is broken between characters
+        for c in word.chars() {
+            current.push(c);
+            if text_width(font, scale, &current) > max_width && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+                if lines.len() == max_lines {
+                    return ellipsize(font, scale, lines, max_width);
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn ellipsize(
+    font: &FontArc,
+    scale: PxScale,
+    mut lines: Vec<String>,
+    max_width: f32,
+) -> Vec<String> {
+    if let Some(last) = lines.last_mut() {
+        while !last.is_empty() && text_width(font, scale, &format!("{}…", last)) > max_width {
+            last.pop();
+        }
+        *last = format!("{}…", last.trim_end());
+    }
+    lines
+}
+
+fn text_width(font: &FontArc, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut previous: Option<GlyphId> = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Blend one line of text onto the canvas, starting at `x` on the given baseline
+fn draw_text(
+    canvas: &mut RgbaImage,
+    font: &FontArc,
+    scale: PxScale,
+    x: f32,
+    baseline: f32,
+    text: &str,
+    color: Rgba<u8>,
+) {
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    let mut previous: Option<GlyphId> = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, baseline));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px < 0 || py < 0 || px >= canvas.width() as i64 || py >= canvas.height() as i64 {
+                return;
+            }
+            let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+            let alpha = coverage.clamp(0.0, 1.0);
+            for channel in 0..3 {
+                pixel.0[channel] = (pixel.0[channel] as f32 * (1.0 - alpha)
+                    + color.0[channel] as f32 * alpha)
+                    .round() as u8;
+            }
+        });
+    }
+}
@@ -0,0 +1,651 @@
+use std::{sync::Arc, time::Duration};
+
+use entity::{sea_orm_active_enums::AccountTier, shares, users};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{error, instrument, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::SharesConfig,
+    error::{ApiError, Result},
+    models::share::{
+        CreateShareRequest, OEmbedQuery, OEmbedResponse, ShareListResponse, ShareMediaInput,
+        ShareResponse,
+    },
+    services::{
+        image_processing,
+        object_store::{self, ObjectStore},
+        share_page,
+        share_preview::{self, PreviewRenderer},
+        ImageService,
+    },
+};
+
+const SLUG_LENGTH: usize = 10;
+const EXCERPT_CHARS: usize = 200;
+// Size of the rich embed offered through oEmbed, unless the consumer asks for less
+const EMBED_WIDTH: u32 = 600;
+const EMBED_HEIGHT: u32 = 800;
+
+/// Web reader links: path snapshots published at /s/{slug}
+pub struct ShareService {
+    db: DatabaseConnection,
+    object_store: Arc<dyn ObjectStore>,
+    image_service: Arc<ImageService>,
+    config: SharesConfig,
+    signed_url_ttl: Duration,
+    preview: PreviewRenderer,
+}
+
+/// Stored form of a share's content (shares.snapshot)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareSnapshot {
+    pub summary: Option<String>,
+    pub nodes: Vec<SharedNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedNode {
+    pub title: Option<String>,
+    pub content: String,
+    pub media: Vec<SharedMedia>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "source",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum SharedMedia {
+    // Hot-linked from wherever the client found it
+    External {
+        url: String,
+        alt: Option<String>,
+    },
+    // One of the owner's generated images; it outlives the share
+    Image {
+        image_id: Uuid,
+        storage_key: String,
+        alt: Option<String>,
+    },
+    // Embedded in the request and stored for this share; deleted with it
+    Upload {
+        storage_key: String,
+        alt: Option<String>,
+    },
+}
+
+/// A share as shown to readers, with media resolved to fetchable URLs
+#[derive(Debug, Clone)]
+pub struct SharePage {
+    pub title: String,
+    pub summary: Option<String>,
+    pub nodes: Vec<SharePageNode>,
+    pub view_count: i64,
+    // Unfurling metadata
+    pub url: String,
+    pub excerpt: String,
+    pub preview_image_url: String,
+    pub oembed_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SharePageNode {
+    pub title: Option<String>,
+    pub content: String,
+    pub images: Vec<SharePageImage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SharePageImage {
+    pub url: String,
+    pub alt: Option<String>,
+}
+
+impl ShareService {
+    pub fn new(
+        db: DatabaseConnection,
+        object_store: Arc<dyn ObjectStore>,
+        image_service: Arc<ImageService>,
+        config: SharesConfig,
+        signed_url_ttl: Duration,
+        preview: PreviewRenderer,
+    ) -> Self {
+        Self {
+            db,
+            object_store,
+            image_service,
+            config,
+            signed_url_ttl,
+            preview,
+        }
+    }
+
+    /// Public address of a share
+    pub fn share_url(&self, slug: &str) -> String {
+        format!(
+            "{}/s/{}",
+            self.config.public_base_url.trim_end_matches('/'),
+            slug
+        )
+    }
+
+    /// Public address of a share's preview card
+    pub fn preview_image_url(&self, slug: &str) -> String {
+        format!("{}/preview.jpg", self.share_url(slug))
+    }
+
+    /// oEmbed lookup for a share, as advertised by its page
+    pub fn oembed_url(&self, slug: &str) -> String {
+        let endpoint = format!(
+            "{}/oembed",
+            self.config.public_base_url.trim_end_matches('/')
+        );
+        reqwest::Url::parse_with_params(
+            &endpoint,
+            &[("url", self.share_url(slug).as_str()), ("format", "json")],
+        )
+        .map(String::from)
+        .unwrap_or(endpoint)
+    }
+
+    fn active_limit(&self, tier: &AccountTier) -> u32 {
+        match tier {
+            AccountTier::Pro => self.config.pro_active_limit,
+            AccountTier::Free => self.config.free_active_limit,
+        }
+    }
+
+    fn active_limit_error(&self, tier: &AccountTier) -> ApiError {
+        let limit = self.active_limit(tier);
+        match tier {
+            AccountTier::Free => ApiError::ProRequired(format!(
+                "Free accounts can have {} active shares; upgrade to Pro for more",
+                limit
+            )),
+            AccountTier::Pro => ApiError::QuotaExceeded(format!(
+                "You already have {} active shares; delete one to share another",
+                limit
+            )),
+        }
+    }
+
+    /// Publish a snapshot, within the tier's limit on unexpired shares
+    #[instrument(skip(self, request))]
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        tier: &AccountTier,
+        request: CreateShareRequest,
+    ) -> Result<ShareResponse> {
+        if request.nodes.len() > self.config.max_nodes {
+            return Err(ApiError::BadRequest(format!(
+                "A share can hold at most {} nodes",
+                self.config.max_nodes
+            )));
+        }
+
+        // Fail fast before uploading media; `insert_within_limit` enforces the limit
+        if self.count_active(user_id, &self.db).await? >= self.active_limit(tier) as u64 {
+            return Err(self.active_limit_error(tier));
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let ttl_days = match tier {
+            AccountTier::Free => Some(
+                request
+                    .expires_in_days
+                    .unwrap_or(self.config.free_max_ttl_days)
+                    .min(self.config.free_max_ttl_days),
+            ),
+            AccountTier::Pro => request.expires_in_days,
+        };
+        let expires_at = ttl_days.map(|days| now + time::Duration::days(days as i64));
+
+        let share_id = Uuid::new_v4();
+        let mut snapshot = ShareSnapshot {
+            summary: request.summary,
+            nodes: Vec::with_capacity(request.nodes.len()),
+        };
+        for (node_index, node) in request.nodes.into_iter().enumerate() {
+            snapshot.nodes.push(SharedNode {
+                title: node.title,
+                content: node.content,
+                media: Vec::with_capacity(node.media.len()),
+            });
+            for (media_index, input) in node.media.into_iter().enumerate() {
+                let key_stem = format!("shares/{}/{}-{}", share_id, node_index, media_index);
+                match self.resolve_media(user_id, input, &key_stem).await {
+                    Ok(media) => snapshot.nodes[node_index].media.push(media),
+                    Err(err) => {
+                        self.discard_uploads(&snapshot).await;
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        let share = shares::ActiveModel {
+            id: Set(share_id),
+            user_id: Set(user_id),
+            slug: Set(new_slug()),
+            title: Set(request.title),
+            snapshot: Set(serde_json::to_value(&snapshot).expect("snapshot serializes to JSON")),
+            view_count: Set(0),
+            expires_at: Set(expires_at),
+            created_at: Set(now),
+        };
+        let share = match self.insert_within_limit(user_id, tier, share).await {
+            Ok(share) => share,
+            Err(err) => {
+                self.discard_uploads(&snapshot).await;
+                return Err(err);
+            }
+        };
+
+        Ok(self.to_response(share, snapshot.nodes.len()))
+    }
+
+    /// The user's shares, newest first
+    #[instrument(skip(self))]
+    pub async fn list(&self, user_id: Uuid, tier: &AccountTier) -> Result<ShareListResponse> {
+        let shares = shares::Entity::find()
+            .filter(shares::Column::UserId.eq(user_id))
+            .order_by_desc(shares::Column::CreatedAt)
+            .all(&self.db)
+            .await?;
+
+        Ok(ShareListResponse {
+            shares: shares
+                .into_iter()
+                .map(|share| {
+                    let node_count = snapshot_of(&share).nodes.len();
+                    self.to_response(share, node_count)
+                })
+                .collect(),
+            active_count: self.count_active(user_id, &self.db).await?,
+            active_limit: self.active_limit(tier),
+        })
+    }
+
+    /// Unpublish a share and remove the media stored for it
+    #[instrument(skip(self))]
+    pub async fn delete(&self, user_id: Uuid, share_id: Uuid) -> Result<()> {
+        let share = shares::Entity::find_by_id(share_id)
+            .filter(shares::Column::UserId.eq(user_id))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Share not found".to_string()))?;
+
+        shares::Entity::delete_by_id(share.id)
+            .exec(&self.db)
+            .await?;
+        self.discard_uploads(&snapshot_of(&share)).await;
+        let preview_key = preview_key(share.id);
+        if let Err(err) = self.object_store.delete(&preview_key).await {
+            error!("Failed to delete preview card {}: {}", preview_key, err);
+        }
+
+        Ok(())
+    }
+
+    /// Count a view of an unexpired share and return it for rendering
+    #[instrument(skip(self))]
+    pub async fn view(&self, slug: &str) -> Result<Option<SharePage>> {
+        let share = shares::Entity::update_many()
+            .col_expr(
+                shares::Column::ViewCount,
+                Expr::col(shares::Column::ViewCount).add(1),
+            )
+            .filter(shares::Column::Slug.eq(slug))
+            .filter(unexpired(OffsetDateTime::now_utc()))
+            .exec_with_returning(&self.db)
+            .await?
+            .pop();
+        let Some(share) = share else {
+            return Ok(None);
+        };
+
+        let snapshot = snapshot_of(&share);
+        let excerpt = excerpt(&snapshot);
+        let nodes = snapshot
+            .nodes
+            .into_iter()
+            .map(|node| SharePageNode {
+                title: node.title,
+                content: node.content,
+                images: node
+                    .media
+                    .into_iter()
+                    .map(|media| self.page_image(media))
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Some(SharePage {
+            url: self.share_url(&share.slug),
+            excerpt,
+            preview_image_url: self.preview_image_url(&share.slug),
+            oembed_url: self.oembed_url(&share.slug),
+            title: share.title,
+            summary: snapshot.summary,
+            nodes,
+            view_count: share.view_count,
+        }))
+    }
+
+    /// JPEG preview card of an unexpired share, rendered on first request and kept in storage
+    #[instrument(skip(self))]
+    pub async fn preview_card(&self, slug: &str) -> Result<Option<Vec<u8>>> {
+        let Some(share) = self.find_active(slug).await? else {
+            return Ok(None);
+        };
+
+        // Snapshots never change, so neither does their card
+        let key = preview_key(share.id);
+        match self.object_store.get(&key).await {
+            Ok(bytes) => return Ok(Some(bytes)),
+            Err(ApiError::NotFound(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let snapshot = snapshot_of(&share);
+        let cover = match cover_key(&snapshot) {
+            Some(cover_key) => match self.object_store.get(cover_key).await {
+                Ok(bytes) => Some(bytes),
+                Err(err) => {
+                    warn!(
+                        "Rendering preview card without cover {}: {}",
+                        cover_key, err
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let renderer = self.preview.clone();
+        let (title, excerpt) = (share.title, excerpt(&snapshot));
+        let bytes = tokio::task::spawn_blocking(move || {
+            renderer.render(&title, &excerpt, cover.as_deref())
+        })
+        .await
+        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Preview rendering panicked: {}", e)))??;
+
+        // A failed upload only costs a re-render next time
+        if let Err(err) = self
+            .object_store
+            .put(&key, bytes.clone(), share_preview::CARD_MIME_TYPE)
+            .await
+        {
+            error!("Failed to store preview card {}: {}", key, err);
+        }
+
+        Ok(Some(bytes))
+    }
+
+    /// oEmbed description of the share a URL points at; None for other URLs and unknown shares
+    #[instrument(skip(self))]
+    pub async fn oembed(&self, query: &OEmbedQuery) -> Result<Option<OEmbedResponse>> {
+        let prefix = self.share_url("");
+        let Some(slug) = query
+            .url
+            .strip_prefix(&prefix)
+            .map(|rest| rest.split(['?', '#', '/']).next().unwrap_or_default())
+        else {
+            return Ok(None);
+        };
+        let Some(share) = self.find_active(slug).await? else {
+            return Ok(None);
+        };
+
+        let width = query
+            .maxwidth
+            .map_or(EMBED_WIDTH, |max| max.min(EMBED_WIDTH));
+        let height = query
+            .maxheight
+            .map_or(EMBED_HEIGHT, |max| max.min(EMBED_HEIGHT));
+        // The thumbnail must respect the consumer's size limits too
+        let thumbnail_fits = query
+            .maxwidth
+            .is_none_or(|max| max >= share_preview::CARD_WIDTH)
+            && query
+                .maxheight
+                .is_none_or(|max| max >= share_preview::CARD_HEIGHT);
+        let url = self.share_url(&share.slug);
+
+        Ok(Some(OEmbedResponse {
+            kind: "rich",
+            version: "1.0",
+            html: format!(
+                "<iframe src=\"{}\" width=\"{}\" height=\"{}\" title=\"{}\" \
+                 style=\"border:0\" loading=\"lazy\"></iframe>",
+                share_page::escape(&url),
+                width,
+                height,
+                share_page::escape(&share.title)
+            ),
+            width,
+            height,
+            provider_name: "Talevonia",
+            provider_url: self
+                .config
+                .public_base_url
+                .trim_end_matches('/')
+                .to_string(),
+            thumbnail_url: thumbnail_fits.then(|| self.preview_image_url(&share.slug)),
+            thumbnail_width: thumbnail_fits.then_some(share_preview::CARD_WIDTH),
+            thumbnail_height: thumbnail_fits.then_some(share_preview::CARD_HEIGHT),
+            cache_age: 3600,
+            title: share.title,
+        }))
+    }
+
+    /// An unexpired share, without counting a view
+    async fn find_active(&self, slug: &str) -> Result<Option<shares::Model>> {
+        Ok(shares::Entity::find()
+            .filter(shares::Column::Slug.eq(slug))
+            .filter(unexpired(OffsetDateTime::now_utc()))
+            .one(&self.db)
+            .await?)
+    }
+
+    async fn count_active(&self, user_id: Uuid, db: &impl ConnectionTrait) -> Result<u64> {
+        Ok(shares::Entity::find()
+            .filter(shares::Column::UserId.eq(user_id))
+            .filter(unexpired(OffsetDateTime::now_utc()))
+            .count(db)
+            .await?)
+    }
+
+    /// Insert a share unless the user reached their active-share limit meanwhile
+    ///
+    /// Locks the user's row, so concurrent creates are counted one after another.
+    async fn insert_within_limit(
+        &self,
+        user_id: Uuid,
+        tier: &AccountTier,
+        share: shares::ActiveModel,
+    ) -> Result<shares::Model> {
+        let txn = self.db.begin().await?;
+
+        users::Entity::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        if self.count_active(user_id, &txn).await? >= self.active_limit(tier) as u64 {
+            return Err(self.active_limit_error(tier));
+        }
+        let share = share.insert(&txn).await?;
+
+        txn.commit().await?;
+        Ok(share)
+    }
+
+    async fn resolve_media(
+        &self,
+        user_id: Uuid,
+        input: ShareMediaInput,
+        key_stem: &str,
+    ) -> Result<SharedMedia> {
+        let alt = input.alt;
+        if let Some(image_id) = input.image_id {
+            let record = self.image_service.find(user_id, image_id).await?;
+            let storage_key = ImageService::display_key(&record).ok_or_else(|| {
+                ApiError::BadRequest(format!("Image {} has no stored output", image_id))
+            })?;
+            return Ok(SharedMedia::Image {
+                image_id,
+                storage_key,
+                alt,
+            });
+        }
+
+        if let Some(url) = input.url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err(ApiError::BadRequest(
+                    "Media URLs must use http or https".to_string(),
+                ));
+            }
+            return Ok(SharedMedia::External { url, alt });
+        }
+
+        let (bytes, info) = image_processing::decode_embedded(
+            input.data.as_deref().unwrap_or_default(),
+            self.config.max_media_bytes,
+        )?;
+        // A small, highly compressed file can still decode to an enormous bitmap
+        if u64::from(info.width) * u64::from(info.height) > self.config.max_pixels {
+            return Err(ApiError::BadRequest(format!(
+                "Embedded media must not exceed {} pixels",
+                self.config.max_pixels
+            )));
+        }
+
+        let storage_key = format!(
+            "{}.{}",
+            key_stem,
+            object_store::extension_for_content_type(&info.mime_type)
+        );
+        self.object_store
+            .put(&storage_key, bytes, &info.mime_type)
+            .await?;
+        Ok(SharedMedia::Upload { storage_key, alt })
+    }
+
+    fn page_image(&self, media: SharedMedia) -> SharePageImage {
+        match media {
+            SharedMedia::External { url, alt } => SharePageImage { url, alt },
+            SharedMedia::Image {
+                storage_key, alt, ..
+            }
+            | SharedMedia::Upload { storage_key, alt } => SharePageImage {
+                url: self
+                    .object_store
+                    .signed_url(&storage_key, self.signed_url_ttl)
+                    .url,
+                alt,
+            },
+        }
+    }
+
+    /// Delete the media uploaded with a share; generated images stay with their owner
+    async fn discard_uploads(&self, snapshot: &ShareSnapshot) {
+        for media in snapshot.nodes.iter().flat_map(|node| &node.media) {
+            if let SharedMedia::Upload { storage_key, .. } = media {
+                if let Err(err) = self.object_store.delete(storage_key).await {
+                    error!("Failed to delete shared media {}: {}", storage_key, err);
+                }
+            }
+        }
+    }
+
+    fn to_response(&self, share: shares::Model, node_count: usize) -> ShareResponse {
+        ShareResponse {
+            id: share.id,
+            share_url: self.share_url(&share.slug),
+            slug: share.slug,
+            title: share.title,
+            node_count,
+            view_count: share.view_count,
+            expires_at: share.expires_at,
+            created_at: share.created_at,
+        }
+    }
+}
+
+fn snapshot_of(share: &shares::Model) -> ShareSnapshot {
+    serde_json::from_value(share.snapshot.clone()).unwrap_or(ShareSnapshot {
+        summary: None,
+        nodes: Vec::new(),
+    })
+}
+
+/// Object key of a share's preview card
+fn preview_key(share_id: Uuid) -> String {
+    format!("shares/{}/preview.jpg", share_id)
+}
+
+/// First stored illustration; hot-linked images are not fetched server-side
+fn cover_key(snapshot: &ShareSnapshot) -> Option<&str> {
+    snapshot
+        .nodes
+        .iter()
+        .flat_map(|node| &node.media)
+        .find_map(|media| match media {
+            SharedMedia::Image { storage_key, .. } | SharedMedia::Upload { storage_key, .. } => {
+                Some(storage_key.as_str())
+            }
+            SharedMedia::External { .. } => None,
+        })
+}
+
+/// Description for link previews: the summary, else the opening of the first non-empty node
+fn excerpt(snapshot: &ShareSnapshot) -> String {
+    let source = snapshot
+        .summary
+        .as_deref()
+        .filter(|summary| !summary.trim().is_empty())
+        .or_else(|| {
+            snapshot
+                .nodes
+                .iter()
+                .map(|node| node.content.as_str())
+                .find(|content| !content.trim().is_empty())
+        })
+        .unwrap_or_default();
+
+    let collapsed = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= EXCERPT_CHARS {
+        return collapsed;
+    }
+    let truncated: String = collapsed.chars().take(EXCERPT_CHARS - 1).collect();
+    // Prefer to end on a word boundary
+    let truncated = match truncated.rfind(' ') {
+        Some(space) if space > EXCERPT_CHARS / 2 => &truncated[..space],
+        _ => truncated.as_str(),
+    };
+    format!("{}…", truncated.trim_end())
+}
+
+fn unexpired(now: OffsetDateTime) -> Condition {
+    Condition::any()
+        .add(shares::Column::ExpiresAt.is_null())
+        .add(shares::Column::ExpiresAt.gt(now))
+}
+
+fn new_slug() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SLUG_LENGTH)
+        .map(char::from)
+        .collect()
+}
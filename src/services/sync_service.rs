@@ -0,0 +1,760 @@
+use entity::{
+    media_assets, node_media, stories, story_node_revisions, story_nodes, user_sync_state,
+};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde::Serialize;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    error::{ApiError, Result},
+    models::sync::{
+        SyncApplied, SyncChanges, SyncConflict, SyncConflictReason, SyncEntity, SyncMediaAsset,
+        SyncMerge, SyncMergeOutcome, SyncNodeMedia, SyncPullQuery, SyncPullResponse,
+        SyncPushResponse, SyncStory, SyncStoryNode,
+    },
+    services::node_merge::{self, NodeFields},
+};
+
+// Node versions kept as merge bases; edits based on anything older are reported as conflicts
+const NODE_REVISION_HISTORY: i64 = 50;
+
+/// Multi-device sync of the story model (stories, story_nodes, node_media, media_assets)
+///
+/// Every accepted change takes the next value of the owner's change counter, so a pull resumes
+/// from the last counter value it saw. Pushes for one user are applied one at a time, which
+/// keeps counter order and commit order the same.
+pub struct SyncService {
+    db: DatabaseConnection,
+}
+
+impl SyncService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Rows changed after the query's cursor, oldest change first
+    #[instrument(skip(self))]
+    pub async fn pull(&self, user_id: Uuid, query: &SyncPullQuery) -> Result<SyncPullResponse> {
+        // Each table's oldest changes; the overall oldest `limit` are among them
+        let fetch = query.limit + 1;
+        let mut changes: Vec<(i64, Change)> = Vec::new();
+        changes.extend(
+            stories::Entity::find()
+                .filter(stories::Column::UserId.eq(user_id))
+                .filter(stories::Column::ChangeSeq.gt(query.cursor))
+                .order_by_asc(stories::Column::ChangeSeq)
+                .limit(fetch)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| (row.change_seq, Change::Story(story_record(row)))),
+        );
+        changes.extend(
+            story_nodes::Entity::find()
+                .filter(story_nodes::Column::UserId.eq(user_id))
+                .filter(story_nodes::Column::ChangeSeq.gt(query.cursor))
+                .order_by_asc(story_nodes::Column::ChangeSeq)
+                .limit(fetch)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| (row.change_seq, Change::StoryNode(node_record(row)))),
+        );
+        changes.extend(
+            node_media::Entity::find()
+                .filter(node_media::Column::UserId.eq(user_id))
+                .filter(node_media::Column::ChangeSeq.gt(query.cursor))
+                .order_by_asc(node_media::Column::ChangeSeq)
+                .limit(fetch)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| (row.change_seq, Change::NodeMedia(media_record(row)))),
+        );
+        changes.extend(
+            media_assets::Entity::find()
+                .filter(media_assets::Column::UserId.eq(user_id))
+                .filter(media_assets::Column::ChangeSeq.gt(query.cursor))
+                .order_by_asc(media_assets::Column::ChangeSeq)
+                .limit(fetch)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| (row.change_seq, Change::MediaAsset(asset_record(row)))),
+        );
+
+        changes.sort_by_key(|(seq, _)| *seq);
+        let has_more = changes.len() as u64 > query.limit;
+        changes.truncate(query.limit as usize);
+        let cursor = changes.last().map_or(query.cursor, |(seq, _)| *seq);
+
+        let mut grouped = SyncChanges::default();
+        for (_, change) in changes {
+            match change {
+                Change::Story(row) => grouped.stories.push(row),
+                Change::StoryNode(row) => grouped.story_nodes.push(row),
+                Change::NodeMedia(row) => grouped.node_media.push(row),
+                Change::MediaAsset(row) => grouped.media_assets.push(row),
+            }
+        }
+
+        Ok(SyncPullResponse {
+            changes: grouped,
+            cursor,
+            has_more,
+        })
+    }
+
+    /// Apply the rows whose base version matches the server; report the others as conflicts
+    ///
+    /// Story node edits based on an outdated version are merged with the server's instead.
+    /// Tables are applied parents first (stories, nodes by depth, media, assets), so a push may
+    /// carry a new story together with its nodes and media.
+    #[instrument(skip(self, changes))]
+    pub async fn push(&self, user_id: Uuid, changes: SyncChanges) -> Result<SyncPushResponse> {
+        if changes.is_empty() {
+            return Ok(SyncPushResponse::default());
+        }
+
+        let txn = self.db.begin().await?;
+
+        // Lock the user's counter for the rest of the transaction
+        user_sync_state::Entity::insert(user_sync_state::ActiveModel {
+            user_id: Set(user_id),
+            last_change_seq: Set(0),
+        })
+        .on_conflict(
+            OnConflict::column(user_sync_state::Column::UserId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+        let state = user_sync_state::Entity::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                ApiError::Internal(anyhow::anyhow!("Sync state missing for user {}", user_id))
+            })?;
+
+        let mut push = Push {
+            txn: &txn,
+            user_id,
+            last_change_seq: state.last_change_seq,
+            now: OffsetDateTime::now_utc(),
+            response: SyncPushResponse::default(),
+        };
+        for story in changes.stories {
+            push.story(story).await?;
+        }
+        let mut nodes = changes.story_nodes;
+        nodes.sort_by_key(|node| node.depth);
+        for node in nodes {
+            push.story_node(node).await?;
+        }
+        for media in changes.node_media {
+            push.node_media(media).await?;
+        }
+        for asset in changes.media_assets {
+            push.media_asset(asset).await?;
+        }
+
+        let last_change_seq = push.last_change_seq;
+        let response = push.response;
+        if last_change_seq != state.last_change_seq {
+            user_sync_state::ActiveModel {
+                user_id: Set(user_id),
+                last_change_seq: Set(last_change_seq),
+            }
+            .update(&txn)
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(response)
+    }
+}
+
+enum Change {
+    Story(SyncStory),
+    StoryNode(SyncStoryNode),
+    NodeMedia(SyncNodeMedia),
+    MediaAsset(SyncMediaAsset),
+}
+
+/// One push being applied inside its transaction
+struct Push<'a> {
+    txn: &'a DatabaseTransaction,
+    user_id: Uuid,
+    last_change_seq: i64,
+    now: OffsetDateTime,
+    response: SyncPushResponse,
+}
+
+impl Push<'_> {
+    async fn story(&mut self, change: SyncStory) -> Result<()> {
+        let stored = stories::Entity::find_by_id(change.id).one(self.txn).await?;
+        let version = match self.next_version(
+            stored.as_ref().map(|row| (row.user_id, row.version)),
+            change.version,
+        ) {
+            Ok(version) => version,
+            Err(reason) => {
+                self.conflict(
+                    SyncEntity::Story,
+                    change.id,
+                    reason,
+                    stored.map(story_record),
+                );
+                return Ok(());
+            }
+        };
+
+        let row = stories::ActiveModel {
+            id: Set(change.id),
+            user_id: Set(self.user_id),
+            title: Set(change.title),
+            description: Set(change.description),
+            cover_media_id: Set(change.cover_media_id),
+            version: Set(version),
+            change_seq: Set(self.next_change_seq()),
+            deleted_at: Set(self.tombstone(
+                change.deleted,
+                stored.as_ref().and_then(|row| row.deleted_at),
+            )),
+            created_at: Set(change.created_at),
+            updated_at: Set(change.updated_at),
+        };
+        if stored.is_some() {
+            row.update(self.txn).await?;
+        } else {
+            row.insert(self.txn).await?;
+        }
+
+        self.applied(SyncEntity::Story, change.id, version);
+        Ok(())
+    }
+
+    async fn story_node(&mut self, change: SyncStoryNode) -> Result<()> {
+        let stored = story_nodes::Entity::find_by_id(change.id)
+            .one(self.txn)
+            .await?;
+        let version = match self.next_version(
+            stored.as_ref().map(|row| (row.user_id, row.version)),
+            change.version,
+        ) {
+            Ok(version) => version,
+            Err(reason) => match stored {
+                Some(stored)
+                    if reason == SyncConflictReason::VersionMismatch
+                        && mergeable(&stored, &change) =>
+                {
+                    return self.merge_story_node(stored, change).await;
+                }
+                stored => {
+                    self.conflict(
+                        SyncEntity::StoryNode,
+                        change.id,
+                        reason,
+                        stored.map(node_record),
+                    );
+                    return Ok(());
+                }
+            },
+        };
+
+        if !self
+            .has_parent(change.parent_node_id, change.id, change.story_id)
+            .await?
+            || !self.has_story(change.story_id).await?
+        {
+            self.missing_reference(SyncEntity::StoryNode, change.id);
+            return Ok(());
+        }
+
+        let row = story_nodes::ActiveModel {
+            id: Set(change.id),
+            user_id: Set(self.user_id),
+            story_id: Set(change.story_id),
+            parent_node_id: Set(change.parent_node_id),
+            sibling_order: Set(change.sibling_order),
+            depth: Set(change.depth),
+            summary: Set(change.summary),
+            content: Set(change.content),
+            tags: Set(Some(tags_json(&change.tags))),
+            version: Set(version),
+            change_seq: Set(self.next_change_seq()),
+            deleted_at: Set(self.tombstone(
+                change.deleted,
+                stored.as_ref().and_then(|row| row.deleted_at),
+            )),
+            created_at: Set(change.created_at),
+            updated_at: Set(change.updated_at),
+        };
+        let node = if stored.is_some() {
+            row.update(self.txn).await?
+        } else {
+            row.insert(self.txn).await?
+        };
+        self.record_revision(&node).await?;
+
+        self.applied(SyncEntity::StoryNode, change.id, version);
+        Ok(())
+    }
+
+    /// Combine a node edit based on an older version with the server's edits since then
+    ///
+    /// When the edits overlap, the server's node is left as is and the pushed edit becomes a new
+    /// sibling branch, so neither is lost.
+    async fn merge_story_node(
+        &mut self,
+        stored: story_nodes::Model,
+        change: SyncStoryNode,
+    ) -> Result<()> {
+        // The base may have dropped out of the kept history
+        let Some(base) = story_node_revisions::Entity::find_by_id((stored.id, change.version))
+            .one(self.txn)
+            .await?
+            .filter(|base| !base.deleted)
+        else {
+            self.conflict(
+                SyncEntity::StoryNode,
+                change.id,
+                SyncConflictReason::VersionMismatch,
+                Some(node_record(stored)),
+            );
+            return Ok(());
+        };
+
+        let local = NodeFields {
+            parent_node_id: change.parent_node_id,
+            sibling_order: change.sibling_order,
+            depth: change.depth,
+            summary: change.summary.clone(),
+            content: change.content.clone(),
+            tags: change.tags.clone(),
+        };
+        let Some(fields) = node_merge::merge(&revision_fields(base), &local, &node_fields(&stored))
+        else {
+            let branch = story_nodes::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(self.user_id),
+                story_id: Set(stored.story_id),
+                parent_node_id: Set(stored.parent_node_id),
+                sibling_order: Set(stored.sibling_order + 1),
+                depth: Set(stored.depth),
+                summary: Set(change.summary),
+                content: Set(change.content),
+                tags: Set(Some(tags_json(&change.tags))),
+                version: Set(1),
+                change_seq: Set(self.next_change_seq()),
+                deleted_at: Set(None),
+                created_at: Set(self.now),
+                updated_at: Set(change.updated_at),
+            }
+            .insert(self.txn)
+            .await?;
+            self.record_revision(&branch).await?;
+
+            self.merged(
+                change.id,
+                SyncMergeOutcome::Branched,
+                node_record(stored),
+                Some(node_record(branch)),
+            );
+            return Ok(());
+        };
+
+        if !self
+            .has_parent(fields.parent_node_id, stored.id, stored.story_id)
+            .await?
+        {
+            self.missing_reference(SyncEntity::StoryNode, change.id);
+            return Ok(());
+        }
+
+        let version = stored.version + 1;
+        let updated_at = stored.updated_at.max(change.updated_at);
+        let mut row: story_nodes::ActiveModel = stored.into();
+        row.parent_node_id = Set(fields.parent_node_id);
+        row.sibling_order = Set(fields.sibling_order);
+        row.depth = Set(fields.depth);
+        row.summary = Set(fields.summary);
+        row.content = Set(fields.content);
+        row.tags = Set(Some(tags_json(&fields.tags)));
+        row.version = Set(version);
+        row.change_seq = Set(self.next_change_seq());
+        row.updated_at = Set(updated_at);
+        let node = row.update(self.txn).await?;
+        self.record_revision(&node).await?;
+
+        self.merged(change.id, SyncMergeOutcome::Merged, node_record(node), None);
+        Ok(())
+    }
+
+    /// Keep a node version as a future merge base, dropping the oldest beyond the history limit
+    async fn record_revision(&self, node: &story_nodes::Model) -> Result<()> {
+        story_node_revisions::Entity::insert(story_node_revisions::ActiveModel {
+            node_id: Set(node.id),
+            version: Set(node.version),
+            parent_node_id: Set(node.parent_node_id),
+            sibling_order: Set(node.sibling_order),
+            depth: Set(node.depth),
+            summary: Set(node.summary.clone()),
+            content: Set(node.content.clone()),
+            tags: Set(node.tags.clone()),
+            deleted: Set(node.deleted_at.is_some()),
+            created_at: Set(self.now),
+        })
+        .exec_without_returning(self.txn)
+        .await?;
+
+        story_node_revisions::Entity::delete_many()
+            .filter(story_node_revisions::Column::NodeId.eq(node.id))
+            .filter(story_node_revisions::Column::Version.lte(node.version - NODE_REVISION_HISTORY))
+            .exec(self.txn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn node_media(&mut self, change: SyncNodeMedia) -> Result<()> {
+        let stored = node_media::Entity::find_by_id(change.id)
+            .one(self.txn)
+            .await?;
+        let version = match self.next_version(
+            stored.as_ref().map(|row| (row.user_id, row.version)),
+            change.version,
+        ) {
+            Ok(version) => version,
+            Err(reason) => {
+                self.conflict(
+                    SyncEntity::NodeMedia,
+                    change.id,
+                    reason,
+                    stored.map(media_record),
+                );
+                return Ok(());
+            }
+        };
+
+        let node_found = match change.node_id {
+            None => true,
+            Some(node_id) => self.has_node(node_id, change.story_id).await?,
+        };
+        if !node_found || !self.has_story(change.story_id).await? {
+            self.missing_reference(SyncEntity::NodeMedia, change.id);
+            return Ok(());
+        }
+
+        let row = node_media::ActiveModel {
+            id: Set(change.id),
+            user_id: Set(self.user_id),
+            story_id: Set(change.story_id),
+            node_id: Set(change.node_id),
+            media_type: Set(change.media_type),
+            source: Set(change.source),
+            caption: Set(change.caption),
+            order_index: Set(change.order_index),
+            is_primary: Set(change.is_primary),
+            version: Set(version),
+            change_seq: Set(self.next_change_seq()),
+            deleted_at: Set(self.tombstone(
+                change.deleted,
+                stored.as_ref().and_then(|row| row.deleted_at),
+            )),
+            created_at: Set(change.created_at),
+            updated_at: Set(change.updated_at),
+        };
+        if stored.is_some() {
+            row.update(self.txn).await?;
+        } else {
+            row.insert(self.txn).await?;
+        }
+
+        self.applied(SyncEntity::NodeMedia, change.id, version);
+        Ok(())
+    }
+
+    async fn media_asset(&mut self, change: SyncMediaAsset) -> Result<()> {
+        let stored = media_assets::Entity::find_by_id(change.id)
+            .one(self.txn)
+            .await?;
+        let version = match self.next_version(
+            stored.as_ref().map(|row| (row.user_id, row.version)),
+            change.version,
+        ) {
+            Ok(version) => version,
+            Err(reason) => {
+                self.conflict(
+                    SyncEntity::MediaAsset,
+                    change.id,
+                    reason,
+                    stored.map(asset_record),
+                );
+                return Ok(());
+            }
+        };
+
+        let media_found = node_media::Entity::find_by_id(change.media_id)
+            .filter(node_media::Column::UserId.eq(self.user_id))
+            .count(self.txn)
+            .await?
+            > 0;
+        if !media_found {
+            self.missing_reference(SyncEntity::MediaAsset, change.id);
+            return Ok(());
+        }
+
+        let row = media_assets::ActiveModel {
+            id: Set(change.id),
+            user_id: Set(self.user_id),
+            media_id: Set(change.media_id),
+            asset_type: Set(change.asset_type),
+            mime_type: Set(change.mime_type),
+            local_path: Set(change.local_path),
+            remote_url: Set(change.remote_url),
+            width: Set(change.width),
+            height: Set(change.height),
+            duration_ms: Set(change.duration_ms),
+            size_bytes: Set(change.size_bytes),
+            extra_meta: Set(change.extra_meta),
+            version: Set(version),
+            change_seq: Set(self.next_change_seq()),
+            deleted_at: Set(self.tombstone(
+                change.deleted,
+                stored.as_ref().and_then(|row| row.deleted_at),
+            )),
+            created_at: Set(change.created_at),
+            updated_at: Set(change.updated_at),
+        };
+        if stored.is_some() {
+            row.update(self.txn).await?;
+        } else {
+            row.insert(self.txn).await?;
+        }
+
+        self.applied(SyncEntity::MediaAsset, change.id, version);
+        Ok(())
+    }
+
+    /// Version the row gets if a change based on `base_version` is accepted
+    fn next_version(
+        &self,
+        stored: Option<(Uuid, i64)>,
+        base_version: i64,
+    ) -> std::result::Result<i64, SyncConflictReason> {
+        match stored {
+            Some((owner, _)) if owner != self.user_id => Err(SyncConflictReason::IdInUse),
+            Some((_, version)) if version == base_version => Ok(version + 1),
+            // A new row must not claim a base version
+            None if base_version == 0 => Ok(1),
+            _ => Err(SyncConflictReason::VersionMismatch),
+        }
+    }
+
+    fn next_change_seq(&mut self) -> i64 {
+        self.last_change_seq += 1;
+        self.last_change_seq
+    }
+
+    /// Keep the original deletion time when a tombstone is pushed again
+    fn tombstone(
+        &self,
+        deleted: bool,
+        deleted_at: Option<OffsetDateTime>,
+    ) -> Option<OffsetDateTime> {
+        deleted.then(|| deleted_at.unwrap_or(self.now))
+    }
+
+    async fn has_parent(
+        &self,
+        parent_id: Option<Uuid>,
+        node_id: Uuid,
+        story_id: Uuid,
+    ) -> Result<bool> {
+        match parent_id {
+            None => Ok(true),
+            Some(parent_id) if parent_id == node_id => Ok(false),
+            Some(parent_id) => self.has_node(parent_id, story_id).await,
+        }
+    }
+
+    async fn has_story(&self, story_id: Uuid) -> Result<bool> {
+        Ok(stories::Entity::find_by_id(story_id)
+            .filter(stories::Column::UserId.eq(self.user_id))
+            .count(self.txn)
+            .await?
+            > 0)
+    }
+
+    async fn has_node(&self, node_id: Uuid, story_id: Uuid) -> Result<bool> {
+        Ok(story_nodes::Entity::find_by_id(node_id)
+            .filter(story_nodes::Column::UserId.eq(self.user_id))
+            .filter(story_nodes::Column::StoryId.eq(story_id))
+            .count(self.txn)
+            .await?
+            > 0)
+    }
+
+    fn applied(&mut self, entity: SyncEntity, id: Uuid, version: i64) {
+        self.response.applied.push(SyncApplied {
+            entity,
+            id,
+            version,
+        });
+    }
+
+    fn merged(
+        &mut self,
+        id: Uuid,
+        outcome: SyncMergeOutcome,
+        node: SyncStoryNode,
+        branch: Option<SyncStoryNode>,
+    ) {
+        self.response.merged.push(SyncMerge {
+            id,
+            outcome,
+            node,
+            branch,
+        });
+    }
+
+    fn conflict<T: Serialize>(
+        &mut self,
+        entity: SyncEntity,
+        id: Uuid,
+        reason: SyncConflictReason,
+        server: Option<T>,
+    ) {
+        // Another account's row is never echoed back
+        let server = match reason {
+            SyncConflictReason::VersionMismatch => {
+                server.and_then(|row| serde_json::to_value(row).ok())
+            }
+            _ => None,
+        };
+        self.response.conflicts.push(SyncConflict {
+            entity,
+            id,
+            reason,
+            server,
+        });
+    }
+
+    fn missing_reference(&mut self, entity: SyncEntity, id: Uuid) {
+        self.conflict::<()>(entity, id, SyncConflictReason::MissingReference, None);
+    }
+}
+
+fn story_record(row: stories::Model) -> SyncStory {
+    SyncStory {
+        id: row.id,
+        version: row.version,
+        deleted: row.deleted_at.is_some(),
+        title: row.title,
+        description: row.description,
+        cover_media_id: row.cover_media_id,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+fn node_record(row: story_nodes::Model) -> SyncStoryNode {
+    SyncStoryNode {
+        id: row.id,
+        version: row.version,
+        deleted: row.deleted_at.is_some(),
+        story_id: row.story_id,
+        parent_node_id: row.parent_node_id,
+        sibling_order: row.sibling_order,
+        depth: row.depth,
+        summary: row.summary,
+        content: row.content,
+        tags: tags_from_json(row.tags),
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+/// Only edits of a live node within its story are merged; anything else stays a conflict
+fn mergeable(stored: &story_nodes::Model, change: &SyncStoryNode) -> bool {
+    change.version > 0
+        && !change.deleted
+        && stored.deleted_at.is_none()
+        && stored.story_id == change.story_id
+}
+
+fn node_fields(row: &story_nodes::Model) -> NodeFields {
+    NodeFields {
+        parent_node_id: row.parent_node_id,
+        sibling_order: row.sibling_order,
+        depth: row.depth,
+        summary: row.summary.clone(),
+        content: row.content.clone(),
+        tags: tags_from_json(row.tags.clone()),
+    }
+}
+
+fn revision_fields(row: story_node_revisions::Model) -> NodeFields {
+    NodeFields {
+        parent_node_id: row.parent_node_id,
+        sibling_order: row.sibling_order,
+        depth: row.depth,
+        summary: row.summary,
+        content: row.content,
+        tags: tags_from_json(row.tags),
+    }
+}
+
+fn tags_json(tags: &[String]) -> serde_json::Value {
+    serde_json::to_value(tags).expect("tags serialize to JSON")
+}
+
+fn tags_from_json(tags: Option<serde_json::Value>) -> Vec<String> {
+    tags.and_then(|tags| serde_json::from_value(tags).ok())
+        .unwrap_or_default()
+}
+
+fn media_record(row: node_media::Model) -> SyncNodeMedia {
+    SyncNodeMedia {
+        id: row.id,
+        version: row.version,
+        deleted: row.deleted_at.is_some(),
+        story_id: row.story_id,
+        node_id: row.node_id,
+        media_type: row.media_type,
+        source: row.source,
+        caption: row.caption,
+        order_index: row.order_index,
+        is_primary: row.is_primary,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+fn asset_record(row: media_assets::Model) -> SyncMediaAsset {
+    SyncMediaAsset {
+        id: row.id,
+        version: row.version,
+        deleted: row.deleted_at.is_some(),
+        media_id: row.media_id,
+        asset_type: row.asset_type,
+        mime_type: row.mime_type,
+        local_path: row.local_path,
+        remote_url: row.remote_url,
+        width: row.width,
+        height: row.height,
+        duration_ms: row.duration_ms,
+        size_bytes: row.size_bytes,
+        extra_meta: row.extra_meta,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
@@ -0,0 +1,133 @@
+use crate::error::{ApiError, Result};
+use base64::Engine;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::*;
+
+/// SHA-256 fingerprint of Apple Root CA - G3, the anchor for App Store signed payloads
+pub const APPLE_ROOT_CA_G3_SHA256: &str =
+    "63343abfb89a6a03ebb57e9b3f5fa7be7c4f5c756f3017b3a8c488c3653e9179";
+
+/// Marker extension present on Apple's App Store receipt signing certificates
+const APPLE_RECEIPT_SIGNER_OID: &str = "1.2.840.113635.100.6.11.1";
+/// Marker extension present on Apple's WWDR intermediate certificates
+const APPLE_WWDR_INTERMEDIATE_OID: &str = "1.2.840.113635.100.6.2.1";
+
+/// Cheap structural check used to route StoreKit 2 payloads away from legacy receipts
+pub fn looks_like_jws(value: &str) -> bool {
+    value.starts_with("eyJ") && value.split('.').count() == 3
+}
+
+/// Verify an App Store signed payload (JWS) and decode its claims
+///
+/// This method:
+/// 1. Reads the x5c chain (leaf, intermediate, root) from the ES256 header
+/// 2. Checks the root's fingerprint against the trusted Apple roots
+/// 3. Validates the chain signatures, validity windows and Apple marker extensions
+/// 4. Verifies the JWS signature with the leaf certificate's key
+pub fn verify_signed_payload<T: DeserializeOwned>(
+    signed_payload: &str,
+    trusted_root_sha256: &[String],
+) -> Result<T> {
+    // 1. Read the certificate chain from the header
+    let header = decode_header(signed_payload)
+        .map_err(|e| ApiError::InvalidReceipt(format!("Invalid JWS header: {}", e)))?;
+
+    if header.alg != Algorithm::ES256 {
+        return Err(ApiError::InvalidReceipt(format!(
+            "Unexpected JWS algorithm: {:?}",
+            header.alg
+        )));
+    }
+
+    let chain = header
+        .x5c
+        .ok_or_else(|| ApiError::InvalidReceipt("Missing x5c chain in JWS header".to_string()))?
+        .iter()
+        .map(|cert| base64::engine::general_purpose::STANDARD.decode(cert))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| {
+            ApiError::InvalidReceipt(format!("Invalid x5c certificate encoding: {}", e))
+        })?;
+
+    let [leaf_der, intermediate_der, root_der] = chain.as_slice() else {
+        return Err(ApiError::InvalidReceipt(format!(
+            "Expected 3 certificates in x5c chain, got {}",
+            chain.len()
+        )));
+    };
+
+    // 2. Pin the root certificate
+    let root_fingerprint = format!("{:x}", Sha256::digest(root_der));
+    let root_trusted = trusted_root_sha256
+        .iter()
+        .any(|fp| fp.replace(':', "").eq_ignore_ascii_case(&root_fingerprint));
+    if !root_trusted {
+        return Err(ApiError::InvalidReceipt(
+            "JWS chain is not anchored in a trusted Apple root".to_string(),
+        ));
+    }
+
+    // 3. Validate the chain
+    let leaf = parse_certificate(leaf_der)?;
+    let intermediate = parse_certificate(intermediate_der)?;
+    let root = parse_certificate(root_der)?;
+
+    for cert in [&leaf, &intermediate, &root] {
+        if !cert.validity().is_valid() {
+            return Err(ApiError::InvalidReceipt(format!(
+                "Certificate outside its validity period: {}",
+                cert.subject()
+            )));
+        }
+    }
+
+    if !intermediate.is_ca() || !root.is_ca() {
+        return Err(ApiError::InvalidReceipt(
+            "JWS chain contains a non-CA issuer certificate".to_string(),
+        ));
+    }
+
+    if !has_extension(&leaf, APPLE_RECEIPT_SIGNER_OID)
+        || !has_extension(&intermediate, APPLE_WWDR_INTERMEDIATE_OID)
+    {
+        return Err(ApiError::InvalidReceipt(
+            "JWS chain is missing Apple certificate markers".to_string(),
+        ));
+    }
+
+    leaf.verify_signature(Some(intermediate.public_key()))
+        .and_then(|_| intermediate.verify_signature(Some(root.public_key())))
+        .and_then(|_| root.verify_signature(None))
+        .map_err(|e| {
+            ApiError::InvalidReceipt(format!("Certificate chain verification failed: {}", e))
+        })?;
+
+    // 4. Verify the payload signature with the leaf key
+    let decoding_key = DecodingKey::from_ec_der(&leaf.public_key().subject_public_key.data);
+
+    // Signed payloads carry no exp/aud claims; freshness is judged by the caller
+    let mut validation = Validation::new(Algorithm::ES256);
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+
+    let token_data = decode::<T>(signed_payload, &decoding_key, &validation).map_err(|e| {
+        ApiError::InvalidReceipt(format!("JWS signature verification failed: {}", e))
+    })?;
+
+    Ok(token_data.claims)
+}
+
+fn parse_certificate(der: &[u8]) -> Result<X509Certificate<'_>> {
+    X509Certificate::from_der(der)
+        .map(|(_, cert)| cert)
+        .map_err(|e| ApiError::InvalidReceipt(format!("Invalid x5c certificate: {}", e)))
+}
+
+fn has_extension(cert: &X509Certificate<'_>, oid: &str) -> bool {
+    cert.extensions()
+        .iter()
+        .any(|ext| ext.oid.to_id_string() == oid)
+}
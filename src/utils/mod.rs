@@ -1 +1,2 @@
 // Utility functions
+pub mod apple_jws;
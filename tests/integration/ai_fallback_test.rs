@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use backvonia::{
+    config::{AIConfig, ModelFallbackConfig},
+    models::ai::{GenerationParams, PathNode, StoryContext},
+    services::{
+        ai_provider::{OnDelta, TextProvider, TextRequest},
+        AIService,
+    },
+    ApiError,
+};
+use entity::sea_orm_active_enums::AccountTier;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+
+const CANDIDATE: &str = "=== CANDIDATE 1 ===\nTITLE: A quiet hallway\nCONTENT: Nobody was there.";
+
+#[derive(Clone, Copy)]
+enum Reply {
+    Text(&'static str),
+    // Overloaded or rate limited
+    Unavailable,
+    // Rejected the request itself
+    Rejected,
+}
+
+/// Provider whose behaviour is scripted per model, recording the models it was asked for
+struct PerModelProvider {
+    replies: HashMap<String, Reply>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl PerModelProvider {
+    fn new(replies: &[(&str, Reply)]) -> Arc<Self> {
+        Arc::new(Self {
+            replies: replies
+                .iter()
+                .map(|(model, reply)| (model.to_string(), *reply))
+                .collect(),
+            calls: Mutex::new(vec![]),
+        })
+    }
+
+    fn reply(&self, request: &TextRequest) -> backvonia::Result<String> {
+        self.calls.lock().unwrap().push(request.model.clone());
+        match self.replies[&request.model] {
+            Reply::Text(text) => Ok(text.to_string()),
+            Reply::Unavailable => Err(ApiError::AIProviderUnavailable(format!(
+                "{} is overloaded",
+                request.model
+            ))),
+            Reply::Rejected => Err(ApiError::AIProvider(format!(
+                "{} rejected the request",
+                request.model
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl TextProvider for PerModelProvider {
+    async fn complete(&self, request: &TextRequest) -> backvonia::Result<Vec<String>> {
+        Ok(vec![self.reply(request)?])
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> backvonia::Result<Vec<String>> {
+        let text = self.reply(request)?;
+        on_delta(0, &text);
+        Ok(vec![text])
+    }
+}
+
+/// Build a service whose light tier is `test/light` followed by `fallbacks`
+fn create_ai_service(fallbacks: &[&str], provider: Arc<PerModelProvider>) -> AIService {
+    let routing = serde_json::json!({
+        "free_default_tier": "light",
+        "pro_default_tier": "light",
+    });
+    let mut config: AIConfig = serde_json::from_value(serde_json::json!({
+        "openrouter": {
+            "api_key": "test-key",
+            "api_base": "http://127.0.0.1:9",
+            "model_tiers": {
+                "premium": { "model": "test/premium" },
+                "standard": { "model": "test/standard" },
+                "light": { "model": "test/light" },
+            },
+            "image_models": {
+                "free": { "model": "test/image" },
+                "pro": { "model": "test/image" },
+            },
+            "ai_routing": {
+                "fix_grammar": routing,
+                "shorten": routing,
+                "rewrite": routing,
+                "ideas": routing,
+                "continue": routing,
+                "expand": routing,
+            },
+            "request_timeout_ms": 5000,
+            "retry_attempts": 0,
+        },
+    }))
+    .unwrap();
+    config.openrouter.model_tiers.light.fallbacks = fallbacks
+        .iter()
+        .map(|model| ModelFallbackConfig {
+            model: model.to_string(),
+            provider: "openrouter".to_string(),
+        })
+        .collect();
+
+    AIService::new(&config).with_text_provider("openrouter", provider)
+}
+
+fn story_context() -> StoryContext {
+    StoryContext {
+        title: Some("Test".to_string()),
+        tags: vec![],
+        language: "en".to_string(),
+        background: None,
+        active_characters: None,
+    }
+}
+
+fn path_nodes() -> Vec<PathNode> {
+    vec![PathNode {
+        summary: None,
+        content: "The door creaked.".to_string(),
+    }]
+}
+
+async fn continue_prose(service: &AIService) -> backvonia::Result<String> {
+    let (candidates, model) = service
+        .generate_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await?;
+    assert_eq!(candidates[0].content, "Nobody was there.");
+    Ok(model)
+}
+
+#[tokio::test]
+async fn test_unavailable_model_falls_back_to_next() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Unavailable),
+        ("test/light-2", Reply::Unavailable),
+        ("test/light-3", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2", "test/light-3"], provider.clone());
+
+    let model = continue_prose(&service).await.unwrap();
+
+    // The model that actually answered is reported
+    assert_eq!(model, "test/light-3");
+    assert_eq!(
+        *provider.calls.lock().unwrap(),
+        vec!["test/light", "test/light-2", "test/light-3"]
+    );
+}
+
+#[tokio::test]
+async fn test_unparseable_output_falls_back_to_next() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Text("I'd rather not write that.")),
+        ("test/light-2", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let model = continue_prose(&service).await.unwrap();
+
+    assert_eq!(model, "test/light-2");
+    assert_eq!(provider.calls.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_primary_model_answers_without_fallback() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Text(CANDIDATE)),
+        ("test/light-2", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let model = continue_prose(&service).await.unwrap();
+
+    assert_eq!(model, "test/light");
+    assert_eq!(*provider.calls.lock().unwrap(), vec!["test/light"]);
+}
+
+#[tokio::test]
+async fn test_rejected_request_does_not_fall_back() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Rejected),
+        ("test/light-2", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let err = continue_prose(&service).await.unwrap_err();
+
+    assert!(matches!(err, ApiError::AIProvider(_)));
+    assert_eq!(*provider.calls.lock().unwrap(), vec!["test/light"]);
+}
+
+#[tokio::test]
+async fn test_exhausted_chain_returns_last_error() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Unavailable),
+        ("test/light-2", Reply::Unavailable),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let err = continue_prose(&service).await.unwrap_err();
+
+    assert!(matches!(err, ApiError::AIProviderUnavailable(msg) if msg.contains("test/light-2")));
+}
+
+#[tokio::test]
+async fn test_stream_falls_back_before_first_delta() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Unavailable),
+        ("test/light-2", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let (deltas_tx, mut deltas_rx) = mpsc::unbounded_channel();
+    let (candidates, model) = service
+        .stream_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+            &deltas_tx,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(model, "test/light-2");
+    assert_eq!(candidates[0].content, "Nobody was there.");
+    assert_eq!(deltas_rx.recv().await.unwrap().delta, CANDIDATE);
+}
+
+#[tokio::test]
+async fn test_stream_does_not_fall_back_after_output_was_sent() {
+    let provider = PerModelProvider::new(&[
+        ("test/light", Reply::Text("I'd rather not write that.")),
+        ("test/light-2", Reply::Text(CANDIDATE)),
+    ]);
+    let service = create_ai_service(&["test/light-2"], provider.clone());
+
+    let (deltas_tx, _deltas_rx) = mpsc::unbounded_channel();
+    let result = service
+        .stream_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+            &deltas_tx,
+        )
+        .await;
+
+    // The client already saw the first model's text, so switching models would garble it
+    assert!(result.is_err());
+    assert_eq!(*provider.calls.lock().unwrap(), vec!["test/light"]);
+}
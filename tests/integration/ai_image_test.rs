@@ -1,4 +1,8 @@
-use backvonia::{config::QuotaConfig, models::common::AIOperation, services::QuotaService};
+use backvonia::{
+    config::{ProductCatalog, QuotaConfig},
+    models::{common::AIOperation, credits::UsageContext},
+    services::QuotaService,
+};
 use entity::sea_orm_active_enums::AccountTier;
 use entity::user_credit_balance;
 use migration::{Migrator, MigratorTrait};
@@ -27,6 +31,10 @@ fn create_test_quota_config() -> QuotaConfig {
     QuotaConfig {
         free_text_daily_limit: 15,
         pro_text_daily_limit: 5000,
+        hold_ttl_secs: 600,
+        hold_sweep_interval_secs: 60,
+        free_image_regenerations: 2,
+        pro_image_regenerations: 20,
     }
 }
 
@@ -74,7 +82,7 @@ async fn total_credits_remaining(db: &DatabaseConnection, user_id: Uuid) -> i32
 async fn test_image_generation_failure_refunds_credits() {
     let db = setup_test_db().await;
     let quota_config = create_test_quota_config();
-    let quota_service = QuotaService::new(db.clone(), &quota_config);
+    let quota_service = QuotaService::new(db.clone(), &quota_config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -86,8 +94,8 @@ async fn test_image_generation_failure_refunds_credits() {
 
     // Simulate the flow that happens in the route handler:
     // 1. Deduct credits
-    quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = quota_service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
     assert_eq!(total_credits_remaining(&db, user_id).await, 5); // 15 - 10 = 5
@@ -97,7 +105,7 @@ async fn test_image_generation_failure_refunds_credits() {
 
     // 3. Refund credits
     quota_service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
@@ -106,7 +114,7 @@ async fn test_image_generation_failure_refunds_credits() {
 
     // 5. Verify user can still use the service (credits are available)
     let second_attempt = quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await;
     assert!(
         second_attempt.is_ok(),
@@ -121,7 +129,7 @@ async fn test_image_generation_failure_refunds_credits() {
 async fn test_multiple_failures_multiple_refunds() {
     let db = setup_test_db().await;
     let quota_config = create_test_quota_config();
-    let quota_service = QuotaService::new(db.clone(), &quota_config);
+    let quota_service = QuotaService::new(db.clone(), &quota_config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -136,8 +144,8 @@ async fn test_multiple_failures_multiple_refunds() {
         assert_eq!(total_credits_remaining(&db, user_id).await, 15);
 
         // Deduct
-        quota_service
-            .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        let charge = quota_service
+            .reserve(user_id, &tier, AIOperation::ImageGenerate)
             .await
             .unwrap();
 
@@ -146,7 +154,7 @@ async fn test_multiple_failures_multiple_refunds() {
 
         // Refund
         quota_service
-            .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+            .release(user_id, &tier, &charge, &UsageContext::default())
             .await
             .unwrap();
 
@@ -162,7 +170,7 @@ async fn test_multiple_failures_multiple_refunds() {
 async fn test_partial_failure_preserves_successful_operations() {
     let db = setup_test_db().await;
     let quota_config = create_test_quota_config();
-    let quota_service = QuotaService::new(db.clone(), &quota_config);
+    let quota_service = QuotaService::new(db.clone(), &quota_config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -171,7 +179,7 @@ async fn test_partial_failure_preserves_successful_operations() {
 
     // First operation: success (don't refund)
     quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await
         .unwrap();
 
@@ -179,8 +187,8 @@ async fn test_partial_failure_preserves_successful_operations() {
     assert_eq!(total_credits_remaining(&db, user_id).await, 10);
 
     // Second operation: failure (deduct then refund)
-    quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = quota_service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
@@ -189,7 +197,7 @@ async fn test_partial_failure_preserves_successful_operations() {
 
     // Refund the failed image operation
     quota_service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
@@ -204,7 +212,7 @@ async fn test_partial_failure_preserves_successful_operations() {
 async fn test_free_tier_single_failure_can_retry() {
     let db = setup_test_db().await;
     let quota_config = create_test_quota_config();
-    let quota_service = QuotaService::new(db.clone(), &quota_config);
+    let quota_service = QuotaService::new(db.clone(), &quota_config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -215,19 +223,19 @@ async fn test_free_tier_single_failure_can_retry() {
     // After one failure with refund, user should be able to retry
 
     // First attempt: fail
-    quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = quota_service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
     quota_service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
     // Second attempt: should succeed (credits available)
     let result = quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await;
 
     assert!(
@@ -245,7 +253,7 @@ async fn test_free_tier_single_failure_can_retry() {
 async fn test_analytics_accuracy_after_refund() {
     let db = setup_test_db().await;
     let quota_config = create_test_quota_config();
-    let quota_service = QuotaService::new(db.clone(), &quota_config);
+    let quota_service = QuotaService::new(db.clone(), &quota_config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -255,7 +263,7 @@ async fn test_analytics_accuracy_after_refund() {
 
     // Successful image generation
     quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
@@ -271,13 +279,13 @@ async fn test_analytics_accuracy_after_refund() {
     assert_eq!(usage.image_count, 10); // 10 credits used
 
     // Failed image generation (deduct then refund)
-    quota_service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = quota_service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
     quota_service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
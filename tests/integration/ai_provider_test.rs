@@ -0,0 +1,527 @@
+use async_trait::async_trait;
+use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use backvonia::{
+    config::{AIConfig, ModelFallbackConfig, IMAGE_REQUEST_TIMEOUT_MS},
+    models::ai::{
+        GenerationParams, ImageParams, ImageResolution, ImageStoryContext, NodeContext,
+        NodeToSummarize, PathNode, StoryContext,
+    },
+    services::{
+        ai_provider::{
+            ImageProvider, ImageRequest, OnDelta, ProviderImage, ReferenceImage, TextProvider,
+            TextRequest,
+        },
+        AIService,
+    },
+    ApiError,
+};
+use entity::sea_orm_active_enums::AccountTier;
+use std::sync::{Arc, Mutex};
+
+/// Deterministic provider that answers every request with fixed text and records what it saw
+#[derive(Default)]
+struct ScriptedProvider {
+    reply: String,
+    image: Option<ProviderImage>,
+    requests: Mutex<Vec<TextRequest>>,
+    image_requests: Mutex<Vec<ImageRequest>>,
+}
+
+impl ScriptedProvider {
+    fn new(reply: &str) -> Arc<Self> {
+        Arc::new(Self {
+            reply: reply.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn with_image(bytes: Vec<u8>, mime_type: Option<&str>) -> Arc<Self> {
+        Arc::new(Self {
+            image: Some(ProviderImage {
+                bytes,
+                mime_type: mime_type.map(str::to_string),
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+#[async_trait]
+impl TextProvider for ScriptedProvider {
+    async fn complete(&self, request: &TextRequest) -> backvonia::Result<Vec<String>> {
+        self.requests.lock().unwrap().push(request.clone());
+        Ok(vec![self.reply.clone(); request.n as usize])
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> backvonia::Result<Vec<String>> {
+        self.requests.lock().unwrap().push(request.clone());
+        on_delta(0, &self.reply);
+        Ok(vec![self.reply.clone()])
+    }
+}
+
+#[async_trait]
+impl ImageProvider for ScriptedProvider {
+    async fn generate_image(&self, request: &ImageRequest) -> backvonia::Result<ProviderImage> {
+        self.image_requests.lock().unwrap().push(request.clone());
+        Ok(self.image.clone().expect("no scripted image"))
+    }
+}
+
+/// Build an AI config whose light tier is served by `light_provider`
+fn create_ai_config(light_provider: &str, providers: serde_json::Value) -> AIConfig {
+    let routing = serde_json::json!({
+        "free_default_tier": "light",
+        "pro_default_tier": "light",
+    });
+    serde_json::from_value(serde_json::json!({
+        "openrouter": {
+            "api_key": "test-key",
+            // Nothing listens here; requests must not reach OpenRouter
+            "api_base": "http://127.0.0.1:9",
+            "model_tiers": {
+                "premium": { "model": "test/premium" },
+                "standard": { "model": "test/standard" },
+                "light": { "model": "test/light", "provider": light_provider },
+            },
+            "image_models": {
+                "free": { "model": "test/image-free" },
+                "pro": {
+                    "model": "test/image-pro",
+                    "resolutions": { "hd": { "image_size": "2K" } },
+                },
+            },
+            "ai_routing": {
+                "fix_grammar": routing,
+                "shorten": routing,
+                "rewrite": routing,
+                "ideas": routing,
+                "continue": routing,
+                "expand": routing,
+            },
+            "request_timeout_ms": 5000,
+            "retry_attempts": 0,
+        },
+        "providers": providers,
+    }))
+    .unwrap()
+}
+
+fn story_context() -> StoryContext {
+    StoryContext {
+        title: Some("Test".to_string()),
+        tags: vec![],
+        language: "en".to_string(),
+        background: None,
+        active_characters: None,
+    }
+}
+
+fn image_context() -> (ImageStoryContext, NodeContext) {
+    let context = ImageStoryContext {
+        title: "Test".to_string(),
+        language: "en".to_string(),
+        genre: None,
+        tone: None,
+        setting: None,
+    };
+    let node = NodeContext {
+        summary: Some("A lighthouse at dusk".to_string()),
+        content: None,
+        tags: vec![],
+    };
+    (context, node)
+}
+
+fn encode_image(width: u32, height: u32, format: image::ImageFormat) -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(width, height)
+        .write_to(&mut bytes, format)
+        .unwrap();
+    bytes.into_inner()
+}
+
+fn path_nodes() -> Vec<PathNode> {
+    vec![PathNode {
+        summary: None,
+        content: "The door creaked.".to_string(),
+    }]
+}
+
+#[tokio::test]
+async fn test_injected_text_provider_serves_requests() {
+    let provider = ScriptedProvider::new(
+        "=== CANDIDATE 1 ===\nTITLE: A quiet hallway\nCONTENT: Nobody was there.",
+    );
+    let service = AIService::new(&create_ai_config("openrouter", serde_json::json!({})))
+        .with_text_provider("openrouter", provider.clone());
+
+    let (candidates, model) = service
+        .generate_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(model, "test/light");
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].title.as_deref(), Some("A quiet hallway"));
+    assert_eq!(candidates[0].content, "Nobody was there.");
+
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].model, "test/light");
+    assert_eq!(requests[0].messages[0].role, "system");
+}
+
+#[tokio::test]
+async fn test_injected_image_provider_serves_requests() {
+    let png = encode_image(32, 18, image::ImageFormat::Png);
+    let provider = ScriptedProvider::with_image(png.clone(), Some("image/png"));
+    let service = AIService::new(&create_ai_config("openrouter", serde_json::json!({})))
+        .with_image_provider("openrouter", provider.clone());
+
+    let (context, node) = image_context();
+    let params = ImageParams {
+        aspect_ratio: "16:9".to_string(),
+        ..ImageParams::default()
+    };
+
+    let (bytes, metadata) = service
+        .generate_image(&context, &node, &params, None, &AccountTier::Pro)
+        .await
+        .unwrap();
+
+    assert_eq!(bytes, png);
+    assert_eq!(metadata.provider, "openrouter");
+    assert_eq!(metadata.model, "test/image-pro");
+    assert_eq!(
+        service.image_model_name(&AccountTier::Pro),
+        "openrouter/test/image-pro"
+    );
+    assert_eq!(metadata.mime_type, "image/png");
+    assert_eq!((metadata.width, metadata.height), (32, 18));
+
+    let requests = provider.image_requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].aspect_ratio, "16:9");
+    assert!(requests[0].size_params.is_empty());
+}
+
+#[tokio::test]
+async fn test_image_metadata_comes_from_returned_bytes() {
+    // The provider claims PNG but returns a JPEG
+    let jpeg = encode_image(20, 30, image::ImageFormat::Jpeg);
+    let provider = ScriptedProvider::with_image(jpeg, Some("image/png"));
+    let service = AIService::new(&create_ai_config("openrouter", serde_json::json!({})))
+        .with_image_provider("openrouter", provider);
+
+    let (context, node) = image_context();
+    let (_, metadata) = service
+        .generate_image(
+            &context,
+            &node,
+            &ImageParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(metadata.mime_type, "image/jpeg");
+    assert_eq!((metadata.width, metadata.height), (20, 30));
+}
+
+#[tokio::test]
+async fn test_hd_resolution_maps_to_model_size_params() {
+    let provider = ScriptedProvider::with_image(
+        encode_image(8, 8, image::ImageFormat::Png),
+        Some("image/png"),
+    );
+    let service = AIService::new(&create_ai_config("openrouter", serde_json::json!({})))
+        .with_image_provider("openrouter", provider.clone());
+
+    let (context, node) = image_context();
+    let params = ImageParams {
+        resolution: ImageResolution::Hd,
+        ..ImageParams::default()
+    };
+    service
+        .generate_image(&context, &node, &params, None, &AccountTier::Pro)
+        .await
+        .unwrap();
+
+    let requests = provider.image_requests.lock().unwrap();
+    assert_eq!(requests[0].size_params["image_size"], "2K");
+}
+
+#[tokio::test]
+async fn test_hd_resolution_requires_pro() {
+    let provider = ScriptedProvider::with_image(vec![], None);
+    let service = AIService::new(&create_ai_config("openrouter", serde_json::json!({})))
+        .with_image_provider("openrouter", provider.clone());
+
+    let (context, node) = image_context();
+    // "high" is accepted as an alias of "hd"
+    let params: ImageParams = serde_json::from_value(serde_json::json!({
+        "resolution": "high",
+    }))
+    .unwrap();
+    assert_eq!(params.resolution, ImageResolution::Hd);
+
+    let err = service
+        .generate_image(&context, &node, &params, None, &AccountTier::Free)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ProRequired(_)));
+    assert!(provider.image_requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_reference_image_requires_supporting_model() {
+    let provider = ScriptedProvider::with_image(
+        encode_image(8, 8, image::ImageFormat::Png),
+        Some("image/png"),
+    );
+    let mut config = create_ai_config("openrouter", serde_json::json!({}));
+    config.openrouter.image_models.pro.supports_reference_image = true;
+    let service = AIService::new(&config).with_image_provider("openrouter", provider.clone());
+
+    let (context, node) = image_context();
+    let reference = ReferenceImage {
+        bytes: encode_image(4, 4, image::ImageFormat::Jpeg),
+        mime_type: "image/jpeg".to_string(),
+    };
+    let params = ImageParams {
+        seed: Some(42),
+        ..ImageParams::default()
+    };
+
+    // The free tier's model takes text only
+    let err = service
+        .generate_image(
+            &context,
+            &node,
+            &params,
+            Some(reference.clone()),
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApiError::BadRequest(_)));
+    assert!(provider.image_requests.lock().unwrap().is_empty());
+
+    service
+        .generate_image(&context, &node, &params, Some(reference), &AccountTier::Pro)
+        .await
+        .unwrap();
+
+    let requests = provider.image_requests.lock().unwrap();
+    assert_eq!(requests[0].seed, Some(42));
+    assert_eq!(
+        requests[0].reference_image.as_ref().unwrap().mime_type,
+        "image/jpeg"
+    );
+}
+
+#[tokio::test]
+async fn test_model_tier_routes_to_openai_compatible_provider() {
+    type Seen = Arc<Mutex<Vec<(Option<String>, serde_json::Value)>>>;
+
+    async fn completions(
+        State(seen): State<Seen>,
+        headers: HeaderMap,
+        Json(request): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        let authorization = headers
+            .get("authorization")
+            .map(|v| v.to_str().unwrap().to_string());
+        seen.lock().unwrap().push((authorization, request));
+        Json(serde_json::json!({
+            "choices": [{ "message": { "content": "1. The door opens" } }]
+        }))
+    }
+
+    let seen: Seen = Arc::default();
+    let app = Router::new()
+        .route("/v1/chat/completions", post(completions))
+        .with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let config = create_ai_config(
+        "local",
+        serde_json::json!({
+            "local": { "api_base": format!("http://{}/v1", addr) }
+        }),
+    );
+    let service = AIService::new(&config);
+
+    let nodes = vec![NodeToSummarize {
+        node_id: "node-1".to_string(),
+        content: "The door creaked open.".to_string(),
+    }];
+    let (summaries, model) = service
+        .generate_summaries(None, &nodes, &AccountTier::Free)
+        .await
+        .unwrap();
+
+    assert_eq!(model, "test/light");
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].summary, "The door opens");
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    // No API key configured, so no credentials are sent
+    assert_eq!(seen[0].0, None);
+    assert_eq!(seen[0].1["model"], "test/light");
+}
+
+#[test]
+fn test_max_generation_time_covers_fallback_chain() {
+    let mut config = create_ai_config(
+        "local",
+        serde_json::json!({
+            "local": {
+                "api_base": "http://127.0.0.1:9",
+                "request_timeout_ms": 200000,
+                "retry_attempts": 1,
+            }
+        }),
+    );
+    // Two attempts plus the longest backoff between them
+    assert_eq!(config.max_generation_ms(), 405000);
+
+    config
+        .openrouter
+        .model_tiers
+        .light
+        .fallbacks
+        .push(ModelFallbackConfig {
+            model: "test/fallback".to_string(),
+            provider: "openrouter".to_string(),
+        });
+    assert_eq!(config.max_generation_ms(), 410000);
+
+    // Image requests are never retried but have a longer timeout
+    let config = create_ai_config("openrouter", serde_json::json!({}));
+    assert_eq!(config.max_generation_ms(), IMAGE_REQUEST_TIMEOUT_MS);
+}
+
+#[tokio::test]
+async fn test_unknown_provider_is_rejected() {
+    // Not validated at load time here, so the lookup itself must fail
+    let service = AIService::new(&create_ai_config("missing", serde_json::json!({})));
+
+    let err = service
+        .generate_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::AIProvider(msg) if msg.contains("missing")));
+}
+
+#[tokio::test]
+async fn test_openrouter_image_data_url_and_size_params() {
+    use backvonia::services::ai_provider::OpenRouterProvider;
+    use base64::Engine;
+
+    type Seen = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    async fn completions(
+        State(seen): State<Seen>,
+        Json(request): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        seen.lock().unwrap().push(request);
+        let webp = encode_image(6, 4, image::ImageFormat::WebP);
+        let data_url = format!(
+            "data:image/webp;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(webp)
+        );
+        Json(serde_json::json!({
+            "choices": [{ "message": { "images": [data_url] } }]
+        }))
+    }
+
+    let seen: Seen = Arc::default();
+    let app = Router::new()
+        .route("/v1/chat/completions", post(completions))
+        .with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let mut config = create_ai_config("openrouter", serde_json::json!({}));
+    config.openrouter.api_base = format!("http://{}/v1", addr);
+    let provider = OpenRouterProvider::new(&config.openrouter);
+
+    let mut size_params = serde_json::Map::new();
+    size_params.insert("image_size".to_string(), "2K".into());
+    let image = provider
+        .generate_image(&ImageRequest {
+            model: "test/image-pro".to_string(),
+            prompt: "A lighthouse".to_string(),
+            aspect_ratio: "3:4".to_string(),
+            size_params,
+            seed: None,
+            reference_image: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(image.mime_type.as_deref(), Some("image/webp"));
+    assert_eq!(image.bytes, encode_image(6, 4, image::ImageFormat::WebP));
+
+    // A reference image turns the prompt into text and image parts
+    provider
+        .generate_image(&ImageRequest {
+            model: "test/image-pro".to_string(),
+            prompt: "The same lighthouse at dawn".to_string(),
+            aspect_ratio: "3:4".to_string(),
+            size_params: serde_json::Map::new(),
+            seed: Some(7),
+            reference_image: Some(ReferenceImage {
+                bytes: vec![1, 2, 3],
+                mime_type: "image/jpeg".to_string(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(
+        seen[0]["image_config"],
+        serde_json::json!({ "aspect_ratio": "3:4", "image_size": "2K" })
+    );
+    assert_eq!(seen[0]["messages"][0]["content"], "A lighthouse");
+    assert!(seen[0].get("seed").is_none());
+
+    assert_eq!(seen[1]["seed"], 7);
+    assert_eq!(
+        seen[1]["messages"][0]["content"],
+        serde_json::json!([
+            { "type": "text", "text": "The same lighthouse at dawn" },
+            { "type": "image_url", "image_url": { "url": "data:image/jpeg;base64,AQID" } },
+        ])
+    );
+}
@@ -0,0 +1,281 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use backvonia::{
+    config::AIConfig,
+    models::ai::{AITextEditMode, EditInput, EditParams, GenerationParams, PathNode, StoryContext},
+    services::AIService,
+};
+use entity::sea_orm_active_enums::AccountTier;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::mpsc;
+
+/// Serve `chunks` as the body of every streamed chat completion
+async fn start_mock_sse_server(chunks: Vec<String>) -> String {
+    async fn completions(
+        State(chunks): State<Arc<Vec<String>>>,
+        Json(request): Json<serde_json::Value>,
+    ) -> Response {
+        if request["stream"] != true {
+            return (StatusCode::BAD_REQUEST, "expected a streaming request").into_response();
+        }
+
+        let body = futures::stream::iter(
+            chunks
+                .iter()
+                .cloned()
+                .map(Ok::<_, Infallible>)
+                .collect::<Vec<_>>(),
+        );
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .body(Body::from_stream(body))
+            .unwrap()
+    }
+
+    let app = Router::new()
+        .route("/chat/completions", post(completions))
+        .with_state(Arc::new(chunks));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+fn create_ai_service(api_base: &str, moderation: serde_json::Value) -> AIService {
+    let routing = serde_json::json!({
+        "free_default_tier": "light",
+        "pro_default_tier": "light",
+    });
+    let config: AIConfig = serde_json::from_value(serde_json::json!({
+        "openrouter": {
+            "api_key": "test-key",
+            "api_base": api_base,
+            "model_tiers": {
+                "premium": { "model": "test/premium" },
+                "standard": { "model": "test/standard" },
+                "light": { "model": "test/light" },
+            },
+            "image_models": {
+                "free": { "model": "test/image" },
+                "pro": { "model": "test/image" },
+            },
+            "ai_routing": {
+                "fix_grammar": routing,
+                "shorten": routing,
+                "rewrite": routing,
+                "ideas": routing,
+                "continue": routing,
+                "expand": routing,
+            },
+            "request_timeout_ms": 5000,
+            "retry_attempts": 0,
+        },
+        "moderation": moderation,
+    }))
+    .unwrap();
+
+    AIService::new(&config)
+}
+
+fn delta_event(index: usize, content: &str) -> String {
+    let chunk = serde_json::json!({
+        "choices": [{ "index": index, "delta": { "content": content } }]
+    });
+    format!("data: {}\n\n", chunk)
+}
+
+fn story_context() -> StoryContext {
+    StoryContext {
+        title: Some("Test".to_string()),
+        tags: vec![],
+        language: "en".to_string(),
+        background: None,
+        active_characters: None,
+    }
+}
+
+fn path_nodes() -> Vec<PathNode> {
+    vec![PathNode {
+        summary: None,
+        content: "The door creaked.".to_string(),
+    }]
+}
+
+#[tokio::test]
+async fn test_prose_stream_relays_deltas_and_parses_candidates() {
+    let pieces = [
+        "=== CANDIDATE 1 ===\n",
+        "TITLE: The door opens\n",
+        "CONTENT: She stepped ",
+        "into the dark.",
+    ];
+    // Split one event across chunks, as a network read may
+    let second = delta_event(0, pieces[1]);
+    let (second_head, second_tail) = second.split_at(10);
+    let chunks = vec![
+        ": OPENROUTER PROCESSING\n\n".to_string(),
+        delta_event(0, pieces[0]),
+        second_head.to_string(),
+        second_tail.to_string() + &delta_event(0, pieces[2]),
+        delta_event(0, pieces[3]),
+        "data: [DONE]\n\n".to_string(),
+    ];
+    let api_base = start_mock_sse_server(chunks).await;
+    let service = create_ai_service(&api_base, serde_json::json!({}));
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (candidates, model) = service
+        .stream_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+            &tx,
+        )
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut deltas = Vec::new();
+    while let Some(delta) = rx.recv().await {
+        deltas.push(delta);
+    }
+    assert_eq!(deltas.len(), 4);
+    assert!(deltas.iter().all(|d| d.candidate_index == 0));
+    let streamed: String = deltas.iter().map(|d| d.delta.as_str()).collect();
+    assert_eq!(streamed, pieces.concat());
+
+    assert_eq!(model, "test/light");
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].title.as_deref(), Some("The door opens"));
+    assert_eq!(candidates[0].content, "She stepped into the dark.");
+}
+
+#[tokio::test]
+async fn test_edit_stream_separates_choices() {
+    let chunks = vec![
+        delta_event(0, "First "),
+        delta_event(1, "Second "),
+        delta_event(0, "draft"),
+        delta_event(1, "draft"),
+        "data: [DONE]\n\n".to_string(),
+    ];
+    let api_base = start_mock_sse_server(chunks).await;
+    let service = create_ai_service(&api_base, serde_json::json!({}));
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (candidates, _) = service
+        .stream_text_edit(
+            AITextEditMode::Rewrite,
+            None,
+            &EditInput {
+                text: "A draft".to_string(),
+                selection: None,
+            },
+            &EditParams {
+                num_candidates: 2,
+                ..EditParams::default()
+            },
+            &AccountTier::Pro,
+            &tx,
+        )
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut indexes = Vec::new();
+    while let Some(delta) = rx.recv().await {
+        indexes.push(delta.candidate_index);
+    }
+    assert_eq!(indexes, vec![0, 1, 0, 1]);
+
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].content, "First draft");
+    assert_eq!(candidates[1].content, "Second draft");
+}
+
+#[tokio::test]
+async fn test_stream_error_event_fails_generation() {
+    let chunks = vec![
+        delta_event(0, "=== CANDIDATE 1 ===\n"),
+        "data: {\"error\":{\"message\":\"upstream overloaded\"}}\n\n".to_string(),
+    ];
+    let api_base = start_mock_sse_server(chunks).await;
+    let service = create_ai_service(&api_base, serde_json::json!({}));
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let result = service
+        .stream_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+            &tx,
+        )
+        .await;
+    drop(tx);
+
+    assert!(result.is_err());
+    // The delta sent before the failure still reached the caller
+    assert!(rx.recv().await.is_some());
+}
+
+#[tokio::test]
+async fn test_moderated_stream_withholds_blocked_candidate() {
+    let chunks = vec![
+        delta_event(0, "=== CANDIDATE 1 ===\nTITLE: Fire\nCONTENT: A "),
+        delta_event(0, "dragon burned the village.\n"),
+        delta_event(0, "=== CANDIDATE 2 ===\nTITLE: Quiet\nCONTENT: "),
+        delta_event(0, "The night stayed still."),
+        "data: [DONE]\n\n".to_string(),
+    ];
+    let api_base = start_mock_sse_server(chunks).await;
+    let service = create_ai_service(
+        &api_base,
+        serde_json::json!({
+            "rules": [{ "category": "violence", "patterns": ["dragon"] }],
+        }),
+    );
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (candidates, _) = service
+        .stream_prose_continuations(
+            &story_context(),
+            &path_nodes(),
+            &GenerationParams {
+                num_candidates: 2,
+                ..GenerationParams::default()
+            },
+            None,
+            &AccountTier::Pro,
+            &tx,
+        )
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut deltas = Vec::new();
+    while let Some(delta) = rx.recv().await {
+        deltas.push(delta);
+    }
+    // Only the screened candidate reaches the client, after the stream completed
+    assert_eq!(deltas.len(), 1);
+    assert_eq!(deltas[0].candidate_index, 0);
+    assert!(!deltas[0].delta.contains("dragon"));
+    assert!(deltas[0].delta.contains("The night stayed still."));
+
+    // Generation succeeds with the remaining candidate, so the route commits the charge
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].title.as_deref(), Some("Quiet"));
+}
@@ -0,0 +1,415 @@
+use axum::{extract::Path, http::HeaderMap, routing::get, Json, Router};
+use backvonia::{
+    config::{IAPConfig, ProductCatalog},
+    models::common::{IAPPlatform, PurchaseTier},
+    services::IAPService,
+    ApiError,
+};
+use base64::Engine;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rcgen::{BasicConstraints, CertificateParams, CustomExtension, DnType, IsCa, KeyPair};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub(super) const BUNDLE_ID: &str = "com.talevonia.app";
+const APPLE_RECEIPT_SIGNER_OID: &[u64] = &[1, 2, 840, 113635, 100, 6, 11, 1];
+const APPLE_WWDR_INTERMEDIATE_OID: &[u64] = &[1, 2, 840, 113635, 100, 6, 2, 1];
+
+/// Test stand-in for Apple's signing chain (root -> WWDR intermediate -> signer)
+pub(super) struct TestSigner {
+    leaf_key: KeyPair,
+    x5c: Vec<String>,
+    pub(super) root_sha256: String,
+}
+
+impl TestSigner {
+    pub(super) fn new() -> Self {
+        let root_key = KeyPair::generate().unwrap();
+        let mut root_params = CertificateParams::new(vec![]).unwrap();
+        root_params
+            .distinguished_name
+            .push(DnType::CommonName, "Test Root CA");
+        root_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let root = root_params.self_signed(&root_key).unwrap();
+
+        let intermediate_key = KeyPair::generate().unwrap();
+        let mut intermediate_params = CertificateParams::new(vec![]).unwrap();
+        intermediate_params
+            .distinguished_name
+            .push(DnType::CommonName, "Test WWDR Intermediate");
+        intermediate_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        intermediate_params
+            .custom_extensions
+            .push(CustomExtension::from_oid_content(
+                APPLE_WWDR_INTERMEDIATE_OID,
+                vec![0x05, 0x00],
+            ));
+        let intermediate = intermediate_params
+            .signed_by(&intermediate_key, &root, &root_key)
+            .unwrap();
+
+        let leaf_key = KeyPair::generate().unwrap();
+        let mut leaf_params = CertificateParams::new(vec![]).unwrap();
+        leaf_params
+            .distinguished_name
+            .push(DnType::CommonName, "Test App Store Signer");
+        leaf_params
+            .custom_extensions
+            .push(CustomExtension::from_oid_content(
+                APPLE_RECEIPT_SIGNER_OID,
+                vec![0x05, 0x00],
+            ));
+        let leaf = leaf_params
+            .signed_by(&leaf_key, &intermediate, &intermediate_key)
+            .unwrap();
+
+        let b64 = |der: &[u8]| base64::engine::general_purpose::STANDARD.encode(der);
+
+        Self {
+            x5c: vec![b64(leaf.der()), b64(intermediate.der()), b64(root.der())],
+            root_sha256: format!("{:x}", Sha256::digest(root.der())),
+            leaf_key,
+        }
+    }
+
+    pub(super) fn sign(&self, payload: &Value) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.x5c = Some(self.x5c.clone());
+        let key = EncodingKey::from_ec_pem(self.leaf_key.serialize_pem().as_bytes()).unwrap();
+        encode(&header, payload, &key).unwrap()
+    }
+}
+
+pub(super) fn now_ms() -> i64 {
+    (time::OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+pub(super) fn transaction_payload(transaction_id: &str, product_id: &str, kind: &str) -> Value {
+    json!({
+        "transactionId": transaction_id,
+        "originalTransactionId": "1000000000000001",
+        "bundleId": BUNDLE_ID,
+        "productId": product_id,
+        "purchaseDate": now_ms() - 60_000,
+        "type": kind,
+        "inAppOwnershipType": "PURCHASED",
+        "quantity": 1,
+        "environment": "Sandbox",
+        "signedDate": now_ms(),
+    })
+}
+
+pub(super) fn create_test_iap_config(signer: &TestSigner) -> IAPConfig {
+    IAPConfig {
+        apple_shared_secret: "test-shared-secret".to_string(),
+        apple_environment: "sandbox".to_string(),
+        apple_bundle_id: BUNDLE_ID.to_string(),
+        apple_issuer_id: None,
+        apple_key_id: None,
+        apple_private_key: None,
+        apple_api_base: None,
+        apple_root_ca_sha256: vec![signer.root_sha256.clone()],
+        google_package_name: None,
+        google_service_account_email: None,
+        google_service_account_private_key: None,
+        google_api_base: "https://androidpublisher.googleapis.com".to_string(),
+        google_token_uri: "https://oauth2.googleapis.com/token".to_string(),
+        google_pubsub_audience: None,
+        google_pubsub_service_account: None,
+        google_oidc_jwks_url: "https://www.googleapis.com/oauth2/v3/certs".to_string(),
+        products: ProductCatalog::default(),
+    }
+}
+
+/// Start a mock App Store Server API that answers with the given signed payloads
+async fn spawn_app_store_api(
+    api_public_key_pem: String,
+    signed_transaction: String,
+    subscription_status: Value,
+) -> String {
+    let check_auth = move |headers: &HeaderMap| {
+        let token = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .expect("missing bearer token")
+            .to_string();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("TESTKEY123"));
+
+        let mut validation = Validation::new(Algorithm::ES256);
+        validation.set_audience(&["appstoreconnect-v1"]);
+        validation.set_issuer(&["test-issuer"]);
+        let key = DecodingKey::from_ec_pem(api_public_key_pem.as_bytes()).unwrap();
+        let claims = decode::<Value>(&token, &key, &validation).unwrap().claims;
+        assert_eq!(claims["bid"], BUNDLE_ID);
+    };
+
+    let check_auth_txn = check_auth.clone();
+    let app = Router::new()
+        .route(
+            "/inApps/v1/transactions/{id}",
+            get(
+                move |Path(_id): Path<String>, headers: HeaderMap| async move {
+                    check_auth_txn(&headers);
+                    Json(json!({ "signedTransactionInfo": signed_transaction }))
+                },
+            ),
+        )
+        .route(
+            "/inApps/v1/subscriptions/{id}",
+            get(
+                move |Path(_id): Path<String>, headers: HeaderMap| async move {
+                    check_auth(&headers);
+                    Json(subscription_status)
+                },
+            ),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+fn with_app_store_api(mut config: IAPConfig, api_key: &KeyPair, base: String) -> IAPConfig {
+    config.apple_issuer_id = Some("test-issuer".to_string());
+    config.apple_key_id = Some("TESTKEY123".to_string());
+    config.apple_private_key = Some(api_key.serialize_pem());
+    config.apple_api_base = Some(base);
+    config
+}
+
+#[tokio::test]
+async fn test_signed_consumable_verified_offline() {
+    let signer = TestSigner::new();
+    let service = IAPService::new(&create_test_iap_config(&signer));
+
+    let jws = signer.sign(&transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    ));
+
+    let verified = service
+        .verify_consumable_purchase(IAPPlatform::Apple, &jws, "2000000000000001")
+        .await
+        .expect("signed consumable should verify");
+
+    assert_eq!(verified.transaction_id, "2000000000000001");
+    assert_eq!(verified.product_id, "com.talevonia.credits.100");
+    assert_eq!(verified.store_payload["bundleId"], BUNDLE_ID);
+
+    // A signed transaction cannot be replayed under a different transaction ID
+    let mismatched = service
+        .verify_consumable_purchase(IAPPlatform::Apple, &jws, "2000000000000002")
+        .await;
+    assert!(mismatched.is_err());
+}
+
+#[tokio::test]
+async fn test_signed_payload_rejected_for_untrusted_root() {
+    let signer = TestSigner::new();
+    let other_signer = TestSigner::new();
+    let service = IAPService::new(&create_test_iap_config(&other_signer));
+
+    let jws = signer.sign(&transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    ));
+
+    let result = service.decode_signed_transaction(&jws);
+    assert!(
+        result.is_err(),
+        "chain anchored in an unknown root must be rejected"
+    );
+}
+
+#[tokio::test]
+async fn test_signed_payload_rejected_when_tampered_or_foreign() {
+    let signer = TestSigner::new();
+    let service = IAPService::new(&create_test_iap_config(&signer));
+
+    // Swap in a payload the signer never signed
+    let jws = signer.sign(&transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    ));
+    let forged_claims = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
+        transaction_payload(
+            "2000000000000001",
+            "com.talevonia.credits.1000",
+            "Consumable",
+        )
+        .to_string(),
+    );
+    let parts: Vec<&str> = jws.split('.').collect();
+    let tampered = format!("{}.{}.{}", parts[0], forged_claims, parts[2]);
+    assert!(service.decode_signed_transaction(&tampered).is_err());
+
+    // Correctly signed, but for another app
+    let mut foreign = transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    );
+    foreign["bundleId"] = json!("com.example.other");
+    assert!(service
+        .decode_signed_transaction(&signer.sign(&foreign))
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_sandbox_transaction_rejected_in_production() {
+    let signer = TestSigner::new();
+    let service = IAPService::new(&IAPConfig {
+        apple_environment: "production".to_string(),
+        ..create_test_iap_config(&signer)
+    });
+
+    let jws = signer.sign(&transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    ));
+    let result = service
+        .verify_consumable_purchase(IAPPlatform::Apple, &jws, "2000000000000001")
+        .await;
+    assert!(matches!(result, Err(ApiError::InvalidReceipt(_))));
+
+    let mut production = transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    );
+    production["environment"] = json!("Production");
+    assert!(service
+        .decode_signed_transaction(&signer.sign(&production))
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_sandbox_notification_rejected_in_production() {
+    let signer = TestSigner::new();
+    let service = IAPService::new(&IAPConfig {
+        apple_environment: "production".to_string(),
+        ..create_test_iap_config(&signer)
+    });
+
+    let notification = |environment: &str| {
+        signer.sign(&json!({
+            "notificationType": "DID_RENEW",
+            "notificationUUID": "b3c0a6c4-0d6e-4a55-9d0c-6f3d2f1f4a10",
+            "signedDate": now_ms(),
+            "data": { "bundleId": BUNDLE_ID, "environment": environment },
+        }))
+    };
+    assert!(matches!(
+        service.decode_notification(&notification("Sandbox")),
+        Err(ApiError::InvalidReceipt(_))
+    ));
+    assert!(service
+        .decode_notification(&notification("Production"))
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_subscription_status_refreshed_from_app_store_api() {
+    let signer = TestSigner::new();
+    let api_key = KeyPair::generate().unwrap();
+
+    // Client holds the original purchase; Apple knows about a later renewal
+    let mut original = transaction_payload(
+        "1000000000000001",
+        "com.talevonia.pro.monthly",
+        "Auto-Renewable Subscription",
+    );
+    original["expiresDate"] = json!(now_ms() - 86_400_000);
+
+    let renewed_expiry = now_ms() + 30 * 86_400_000;
+    let mut renewed = transaction_payload(
+        "1000000000000002",
+        "com.talevonia.pro.monthly",
+        "Auto-Renewable Subscription",
+    );
+    renewed["expiresDate"] = json!(renewed_expiry);
+
+    let renewal_info = json!({
+        "originalTransactionId": "1000000000000001",
+        "autoRenewProductId": "com.talevonia.pro.monthly",
+        "autoRenewStatus": 1,
+        "signedDate": now_ms(),
+    });
+
+    let status = json!({
+        "environment": "Sandbox",
+        "bundleId": BUNDLE_ID,
+        "data": [{
+            "subscriptionGroupIdentifier": "21000000",
+            "lastTransactions": [{
+                "status": 1,
+                "originalTransactionId": "1000000000000001",
+                "signedTransactionInfo": signer.sign(&renewed),
+                "signedRenewalInfo": signer.sign(&renewal_info),
+            }]
+        }]
+    });
+
+    let base = spawn_app_store_api(api_key.public_key_pem(), signer.sign(&renewed), status).await;
+    let config = with_app_store_api(create_test_iap_config(&signer), &api_key, base);
+    let service = IAPService::new(&config);
+
+    let verification = service
+        .verify_receipt(IAPPlatform::Apple, &signer.sign(&original))
+        .await
+        .expect("subscription should verify");
+
+    assert_eq!(verification.purchase_identity, "1000000000000001");
+    assert!(matches!(verification.purchase_tier, PurchaseTier::Pro));
+    assert_eq!(verification.subscription_status.as_deref(), Some("active"));
+    assert_eq!(
+        verification.valid_until.map(|t| t.unix_timestamp()),
+        Some(renewed_expiry / 1000)
+    );
+}
+
+#[tokio::test]
+async fn test_revoked_consumable_rejected_via_transaction_info() {
+    let signer = TestSigner::new();
+    let api_key = KeyPair::generate().unwrap();
+
+    let purchased = transaction_payload(
+        "2000000000000001",
+        "com.talevonia.credits.100",
+        "Consumable",
+    );
+    let mut refunded = purchased.clone();
+    refunded["revocationDate"] = json!(now_ms());
+    refunded["revocationReason"] = json!(0);
+
+    let base =
+        spawn_app_store_api(api_key.public_key_pem(), signer.sign(&refunded), json!({})).await;
+    let config = with_app_store_api(create_test_iap_config(&signer), &api_key, base);
+    let service = IAPService::new(&config);
+
+    let result = service
+        .verify_consumable_purchase(
+            IAPPlatform::Apple,
+            &signer.sign(&purchased),
+            "2000000000000001",
+        )
+        .await;
+
+    assert!(
+        result.is_err(),
+        "refunded transaction must not grant credits"
+    );
+}
@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use backvonia::{
+    config::{AIConfig, CircuitBreakerConfig},
+    models::ai::{CircuitState, NodeToSummarize},
+    services::{
+        ai_provider::{OnDelta, TextProvider, TextRequest},
+        circuit_breaker::CircuitBreakers,
+        AIService,
+    },
+    ApiError,
+};
+use entity::sea_orm_active_enums::AccountTier;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+fn breakers(failure_threshold: u32, open_duration_ms: u64) -> CircuitBreakers {
+    CircuitBreakers::new(&CircuitBreakerConfig {
+        failure_threshold,
+        open_duration_ms,
+    })
+}
+
+fn unavailable() -> ApiError {
+    ApiError::AIProviderUnavailable("503 overloaded".to_string())
+}
+
+#[test]
+fn test_circuit_opens_after_consecutive_failures() {
+    let breakers = breakers(3, 60_000);
+
+    for _ in 0..2 {
+        breakers.acquire("openrouter", "test/light").unwrap();
+        breakers.record_failure("openrouter", "test/light", &unavailable());
+    }
+    breakers.acquire("openrouter", "test/light").unwrap();
+    breakers.record_failure("openrouter", "test/light", &unavailable());
+
+    let err = breakers.acquire("openrouter", "test/light").unwrap_err();
+    assert!(matches!(err, ApiError::AIProviderCircuitOpen(_)));
+
+    // Other models are unaffected
+    breakers.acquire("openrouter", "test/standard").unwrap();
+
+    let status = breakers.status();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].model, "test/light");
+    assert_eq!(status[0].state, CircuitState::Open);
+    assert_eq!(status[0].consecutive_failures, 3);
+    assert!(status[0].opened_at.is_some());
+}
+
+#[test]
+fn test_success_resets_failure_count() {
+    let breakers = breakers(2, 60_000);
+
+    breakers.record_failure("openrouter", "test/light", &unavailable());
+    breakers.record_success("openrouter", "test/light");
+    breakers.record_failure("openrouter", "test/light", &unavailable());
+
+    breakers.acquire("openrouter", "test/light").unwrap();
+    assert_eq!(breakers.status()[0].consecutive_failures, 1);
+}
+
+#[tokio::test]
+async fn test_half_open_admits_single_probe() {
+    let breakers = breakers(1, 20);
+    breakers.record_failure("openrouter", "test/light", &unavailable());
+    assert!(breakers.acquire("openrouter", "test/light").is_err());
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(breakers.status()[0].state, CircuitState::HalfOpen);
+
+    // The first caller probes; everyone else still fails fast
+    breakers.acquire("openrouter", "test/light").unwrap();
+    assert!(breakers.acquire("openrouter", "test/light").is_err());
+
+    breakers.record_success("openrouter", "test/light");
+    breakers.acquire("openrouter", "test/light").unwrap();
+    assert!(breakers.status().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_probe_reopens_circuit() {
+    let breakers = breakers(3, 20);
+    for _ in 0..3 {
+        breakers.record_failure("openrouter", "test/light", &unavailable());
+    }
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    breakers.acquire("openrouter", "test/light").unwrap();
+    breakers.record_failure("openrouter", "test/light", &unavailable());
+
+    assert_eq!(breakers.status()[0].state, CircuitState::Open);
+    assert!(breakers.acquire("openrouter", "test/light").is_err());
+}
+
+#[test]
+fn test_circuit_open_error_response() {
+    let (status, body) = ApiError::AIProviderCircuitOpen("test/light".to_string()).error_response();
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body.error.code, "AI_PROVIDER_CIRCUIT_OPEN");
+}
+
+/// Provider that fails with an availability error until switched healthy, counting its calls
+#[derive(Default)]
+struct FlakyProvider {
+    healthy: AtomicBool,
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl TextProvider for FlakyProvider {
+    async fn complete(&self, _request: &TextRequest) -> backvonia::Result<Vec<String>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.healthy.load(Ordering::SeqCst) {
+            Ok(vec!["1. The door opens".to_string()])
+        } else {
+            Err(unavailable())
+        }
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        _on_delta: &mut OnDelta<'_>,
+    ) -> backvonia::Result<Vec<String>> {
+        self.complete(request).await
+    }
+}
+
+fn create_ai_service(provider: Arc<FlakyProvider>) -> AIService {
+    let routing = serde_json::json!({
+        "free_default_tier": "light",
+        "pro_default_tier": "light",
+    });
+    let config: AIConfig = serde_json::from_value(serde_json::json!({
+        "openrouter": {
+            "api_key": "test-key",
+            "api_base": "http://127.0.0.1:9",
+            "model_tiers": {
+                "premium": { "model": "test/premium" },
+                "standard": { "model": "test/standard" },
+                "light": { "model": "test/light" },
+            },
+            "image_models": {
+                "free": { "model": "test/image" },
+                "pro": { "model": "test/image" },
+            },
+            "ai_routing": {
+                "fix_grammar": routing,
+                "shorten": routing,
+                "rewrite": routing,
+                "ideas": routing,
+                "continue": routing,
+                "expand": routing,
+            },
+            "request_timeout_ms": 5000,
+            "retry_attempts": 0,
+        },
+        "circuit_breaker": {
+            "failure_threshold": 2,
+            "open_duration_ms": 20,
+        },
+    }))
+    .unwrap();
+
+    AIService::new(&config).with_text_provider("openrouter", provider)
+}
+
+async fn summarize(service: &AIService) -> backvonia::Result<()> {
+    let nodes = vec![NodeToSummarize {
+        node_id: "node-1".to_string(),
+        content: "The door creaked open.".to_string(),
+    }];
+    service
+        .generate_summaries(None, &nodes, &AccountTier::Free)
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn test_open_circuit_fails_fast_without_calling_provider() {
+    let provider = Arc::new(FlakyProvider::default());
+    let service = create_ai_service(provider.clone());
+
+    for _ in 0..2 {
+        let err = summarize(&service).await.unwrap_err();
+        assert!(matches!(err, ApiError::AIProviderUnavailable(_)));
+    }
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+    let err = summarize(&service).await.unwrap_err();
+    assert!(matches!(err, ApiError::AIProviderCircuitOpen(_)));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+    let health = service.provider_health();
+    assert_eq!(health[0].model, "test/light");
+    assert_eq!(health[0].state, CircuitState::Open);
+
+    // Once the provider recovers, the probe closes the circuit
+    provider.healthy.store(true, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    summarize(&service).await.unwrap();
+    assert!(service.provider_health().is_empty());
+}
@@ -0,0 +1,278 @@
+use std::{sync::Arc, time::Duration};
+
+use backvonia::{
+    config::{CompressedImageFormat, ImageProcessingConfig, LocalStorageConfig, WatermarkConfig},
+    services::{
+        image_processing::{decode_embedded, probe, ImageProcessor, ProcessedImage},
+        object_store::{LocalObjectStore, ObjectStore},
+        ImageService,
+    },
+    ApiError,
+};
+use base64::Engine;
+use image::{ImageFormat, Rgba, RgbaImage};
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+fn encode_image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    encode_filled(width, height, Rgba([0, 0, 0, 0]), format)
+}
+
+fn encode_filled(width: u32, height: u32, color: Rgba<u8>, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    RgbaImage::from_pixel(width, height, color)
+        .write_to(&mut bytes, format)
+        .unwrap();
+    bytes.into_inner()
+}
+
+fn process(config: &ImageProcessingConfig, bytes: Vec<u8>, watermark: bool) -> ProcessedImage {
+    let info = probe(&bytes, None).unwrap();
+    ImageProcessor::new(config)
+        .unwrap()
+        .process(bytes, info, watermark)
+        .unwrap()
+}
+
+/// Opaque red overlay written to a temporary file
+fn watermark_config() -> ImageProcessingConfig {
+    let path = std::env::temp_dir().join(format!("backvonia-watermark-{}.png", Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        encode_filled(10, 10, Rgba([255, 0, 0, 255]), ImageFormat::Png),
+    )
+    .unwrap();
+
+    ImageProcessingConfig {
+        watermark: Some(WatermarkConfig {
+            image_path: path.to_string_lossy().into_owned(),
+            opacity: 1.0,
+            width_ratio: 0.25,
+            margin_ratio: 0.0,
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_probe_reads_format_and_dimensions() {
+    for (format, mime_type) in [
+        (ImageFormat::Png, "image/png"),
+        (ImageFormat::WebP, "image/webp"),
+        (ImageFormat::Gif, "image/gif"),
+    ] {
+        let info = probe(&encode_image(12, 7, format), None).unwrap();
+        assert_eq!(info.mime_type, mime_type);
+        assert_eq!((info.width, info.height), (12, 7));
+    }
+}
+
+#[test]
+fn test_probe_prefers_bytes_over_declared_mime() {
+    let info = probe(&encode_image(4, 4, ImageFormat::Png), Some("image/webp")).unwrap();
+
+    assert_eq!(info.mime_type, "image/png");
+}
+
+#[test]
+fn test_probe_rejects_unrecognised_bytes() {
+    let err = probe(b"definitely not an image", Some("image/png")).unwrap_err();
+    assert!(matches!(err, ApiError::AIProvider(_)));
+
+    let err = probe(b"definitely not an image", None).unwrap_err();
+    assert!(matches!(err, ApiError::AIProvider(_)));
+}
+
+#[test]
+fn test_decode_embedded_accepts_data_urls_and_bare_base64() {
+    let bytes = encode_image(5, 3, ImageFormat::Png);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+
+    for data in [
+        encoded.clone(),
+        format!("data:image/png;base64,{}", encoded),
+    ] {
+        let (decoded, info) = decode_embedded(&data, 1024).unwrap();
+        assert_eq!(decoded, bytes);
+        assert_eq!(info.mime_type, "image/png");
+        assert_eq!((info.width, info.height), (5, 3));
+    }
+
+    for (data, max_bytes) in [
+        (encoded.as_str(), bytes.len() / 2),
+        ("not base64!", 1024),
+        ("bm90IGFuIGltYWdl", 1024),
+    ] {
+        let err = decode_embedded(data, max_bytes).unwrap_err();
+        assert!(matches!(err, ApiError::BadRequest(_)));
+    }
+}
+
+#[test]
+fn test_process_builds_thumbnail_and_compressed_variants() {
+    let bytes = encode_image(1200, 600, ImageFormat::Png);
+    let processed = process(&ImageProcessingConfig::default(), bytes.clone(), false);
+
+    // Nothing to watermark, so the original is stored as generated
+    assert_eq!(processed.original.bytes, bytes);
+    assert_eq!(processed.original.info.mime_type, "image/png");
+
+    let variants: Vec<_> = processed
+        .variants
+        .iter()
+        .map(|v| (v.name, v.image.info.width, v.image.info.height))
+        .collect();
+    assert_eq!(
+        variants,
+        vec![("thumbnail", 256, 128), ("compressed", 1024, 512)]
+    );
+    for variant in &processed.variants {
+        assert_eq!(variant.image.info.mime_type, "image/jpeg");
+        let info = probe(&variant.image.bytes, None).unwrap();
+        assert_eq!(info, variant.image.info);
+    }
+}
+
+#[test]
+fn test_process_never_enlarges() {
+    let processed = process(
+        &ImageProcessingConfig::default(),
+        encode_image(100, 50, ImageFormat::Png),
+        false,
+    );
+
+    for variant in &processed.variants {
+        assert_eq!(
+            (variant.image.info.width, variant.image.info.height),
+            (100, 50)
+        );
+    }
+}
+
+#[test]
+fn test_process_webp_variants() {
+    let config = ImageProcessingConfig {
+        compressed_format: CompressedImageFormat::Webp,
+        ..Default::default()
+    };
+    let processed = process(&config, encode_image(600, 600, ImageFormat::Png), false);
+
+    for variant in &processed.variants {
+        assert_eq!(variant.image.info.mime_type, "image/webp");
+        assert_eq!(
+            probe(&variant.image.bytes, None).unwrap().mime_type,
+            "image/webp"
+        );
+        // Lossy bitstream ("VP8L" would be lossless)
+        let bytes = &variant.image.bytes;
+        assert!(bytes.windows(4).any(|chunk| chunk == b"VP8 "));
+        assert!(!bytes.windows(4).any(|chunk| chunk == b"VP8L"));
+    }
+}
+
+#[test]
+fn test_watermark_is_drawn_bottom_right_when_requested() {
+    let config = watermark_config();
+    let white = encode_filled(100, 100, Rgba([255, 255, 255, 255]), ImageFormat::Png);
+
+    let processed = process(&config, white.clone(), true);
+    assert_eq!(processed.original.info.mime_type, "image/png");
+    let original = image::load_from_memory(&processed.original.bytes)
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(original.get_pixel(95, 95), &Rgba([255, 0, 0, 255]));
+    assert_eq!(original.get_pixel(5, 5), &Rgba([255, 255, 255, 255]));
+
+    // Renditions are cut from the watermarked image
+    let thumbnail = image::load_from_memory(&processed.variants[0].image.bytes)
+        .unwrap()
+        .into_rgb8();
+    let corner = thumbnail.get_pixel(95, 95);
+    assert!(corner[0] > 200 && corner[1] < 60 && corner[2] < 60);
+
+    // Not requested (Pro tier), so the original is untouched
+    let processed = process(&config, white.clone(), false);
+    assert_eq!(processed.original.bytes, white);
+}
+
+#[test]
+fn test_missing_watermark_file_is_a_startup_error() {
+    let config = ImageProcessingConfig {
+        watermark: Some(WatermarkConfig {
+            image_path: "/nonexistent/watermark.png".to_string(),
+            opacity: 0.5,
+            width_ratio: 0.2,
+            margin_ratio: 0.02,
+        }),
+        ..Default::default()
+    };
+
+    assert!(ImageProcessor::new(&config).is_err());
+}
+
+#[tokio::test]
+async fn test_store_writes_original_and_variants() {
+    let root = std::env::temp_dir().join(format!("backvonia-images-{}", Uuid::new_v4()));
+    let store = Arc::new(LocalObjectStore::new(
+        &LocalStorageConfig {
+            root: root.to_string_lossy().into_owned(),
+            public_base_url: "https://api.example.com/files".to_string(),
+            signing_secret: None,
+        },
+        "secret",
+    ));
+    // Storing does not touch the database
+    let service = ImageService::new(
+        DatabaseConnection::Disconnected,
+        store.clone(),
+        Duration::from_secs(3600),
+        ImageProcessor::new(&ImageProcessingConfig::default()).unwrap(),
+    );
+    let (user_id, image_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let bytes = encode_image(512, 512, ImageFormat::Png);
+    let info = probe(&bytes, None).unwrap();
+
+    let stored = service
+        .store(user_id, image_id, bytes, info, false)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        stored.storage_key,
+        format!("images/{}/{}.png", user_id, image_id)
+    );
+    let keys: Vec<&str> = stored
+        .variants
+        .iter()
+        .map(|v| v.storage_key.as_str())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            format!("images/{}/{}_thumbnail.jpg", user_id, image_id),
+            format!("images/{}/{}_compressed.jpg", user_id, image_id),
+        ]
+    );
+    for variant in &stored.variants {
+        let bytes = store.get(&variant.storage_key).await.unwrap();
+        assert_eq!(probe(&bytes, None).unwrap().mime_type, "image/jpeg");
+    }
+
+    let links = service.variant_links(&stored.variants);
+    assert_eq!(links[0].name, "thumbnail");
+    assert_eq!((links[0].width, links[0].height), (256, 256));
+    assert!(links[0].url.contains("signature="));
+
+    service.discard(&stored).await;
+    assert!(matches!(
+        store.get(&stored.storage_key).await.unwrap_err(),
+        ApiError::NotFound(_)
+    ));
+    assert!(matches!(
+        store
+            .get(&stored.variants[0].storage_key)
+            .await
+            .unwrap_err(),
+        ApiError::NotFound(_)
+    ));
+}
@@ -1,5 +1,14 @@
-use backvonia::middleware::UserIdentity;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::DefaultBodyLimit,
+    http::{Request, StatusCode},
+    middleware,
+    routing::post,
+    Router,
+};
+use backvonia::middleware::{logging_middleware, UserIdentity};
 use entity::sea_orm_active_enums::AccountTier;
+use tower::ServiceExt;
 use uuid::Uuid;
 
 #[test]
@@ -20,3 +29,47 @@ fn test_account_tier_variants() {
 
     assert_ne!(free, pro);
 }
+
+/// Echo the body length through the logging middleware, with the route's own body limit
+fn logged_upload_router(body_limit: usize) -> Router {
+    Router::new()
+        .route(
+            "/upload",
+            post(|body: Bytes| async move { body.len().to_string() })
+                .layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .layer(middleware::from_fn(logging_middleware))
+}
+
+async fn upload(router: Router, len: usize) -> (StatusCode, String) {
+    let response = router
+        .oneshot(
+            Request::post("/upload")
+                .body(Body::from(vec![b'a'; len]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn test_logging_passes_large_bodies_to_the_route_limit() {
+    // Over the 1 MB logged size, within the route's limit
+    let (status, body) = upload(logged_upload_router(4 * 1024 * 1024), 3 * 1024 * 1024).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, (3 * 1024 * 1024).to_string());
+
+    // The route's limit still applies
+    let (status, _) = upload(logged_upload_router(4 * 1024 * 1024), 5 * 1024 * 1024).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_logging_buffers_small_bodies() {
+    let (status, body) = upload(logged_upload_router(1024), 512).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "512");
+}
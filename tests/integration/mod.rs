@@ -1,8 +1,27 @@
 // Integration tests
 
+mod ai_fallback_test;
 mod ai_image_test;
+mod ai_provider_test;
+mod ai_stream_test;
+mod app_store_jws_test;
 mod backward_compatibility_test;
+mod circuit_breaker_test;
+mod credit_fifo_test;
+mod credit_hold_test;
 mod credits_test;
+mod export_test;
+mod google_notifications_test;
+mod google_play_test;
+mod iap_notifications_test;
+mod image_history_test;
+mod image_processing_test;
 mod middleware_test;
+mod moderation_test;
+mod object_store_test;
+mod product_catalog_test;
 mod quota_test;
 mod race_condition_test;
+mod share_test;
+mod sync_test;
+mod usage_ledger_test;
@@ -0,0 +1,323 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use backvonia::{
+    config::{AIConfig, ModerationConfig},
+    models::ai::{AITextEditMode, EditInput, EditParams, GenerationParams, PathNode, StoryContext},
+    services::{
+        ai_provider::{OnDelta, TextProvider, TextRequest},
+        moderation::Moderator,
+        AIService,
+    },
+    ApiError,
+};
+use entity::sea_orm_active_enums::AccountTier;
+use std::sync::{Arc, Mutex};
+
+fn moderation_config(value: serde_json::Value) -> ModerationConfig {
+    serde_json::from_value(value).unwrap()
+}
+
+fn rules() -> serde_json::Value {
+    serde_json::json!({
+        "rules": [
+            { "category": "self_harm", "patterns": ["\\bhurt myself\\b"] },
+            { "category": "profanity", "action": "mask", "patterns": ["\\bdarn\\b"] },
+            { "category": "violence", "action": "flag", "patterns": ["\\bsword\\b"] },
+        ]
+    })
+}
+
+#[test]
+fn test_rules_flag_block_and_mask() {
+    let moderator = Moderator::new(&moderation_config(rules()));
+
+    let verdict = moderator.check_rules("I want to HURT MYSELF with a sword");
+    assert!(verdict.blocked);
+    assert_eq!(verdict.flags, vec!["self_harm", "violence"]);
+
+    let verdict = moderator.check_rules("Darn, the sword is heavy");
+    assert!(!verdict.blocked);
+    assert_eq!(verdict.flags, vec!["profanity", "violence"]);
+
+    assert_eq!(
+        moderator.mask("Darn, the sword is heavy"),
+        "****, the sword is heavy"
+    );
+}
+
+#[test]
+fn test_disabled_without_rules_or_model() {
+    let moderator = Moderator::new(&ModerationConfig::default());
+
+    assert!(!moderator.is_enabled());
+    assert_eq!(
+        moderator.check_rules("anything").flags,
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_parse_classification_only_counts_known_categories() {
+    let config = moderation_config(serde_json::json!({
+        "model": {
+            "model": "test/guard",
+            "categories": ["violence", "hate"],
+            "block_categories": ["hate"],
+        }
+    }));
+    let model = config.model.as_ref().unwrap();
+
+    let verdict = Moderator::parse_classification(model, "Violence, weather");
+    assert_eq!(verdict.flags, vec!["violence"]);
+    assert!(!verdict.blocked);
+
+    let verdict = Moderator::parse_classification(model, "hate.");
+    assert!(verdict.blocked);
+
+    assert!(Moderator::parse_classification(model, "NONE")
+        .flags
+        .is_empty());
+}
+
+#[test]
+fn test_content_rejected_error_response() {
+    let (status, body) = ApiError::ContentRejected("self_harm".to_string()).error_response();
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body.error.code, "CONTENT_REJECTED");
+}
+
+/// Provider that answers each request with the next scripted reply, recording prompts
+struct ScriptedProvider {
+    replies: Mutex<Vec<&'static str>>,
+    requests: Mutex<Vec<TextRequest>>,
+}
+
+impl ScriptedProvider {
+    fn new(replies: &[&'static str]) -> Arc<Self> {
+        Arc::new(Self {
+            replies: Mutex::new(replies.iter().rev().copied().collect()),
+            requests: Mutex::new(vec![]),
+        })
+    }
+}
+
+#[async_trait]
+impl TextProvider for ScriptedProvider {
+    async fn complete(&self, request: &TextRequest) -> backvonia::Result<Vec<String>> {
+        self.requests.lock().unwrap().push(request.clone());
+        let reply = self
+            .replies
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("unexpected request");
+        Ok(vec![reply.to_string(); request.n as usize])
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &TextRequest,
+        on_delta: &mut OnDelta<'_>,
+    ) -> backvonia::Result<Vec<String>> {
+        let contents = self.complete(request).await?;
+        on_delta(0, &contents[0]);
+        Ok(contents)
+    }
+}
+
+fn create_ai_service(moderation: serde_json::Value) -> AIService {
+    let routing = serde_json::json!({
+        "free_default_tier": "light",
+        "pro_default_tier": "light",
+    });
+    let config: AIConfig = serde_json::from_value(serde_json::json!({
+        "openrouter": {
+            "api_key": "test-key",
+            "api_base": "http://127.0.0.1:9",
+            "model_tiers": {
+                "premium": { "model": "test/premium" },
+                "standard": { "model": "test/standard" },
+                "light": { "model": "test/light" },
+            },
+            "image_models": {
+                "free": { "model": "test/image" },
+                "pro": { "model": "test/image" },
+            },
+            "ai_routing": {
+                "fix_grammar": routing,
+                "shorten": routing,
+                "rewrite": routing,
+                "ideas": routing,
+                "continue": routing,
+                "expand": routing,
+            },
+            "request_timeout_ms": 5000,
+            "retry_attempts": 0,
+        },
+        "moderation": moderation,
+    }))
+    .unwrap();
+
+    AIService::new(&config)
+}
+
+fn story_context() -> StoryContext {
+    StoryContext {
+        title: Some("Test".to_string()),
+        tags: vec![],
+        language: "en".to_string(),
+        background: None,
+        active_characters: None,
+    }
+}
+
+async fn continue_prose(
+    service: &AIService,
+    content: &str,
+) -> backvonia::Result<Vec<backvonia::models::ai::TextCandidate>> {
+    let nodes = vec![PathNode {
+        summary: None,
+        content: content.to_string(),
+    }];
+    service
+        .generate_prose_continuations(
+            &story_context(),
+            &nodes,
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await
+        .map(|(candidates, _)| candidates)
+}
+
+#[tokio::test]
+async fn test_disallowed_input_is_rejected_before_generation() {
+    let provider = ScriptedProvider::new(&[]);
+    let service = create_ai_service(rules()).with_text_provider("openrouter", provider.clone());
+
+    let err = continue_prose(&service, "Then I hurt myself.")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ContentRejected(msg) if msg.contains("self_harm")));
+    assert!(provider.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_candidates_are_flagged_and_masked() {
+    let provider = ScriptedProvider::new(&[
+        "=== CANDIDATE 1 ===\nTITLE: Darn it\nCONTENT: She drew her sword.",
+    ]);
+    let service = create_ai_service(rules()).with_text_provider("openrouter", provider);
+
+    let candidates = continue_prose(&service, "The door creaked.").await.unwrap();
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].title.as_deref(), Some("**** it"));
+    assert_eq!(candidates[0].content, "She drew her sword.");
+    assert_eq!(candidates[0].safety_flags, vec!["profanity", "violence"]);
+}
+
+#[tokio::test]
+async fn test_rejects_when_every_candidate_is_blocked() {
+    let provider = ScriptedProvider::new(&[
+        "=== CANDIDATE 1 ===\nTITLE: Dark turn\nCONTENT: He wanted to hurt myself.",
+    ]);
+    let service = create_ai_service(rules()).with_text_provider("openrouter", provider);
+
+    let err = continue_prose(&service, "The door creaked.")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ContentRejected(_)));
+}
+
+#[tokio::test]
+async fn test_moderation_model_screens_edit_candidates() {
+    let generator = ScriptedProvider::new(&["A gentle rewrite."]);
+    let guard = ScriptedProvider::new(&["NONE", "violence"]);
+    let service = create_ai_service(serde_json::json!({
+        "model": { "model": "test/guard", "provider": "guard" },
+    }))
+    .with_text_provider("openrouter", generator)
+    .with_text_provider("guard", guard.clone());
+
+    let (candidates, _) = service
+        .generate_text_edit(
+            AITextEditMode::Rewrite,
+            None,
+            &EditInput {
+                text: "The knight fought.".to_string(),
+                selection: None,
+            },
+            &EditParams::default(),
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(candidates[0].content, "A gentle rewrite.");
+    assert_eq!(candidates[0].safety_flags, vec!["violence"]);
+
+    // Input first, then the candidate
+    let requests = guard.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].model, "test/guard");
+    assert!(requests[0].messages[1]
+        .content
+        .contains("The knight fought."));
+    assert_eq!(requests[1].messages[1].content, "A gentle rewrite.");
+}
+
+#[tokio::test]
+async fn test_disallowed_story_context_is_rejected() {
+    let provider = ScriptedProvider::new(&[]);
+    let service = create_ai_service(rules()).with_text_provider("openrouter", provider.clone());
+
+    let context = StoryContext {
+        tags: vec!["hurt myself".to_string()],
+        ..story_context()
+    };
+    let nodes = vec![PathNode {
+        summary: None,
+        content: "The door creaked.".to_string(),
+    }];
+    let err = service
+        .generate_prose_continuations(
+            &context,
+            &nodes,
+            &GenerationParams::default(),
+            None,
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ContentRejected(msg) if msg.contains("self_harm")));
+    assert!(provider.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_edit_text_around_selection_is_screened() {
+    let provider = ScriptedProvider::new(&[]);
+    let service = create_ai_service(rules()).with_text_provider("openrouter", provider.clone());
+
+    let err = service
+        .generate_text_edit(
+            AITextEditMode::Rewrite,
+            None,
+            &EditInput {
+                text: "The door creaked. Then I hurt myself.".to_string(),
+                selection: Some("The door creaked.".to_string()),
+            },
+            &EditParams::default(),
+            &AccountTier::Free,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ContentRejected(msg) if msg.contains("self_harm")));
+    assert!(provider.requests.lock().unwrap().is_empty());
+}
@@ -0,0 +1,97 @@
+use backvonia::{
+    config::{ProductCatalog, ProductKind},
+    models::common::{IAPPlatform, PurchaseTier},
+};
+
+/// Load a catalog the way Config::load reads `iap.products` from config.yaml
+fn catalog_from_yaml(yaml: &str) -> ProductCatalog {
+    ::config::Config::builder()
+        .add_source(::config::File::from_str(yaml, ::config::FileFormat::Yaml))
+        .build()
+        .unwrap()
+        .get("products")
+        .expect("catalog should deserialize")
+}
+
+#[test]
+fn test_default_catalog_matches_launch_products() {
+    let catalog = ProductCatalog::default();
+
+    assert_eq!(
+        catalog.credit_amount(IAPPlatform::Apple, "com.talevonia.tale.credits.500"),
+        Some(500)
+    );
+    assert_eq!(
+        catalog.credit_amount(IAPPlatform::Google, "com.talevonia.tale.credits.2000"),
+        Some(2000)
+    );
+    assert_eq!(
+        catalog.tier_for(IAPPlatform::Apple, Some("com.talevonia.pro.yearly")),
+        PurchaseTier::Pro
+    );
+
+    // Subscriptions are not credit packs
+    assert_eq!(
+        catalog.credit_amount(IAPPlatform::Apple, "com.talevonia.pro.monthly"),
+        None
+    );
+
+    // Unknown products never grant Pro, even if they look like a Pro variant
+    assert_eq!(
+        catalog.tier_for(IAPPlatform::Apple, Some("com.talevonia.pro.lifetime")),
+        PurchaseTier::Free
+    );
+    assert_eq!(
+        catalog.tier_for(IAPPlatform::Google, Some("com.example.pro")),
+        PurchaseTier::Free
+    );
+}
+
+#[test]
+fn test_catalog_loaded_from_config() {
+    let catalog = catalog_from_yaml(
+        r#"
+products:
+  - product_id: com.talevonia.tale.credits.50
+    kind: consumable
+    credits: 50
+  - product_id: com.talevonia.plus.monthly
+    kind: subscription
+    tier: pro
+    monthly_allocation: 300
+  - product_id: com.talevonia.plus.monthly
+    platform: google
+    kind: subscription
+    tier: pro
+    monthly_allocation: 250
+"#,
+    );
+
+    assert_eq!(
+        catalog.credit_amount(IAPPlatform::Apple, "com.talevonia.tale.credits.50"),
+        Some(50)
+    );
+    // Products not in the configured catalog are unknown
+    assert_eq!(
+        catalog.credit_amount(IAPPlatform::Apple, "com.talevonia.tale.credits.100"),
+        None
+    );
+
+    // Platform-specific entries win over shared ones
+    let google = catalog
+        .find(IAPPlatform::Google, "com.talevonia.plus.monthly")
+        .unwrap();
+    assert_eq!(google.kind, ProductKind::Subscription);
+    assert_eq!(
+        catalog.monthly_allocation(IAPPlatform::Google, "com.talevonia.plus.monthly"),
+        Some(250)
+    );
+    assert_eq!(
+        catalog.monthly_allocation(IAPPlatform::Apple, "com.talevonia.plus.monthly"),
+        Some(300)
+    );
+    assert_eq!(
+        catalog.tier_for(IAPPlatform::Apple, Some("com.talevonia.plus.monthly")),
+        PurchaseTier::Pro
+    );
+}
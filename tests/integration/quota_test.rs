@@ -1,4 +1,11 @@
-use backvonia::{config::QuotaConfig, models::common::AIOperation, services::QuotaService};
+use backvonia::{
+    config::{ProductCatalog, QuotaConfig},
+    models::{
+        common::AIOperation,
+        credits::{CreditReservation, UsageContext},
+    },
+    services::QuotaService,
+};
 use entity::sea_orm_active_enums::AccountTier;
 use entity::user_credit_balance;
 use migration::{Migrator, MigratorTrait};
@@ -29,6 +36,10 @@ fn create_test_quota_config() -> QuotaConfig {
     QuotaConfig {
         free_text_daily_limit: 15,
         pro_text_daily_limit: 5000,
+        hold_ttl_secs: 600,
+        hold_sweep_interval_secs: 60,
+        free_image_regenerations: 2,
+        pro_image_regenerations: 20,
     }
 }
 
@@ -82,7 +93,11 @@ async fn get_balance(db: &DatabaseConnection, user_id: Uuid) -> (i32, i32, i32)
 async fn test_quota_race_condition_prevented() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = Arc::new(QuotaService::new(db.clone(), &config));
+    let service = Arc::new(QuotaService::new(
+        db.clone(),
+        &config,
+        &ProductCatalog::default(),
+    ));
 
     // Test identity with free tier (15 credits from subscription)
     // Each ContinueProse operation costs 5 credits, so should allow 3 operations
@@ -100,18 +115,14 @@ async fn test_quota_race_condition_prevented() {
         let barrier = Arc::clone(&barrier);
         let tier_clone = tier.clone();
 
-        let handle: tokio::task::JoinHandle<backvonia::error::Result<()>> =
+        let handle: tokio::task::JoinHandle<backvonia::error::Result<CreditReservation>> =
             tokio::spawn(async move {
                 // Wait for all tasks to be ready
                 barrier.wait().await;
 
                 // Try to use credits atomically (ContinueProse = 5 credits)
                 service
-                    .check_and_increment_quota_weighted(
-                        user_id,
-                        &tier_clone,
-                        AIOperation::ContinueProse,
-                    )
+                    .reserve(user_id, &tier_clone, AIOperation::ContinueProse)
                     .await
             });
 
@@ -119,11 +130,12 @@ async fn test_quota_race_condition_prevented() {
     }
 
     // Collect results
-    let results: Vec<backvonia::error::Result<()>> = futures::future::join_all(handles)
-        .await
-        .into_iter()
-        .map(|r| r.unwrap())
-        .collect();
+    let results: Vec<backvonia::error::Result<CreditReservation>> =
+        futures::future::join_all(handles)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
 
     // Count successes and failures
     let successes = results.iter().filter(|r| r.is_ok()).count();
@@ -147,7 +159,7 @@ async fn test_quota_race_condition_prevented() {
 async fn test_quota_check_and_increment_atomic() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = QuotaService::new(db.clone(), &config);
+    let service = QuotaService::new(db.clone(), &config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -156,7 +168,7 @@ async fn test_quota_check_and_increment_atomic() {
 
     // First operation should succeed (ContinueProse = 5 credits)
     let result1 = service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await;
     assert!(result1.is_ok());
     let (_, _, total1) = get_balance(&db, user_id).await;
@@ -164,7 +176,7 @@ async fn test_quota_check_and_increment_atomic() {
 
     // Second operation should succeed
     let result2 = service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await;
     assert!(result2.is_ok());
     let (_, _, total2) = get_balance(&db, user_id).await;
@@ -172,7 +184,7 @@ async fn test_quota_check_and_increment_atomic() {
 
     // Third operation should succeed
     let result3 = service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await;
     assert!(result3.is_ok());
     let (_, _, total3) = get_balance(&db, user_id).await;
@@ -180,7 +192,7 @@ async fn test_quota_check_and_increment_atomic() {
 
     // Fourth operation should fail (quota exceeded)
     let result4 = service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await;
     assert!(result4.is_err());
 
@@ -212,7 +224,7 @@ async fn test_quota_pro_tier_limits() {
 async fn test_refund_quota_after_failure() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = QuotaService::new(db.clone(), &config);
+    let service = QuotaService::new(db.clone(), &config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -222,8 +234,8 @@ async fn test_refund_quota_after_failure() {
     assert_eq!(initial_total, 15); // Free tier starts with 15 credits
 
     // Deduct credits for image generation (10 credits)
-    service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
@@ -232,7 +244,7 @@ async fn test_refund_quota_after_failure() {
 
     // Simulate failure and refund
     service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
@@ -248,27 +260,34 @@ async fn test_refund_quota_after_failure() {
 async fn test_refund_does_not_create_negative_usage() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = QuotaService::new(db.clone(), &config);
+    let service = QuotaService::new(db.clone(), &config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
 
     seed_user_balance(&db, user_id, &tier, &config).await;
 
-    // Try to refund without any prior deduction
-    // This should succeed (defensive programming - just adds credits)
+    // A reservation that was never charged cannot be refunded
+    let charge = CreditReservation {
+        id: Uuid::new_v4(),
+        operation: AIOperation::ImageGenerate,
+        cost: 10,
+        subscription_credits: 10,
+        extra_credits: 0,
+        usage_date: time::OffsetDateTime::now_utc().date(),
+    };
     let result = service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await;
 
     assert!(
-        result.is_ok(),
-        "Refund should not fail even without prior deduction"
+        result.is_err(),
+        "Refund should fail without a prior deduction"
     );
 
-    // Check that credits were added
+    // Check that no credits were added
     let (_, _, total) = get_balance(&db, user_id).await;
-    assert_eq!(total, 25); // 15 initial + 10 refunded
+    assert_eq!(total, 15);
 
     println!("✅ Refund without negative usage test passed");
 }
@@ -278,7 +297,7 @@ async fn test_refund_does_not_create_negative_usage() {
 async fn test_refund_multiple_operations() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = QuotaService::new(db.clone(), &config);
+    let service = QuotaService::new(db.clone(), &config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -286,14 +305,14 @@ async fn test_refund_multiple_operations() {
     seed_user_balance(&db, user_id, &tier, &config).await;
 
     // Deduct for text operation (5 credits)
-    service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+    let text_charge = service
+        .reserve(user_id, &tier, AIOperation::ContinueProse)
         .await
         .unwrap();
 
     // Deduct for image operation (10 credits)
-    service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let image_charge = service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
@@ -303,7 +322,7 @@ async fn test_refund_multiple_operations() {
 
     // Refund text operation
     service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ContinueProse)
+        .release(user_id, &tier, &text_charge, &UsageContext::default())
         .await
         .unwrap();
 
@@ -313,7 +332,7 @@ async fn test_refund_multiple_operations() {
 
     // Refund image operation
     service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &image_charge, &UsageContext::default())
         .await
         .unwrap();
 
@@ -329,7 +348,7 @@ async fn test_refund_multiple_operations() {
 async fn test_refund_with_extra_credits() {
     let db = setup_test_db().await;
     let config = create_test_quota_config();
-    let service = QuotaService::new(db.clone(), &config);
+    let service = QuotaService::new(db.clone(), &config, &ProductCatalog::default());
 
     let user_id = Uuid::new_v4();
     let tier = AccountTier::Free;
@@ -358,8 +377,8 @@ async fn test_refund_with_extra_credits() {
     assert_eq!(extra, 50);
 
     // Deduct for image (10 credits from subscription)
-    service
-        .check_and_increment_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+    let charge = service
+        .reserve(user_id, &tier, AIOperation::ImageGenerate)
         .await
         .unwrap();
 
@@ -371,15 +390,23 @@ async fn test_refund_with_extra_credits() {
 
     // Refund the image operation
     service
-        .refund_quota_weighted(user_id, &tier, AIOperation::ImageGenerate)
+        .release(user_id, &tier, &charge, &UsageContext::default())
         .await
         .unwrap();
 
-    // Refund goes to extra credits (65 total: 5 subscription + 60 extra)
+    // Refund goes back to subscription credits, where it came from
     let (sub, extra, total) = get_balance(&db, user_id).await;
     assert_eq!(total, 65);
-    assert_eq!(sub, 5);
-    assert_eq!(extra, 60);
+    assert_eq!(sub, 15);
+    assert_eq!(extra, 50);
+
+    // The same reservation cannot be refunded twice
+    let result = service
+        .release(user_id, &tier, &charge, &UsageContext::default())
+        .await;
+    assert!(result.is_err());
+    let (_, _, total) = get_balance(&db, user_id).await;
+    assert_eq!(total, 65);
 
     println!("✅ Refund with extra credits test passed");
 }